@@ -0,0 +1,3 @@
+-- Optional per-database commit message convention (JSON: { "pattern": ..., "required_prefixes": [...] })
+ALTER TABLE IF EXISTS public.databases
+    ADD COLUMN IF NOT EXISTS commit_message_policy jsonb;
@@ -0,0 +1,3 @@
+-- Save counter for optimistic concurrency on working commit edits (If-Match)
+ALTER TABLE IF EXISTS public.working_commits
+    ADD COLUMN IF NOT EXISTS version bigint NOT NULL DEFAULT 0;
@@ -0,0 +1,10 @@
+-- What a merge commit combined; NULL on ordinary commits
+ALTER TABLE IF EXISTS public.commits
+    ADD COLUMN IF NOT EXISTS source_branch character varying(255),
+    ADD COLUMN IF NOT EXISTS source_commit_hash character varying(64),
+    ADD COLUMN IF NOT EXISTS target_commit_hash character varying(64),
+    ADD COLUMN IF NOT EXISTS merge_strategy character varying(50);
+
+-- The same, kept on a merge working commit until it is committed
+ALTER TABLE IF EXISTS public.working_commits
+    ADD COLUMN IF NOT EXISTS merge_metadata jsonb;
@@ -1,4 +1,4 @@
-use crate::api::handlers::{AppState, ErrorResponse};
+use crate::api::handlers::{store_error, AppState, ErrorResponse};
 use crate::logic::branch_ops::BranchOperations;
 use crate::store::traits::Store;
 use axum::{
@@ -8,6 +8,7 @@ use axum::{
     Json as RequestJson,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 // Branch operation request/response structures
 #[derive(Debug, Deserialize)]
@@ -38,6 +39,27 @@ pub struct DeleteBranchRequest {
     pub force: Option<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DeleteBranchesRequest {
+    pub branches: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteBranchesResponse {
+    pub deleted: usize,
+    pub skipped: usize,
+    pub results: Vec<BranchDeletionResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BranchDeletionResult {
+    pub branch: String,
+    pub deleted: bool,
+    /// Why the branch was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
 /// POST /databases/{db_id}/versions/{branch_id}/merge
 /// Merge this branch into target branch
 pub async fn merge_branch<S: Store>(
@@ -55,10 +77,7 @@ pub async fn merge_branch<S: Store>(
             ));
         }
         Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(&e.to_string())),
-            ));
+            return Err(store_error(e));
         }
     }
 
@@ -126,10 +145,7 @@ pub async fn delete_branch<S: Store>(
             ));
         }
         Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(&e.to_string())),
-            ));
+            return Err(store_error(e));
         }
     }
 
@@ -155,3 +171,186 @@ pub async fn delete_branch<S: Store>(
     }
 }
 
+/// POST /databases/{db_id}/branches/delete-batch
+/// Delete several merged or archived branches in one transaction. The database's default
+/// branch, active branches and branches that other kept branches were created from are
+/// skipped and reported instead.
+pub async fn delete_branches<S: Store>(
+    Path(db_id): Path<String>,
+    State(store): State<AppState<S>>,
+    RequestJson(req): RequestJson<DeleteBranchesRequest>,
+) -> Result<Json<DeleteBranchesResponse>, (StatusCode, Json<ErrorResponse>)> {
+
+    let database = match store.get_database(&db_id).await.map_err(store_error)? {
+        Some(database) => database,
+        None => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse::new("Database not found")),
+            ));
+        }
+    };
+    let branches = store
+        .list_branches_for_database(&db_id)
+        .await
+        .map_err(store_error)?;
+
+    // Requested names in order, without duplicates
+    let mut requested: Vec<String> = Vec::new();
+    for name in req.branches {
+        if !requested.contains(&name) {
+            requested.push(name);
+        }
+    }
+
+    let mut skipped: HashMap<String, String> = HashMap::new();
+    for name in &requested {
+        let reason = match branches.iter().find(|b| &b.name == name) {
+            None => Some("Branch not found"),
+            Some(b) if b.name == database.default_branch_name => {
+                Some("Default branch is protected")
+            }
+            Some(b) if !b.can_be_deleted() => {
+                Some("Only merged or archived branches can be deleted")
+            }
+            Some(_) => None,
+        };
+        if let Some(reason) = reason {
+            skipped.insert(name.clone(), reason.to_string());
+        }
+    }
+
+    // A branch that a kept branch was created from can't go; skipping it may in turn keep
+    // its own parent, so repeat until nothing changes
+    loop {
+        let deleting: HashSet<&String> = requested
+            .iter()
+            .filter(|name| !skipped.contains_key(*name))
+            .collect();
+        let blocked: Vec<String> = deleting
+            .iter()
+            .filter(|name| {
+                branches.iter().any(|b| {
+                    b.parent_branch_name.as_ref() == Some(**name) && !deleting.contains(&b.name)
+                })
+            })
+            .map(|name| name.to_string())
+            .collect();
+        if blocked.is_empty() {
+            break;
+        }
+        for name in blocked {
+            skipped.insert(name, "Other branches were created from this branch".to_string());
+        }
+    }
+
+    let to_delete: Vec<String> = requested
+        .iter()
+        .filter(|name| !skipped.contains_key(*name))
+        .cloned()
+        .collect();
+    let deleted = if to_delete.is_empty() {
+        0
+    } else {
+        store
+            .delete_branches(&db_id, &to_delete)
+            .await
+            .map_err(store_error)?
+    };
+
+    let results = requested
+        .into_iter()
+        .map(|name| {
+            let reason = skipped.remove(&name);
+            BranchDeletionResult {
+                deleted: reason.is_none(),
+                branch: name,
+                reason,
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(Json(DeleteBranchesResponse {
+        deleted,
+        skipped: results.len() - to_delete.len(),
+        results,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Branch, BranchStatus, Database};
+    use crate::store::traits::{BranchStore, DatabaseStore};
+    use crate::store::InMemoryStore;
+    use std::sync::Arc;
+
+    fn branch(db_id: &str, name: &str, parent: &str, status: BranchStatus) -> Branch {
+        let mut branch = Branch::new(db_id.to_string(), name.to_string(), None, None);
+        branch.parent_branch_name = Some(parent.to_string());
+        branch.status = status;
+        branch
+    }
+
+    #[tokio::test]
+    async fn test_delete_branches_skips_unsafe_branches() {
+        let store = InMemoryStore::new();
+        let database = Database::new("housekeeping".to_string(), None);
+        let db_id = database.id.clone();
+        store.upsert_database(database).await.unwrap();
+        let mut main = Branch::new_main_branch(db_id.clone(), None);
+        main.status = BranchStatus::Archived;
+        store.upsert_branch(main).await.unwrap();
+        for b in [
+            branch(&db_id, "feature-a", "main", BranchStatus::Merged),
+            branch(&db_id, "feature-b", "main", BranchStatus::Active),
+            branch(&db_id, "release", "main", BranchStatus::Archived),
+            branch(&db_id, "hotfix", "release", BranchStatus::Active),
+            branch(&db_id, "old", "main", BranchStatus::Archived),
+            branch(&db_id, "old-child", "old", BranchStatus::Merged),
+        ] {
+            store.upsert_branch(b).await.unwrap();
+        }
+        let store = Arc::new(store);
+
+        let names = ["feature-a", "feature-b", "release", "main", "missing", "old", "old-child"];
+        let Json(response) = delete_branches(
+            Path(db_id.clone()),
+            State(Arc::clone(&store)),
+            RequestJson(DeleteBranchesRequest {
+                branches: names.iter().map(|n| n.to_string()).collect(),
+            }),
+        )
+        .await
+        .unwrap();
+
+        let outcome: Vec<(&str, bool)> = response
+            .results
+            .iter()
+            .map(|r| (r.branch.as_str(), r.deleted))
+            .collect();
+        assert_eq!(
+            outcome,
+            vec![
+                ("feature-a", true),
+                ("feature-b", false),
+                ("release", false),
+                ("main", false),
+                ("missing", false),
+                ("old", true),
+                ("old-child", true),
+            ]
+        );
+        assert_eq!(response.deleted, 3);
+        assert_eq!(response.skipped, 4);
+
+        let remaining: Vec<String> = store
+            .list_branches_for_database(&db_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(remaining.len(), 4);
+        assert!(!remaining.contains(&"old".to_string()));
+    }
+}
@@ -0,0 +1,702 @@
+//! Read-only GraphQL API over databases, branches, commits and instances
+//!
+//! Instances are read from committed data, either a branch head or a specific commit, and
+//! expanded with the same `Expander` the REST endpoints use. Related instances are nested
+//! under each relationship up to the query's `depth`, mirroring the REST `depth` parameter:
+//! at depth 0 relationships resolve to IDs only, each extra level includes one more layer of
+//! related instances.
+
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, InputValueResult, Name, Object, Result, Scalar,
+    ScalarType, Value as GraphQLValue,
+};
+use axum::extract::State;
+use axum::Extension;
+use serde_json::{Map, Value};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::api::handlers::AppState;
+use crate::config::ExpandConfig;
+use crate::logic::{ExpandSpec, Expander, PoolCache, SimpleEvaluator};
+use crate::model::{Branch, Commit, Database, ExpandedInstance, Instance, Schema};
+use crate::store::traits::Store;
+
+pub type GraphQLSchema<S> = async_graphql::Schema<QueryRoot<S>, EmptyMutation, EmptySubscription>;
+
+/// Build the schema for one store; `expand_config` caps the `depth` argument
+pub fn build_schema<S: Store + 'static>(
+    store: Arc<S>,
+    expand_config: ExpandConfig,
+) -> GraphQLSchema<S> {
+    async_graphql::Schema::build(QueryRoot(PhantomData), EmptyMutation, EmptySubscription)
+        .data(store)
+        .data(expand_config)
+        .finish()
+}
+
+/// Execute a GraphQL request. Query errors are reported in the response's `errors`, as
+/// GraphQL clients expect, so the status is always 200.
+pub async fn graphql_handler<S: Store + 'static>(
+    State(store): State<AppState<S>>,
+    Extension(expand_config): Extension<ExpandConfig>,
+    axum::Json(request): axum::Json<async_graphql::Request>,
+) -> axum::Json<async_graphql::Response> {
+    axum::Json(build_schema(store, expand_config).execute(request).await)
+}
+
+fn store<'a, S: Store + 'static>(ctx: &Context<'a>) -> &'a Arc<S> {
+    ctx.data_unchecked::<Arc<S>>()
+}
+
+fn check_depth(ctx: &Context<'_>, depth: usize) -> Result<usize> {
+    let max_depth = ctx.data_unchecked::<ExpandConfig>().max_depth;
+    if depth > max_depth {
+        return Err(format!(
+            "Requested depth {} exceeds the maximum expansion depth of {}",
+            depth, max_depth
+        )
+        .into());
+    }
+    Ok(depth)
+}
+
+/// Arbitrary JSON output. Property values are converted to GraphQL values directly rather
+/// than round-tripped through serde, so numbers keep their exact `serde_json` form.
+pub struct JsonValue(Value);
+
+fn to_graphql_value(value: Value) -> GraphQLValue {
+    match value {
+        Value::Null => GraphQLValue::Null,
+        Value::Bool(b) => GraphQLValue::Boolean(b),
+        Value::Number(n) => GraphQLValue::Number(n),
+        Value::String(s) => GraphQLValue::String(s),
+        Value::Array(items) => {
+            GraphQLValue::List(items.into_iter().map(to_graphql_value).collect())
+        }
+        Value::Object(map) => GraphQLValue::Object(
+            map.into_iter()
+                .map(|(key, value)| (Name::new(key), to_graphql_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[Scalar(name = "JSON")]
+impl ScalarType for JsonValue {
+    fn parse(value: GraphQLValue) -> InputValueResult<Self> {
+        Ok(JsonValue(value.into_json()?))
+    }
+
+    fn to_value(&self) -> GraphQLValue {
+        to_graphql_value(self.0.clone())
+    }
+}
+
+pub struct QueryRoot<S>(PhantomData<S>);
+
+#[Object(name = "Query")]
+impl<S: Store + 'static> QueryRoot<S> {
+    async fn databases(&self, ctx: &Context<'_>) -> Result<Vec<DatabaseObject<S>>> {
+        let databases = store::<S>(ctx).list_databases().await?;
+        Ok(databases.into_iter().map(DatabaseObject::new).collect())
+    }
+
+    async fn database(&self, ctx: &Context<'_>, id: String) -> Result<Option<DatabaseObject<S>>> {
+        let database = store::<S>(ctx).get_database(&id).await?;
+        Ok(database.map(DatabaseObject::new))
+    }
+
+    async fn commit(&self, ctx: &Context<'_>, hash: String) -> Result<Option<CommitObject<S>>> {
+        let commit = store::<S>(ctx).get_commit(&hash).await?;
+        Ok(commit.map(CommitObject::new))
+    }
+
+    /// Instances at a branch head; `branch` defaults to the database's default branch
+    async fn instances(
+        &self,
+        ctx: &Context<'_>,
+        database_id: String,
+        branch: Option<String>,
+        class: Option<String>,
+        #[graphql(default)] depth: usize,
+    ) -> Result<Vec<InstanceObject>> {
+        let depth = check_depth(ctx, depth)?;
+        let store = store::<S>(ctx);
+        let Some(database) = store.get_database(&database_id).await? else {
+            return Err(format!("Database '{}' not found", database_id).into());
+        };
+        let branch = branch.unwrap_or(database.default_branch_name);
+        let data = branch_data(store.as_ref(), &database_id, &branch).await?;
+        InstanceObject::list(data, class.as_deref(), None, depth).await
+    }
+
+    /// One instance at a branch head; `branch` defaults to the database's default branch
+    async fn instance(
+        &self,
+        ctx: &Context<'_>,
+        database_id: String,
+        branch: Option<String>,
+        id: String,
+        #[graphql(default)] depth: usize,
+    ) -> Result<Option<InstanceObject>> {
+        let depth = check_depth(ctx, depth)?;
+        let store = store::<S>(ctx);
+        let Some(database) = store.get_database(&database_id).await? else {
+            return Err(format!("Database '{}' not found", database_id).into());
+        };
+        let branch = branch.unwrap_or(database.default_branch_name);
+        let data = branch_data(store.as_ref(), &database_id, &branch).await?;
+        let mut found = InstanceObject::list(data, None, Some(&[id]), depth).await?;
+        Ok(found.pop())
+    }
+}
+
+/// Schema and instances at a branch head; a branch without commits has neither
+async fn branch_data<S: Store>(store: &S, db_id: &str, branch: &str) -> Result<Arc<InstanceSet>> {
+    let db_id = db_id.to_string();
+    let Some(head) = store.get_branch(&db_id, branch).await? else {
+        return Err(format!("Branch '{}' not found", branch).into());
+    };
+    let schema = store
+        .get_schema(&db_id, branch)
+        .await?
+        .unwrap_or_else(|| Schema {
+            id: format!("schema-{}", db_id),
+            classes: Vec::new(),
+            description: None,
+        });
+    let instances = store
+        .list_instances_for_branch(&db_id, branch, None)
+        .await?;
+    Ok(Arc::new(InstanceSet {
+        schema,
+        instances,
+        pools: PoolCache::new(head.current_commit_hash.unwrap_or_default()),
+    }))
+}
+
+pub struct DatabaseObject<S> {
+    database: Database,
+    store: PhantomData<S>,
+}
+
+impl<S> DatabaseObject<S> {
+    fn new(database: Database) -> Self {
+        Self {
+            database,
+            store: PhantomData,
+        }
+    }
+}
+
+#[Object(name = "Database")]
+impl<S: Store + 'static> DatabaseObject<S> {
+    async fn id(&self) -> &str {
+        &self.database.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.database.name
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.database.description.as_deref()
+    }
+
+    async fn created_at(&self) -> &str {
+        &self.database.created_at
+    }
+
+    async fn default_branch_name(&self) -> &str {
+        &self.database.default_branch_name
+    }
+
+    async fn branches(&self, ctx: &Context<'_>) -> Result<Vec<BranchObject<S>>> {
+        let branches = store::<S>(ctx)
+            .list_branches_for_database(&self.database.id)
+            .await?;
+        Ok(branches.into_iter().map(BranchObject::new).collect())
+    }
+
+    async fn branch(&self, ctx: &Context<'_>, name: String) -> Result<Option<BranchObject<S>>> {
+        let branch = store::<S>(ctx).get_branch(&self.database.id, &name).await?;
+        Ok(branch.map(BranchObject::new))
+    }
+}
+
+pub struct BranchObject<S> {
+    branch: Branch,
+    store: PhantomData<S>,
+}
+
+impl<S> BranchObject<S> {
+    fn new(branch: Branch) -> Self {
+        Self {
+            branch,
+            store: PhantomData,
+        }
+    }
+}
+
+#[Object(name = "Branch")]
+impl<S: Store + 'static> BranchObject<S> {
+    async fn name(&self) -> &str {
+        &self.branch.name
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.branch.description.as_deref()
+    }
+
+    async fn created_at(&self) -> &str {
+        &self.branch.created_at
+    }
+
+    async fn parent_branch_name(&self) -> Option<&str> {
+        self.branch.parent_branch_name.as_deref()
+    }
+
+    async fn current_commit_hash(&self) -> Option<&str> {
+        self.branch.current_commit_hash.as_deref()
+    }
+
+    async fn status(&self) -> Result<String> {
+        Ok(serde_json::to_value(&self.branch.status)?
+            .as_str()
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// The commit at the branch head
+    async fn head(&self, ctx: &Context<'_>) -> Result<Option<CommitObject<S>>> {
+        let Some(hash) = &self.branch.current_commit_hash else {
+            return Ok(None);
+        };
+        let commit = store::<S>(ctx).get_commit(hash).await?;
+        Ok(commit.map(CommitObject::new))
+    }
+
+    async fn instances(
+        &self,
+        ctx: &Context<'_>,
+        class: Option<String>,
+        #[graphql(default)] depth: usize,
+    ) -> Result<Vec<InstanceObject>> {
+        let depth = check_depth(ctx, depth)?;
+        let data = branch_data(
+            store::<S>(ctx).as_ref(),
+            &self.branch.database_id,
+            &self.branch.name,
+        )
+        .await?;
+        InstanceObject::list(data, class.as_deref(), None, depth).await
+    }
+}
+
+pub struct CommitObject<S> {
+    commit: Commit,
+    store: PhantomData<S>,
+}
+
+impl<S> CommitObject<S> {
+    fn new(commit: Commit) -> Self {
+        Self {
+            commit,
+            store: PhantomData,
+        }
+    }
+}
+
+#[Object(name = "Commit")]
+impl<S: Store + 'static> CommitObject<S> {
+    async fn hash(&self) -> &str {
+        &self.commit.hash
+    }
+
+    async fn database_id(&self) -> &str {
+        &self.commit.database_id
+    }
+
+    async fn parent_hash(&self) -> Option<&str> {
+        self.commit.parent_hash.as_deref()
+    }
+
+    async fn author(&self) -> Option<&str> {
+        self.commit.author.as_deref()
+    }
+
+    async fn message(&self) -> Option<&str> {
+        self.commit.message.as_deref()
+    }
+
+    async fn created_at(&self) -> &str {
+        &self.commit.created_at
+    }
+
+    async fn schema_classes_count(&self) -> i32 {
+        self.commit.schema_classes_count
+    }
+
+    async fn instances_count(&self) -> i32 {
+        self.commit.instances_count
+    }
+
+    async fn instances(
+        &self,
+        ctx: &Context<'_>,
+        class: Option<String>,
+        #[graphql(default)] depth: usize,
+    ) -> Result<Vec<InstanceObject>> {
+        let depth = check_depth(ctx, depth)?;
+        let data = self.commit.get_data().map_err(|e| e.to_string())?;
+        let data = Arc::new(InstanceSet {
+            schema: data.schema,
+            instances: data.instances,
+            pools: PoolCache::new(self.commit.hash.as_str()),
+        });
+        InstanceObject::list(data, class.as_deref(), None, depth).await
+    }
+}
+
+/// The schema and instances an instance is expanded against, with the relationship pools
+/// resolved so far; nested instances share it, so a query resolves each pool once
+struct InstanceSet {
+    schema: Schema,
+    instances: Vec<Instance>,
+    pools: PoolCache,
+}
+
+#[derive(Clone)]
+pub struct InstanceObject {
+    data: Arc<InstanceSet>,
+    instance: Instance,
+    expanded: ExpandedInstance,
+    /// Remaining levels of related instances to include
+    depth: usize,
+}
+
+impl InstanceObject {
+    /// Expand the instances of `data`, optionally narrowed to a class or to IDs (kept in
+    /// the order given)
+    async fn list(
+        data: Arc<InstanceSet>,
+        class: Option<&str>,
+        ids: Option<&[String]>,
+        depth: usize,
+    ) -> Result<Vec<Self>> {
+        let selected: Vec<&Instance> = match ids {
+            Some(ids) => ids
+                .iter()
+                .filter_map(|id| data.instances.iter().find(|i| &i.id == id))
+                .collect(),
+            None => data.instances.iter().collect(),
+        };
+
+        let mut objects = Vec::new();
+        for instance in selected {
+            if class.is_some_and(|class| instance.class_id != class) {
+                continue;
+            }
+            let expanded = Expander::expand_instance_with_cache(
+                instance,
+                &data.instances,
+                &data.schema,
+                &ExpandSpec::default(),
+                Some(&data.pools),
+            )
+            .await?;
+            objects.push(InstanceObject {
+                data: Arc::clone(&data),
+                instance: instance.clone(),
+                expanded,
+                depth,
+            });
+        }
+        Ok(objects)
+    }
+}
+
+#[Object(name = "Instance")]
+impl InstanceObject {
+    async fn id(&self) -> &str {
+        &self.instance.id
+    }
+
+    async fn class(&self) -> &str {
+        &self.instance.class_id
+    }
+
+    /// Property values by name, conditional properties evaluated; `names` selects a subset
+    async fn properties(&self, names: Option<Vec<String>>) -> JsonValue {
+        let properties = self
+            .expanded
+            .properties
+            .iter()
+            .filter(|(name, _)| names.as_ref().is_none_or(|names| names.contains(name)))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Map<String, Value>>();
+        JsonValue(Value::Object(properties))
+    }
+
+    /// A single property value, or null when the instance doesn't have it
+    async fn property(&self, name: String) -> Option<JsonValue> {
+        self.expanded.properties.get(&name).cloned().map(JsonValue)
+    }
+
+    /// Derived property values by name; all of the class's derived properties by default
+    async fn derived(&self, names: Option<Vec<String>>) -> Result<JsonValue> {
+        let names = match names {
+            Some(names) => names,
+            None => self
+                .data
+                .schema
+                .get_class_by_id(&self.instance.class_id)
+                .map(|class| class.derived.iter().map(|d| d.name.clone()).collect())
+                .unwrap_or_default(),
+        };
+        let values = SimpleEvaluator::evaluate_derived_properties(
+            &self.instance,
+            &self.data.schema,
+            &names,
+            &self.data.instances,
+        )?;
+        Ok(JsonValue(Value::Object(values.into_iter().collect())))
+    }
+
+    async fn relationships(&self) -> Vec<RelationshipObject> {
+        let Some(class_def) = self.data.schema.get_class_by_id(&self.instance.class_id) else {
+            return Vec::new();
+        };
+        class_def
+            .relationships
+            .iter()
+            .filter_map(|rel_def| {
+                let resolved = self.expanded.relationships.get(&rel_def.id)?;
+                Some(RelationshipObject {
+                    data: Arc::clone(&self.data),
+                    id: rel_def.id.clone(),
+                    name: rel_def.name.clone(),
+                    ids: resolved.materialized_ids.clone(),
+                    depth: self.depth,
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct RelationshipObject {
+    data: Arc<InstanceSet>,
+    id: String,
+    name: String,
+    ids: Vec<String>,
+    depth: usize,
+}
+
+#[Object(name = "Relationship")]
+impl RelationshipObject {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// IDs of the related instances
+    async fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// The related instances, or null once the requested depth is used up
+    async fn instances(&self) -> Result<Option<Vec<InstanceObject>>> {
+        if self.depth == 0 {
+            return Ok(None);
+        }
+        let related = InstanceObject::list(
+            Arc::clone(&self.data),
+            None,
+            Some(&self.ids),
+            self.depth - 1,
+        )
+        .await?;
+        Ok(Some(related))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{
+        Id, NewCommit, NewWorkingCommit, PropertyValue, RelationshipSelection, TypedValue,
+    };
+    use crate::store::traits::{BranchStore, CommitStore, DatabaseStore, WorkingCommitStore};
+    use crate::store::InMemoryStore;
+    use serde_json::json;
+
+    fn instance(id: &str, class_id: &str, properties: Value) -> Instance {
+        let properties = serde_json::from_value::<Map<String, Value>>(properties)
+            .unwrap()
+            .into_iter()
+            .map(|(name, value)| {
+                let data_type = serde_json::from_value(json!(match value {
+                    Value::Number(_) => "number",
+                    _ => "string",
+                }))
+                .unwrap();
+                (
+                    name,
+                    PropertyValue::Literal(TypedValue { value, data_type }),
+                )
+            })
+            .collect();
+        Instance {
+            id: id.to_string(),
+            class_id: class_id.to_string(),
+            properties,
+            ..Instance::default()
+        }
+    }
+
+    /// A committed main branch with a chair whose `legs` relationship selects two legs
+    async fn chair_store() -> (Arc<InMemoryStore>, Id) {
+        let store = InMemoryStore::new();
+        let database = Database::new("graphql".to_string(), None);
+        store.upsert_database(database.clone()).await.unwrap();
+        store
+            .upsert_branch(Branch::new_main_branch(database.id.clone(), None))
+            .await
+            .unwrap();
+
+        let mut wc = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        wc.schema_data.classes = serde_json::from_value(json!([
+            {
+                "id": "class-chair",
+                "name": "Chair",
+                "properties": [],
+                "derived": [],
+                "relationships": [{
+                    "id": "rel-legs",
+                    "name": "legs",
+                    "targets": ["class-leg"],
+                    "quantifier": "any"
+                }]
+            },
+            { "id": "class-leg", "name": "Leg", "properties": [], "relationships": [], "derived": [] }
+        ]))
+        .unwrap();
+        let mut chair = instance(
+            "chair-1",
+            "class-chair",
+            json!({ "price": 120, "color": "oak" }),
+        );
+        chair.relationships.insert(
+            "rel-legs".to_string(),
+            RelationshipSelection::SimpleIds(vec!["leg-1".to_string(), "leg-2".to_string()]),
+        );
+        wc.instances_data = vec![
+            chair,
+            instance("leg-1", "class-leg", json!({ "wood": "oak" })),
+            instance("leg-2", "class-leg", json!({ "wood": "pine" })),
+        ];
+        store.update_working_commit(wc.clone()).await.unwrap();
+
+        let commit = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc.id,
+                message: "Add chair".to_string(),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+        let mut main = store
+            .get_branch(&database.id, "main")
+            .await
+            .unwrap()
+            .unwrap();
+        main.current_commit_hash = Some(commit.hash);
+        store.upsert_branch(main).await.unwrap();
+        (Arc::new(store), database.id)
+    }
+
+    async fn query(store: &Arc<InMemoryStore>, query: String) -> Value {
+        let response = build_schema(Arc::clone(store), ExpandConfig::default())
+            .execute(query)
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        response.data.into_json().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_instance_with_one_level_of_relationships() {
+        let (store, db_id) = chair_store().await;
+        let data = query(
+            &store,
+            format!(
+                r#"{{ instance(databaseId: "{}", id: "chair-1", depth: 1) {{
+                    id
+                    relationships {{ name ids instances {{ id property(name: "wood") }} }}
+                }} }}"#,
+                db_id
+            ),
+        )
+        .await;
+
+        assert_eq!(
+            data,
+            json!({ "instance": {
+                "id": "chair-1",
+                "relationships": [{
+                    "name": "legs",
+                    "ids": ["leg-1", "leg-2"],
+                    "instances": [
+                        { "id": "leg-1", "property": "oak" },
+                        { "id": "leg-2", "property": "pine" }
+                    ]
+                }]
+            } })
+        );
+
+        // Without depth the relationship only carries IDs
+        let data = query(
+            &store,
+            format!(
+                r#"{{ instance(databaseId: "{}", id: "chair-1") {{
+                    relationships {{ ids instances {{ id }} }}
+                }} }}"#,
+                db_id
+            ),
+        )
+        .await;
+        assert_eq!(
+            data["instance"]["relationships"][0],
+            json!({ "ids": ["leg-1", "leg-2"], "instances": null })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_instances_select_specific_properties() {
+        let (store, db_id) = chair_store().await;
+        let data = query(
+            &store,
+            format!(
+                r#"{{ instances(databaseId: "{}", class: "class-chair") {{
+                    id
+                    properties(names: ["price"])
+                }} }}"#,
+                db_id
+            ),
+        )
+        .await;
+
+        assert_eq!(
+            data,
+            json!({ "instances": [{ "id": "chair-1", "properties": { "price": 120 } }] })
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Replaying retried POST requests that carry an `Idempotency-Key`
+//!
+//! A POST under `/databases/{db_id}/` with an `Idempotency-Key` header runs once. Its successful
+//! response is kept for `idempotency.ttl_secs` and returned again, marked with
+//! `Idempotent-Replayed: true`, to later requests with the same key, so a client retrying after
+//! a network error doesn't stage a second instance or create a second commit. Keys are scoped
+//! per database. Only 2xx responses are kept, so a failed request can be retried with the same
+//! key. Reusing a key for a different request (method, path, query or body) is rejected with
+//! 422, and a repeat that arrives while the first request is still running gets 409.
+//! A request that never completes releases its key.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api::handlers::ErrorResponse;
+
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+pub const IDEMPOTENT_REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Longest accepted key
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Largest request body read for fingerprinting; the same as axum's `DefaultBodyLimit`, which
+/// the extractors would apply to the body anyway
+const MAX_REQUEST_BODY_LEN: usize = 2 * 1024 * 1024;
+
+/// Database ID and idempotency key
+type Scope = (String, String);
+
+/// Responses by database and idempotency key, shared by all requests. Entries older than the
+/// TTL are dropped whenever a new key is claimed.
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<Scope, Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// SHA-256 of the request's method, URI and body
+    fingerprint: String,
+    stored_at: Instant,
+    /// `None` while the first request is still running
+    response: Option<StoredResponse>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// What to do with a request carrying a key
+enum Claim {
+    /// First use of the key: run the request
+    Run,
+    Replay(StoredResponse),
+    InProgress,
+    /// The key was used for a different request
+    Mismatch,
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of keys held, including those whose request is still running
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn claim(&self, scope: &Scope, fingerprint: &str) -> Claim {
+        let mut entries = self.lock();
+        let now = Instant::now();
+        entries.retain(|_, entry| now.duration_since(entry.stored_at) < self.ttl);
+        match entries.get(scope) {
+            Some(entry) if entry.fingerprint != fingerprint => Claim::Mismatch,
+            Some(Entry {
+                response: Some(response),
+                ..
+            }) => Claim::Replay(response.clone()),
+            Some(_) => Claim::InProgress,
+            None => {
+                entries.insert(
+                    scope.clone(),
+                    Entry {
+                        fingerprint: fingerprint.to_string(),
+                        stored_at: now,
+                        response: None,
+                    },
+                );
+                Claim::Run
+            }
+        }
+    }
+
+    /// Keep the response of a claimed key, or release the key when there is none to keep
+    fn complete(&self, scope: &Scope, response: Option<StoredResponse>) {
+        let mut entries = self.lock();
+        match (response, entries.get_mut(scope)) {
+            (Some(response), Some(entry)) => {
+                entry.stored_at = Instant::now();
+                entry.response = Some(response);
+            }
+            _ => {
+                entries.remove(scope);
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Scope, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A claimed key. Dropping it without `complete` (the client disconnected and the request
+/// future was dropped, or the handler panicked) releases the key, so it isn't answered with
+/// 409 for the rest of the TTL.
+struct ClaimGuard {
+    cache: Arc<IdempotencyCache>,
+    scope: Option<Scope>,
+}
+
+impl ClaimGuard {
+    fn complete(mut self, response: Option<StoredResponse>) {
+        if let Some(scope) = self.scope.take() {
+            self.cache.complete(&scope, response);
+        }
+    }
+}
+
+impl Drop for ClaimGuard {
+    fn drop(&mut self) {
+        if let Some(scope) = self.scope.take() {
+            self.cache.complete(&scope, None);
+        }
+    }
+}
+
+/// Middleware running keyed POST requests once; needs an `Extension<Arc<IdempotencyCache>>`
+/// and passes requests through untouched without one
+pub async fn idempotency(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get(&IDEMPOTENCY_KEY_HEADER).cloned() else {
+        return next.run(request).await;
+    };
+    let Some(db_id) = database_id(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let Some(cache) = request.extensions().get::<Arc<IdempotencyCache>>().cloned() else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => key.to_string(),
+        _ => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    "Idempotency-Key must be 1 to {} visible ASCII characters",
+                    MAX_IDEMPOTENCY_KEY_LEN
+                ),
+            )
+        }
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_REQUEST_BODY_LEN).await {
+        Ok(body) => body,
+        Err(_) => {
+            return error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Failed to read the request body; it may exceed the 2 MB limit",
+            )
+        }
+    };
+    let scope = (db_id, key);
+    match cache.claim(&scope, &fingerprint(&parts.method, &parts.uri, &body)) {
+        Claim::Run => {}
+        Claim::Replay(stored) => return replay(stored),
+        Claim::InProgress => {
+            return error(
+                StatusCode::CONFLICT,
+                "A request with this Idempotency-Key is still in progress",
+            )
+        }
+        Claim::Mismatch => {
+            return error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "This Idempotency-Key was already used for a different request",
+            )
+        }
+    }
+
+    let claim = ClaimGuard {
+        cache,
+        scope: Some(scope),
+    };
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if !response.status().is_success() {
+        claim.complete(None);
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(_) => {
+            claim.complete(None);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    claim.complete(Some(StoredResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    }));
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// The `{db_id}` of a `/databases/{db_id}/...` path
+fn database_id(path: &str) -> Option<String> {
+    let (db_id, _) = path.strip_prefix("/databases/")?.split_once('/')?;
+    (!db_id.is_empty()).then(|| db_id.to_string())
+}
+
+fn fingerprint(method: &Method, uri: &axum::http::Uri, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{} {}\n", method, uri));
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let mut response = (stored.status, Body::from(stored.body)).into_response();
+    *response.headers_mut() = stored.headers;
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(ErrorResponse::new(message))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Branch, Database};
+    use crate::store::traits::{BranchStore, DatabaseStore};
+    use crate::store::InMemoryStore;
+    use axum::Extension;
+    use tower::Service;
+
+    async fn app() -> (axum::Router, String) {
+        let store = InMemoryStore::new();
+        let database = Database::new("idempotency".to_string(), None);
+        store.upsert_database(database.clone()).await.unwrap();
+        store
+            .upsert_branch(Branch::new_main_branch(database.id.clone(), None))
+            .await
+            .unwrap();
+        let app = crate::api::routes::create_router()
+            .layer(Extension(Arc::new(IdempotencyCache::new(
+                Duration::from_secs(60),
+            ))))
+            .with_state(Arc::new(store));
+        (app, database.id)
+    }
+
+    /// Stage a new part without an ID, so each run generates one
+    async fn create_part(
+        app: &mut axum::Router,
+        db_id: &str,
+        key: Option<&str>,
+        price: i32,
+    ) -> Response {
+        let mut request = Request::post(format!(
+            "/databases/{}/branches/main/working-commit/instances",
+            db_id
+        ))
+        .header("content-type", "application/json");
+        if let Some(key) = key {
+            request = request.header(&IDEMPOTENCY_KEY_HEADER, key);
+        }
+        let body = serde_json::json!({
+            "id": "",
+            "class": "class-part",
+            "properties": {"price": price},
+            "relationships": {}
+        });
+        app.call(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn instance_id(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let instance: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        instance["id"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_repeated_key_returns_the_same_instance() {
+        let (mut app, db_id) = app().await;
+
+        let first = create_part(&mut app, &db_id, Some("retry-1"), 10).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert!(first.headers().get(&IDEMPOTENT_REPLAYED_HEADER).is_none());
+        let first_id = instance_id(first).await;
+
+        let retried = create_part(&mut app, &db_id, Some("retry-1"), 10).await;
+        assert_eq!(retried.status(), StatusCode::OK);
+        assert_eq!(retried.headers()[&IDEMPOTENT_REPLAYED_HEADER], "true");
+        assert_eq!(instance_id(retried).await, first_id);
+
+        // The same key for a different body is refused instead of replayed
+        let changed = create_part(&mut app, &db_id, Some("retry-1"), 20).await;
+        assert_eq!(changed.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_different_key_creates_a_new_instance() {
+        let (mut app, db_id) = app().await;
+
+        let first = instance_id(create_part(&mut app, &db_id, Some("key-a"), 10).await).await;
+        let second = instance_id(create_part(&mut app, &db_id, Some("key-b"), 10).await).await;
+        assert_ne!(first, second);
+
+        // Without a key every request runs
+        let unkeyed = instance_id(create_part(&mut app, &db_id, None, 10).await).await;
+        assert_ne!(unkeyed, first);
+        assert_ne!(unkeyed, second);
+    }
+
+    #[test]
+    fn test_keys_are_scoped_per_database() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let key = |db_id: &str| (db_id.to_string(), "key".to_string());
+
+        assert!(matches!(cache.claim(&key("db-1"), "request"), Claim::Run));
+        assert!(matches!(
+            cache.claim(&key("db-1"), "request"),
+            Claim::InProgress
+        ));
+        assert!(matches!(cache.claim(&key("db-2"), "request"), Claim::Run));
+
+        // A failed request releases its key for a retry
+        cache.complete(&key("db-2"), None);
+        assert!(matches!(cache.claim(&key("db-2"), "request"), Claim::Run));
+    }
+
+    #[test]
+    fn test_abandoned_claim_releases_the_key() {
+        let cache = Arc::new(IdempotencyCache::new(Duration::from_secs(60)));
+        let key = ("db-1".to_string(), "key".to_string());
+        assert!(matches!(cache.claim(&key, "request"), Claim::Run));
+
+        // The request future is dropped before it completes
+        drop(ClaimGuard {
+            cache: Arc::clone(&cache),
+            scope: Some(key.clone()),
+        });
+        assert!(cache.is_empty());
+        assert!(matches!(cache.claim(&key, "request"), Claim::Run));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_refused() {
+        let (mut app, db_id) = app().await;
+        let request = Request::post(format!(
+            "/databases/{}/branches/main/working-commit/instances",
+            db_id
+        ))
+        .header("content-type", "application/json")
+        .header(&IDEMPOTENCY_KEY_HEADER, "big")
+        .body(Body::from(vec![b' '; MAX_REQUEST_BODY_LEN + 1]))
+        .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_expired_keys_are_dropped() {
+        let cache = IdempotencyCache::new(Duration::ZERO);
+        let key = ("db-1".to_string(), "key".to_string());
+        assert!(matches!(cache.claim(&key, "request"), Claim::Run));
+        cache.complete(
+            &key,
+            Some(StoredResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: Bytes::new(),
+            }),
+        );
+
+        assert!(matches!(cache.claim(&key, "request"), Claim::Run));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_database_id_from_path() {
+        assert_eq!(
+            database_id("/databases/db-1/branches/main/working-commit/commit"),
+            Some("db-1".to_string())
+        );
+        assert_eq!(database_id("/databases"), None);
+        assert_eq!(database_id("/databases/db-1"), None);
+        assert_eq!(database_id("/graphql"), None);
+    }
+}
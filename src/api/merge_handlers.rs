@@ -1,5 +1,8 @@
-use crate::api::handlers::{AppState, ErrorResponse};
-use crate::logic::branch_ops_v2::{BranchOperationsV2, ResolveConflictsRequest};
+use crate::api::handlers::{store_error, AppState, CommitResponse, ErrorResponse};
+use crate::logic::branch_ops_v2::{
+    BranchOperationsV2, FastForwardBlocked, ResolveConflictsRequest,
+};
+use crate::model::merge::{MergeOptions, MergeStrategy, RelationshipMergeStrategy};
 use crate::store::traits::{BranchStore, CommitStore, Store, WorkingCommitStore};
 use axum::{
     extract::{Path, State},
@@ -14,6 +17,15 @@ use serde::{Deserialize, Serialize};
 pub struct StartMergeRequest {
     pub source_branch: String,
     pub author: Option<String>,
+    /// How concurrent id-list relationship edits are combined (defaults to union)
+    #[serde(default)]
+    pub relationship_merge: RelationshipMergeStrategy,
+    /// `ours` or `theirs` settles conflicts automatically; `recursive` (default) keeps them
+    #[serde(default)]
+    pub strategy: MergeStrategy,
+    /// Create a merge working commit even when the target branch could be fast-forwarded
+    #[serde(default)]
+    pub no_ff: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -22,6 +34,8 @@ pub struct StartMergeResponse {
     pub working_commit_id: Option<String>,
     pub conflicts: Vec<MergeConflictInfo>,
     pub message: String,
+    /// The target branch head was moved to the source head instead of merging
+    pub fast_forward: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -37,6 +51,21 @@ pub struct MergeConflictInfo {
     pub right_value: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RevertRequest {
+    pub author: Option<String>,
+    /// Defaults to `Revert "<original message>"` plus the reverted hash
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevertResponse {
+    pub success: bool,
+    pub commit: Option<CommitResponse>,
+    pub conflicts: Vec<MergeConflictInfo>,
+    pub message: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct MergeStatusResponse {
     pub status: String,
@@ -63,10 +92,7 @@ pub async fn start_merge<S: Store + CommitStore + WorkingCommitStore + BranchSto
             ));
         }
         Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(&e.to_string())),
-            ));
+            return Err(store_error(e));
         }
     }
 
@@ -78,6 +104,11 @@ pub async fn start_merge<S: Store + CommitStore + WorkingCommitStore + BranchSto
         &db_id,
         &target_branch,
         req.author,
+        MergeOptions {
+            relationship_merge: req.relationship_merge,
+            strategy: req.strategy,
+            no_ff: req.no_ff,
+        },
     )
     .await
     {
@@ -104,8 +135,13 @@ pub async fn start_merge<S: Store + CommitStore + WorkingCommitStore + BranchSto
                 working_commit_id: result.working_commit_id,
                 conflicts,
                 message: result.message,
+                fast_forward: result.fast_forward,
             }))
         }
+        Err(e) if e.is::<FastForwardBlocked>() => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse::new(&e.to_string())),
+        )),
         Err(e) => Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse::new(&e.to_string())),
@@ -130,10 +166,7 @@ pub async fn validate_merge<S: Store + CommitStore>(
             ));
         }
         Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(&e.to_string())),
-            ));
+            return Err(store_error(e));
         }
     }
 
@@ -170,12 +203,10 @@ pub async fn validate_merge<S: Store + CommitStore>(
                 "common_ancestor": result.common_ancestor,
                 "conflicts": conflicts,
                 "validation_result": result.validation_result,
+                "introduced_errors": result.introduced_errors,
             })))
         }
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new(&e.to_string())),
-        )),
+        Err(e) => Err(store_error(e)),
     }
 }
 
@@ -243,10 +274,7 @@ pub async fn get_merge_status<S: WorkingCommitStore>(
                 )),
             }
         }
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new(&e.to_string())),
-        )),
+        Err(e) => Err(store_error(e)),
     }
 }
 
@@ -264,10 +292,7 @@ pub async fn resolve_merge_conflicts<S: WorkingCommitStore + CommitStore + Store
     {
         Ok(wcs) => wcs,
         Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(&e.to_string())),
-            ));
+            return Err(store_error(e));
         }
     };
     
@@ -312,10 +337,7 @@ pub async fn abort_merge<S: WorkingCommitStore>(
     {
         Ok(wcs) => wcs,
         Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(&e.to_string())),
-            ));
+            return Err(store_error(e));
         }
     };
     
@@ -345,4 +367,76 @@ pub async fn abort_merge<S: WorkingCommitStore>(
             Json(ErrorResponse::new(&e.to_string())),
         )),
     }
-}
\ No newline at end of file
+}
+
+/// POST /databases/{db_id}/branches/{branch_id}/revert/{commit_hash}
+/// Commit the inverse of a commit on top of the branch head; 409 with the conflicts when
+/// later changes on the branch make the revert ambiguous
+pub async fn revert_commit<S: Store + CommitStore + WorkingCommitStore + BranchStore>(
+    Path((db_id, branch_name, commit_hash)): Path<(String, String, String)>,
+    State(store): State<AppState<S>>,
+    RequestJson(req): RequestJson<RevertRequest>,
+) -> Result<(StatusCode, Json<RevertResponse>), (StatusCode, Json<ErrorResponse>)> {
+    // Validate database exists
+    match store.get_database(&db_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse::new("Database not found")),
+            ));
+        }
+        Err(e) => {
+            return Err(store_error(e));
+        }
+    }
+
+    match BranchOperationsV2::revert_commit(
+        &*store,
+        &db_id,
+        &branch_name,
+        &commit_hash,
+        req.author,
+        req.message,
+    )
+    .await
+    {
+        Ok(result) => {
+            let conflicts: Vec<MergeConflictInfo> = result
+                .conflicts
+                .into_iter()
+                .enumerate()
+                .map(|(idx, c)| MergeConflictInfo {
+                    index: idx,
+                    conflict_type: format!("{:?}", c.conflict_type),
+                    resource_type: format!("{:?}", c.resource_type),
+                    resource_id: c.resource_id,
+                    field_path: c.field_path,
+                    description: c.description,
+                    base_value: c.base_value,
+                    left_value: c.left_value,
+                    right_value: c.right_value,
+                })
+                .collect();
+            let status = if result.success {
+                StatusCode::OK
+            } else {
+                StatusCode::CONFLICT
+            };
+
+            Ok((
+                status,
+                Json(RevertResponse {
+                    success: result.success,
+                    commit: result.commit.map(CommitResponse::from),
+                    conflicts,
+                    message: result.message,
+                }),
+            ))
+        }
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(&e.to_string())),
+        )),
+    }
+}
@@ -1,6 +1,10 @@
 pub mod branch_handlers;
+pub mod graphql;
 pub mod handlers;
+pub mod idempotency;
 pub mod merge_handlers;
+pub mod pretty_json;
+pub mod request_id;
 pub mod routes;
 pub mod user_extractor;
 
@@ -0,0 +1,134 @@
+//! Indented JSON for read endpoints with `?pretty=true`
+//!
+//! Responses stay compact unless a GET request asks for `pretty` (`?pretty`, `?pretty=true`
+//! or `?pretty=1`). The parameter is stripped from the query before the handler runs, so
+//! handlers that read every query parameter as input never see it. Pretty output goes through
+//! `serde_json::Value`, so object keys come out sorted.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, uri::PathAndQuery, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+const PRETTY_PARAM: &str = "pretty";
+
+/// Middleware re-serializing JSON responses with `serde_json::to_string_pretty` when asked to
+pub async fn pretty_json(mut request: Request, next: Next) -> Response {
+    let pretty = take_pretty_param(&mut request) && request.method() == Method::GET;
+    let response = next.run(request).await;
+    if !pretty || !is_json(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+    {
+        Ok(pretty) => Body::from(pretty),
+        // Not actually JSON: pass the body through untouched
+        Err(_) => Body::from(bytes),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, body)
+}
+
+/// Remove every `pretty` parameter from the request's query; true when the last one is on
+fn take_pretty_param(request: &mut Request) -> bool {
+    let Some(query) = request.uri().query() else {
+        return false;
+    };
+    let mut pretty = None;
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            if name != PRETTY_PARAM {
+                return true;
+            }
+            pretty = Some(!matches!(value, "false" | "0"));
+            false
+        })
+        .collect();
+    let Some(pretty) = pretty else {
+        return false;
+    };
+
+    let path = request.uri().path();
+    let path_and_query = if kept.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, kept.join("&"))
+    };
+    let mut parts = request.uri().clone().into_parts();
+    parts.path_and_query = PathAndQuery::try_from(path_and_query).ok();
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *request.uri_mut() = uri;
+    }
+    pretty
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Query, routing::get, Json, Router};
+    use std::collections::HashMap;
+    use tower::Service;
+
+    /// Echoes the query parameters the handler received
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/echo",
+                get(|Query(params): Query<HashMap<String, String>>| async move {
+                    Json(serde_json::json!({ "params": params, "list": [1, 2] }))
+                }),
+            )
+            .layer(axum::middleware::from_fn(pretty_json))
+    }
+
+    async fn get_body(uri: &str) -> String {
+        let response = app()
+            .call(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pretty_param_indents_json() {
+        let body = get_body("/echo?pretty=true&price=-1").await;
+        assert!(body.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&body).unwrap();
+        // The handler never sees the parameter
+        assert_eq!(value["params"], serde_json::json!({ "price": "-1" }));
+
+        assert!(get_body("/echo?pretty").await.contains('\n'));
+    }
+
+    #[tokio::test]
+    async fn test_responses_are_compact_by_default() {
+        for uri in ["/echo", "/echo?price=-1", "/echo?pretty=false"] {
+            let body = get_body(uri).await;
+            assert!(!body.contains('\n'), "{} returned {}", uri, body);
+        }
+    }
+}
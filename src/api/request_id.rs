@@ -0,0 +1,127 @@
+//! Correlation IDs for requests
+//!
+//! Every request runs in a `request` tracing span carrying its `X-Request-Id` (generated when
+//! the client sent none or an unusable one) and the resolved user, and the ID is echoed back in
+//! the response header. Handlers can read it as `Extension<RequestId>`. Without a tracing
+//! subscriber the span's events are forwarded to `log`, so they carry the ID as a field.
+
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+use tracing::Instrument;
+
+use crate::model::UserContext;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied ID that is kept; longer ones are replaced by a generated one
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Correlation ID of the current request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Middleware attaching a `RequestId` and a tracing span to each request
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let (mut parts, body) = request.into_parts();
+    let user = UserContext::from_request_parts(&mut parts, &())
+        .await
+        .unwrap_or_default();
+    parts.extensions.insert(RequestId(id.clone()));
+    let request = Request::from_parts(parts, body);
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        user_id = %user.user_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let started = Instant::now();
+    let mut response = async {
+        let response = next.run(request).await;
+        tracing::info!(
+            request_id = %id,
+            status = response.status().as_u16(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "request finished"
+        );
+        response
+    }
+    .instrument(span)
+    .await;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Extension, Router};
+    use tower::Service;
+
+    /// Returns the request ID the handler saw
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/id",
+                get(|Extension(RequestId(id)): Extension<RequestId>| async move { id }),
+            )
+            .layer(axum::middleware::from_fn(request_id))
+    }
+
+    async fn call(request_id: Option<&str>) -> (String, String) {
+        let mut request = Request::get("/id");
+        if let Some(id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, id);
+        }
+        let response = app()
+            .call(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let header = response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (header, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_request_id_round_trips() {
+        let (header, seen) = call(Some("trace-42")).await;
+        assert_eq!(header, "trace-42");
+        assert_eq!(seen, "trace-42");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_generated_when_missing() {
+        let (header, seen) = call(None).await;
+        assert!(uuid::Uuid::parse_str(&header).is_ok());
+        assert_eq!(seen, header);
+
+        let (other, _) = call(None).await;
+        assert_ne!(other, header);
+
+        let (replaced, _) = call(Some(&"x".repeat(MAX_REQUEST_ID_LEN + 1))).await;
+        assert!(uuid::Uuid::parse_str(&replaced).is_ok());
+    }
+}
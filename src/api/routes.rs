@@ -1,19 +1,25 @@
 use axum::{
-    routing::{delete, get, patch, post},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use std::sync::Arc;
 
-use crate::api::{branch_handlers, handlers, merge_handlers};
+use crate::api::{
+    branch_handlers, graphql, handlers, idempotency, merge_handlers, pretty_json, request_id,
+};
 use crate::store::traits::Store;
 
 pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
     Router::new()
         // Health check
         .route("/health", get(handlers::health_check))
+        // Resolved user context (audit attribution debugging)
+        .route("/whoami", get(handlers::whoami))
         // API Documentation
         .route("/docs", get(handlers::get_api_docs::<S>))
         .route("/docs/openapi.json", get(handlers::get_openapi_spec::<S>))
+        // Read-only GraphQL API
+        .route("/graphql", post(graphql::graphql_handler::<S>))
         // Type Validation endpoints
         .route(
             "/databases/:db_id/validate",
@@ -63,11 +69,36 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
         .route("/databases", post(handlers::upsert_database::<S>))
         .route("/databases/:db_id", get(handlers::get_database::<S>))
         .route("/databases/:db_id", delete(handlers::delete_database::<S>))
+        .route(
+            "/databases/:db_id/commit-message-policy",
+            put(handlers::update_commit_message_policy::<S>),
+        )
         .route("/databases/:db_id/commits", get(handlers::list_database_commits::<S>))
+        .route("/databases/:db_id/integrity", get(handlers::check_database_integrity::<S>))
+        .route(
+            "/databases/:db_id/integrity/repair",
+            post(handlers::repair_database_integrity::<S>),
+        )
         // NEW: Commit-specific data access endpoints
         .route("/databases/:db_id/commits/:commit_hash/schema", get(handlers::get_commit_schema::<S>))
+        .route(
+            "/databases/:db_id/commits/:commit_hash/diff/:to_hash",
+            get(handlers::get_commit_diff::<S>),
+        )
+        .route(
+            "/databases/:db_id/commits/:commit_hash/is-ancestor/:descendant_hash",
+            get(handlers::get_commit_ancestry::<S>),
+        )
         .route("/databases/:db_id/commits/:commit_hash/instances", get(handlers::get_commit_instances::<S>))
+        .route(
+            "/databases/:db_id/commits/:commit_hash/instances.csv",
+            get(handlers::export_commit_instances_csv::<S>),
+        )
         .route("/databases/:db_id/commits/:commit_hash/schema/classes/:class_id", get(handlers::get_commit_class::<S>))
+        .route(
+            "/databases/:db_id/commits/:commit_hash/schema/classes/:class_id/json-schema",
+            get(handlers::get_commit_class_json_schema::<S>),
+        )
         .route("/databases/:db_id/commits/:commit_hash/instances/:instance_id", get(handlers::get_commit_instance::<S>))
         // DEFAULT BRANCH working-commit endpoints (assumes main branch)
         .route(
@@ -120,6 +151,10 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/instances/:id",
             get(handlers::get_database_instance::<S>),
         )
+        .route(
+            "/databases/:db_id/instances/:instance_id/history",
+            get(handlers::get_instance_history::<S>),
+        )
         // Branch management - READ-ONLY
         .route(
             "/databases/:db_id/branches",
@@ -137,6 +172,22 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_id",
             patch(handlers::update_branch_status::<S>),
         )
+        .route(
+            "/databases/:db_id/branches/:branch_id/stats",
+            get(handlers::get_branch_stats::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/merge-base/:other_branch_id",
+            get(handlers::get_merge_base::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/unarchive",
+            post(handlers::unarchive_branch::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/fork-to-database",
+            post(handlers::fork_branch_to_database::<S>),
+        )
         // Branch-level data access - READ-ONLY
         // For modifications, use working-commit endpoints
         .route(
@@ -147,10 +198,38 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_id/schema/classes/:class_id",
             get(handlers::get_class::<S>),
         )
+        .route(
+            "/databases/:db_id/branches/:branch_id/schema/classes/:class_id/usage",
+            get(handlers::get_class_usage::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/schema/impact",
+            post(handlers::analyze_schema_impact::<S>),
+        )
         .route(
             "/databases/:db_id/branches/:branch_id/instances",
             get(handlers::list_instances::<S>),
         )
+        .route(
+            "/databases/:db_id/branches/:branch_id/search",
+            get(handlers::search_branch_instances::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/instances/batch",
+            post(handlers::create_branch_instances_batch::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/instances/get-many",
+            post(handlers::get_many_branch_instances::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/instances/import.csv",
+            post(handlers::import_branch_instances_csv::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/instances/delete-by-filter",
+            post(handlers::delete_instances_by_filter::<S>),
+        )
         .route(
             "/databases/:db_id/branches/:branch_id/instances/:id",
             get(handlers::get_instance::<S>),
@@ -177,6 +256,10 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_name/merge",
             delete(merge_handlers::abort_merge::<S>),
         )
+        .route(
+            "/databases/:db_id/branches/:branch_id/revert/:commit_hash",
+            post(merge_handlers::revert_commit::<S>),
+        )
         // Legacy merge endpoint (deprecated)
         .route(
             "/databases/:db_id/branches/:branch_id/merge-legacy",
@@ -186,6 +269,10 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_id/delete",
             post(branch_handlers::delete_branch::<S>),
         )
+        .route(
+            "/databases/:db_id/branches/delete-batch",
+            post(branch_handlers::delete_branches::<S>),
+        )
         // Instance-specific query/solve endpoints (GET with URL parameters)
         .route(
             "/databases/:db_id/instances/:instance_id/query",
@@ -209,6 +296,12 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_id/instances/:instance_id/batch-query",
             post(handlers::batch_query_branch_instance_configuration::<S>),
         )
+        // Batch solve endpoints for many instances at once
+        .route("/databases/:db_id/solve/batch", post(handlers::solve_batch::<S>))
+        .route(
+            "/databases/:db_id/branches/:branch_id/solve/batch",
+            post(handlers::solve_branch_batch::<S>),
+        )
         // Analysis endpoints
         .route(
             "/databases/:db_id/instances/:instance_id/analysis",
@@ -244,6 +337,10 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_id/working-commit/validate",
             get(handlers::validate_working_commit::<S>),
         )
+        .route(
+            "/databases/:db_id/branches/:branch_id/working-commit/schema/impact",
+            post(handlers::analyze_working_commit_schema_impact::<S>),
+        )
         // NEW: Working Commit READ endpoints for current state 
         .route(
             "/databases/:db_id/branches/:branch_id/working-commit/schema", 
@@ -299,6 +396,14 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_id/working-commit/schema/classes/:class_id",
             delete(handlers::delete_working_commit_class::<S>),
         )
+        .route(
+            "/databases/:db_id/branches/:branch_id/working-commit/schema/classes/:class_id/rename",
+            post(handlers::rename_working_commit_class::<S>),
+        )
+        .route(
+            "/databases/:db_id/branches/:branch_id/working-commit/schema/classes/:class_id/properties/:property/rename",
+            post(handlers::rename_working_commit_property::<S>),
+        )
         .route(
             "/databases/:db_id/branches/:branch_id/working-commit/instances",
             post(handlers::create_working_commit_instance::<S>),
@@ -311,6 +416,10 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_id/working-commit/instances/:instance_id",
             delete(handlers::delete_working_commit_instance::<S>),
         )
+        .route(
+            "/databases/:db_id/branches/:branch_id/classes/:class_id/apply-defaults",
+            post(handlers::apply_class_defaults::<S>),
+        )
         // Bulk update endpoints for working commits
         .route(
             "/databases/:db_id/branches/:branch_id/working-commit/schema/classes/bulk",
@@ -320,6 +429,20 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/branches/:branch_id/working-commit/instances/bulk",
             patch(handlers::bulk_update_working_commit_instances::<S>),
         )
+        // Editing session heartbeat and recovery after a disconnect
+        .route(
+            "/databases/:db_id/branches/:branch_id/working-commit/touch",
+            post(handlers::touch_working_commit::<S>),
+        )
+        // Live updates for collaborative editing
+        .route(
+            "/databases/:db_id/branches/:branch_id/working-commit/events",
+            get(handlers::stream_working_commit_events::<S>),
+        )
+        .route(
+            "/databases/:db_id/working-commits/recoverable",
+            get(handlers::list_recoverable_working_commits::<S>),
+        )
         // Stage working commit (force persist to database)
         .route(
             "/databases/:db_id/branches/:branch_id/working-commit/stage",
@@ -346,7 +469,7 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
         )
         .route(
             "/tags/:tag_id",
-            delete(handlers::delete_commit_tag::<S>),
+            delete(handlers::delete_commit_tag::<S>).patch(handlers::move_commit_tag::<S>),
         )
         .route(
             "/commits/:commit_hash/tagged",
@@ -356,8 +479,22 @@ pub fn create_router<S: Store + 'static>() -> Router<Arc<S>> {
             "/databases/:db_id/tagged-commits",
             get(handlers::list_tagged_commits::<S>),
         )
+        .route(
+            "/databases/:db_id/releases",
+            get(handlers::list_releases::<S>),
+        )
+        .route(
+            "/databases/:db_id/releases/latest",
+            get(handlers::get_latest_release::<S>),
+        )
         .route(
             "/databases/:db_id/commits/search",
             get(handlers::search_commits_by_tags::<S>),
         )
+        // Replays the stored response of a POST retried with the same `Idempotency-Key`
+        .layer(axum::middleware::from_fn(idempotency::idempotency))
+        // `?pretty=true` indents JSON responses of GET requests
+        .layer(axum::middleware::from_fn(pretty_json::pretty_json))
+        // Correlation ID span around everything else, echoed as `X-Request-Id`
+        .layer(axum::middleware::from_fn(request_id::request_id))
 }
@@ -4,6 +4,16 @@ use serde::{Deserialize, Serialize};
 pub struct AppConfig {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
+    #[serde(default)]
+    pub expand: ExpandConfig,
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    #[serde(default)]
+    pub pagination: PaginationConfig,
+    #[serde(default)]
+    pub delete: DeleteConfig,
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,7 +25,54 @@ pub struct ServerConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
     pub connection_string: Option<String>,
+    /// Largest number of open connections (default 20)
     pub max_connections: Option<u32>,
+    /// Connections kept open even when idle (default 0)
+    pub min_connections: Option<u32>,
+    /// Seconds a request waits for a free connection before failing with 503 (default 30)
+    pub acquire_timeout: Option<u64>,
+    /// Seconds an idle connection above `min_connections` stays open (default 600)
+    pub idle_timeout: Option<u64>,
+}
+
+/// Guardrails for relationship expansion requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpandConfig {
+    /// Deepest relationship expansion instance endpoints perform; a larger `depth` query
+    /// parameter is capped to it (default 10)
+    pub max_depth: usize,
+    /// How many instances a list endpoint expands at the same time (default 16)
+    pub concurrency: usize,
+}
+
+/// Limits for paginated list endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginationConfig {
+    /// Largest `limit` query parameter honored; larger values are capped (default 1000)
+    pub max_limit: usize,
+}
+
+/// Limits for instance deletion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteConfig {
+    /// How many relationship levels `cascade=true` follows below the deleted instance (default 10)
+    pub max_cascade_depth: usize,
+}
+
+/// Replay of POST requests retried with the same `Idempotency-Key`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyConfig {
+    /// Seconds a response is kept for replay (default 86400)
+    pub ttl_secs: u64,
+}
+
+/// Property-level encryption settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Base64-encoded 32-byte master key; per-database data keys are derived from it.
+    /// Required as soon as a schema marks a property `encrypted`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
 }
 
 impl Default for AppConfig {
@@ -23,6 +80,11 @@ impl Default for AppConfig {
         Self {
             server: ServerConfig::default(),
             database: DatabaseConfig::default(),
+            expand: ExpandConfig::default(),
+            encryption: EncryptionConfig::default(),
+            pagination: PaginationConfig::default(),
+            delete: DeleteConfig::default(),
+            idempotency: IdempotencyConfig::default(),
         }
     }
 }
@@ -41,10 +103,42 @@ impl Default for DatabaseConfig {
         Self {
             connection_string: None,
             max_connections: Some(20),
+            min_connections: Some(0),
+            acquire_timeout: Some(30),
+            idle_timeout: Some(600),
+        }
+    }
+}
+
+impl Default for ExpandConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 10,
+            concurrency: 16,
         }
     }
 }
 
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self { max_limit: 1000 }
+    }
+}
+
+impl Default for DeleteConfig {
+    fn default() -> Self {
+        Self {
+            max_cascade_depth: 10,
+        }
+    }
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self { ttl_secs: 86400 }
+    }
+}
+
 impl AppConfig {
     /// Load configuration from environment variables and config file
     pub fn load() -> anyhow::Result<Self> {
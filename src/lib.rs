@@ -47,7 +47,12 @@ pub async fn run_server() -> anyhow::Result<()> {
 
     // Connect to PostgreSQL
     let database_url = config.database_url()?;
-    let postgres_store = crate::store::PostgresStore::new(&database_url).await?;
+    let mut postgres_store =
+        crate::store::PostgresStore::new(&database_url, &config.database).await?;
+    if let Some(key) = &config.encryption.key {
+        let cipher = crate::store::PropertyCipher::from_base64_key(key)?;
+        postgres_store = postgres_store.with_property_cipher(cipher);
+    }
 
     // Run migrations
     postgres_store.migrate().await?;
@@ -55,7 +60,17 @@ pub async fn run_server() -> anyhow::Result<()> {
     let store = Arc::new(postgres_store);
 
     // Create router with state
-    let app = crate::api::routes::create_router().with_state(store);
+    let app = crate::api::routes::create_router()
+        .layer(axum::Extension(config.expand.clone()))
+        .layer(axum::Extension(config.pagination.clone()))
+        .layer(axum::Extension(config.delete.clone()))
+        .layer(axum::Extension(Arc::new(crate::logic::CommitStatsCache::new())))
+        .layer(axum::Extension(Arc::new(
+            crate::api::idempotency::IdempotencyCache::new(std::time::Duration::from_secs(
+                config.idempotency.ttl_secs,
+            )),
+        )))
+        .with_state(store);
 
     let bind_address = config.server_address();
     let listener = TcpListener::bind(&bind_address).await?;
@@ -150,6 +165,10 @@ mod tests {
             created_at: chrono::Utc::now(),
             updated_by: "test".to_string(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
         };
 
         // Test 1: Number vs Number (should work)
@@ -213,6 +232,10 @@ mod tests {
             created_at: chrono::Utc::now(),
             updated_by: "test".to_string(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
         };
 
         let filter_mixed = FilterExpr::Lt {
@@ -291,6 +314,11 @@ mod tests {
             data_type: DataType::String,
             required: Some(true),
             value: None,
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
         };
         assert_eq!(
             prop_no_default.value, None,
@@ -304,6 +332,11 @@ mod tests {
             data_type: DataType::String,
             required: Some(false),
             value: Some(serde_json::json!("active")),
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
         };
         assert_eq!(
             prop_string_default.value,
@@ -318,6 +351,11 @@ mod tests {
             data_type: DataType::Number,
             required: Some(false),
             value: Some(serde_json::json!(0)),
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
         };
         assert_eq!(
             prop_number_default.value,
@@ -332,6 +370,11 @@ mod tests {
             data_type: DataType::Boolean,
             required: Some(false),
             value: Some(serde_json::json!(true)),
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
         };
         assert_eq!(
             prop_boolean_default.value,
@@ -377,6 +420,11 @@ mod tests {
                     data_type: DataType::String,
                     required: Some(true),
                     value: None, // No default value - must be provided
+                    encrypted: false,
+                    min: None,
+                    max: None,
+                    pattern: None,
+                    unique: None,
                 },
                 PropertyDef {
                     id: "prop-status".to_string(),
@@ -384,6 +432,11 @@ mod tests {
                     data_type: DataType::String,
                     required: Some(false),
                     value: Some(serde_json::json!("active")), // Constant/default value
+                    encrypted: false,
+                    min: None,
+                    max: None,
+                    pattern: None,
+                    unique: None,
                 },
                 PropertyDef {
                     id: "prop-priority".to_string(),
@@ -391,6 +444,11 @@ mod tests {
                     data_type: DataType::Number,
                     required: Some(false),
                     value: Some(serde_json::json!(1)), // Default priority
+                    encrypted: false,
+                    min: None,
+                    max: None,
+                    pattern: None,
+                    unique: None,
                 },
                 PropertyDef {
                     id: "prop-featured".to_string(),
@@ -398,12 +456,18 @@ mod tests {
                     data_type: DataType::Boolean,
                     required: Some(false),
                     value: Some(serde_json::json!(false)), // Default to not featured
+                    encrypted: false,
+                    min: None,
+                    max: None,
+                    pattern: None,
+                    unique: None,
                 },
             ],
             relationships: vec![],
             derived: vec![],
             domain_constraint: crate::model::Domain::binary(),
             base: crate::model::Base::default(),
+            constraints: Vec::new(),
             created_by: "test".to_string(),
             created_at: chrono::Utc::now(),
             updated_by: "test".to_string(),
@@ -502,6 +566,11 @@ mod tests {
             data_type: DataType::String,
             required: Some(false),
             value: Some(serde_json::json!("active")),
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
         };
 
         // Verify the new property retains its value
@@ -552,6 +621,10 @@ mod tests {
             created_at: chrono::Utc::now(),
             updated_by: "test".to_string(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
         };
 
         // Serialize the instance
@@ -752,6 +825,7 @@ mod tests {
             author: Some("test".to_string()),
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
+            version: 0,
             schema_data: Schema {
                 id: "schema-001".to_string(),
                 description: Some("Test schema".to_string()),
@@ -760,6 +834,7 @@ mod tests {
             instances_data: vec![],
             status: WorkingCommitStatus::Active,
             merge_state: None,
+            merge_metadata: None,
         };
 
         // Create an instance with PoolBased relationship
@@ -783,6 +858,10 @@ mod tests {
             created_at: chrono::Utc::now(),
             updated_by: "test".to_string(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
         };
 
         // Add instance to working commit (simulating the handler)
@@ -200,6 +200,10 @@ impl InstanceAnalyzer {
                 metadata: None,
             },
             user_metadata: None,
+            include_objective_breakdown: false,
+            timeout_ms: None,
+            objective: None,
+            constraint_checks: Vec::new(),
         };
 
         // Solve once with all objectives and interval-specific constraints
@@ -1,4 +1,4 @@
-use crate::logic::validate_simple::{SimpleValidator, ValidationResult};
+use crate::logic::validate_simple::{SimpleValidator, ValidationError, ValidationResult};
 use crate::model::{Branch, Id, Instance};
 use crate::store::traits::Store;
 use anyhow::{anyhow, Result};
@@ -38,6 +38,9 @@ pub struct MergeValidationResult {
     pub validation_result: Option<ValidationResult>,
     pub simulated_schema_valid: bool,
     pub affected_instances: Vec<String>,
+    /// Validation errors of the simulated merged state that the target branch doesn't have
+    #[serde(default)]
+    pub introduced_errors: Vec<ValidationError>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,6 +59,9 @@ pub struct RebaseValidationResult {
     pub validation_result: Option<ValidationResult>,
     pub needs_rebase: bool,
     pub affected_instances: Vec<String>,
+    /// Validation errors of the simulated rebased state that the target branch doesn't have
+    #[serde(default)]
+    pub introduced_errors: Vec<ValidationError>,
 }
 
 impl BranchOperations {
@@ -336,12 +342,13 @@ impl BranchOperations {
             validation_result: None,
             simulated_schema_valid: true,
             affected_instances: Vec::new(),
+            introduced_errors: Vec::new(),
         };
 
         // Create simulated merged schema (source takes precedence)
         let merged_schema = if let Some(source_schema) = source_schema {
             source_schema
-        } else if let Some(target_schema) = target_schema {
+        } else if let Some(target_schema) = target_schema.clone() {
             target_schema
         } else {
             result.simulated_schema_valid = false;
@@ -354,8 +361,8 @@ impl BranchOperations {
         let mut merged_instances = HashMap::new();
 
         // Add target instances first
-        for instance in target_instances {
-            merged_instances.insert(instance.id.clone(), instance);
+        for instance in &target_instances {
+            merged_instances.insert(instance.id.clone(), instance.clone());
         }
 
         // Add source instances (overriding any conflicts)
@@ -363,46 +370,31 @@ impl BranchOperations {
             merged_instances.insert(instance.id.clone(), instance);
         }
 
-        // Validate all instances against the merged schema
-        let mut validation_errors = Vec::new();
-        let mut validation_warnings = Vec::new();
-        let mut validated_instances = Vec::new();
-
-        for (instance_id, instance) in &merged_instances {
-            validated_instances.push(instance_id.clone());
-            result.affected_instances.push(instance_id.clone());
-
-            match SimpleValidator::validate_instance(store, instance, &merged_schema).await {
-                Ok(instance_validation) => {
-                    if !instance_validation.valid {
-                        result.can_merge = false;
-                        validation_errors.extend(instance_validation.errors);
-                        validation_warnings.extend(instance_validation.warnings);
-                    }
-                }
-                Err(e) => {
-                    result.can_merge = false;
-                    // Convert error to validation error format
-                    validation_errors.push(crate::logic::validate_simple::ValidationError {
-                        instance_id: instance_id.clone(),
-                        error_type:
-                            crate::logic::validate_simple::ValidationErrorType::InvalidValue,
-                        message: format!("Validation failed: {}", e),
-                        property_name: None,
-                        expected: None,
-                        actual: None,
-                    });
-                }
-            }
-        }
+        let mut merged_instances: Vec<Instance> = merged_instances.into_values().collect();
+        merged_instances.sort_by(|a, b| a.id.cmp(&b.id));
+        result.affected_instances = merged_instances.iter().map(|i| i.id.clone()).collect();
+
+        // Validate the merged state, including relationship resolution
+        let validation =
+            SimpleValidator::validate_instances(store, &merged_instances, &merged_schema).await?;
+        result.can_merge = validation.valid;
 
-        result.validation_result = Some(ValidationResult {
-            valid: validation_errors.is_empty(),
-            errors: validation_errors,
-            warnings: validation_warnings,
-            instance_count: merged_instances.len(),
-            validated_instances,
-        });
+        // Errors the target branch already has are not caused by the merge
+        let baseline = match &target_schema {
+            Some(target_schema) => {
+                SimpleValidator::validate_instances(store, &target_instances, target_schema)
+                    .await?
+            }
+            None => ValidationResult {
+                valid: true,
+                errors: Vec::new(),
+                warnings: Vec::new(),
+                instance_count: 0,
+                validated_instances: Vec::new(),
+            },
+        };
+        result.introduced_errors = SimpleValidator::introduced_errors(&baseline, &validation);
+        result.validation_result = Some(validation);
 
         Ok(result)
     }
@@ -440,6 +432,7 @@ impl BranchOperations {
                 validation_result: None,
                 simulated_schema_valid: false,
                 affected_instances: Vec::new(),
+                introduced_errors: Vec::new(),
             };
             return Ok(result);
         }
@@ -455,6 +448,7 @@ impl BranchOperations {
                 validation_result: None,
                 simulated_schema_valid: false,
                 affected_instances: Vec::new(),
+                introduced_errors: Vec::new(),
             };
             return Ok(result);
         }
@@ -671,6 +665,7 @@ impl BranchOperations {
                 validation_result: None,
                 needs_rebase: false,
                 affected_instances: Vec::new(),
+                introduced_errors: Vec::new(),
             };
             return Ok(result);
         }
@@ -686,6 +681,7 @@ impl BranchOperations {
                 validation_result: None,
                 needs_rebase: false,
                 affected_instances: Vec::new(),
+                introduced_errors: Vec::new(),
             };
             return Ok(result);
         }
@@ -712,6 +708,7 @@ impl BranchOperations {
             validation_result: validation_result.validation_result,
             needs_rebase,
             affected_instances: validation_result.affected_instances,
+            introduced_errors: validation_result.introduced_errors,
         })
     }
 }
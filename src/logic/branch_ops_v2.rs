@@ -3,10 +3,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::logic::merge::MergeEngine;
-use crate::logic::validate_simple::ValidationResult;
-use crate::model::merge::{ConflictResolution, MergeState};
+use crate::logic::validate_simple::{SimpleValidator, ValidationError, ValidationResult};
+use crate::model::merge::{ChangeOp, ConflictResolution, MergeMetadata, MergeOptions, MergeState};
 use crate::model::{
-    Id, NewWorkingCommit, WorkingCommit, WorkingCommitStatus,
+    Commit, CommitData, Id, NewCommit, NewWorkingCommit, WorkingCommit, WorkingCommitStatus,
 };
 use crate::store::traits::{BranchStore, CommitStore, Store, WorkingCommitStore};
 
@@ -17,6 +17,18 @@ pub struct MergeOperationResult {
     pub working_commit_id: Option<Id>,
     pub conflicts: Vec<crate::model::merge::MergeConflict>,
     pub message: String,
+    /// The target head was moved to the source head; no working commit was created
+    #[serde(default)]
+    pub fast_forward: bool,
+}
+
+/// A fast-forward refused because the target branch has an active working commit, which
+/// would be left based on a commit that is no longer the branch head
+#[derive(Debug, thiserror::Error)]
+#[error("Branch '{branch}' has an active working commit ({working_commit_id}); commit or discard it before fast-forwarding, or merge with no_ff")]
+pub struct FastForwardBlocked {
+    pub branch: String,
+    pub working_commit_id: Id,
 }
 
 /// Result of a merge validation
@@ -25,7 +37,22 @@ pub struct MergeValidationResult {
     pub can_merge: bool,
     pub common_ancestor: Option<String>,
     pub conflicts: Vec<crate::model::merge::MergeConflict>,
+    /// Validation of the prospective merged state (non-conflicting changes applied)
     pub validation_result: Option<ValidationResult>,
+    /// Validation errors of the merged state that the target branch head doesn't have
+    #[serde(default)]
+    pub introduced_errors: Vec<ValidationError>,
+}
+
+/// Result of reverting a commit
+#[derive(Debug)]
+pub struct RevertResult {
+    pub success: bool,
+    /// The new commit on the branch that undoes the target commit
+    pub commit: Option<Commit>,
+    /// Later changes on the branch that clash with undoing the target commit
+    pub conflicts: Vec<crate::model::merge::MergeConflict>,
+    pub message: String,
 }
 
 /// Request to resolve conflicts
@@ -46,13 +73,14 @@ impl BranchOperationsV2 {
         target_database_id: &Id,
         target_branch_name: &str,
         author: Option<String>,
+        options: MergeOptions,
     ) -> Result<MergeOperationResult> {
         // First check if there's already a merge in progress
         let existing_wcs = store
             .list_working_commits_for_branch(target_database_id, target_branch_name)
             .await?;
 
-        for wc in existing_wcs {
+        for wc in &existing_wcs {
             if wc.status == WorkingCommitStatus::Merging {
                 return Err(anyhow!(
                     "A merge is already in progress on branch '{}'. Please complete or abort it first.",
@@ -71,7 +99,7 @@ impl BranchOperationsV2 {
             .await?
             .ok_or_else(|| anyhow!("Source branch '{}' not found", source_branch_name))?;
 
-        let target_branch = store
+        let mut target_branch = store
             .get_branch(target_database_id, target_branch_name)
             .await?
             .ok_or_else(|| anyhow!("Target branch '{}' not found", target_branch_name))?;
@@ -85,18 +113,56 @@ impl BranchOperationsV2 {
             return Err(anyhow!("Target branch has no commits"));
         };
 
-        // Find common ancestor
-        let common_ancestor =
-            MergeEngine::find_common_ancestor(store, &target_commit, &source_commit)
+        // Nothing was committed on the target since the source diverged: move its head
+        if !options.no_ff
+            && store
+                .is_ancestor(target_database_id, &target_commit, &source_commit)
                 .await?
-                .ok_or_else(|| anyhow!("No common ancestor found between branches"))?;
+                .is_some()
+        {
+            if let Some(active) = existing_wcs
+                .iter()
+                .find(|wc| wc.status == WorkingCommitStatus::Active)
+            {
+                return Err(FastForwardBlocked {
+                    branch: target_branch_name.to_string(),
+                    working_commit_id: active.id.clone(),
+                }
+                .into());
+            }
+            target_branch.current_commit_hash = Some(source_commit.clone());
+            target_branch.commit_message = source_branch.commit_message;
+            target_branch.author = source_branch.author;
+            store.upsert_branch(target_branch).await?;
+            return Ok(MergeOperationResult {
+                success: true,
+                working_commit_id: None,
+                conflicts: Vec::new(),
+                message: format!(
+                    "Fast-forwarded '{}' to {}",
+                    target_branch_name, source_commit
+                ),
+                fast_forward: true,
+            });
+        }
+
+        // Find common ancestor
+        let common_ancestor = MergeEngine::find_common_ancestor(
+            store,
+            target_database_id,
+            &target_commit,
+            &source_commit,
+        )
+        .await?
+        .ok_or_else(|| anyhow!("No common ancestor found between branches"))?;
 
         // Perform three-way merge
-        let merge_result = MergeEngine::three_way_merge(
+        let merge_result = MergeEngine::three_way_merge_with_options(
             store,
             &common_ancestor,
             &target_commit, // left (current)
             &source_commit, // right (incoming)
+            &options,
         )
         .await?;
 
@@ -115,6 +181,12 @@ impl BranchOperationsV2 {
             author.clone(),
         )
         .map_err(|e| anyhow!("Failed to create working commit: {}", e))?;
+        working_commit.merge_metadata = Some(MergeMetadata {
+            source_branch: source_branch_name.to_string(),
+            source_commit_hash: source_commit.clone(),
+            target_commit_hash: target_commit.clone(),
+            strategy: options.strategy.clone(),
+        });
 
         // If there are conflicts, set up merge state
         if !merge_result.conflicts.is_empty() {
@@ -126,6 +198,7 @@ impl BranchOperationsV2 {
                 conflicts: merge_result.conflicts.clone(),
                 resolutions: HashMap::new(),
                 is_rebase: false,
+                options: options.clone(),
             });
 
             // Apply non-conflicting changes
@@ -205,6 +278,7 @@ impl BranchOperationsV2 {
                     conflicts_count
                 )
             },
+            fast_forward: false,
         })
     }
 
@@ -223,6 +297,7 @@ impl BranchOperationsV2 {
                 common_ancestor: None,
                 conflicts: vec![],
                 validation_result: None,
+                introduced_errors: Vec::new(),
             });
         }
 
@@ -244,6 +319,7 @@ impl BranchOperationsV2 {
                 common_ancestor: None,
                 conflicts: vec![],
                 validation_result: None,
+                introduced_errors: Vec::new(),
             });
         };
 
@@ -253,12 +329,18 @@ impl BranchOperationsV2 {
                 common_ancestor: None,
                 conflicts: vec![],
                 validation_result: None,
+                introduced_errors: Vec::new(),
             });
         };
 
         // Find common ancestor
-        let common_ancestor =
-            MergeEngine::find_common_ancestor(store, &target_commit, &source_commit).await?;
+        let common_ancestor = MergeEngine::find_common_ancestor(
+            store,
+            target_database_id,
+            &target_commit,
+            &source_commit,
+        )
+        .await?;
 
         if common_ancestor.is_none() {
             return Ok(MergeValidationResult {
@@ -266,6 +348,7 @@ impl BranchOperationsV2 {
                 common_ancestor: None,
                 conflicts: vec![],
                 validation_result: None,
+                introduced_errors: Vec::new(),
             });
         }
 
@@ -275,20 +358,36 @@ impl BranchOperationsV2 {
         let merge_result =
             MergeEngine::three_way_merge(store, &ancestor, &target_commit, &source_commit).await?;
 
-        // If no conflicts, validate the merged result
-        let validation_result = if merge_result.conflicts.is_empty() {
-            // TODO: Implement validation of merged data
-            // For now, skip validation
-            None
-        } else {
-            None
-        };
+        // Validate the state the merge working commit would start from, the same way
+        // `start_merge` builds it: base plus all non-conflicting changes
+        let base_data = store
+            .get_commit(&ancestor)
+            .await?
+            .ok_or_else(|| anyhow!("Common ancestor commit not found"))?
+            .get_data()
+            .map_err(|e| anyhow!("Failed to get commit data: {}", e))?;
+        let merged_data = MergeEngine::apply_merge_result(&base_data, &merge_result)?;
+        let validation_result =
+            SimpleValidator::validate_instances(store, &merged_data.instances, &merged_data.schema)
+                .await?;
+
+        let target_data = store
+            .get_commit(&target_commit)
+            .await?
+            .ok_or_else(|| anyhow!("Target commit not found"))?
+            .get_data()
+            .map_err(|e| anyhow!("Failed to get commit data: {}", e))?;
+        let baseline =
+            SimpleValidator::validate_instances(store, &target_data.instances, &target_data.schema)
+                .await?;
+        let introduced_errors = SimpleValidator::introduced_errors(&baseline, &validation_result);
 
         Ok(MergeValidationResult {
             can_merge: merge_result.conflicts.is_empty(),
             common_ancestor: Some(ancestor),
             conflicts: merge_result.conflicts,
-            validation_result,
+            validation_result: Some(validation_result),
+            introduced_errors,
         })
     }
 
@@ -314,6 +413,22 @@ impl BranchOperationsV2 {
             .as_mut()
             .ok_or_else(|| anyhow!("Working commit has no merge state"))?;
 
+        for (index, resolution) in &resolutions {
+            let conflict = merge_state
+                .conflicts
+                .get(*index)
+                .ok_or_else(|| anyhow!("Conflict {} does not exist", index))?;
+            if *resolution == ConflictResolution::Union {
+                let (left_op, right_op) = Self::conflict_ops(conflict)?;
+                if MergeEngine::union_relationship_patches(&left_op, &right_op).is_none() {
+                    return Err(anyhow!(
+                        "Conflict {} can't be resolved with union: only conflicts between id-list relationship selections can",
+                        index
+                    ));
+                }
+            }
+        }
+
         // Apply resolutions
         merge_state.resolutions.extend(resolutions);
 
@@ -358,6 +473,7 @@ impl BranchOperationsV2 {
             right_diff,
             &merge_state.conflicts,
             &merge_state.resolutions,
+            &merge_state.options,
         )?;
 
         let merged_data = MergeEngine::apply_merge_result(&base_data, &resolved_merge)?;
@@ -377,26 +493,53 @@ impl BranchOperationsV2 {
     fn apply_resolutions_to_merge(
         left_diff: crate::model::merge::CommitDiff,
         right_diff: crate::model::merge::CommitDiff,
-        _conflicts: &[crate::model::merge::MergeConflict],
-        _resolutions: &HashMap<usize, ConflictResolution>,
+        conflicts: &[crate::model::merge::MergeConflict],
+        resolutions: &HashMap<usize, ConflictResolution>,
+        options: &MergeOptions,
     ) -> Result<crate::model::merge::MergeResult> {
-        // This is a simplified implementation
-        // In a real system, you'd need to carefully apply each resolution
+        // Non-conflicting operations; conflicting ones are left out
+        let base_merge = MergeEngine::merge_diffs_with_options(left_diff, right_diff, options);
+        let mut merged_operations = base_merge.merged_operations;
 
-        // For now, just merge non-conflicting operations
-        let base_merge = MergeEngine::merge_diffs(left_diff, right_diff);
-
-        // Apply resolutions to override conflicts
-        // TODO: Implement proper resolution application
+        // Conflicts are resolved from their recorded operations, so they don't depend on the
+        // order the diffs are recomputed in
+        for (index, conflict) in conflicts.iter().enumerate() {
+            let (left_op, right_op) = Self::conflict_ops(conflict)?;
+            let resolved_op = match resolutions.get(&index) {
+                Some(ConflictResolution::UseLeft) => left_op,
+                Some(ConflictResolution::UseRight) => right_op,
+                Some(ConflictResolution::Union) => {
+                    MergeEngine::union_relationship_patches(&left_op, &right_op)
+                        .ok_or_else(|| anyhow!("Conflict {} can't be resolved with union", index))?
+                }
+                // Skip drops both sides; custom values are not applied
+                _ => continue,
+            };
+            merged_operations.push(resolved_op);
+        }
 
         Ok(crate::model::merge::MergeResult {
             success: true,
             conflicts: vec![], // All resolved
-            merged_operations: base_merge.merged_operations,
+            merged_operations,
             needs_validation: true,
         })
     }
 
+    /// The left and right operations recorded in a merge conflict
+    fn conflict_ops(conflict: &crate::model::merge::MergeConflict) -> Result<(ChangeOp, ChangeOp)> {
+        let op = |value: &Option<serde_json::Value>| -> Result<ChangeOp> {
+            let value = value.clone().ok_or_else(|| {
+                anyhow!(
+                    "Conflict on '{}' has no recorded change",
+                    conflict.resource_id
+                )
+            })?;
+            Ok(serde_json::from_value(value)?)
+        };
+        Ok((op(&conflict.left_value)?, op(&conflict.right_value)?))
+    }
+
     /// Abort a merge operation
     pub async fn abort_merge<S: WorkingCommitStore>(
         store: &S,
@@ -416,4 +559,534 @@ impl BranchOperationsV2 {
 
         Ok(())
     }
+
+    /// Revert a commit: add a commit on top of the branch head that undoes the changes the
+    /// target commit introduced relative to its parent, without rewriting history.
+    ///
+    /// This is a three-way merge with the target commit as base, the branch head as one side
+    /// and the target's parent as the other, so later edits to unrelated fields survive.
+    /// When later edits touch what the target changed, nothing is committed and the
+    /// conflicts are returned. The message, given or defaulted, must satisfy the database's
+    /// commit message policy like any other commit.
+    pub async fn revert_commit<S: Store + CommitStore + WorkingCommitStore + BranchStore>(
+        store: &S,
+        database_id: &Id,
+        branch_name: &str,
+        commit_hash: &str,
+        author: Option<String>,
+        message: Option<String>,
+    ) -> Result<RevertResult> {
+        let mut branch = store
+            .get_branch(database_id, branch_name)
+            .await?
+            .ok_or_else(|| anyhow!("Branch '{}' not found", branch_name))?;
+        let head_hash = branch
+            .current_commit_hash
+            .clone()
+            .ok_or_else(|| anyhow!("Branch '{}' has no commits", branch_name))?;
+
+        let target = store
+            .get_commit(commit_hash)
+            .await?
+            .filter(|commit| &commit.database_id == database_id)
+            .ok_or_else(|| anyhow!("Commit '{}' not found", commit_hash))?;
+        if store
+            .is_ancestor(database_id, commit_hash, &head_hash)
+            .await?
+            .is_none()
+        {
+            return Err(anyhow!(
+                "Commit '{}' is not in the history of branch '{}'",
+                commit_hash,
+                branch_name
+            ));
+        }
+
+        let message = message.unwrap_or_else(|| {
+            format!(
+                "Revert \"{}\"\n\nThis reverts commit {}.",
+                target.message.as_deref().unwrap_or_default(),
+                commit_hash
+            )
+        });
+        let database = store
+            .get_database(database_id)
+            .await?
+            .ok_or_else(|| anyhow!("Database '{}' not found", database_id))?;
+        if let Some(policy) = &database.commit_message_policy {
+            policy.check(&message).map_err(|e| anyhow!(e))?;
+        }
+
+        let load = |commit: &Commit| {
+            commit
+                .get_data()
+                .map_err(|e| anyhow!("Failed to get commit data: {}", e))
+        };
+        let target_data = load(&target)?;
+        let parent_data = match &target.parent_hash {
+            Some(parent_hash) => load(
+                &store
+                    .get_commit(parent_hash)
+                    .await?
+                    .ok_or_else(|| anyhow!("Parent commit '{}' not found", parent_hash))?,
+            )?,
+            // Reverting the root commit removes everything it introduced
+            None => {
+                let mut schema = target_data.schema.clone();
+                schema.classes.clear();
+                CommitData {
+                    schema,
+                    instances: Vec::new(),
+                }
+            }
+        };
+        let head_data = store
+            .get_commit_data(&head_hash)
+            .await?
+            .ok_or_else(|| anyhow!("Branch head commit '{}' not found", head_hash))?;
+
+        let merge_result = MergeEngine::merge_diffs(
+            MergeEngine::compute_diff(&target_data, &head_data)?,
+            MergeEngine::compute_diff(&target_data, &parent_data)?,
+        );
+        if !merge_result.conflicts.is_empty() {
+            return Ok(RevertResult {
+                success: false,
+                commit: None,
+                message: format!(
+                    "Reverting {} conflicts with {} later change(s) on branch '{}'",
+                    commit_hash,
+                    merge_result.conflicts.len(),
+                    branch_name
+                ),
+                conflicts: merge_result.conflicts,
+            });
+        }
+        let reverted = MergeEngine::apply_merge_result(&target_data, &merge_result)?;
+
+        // Stage on the branch's working commit, which must not carry unrelated edits
+        let mut working_commit = match store
+            .get_active_working_commit_for_branch(database_id, branch_name)
+            .await?
+        {
+            Some(wc) => {
+                let untouched = wc.status == WorkingCommitStatus::Active
+                    && wc.based_on_hash == head_hash
+                    && wc.schema_data == head_data.schema
+                    && wc.instances_data == head_data.instances;
+                if !untouched {
+                    return Err(anyhow!(
+                        "Branch '{}' has uncommitted changes; commit or abandon them first",
+                        branch_name
+                    ));
+                }
+                wc
+            }
+            None => {
+                store
+                    .create_working_commit(
+                        database_id,
+                        branch_name,
+                        NewWorkingCommit {
+                            author: author.clone(),
+                        },
+                    )
+                    .await?
+            }
+        };
+        working_commit.schema_data = reverted.schema;
+        working_commit.instances_data = reverted.instances;
+        store.update_working_commit(working_commit.clone()).await?;
+
+        let commit = store
+            .create_commit(NewCommit {
+                database_id: database_id.clone(),
+                working_commit_id: working_commit.id.clone(),
+                message,
+                author: author.or(working_commit.author),
+                based_on_hash: None,
+            })
+            .await?;
+
+        branch.current_commit_hash = Some(commit.hash.clone());
+        branch.commit_message = commit.message.clone();
+        branch.author = commit.author.clone();
+        store.upsert_branch(branch).await?;
+        store.delete_working_commit(&working_commit.id).await?;
+
+        Ok(RevertResult {
+            success: true,
+            message: format!("Reverted commit {}", commit_hash),
+            commit: Some(commit),
+            conflicts: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::merge::RelationshipMergeStrategy;
+    use crate::model::{
+        Branch, ClassDef, CommitMessagePolicy, DataType, Database, Instance, PropertyValue,
+        RelationshipSelection, TypedValue,
+    };
+    use crate::store::traits::DatabaseStore;
+    use crate::store::InMemoryStore;
+    use serde_json::json;
+
+    fn price(instance: &Instance) -> Option<f64> {
+        match instance.properties.get("price") {
+            Some(PropertyValue::Literal(literal)) => literal.value.as_f64(),
+            _ => None,
+        }
+    }
+
+    fn set_price(instance: &mut Instance, value: f64) {
+        instance.properties.insert(
+            "price".to_string(),
+            PropertyValue::Literal(TypedValue {
+                value: json!(value),
+                data_type: DataType::Number,
+            }),
+        );
+    }
+
+    fn part(id: &str, value: f64) -> Instance {
+        let mut instance = Instance {
+            id: id.to_string(),
+            class_id: "class-part".to_string(),
+            ..Instance::default()
+        };
+        set_price(&mut instance, value);
+        instance
+    }
+
+    async fn setup() -> (InMemoryStore, Database) {
+        let store = InMemoryStore::new();
+        let database = Database::new("revert".to_string(), None);
+        store.upsert_database(database.clone()).await.unwrap();
+        store
+            .upsert_branch(Branch::new_main_branch(database.id.clone(), None))
+            .await
+            .unwrap();
+        commit(&store, &database, "base", |wc| {
+            wc.schema_data.classes.push(ClassDef {
+                id: "class-part".to_string(),
+                name: "Part".to_string(),
+                ..ClassDef::default()
+            });
+            wc.instances_data.push(part("part-a", 10.0));
+        })
+        .await;
+        (store, database)
+    }
+
+    async fn commit(
+        store: &InMemoryStore,
+        database: &Database,
+        message: &str,
+        change: impl FnOnce(&mut WorkingCommit),
+    ) -> Commit {
+        commit_on(store, database, "main", message, change).await
+    }
+
+    async fn commit_on(
+        store: &InMemoryStore,
+        database: &Database,
+        branch: &str,
+        message: &str,
+        change: impl FnOnce(&mut WorkingCommit),
+    ) -> Commit {
+        let mut wc = store
+            .create_working_commit(&database.id, branch, NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        change(&mut wc);
+        store.update_working_commit(wc.clone()).await.unwrap();
+        let commit = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc.id.clone(),
+                message: message.to_string(),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+        store.delete_working_commit(&wc.id).await.unwrap();
+        let mut head = store
+            .get_branch(&database.id, branch)
+            .await
+            .unwrap()
+            .unwrap();
+        head.current_commit_hash = Some(commit.hash.clone());
+        store.upsert_branch(head).await.unwrap();
+        commit
+    }
+
+    async fn head_data(store: &InMemoryStore, database: &Database) -> CommitData {
+        let main = store.get_branch(&database.id, "main").await.unwrap().unwrap();
+        store
+            .get_commit_data(&main.current_commit_hash.unwrap())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_revert_instance_addition() {
+        let (store, database) = setup().await;
+        let added = commit(&store, &database, "add part-b", |wc| {
+            wc.instances_data.push(part("part-b", 20.0));
+        })
+        .await;
+        // An unrelated later change survives the revert
+        commit(&store, &database, "add part-c", |wc| {
+            wc.instances_data.push(part("part-c", 30.0));
+        })
+        .await;
+
+        let result =
+            BranchOperationsV2::revert_commit(&store, &database.id, "main", &added.hash, None, None)
+                .await
+                .unwrap();
+        assert!(result.success);
+        let revert = result.commit.unwrap();
+        assert!(revert.message.as_deref().unwrap().starts_with("Revert \"add part-b\""));
+
+        let data = head_data(&store, &database).await;
+        let mut ids: Vec<&str> = data.instances.iter().map(|i| i.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["part-a", "part-c"]);
+
+        // History is kept: the reverted commit is still an ancestor of the new head
+        assert!(store
+            .is_ancestor(&database.id, &added.hash, &revert.hash)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_revert_property_change() {
+        let (store, database) = setup().await;
+        let changed = commit(&store, &database, "raise price", |wc| {
+            set_price(&mut wc.instances_data[0], 15.0);
+        })
+        .await;
+
+        let result = BranchOperationsV2::revert_commit(
+            &store,
+            &database.id,
+            "main",
+            &changed.hash,
+            None,
+            Some("undo price change".to_string()),
+        )
+        .await
+        .unwrap();
+        assert!(result.success);
+        assert_eq!(
+            result.commit.unwrap().message.as_deref(),
+            Some("undo price change")
+        );
+
+        let data = head_data(&store, &database).await;
+        assert_eq!(price(&data.instances[0]), Some(10.0));
+    }
+
+    #[tokio::test]
+    async fn test_revert_follows_commit_message_policy() {
+        let (store, mut database) = setup().await;
+        let changed = commit(&store, &database, "raise price", |wc| {
+            set_price(&mut wc.instances_data[0], 15.0);
+        })
+        .await;
+        database.commit_message_policy = Some(CommitMessagePolicy {
+            pattern: None,
+            required_prefixes: vec!["fix:".to_string()],
+        });
+        store.upsert_database(database.clone()).await.unwrap();
+
+        // The default `Revert "..."` message doesn't carry the prefix
+        let error = BranchOperationsV2::revert_commit(
+            &store,
+            &database.id,
+            "main",
+            &changed.hash,
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("fix:"));
+        let main = store.get_branch(&database.id, "main").await.unwrap().unwrap();
+        assert_eq!(main.current_commit_hash, Some(changed.hash.clone()));
+
+        let result = BranchOperationsV2::revert_commit(
+            &store,
+            &database.id,
+            "main",
+            &changed.hash,
+            None,
+            Some("fix: undo price change".to_string()),
+        )
+        .await
+        .unwrap();
+        assert!(result.success);
+    }
+
+    #[tokio::test]
+    async fn test_revert_conflicting_with_later_edit() {
+        let (store, database) = setup().await;
+        let changed = commit(&store, &database, "raise price", |wc| {
+            set_price(&mut wc.instances_data[0], 15.0);
+        })
+        .await;
+        let later = commit(&store, &database, "raise again", |wc| {
+            set_price(&mut wc.instances_data[0], 20.0);
+        })
+        .await;
+
+        let result = BranchOperationsV2::revert_commit(
+            &store,
+            &database.id,
+            "main",
+            &changed.hash,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(!result.success);
+        assert!(result.commit.is_none());
+        assert!(!result.conflicts.is_empty());
+        assert_eq!(result.conflicts[0].resource_id, "part-a");
+
+        // Nothing was committed
+        let main = store.get_branch(&database.id, "main").await.unwrap().unwrap();
+        assert_eq!(main.current_commit_hash, Some(later.hash));
+    }
+
+    /// Starts a merge of `feature` into `main` after both edited part-a, with concurrent
+    /// relationship edits left as conflicts
+    async fn conflicting_merge(
+        store: &InMemoryStore,
+        database: &Database,
+        main_change: impl FnOnce(&mut Instance),
+        feature_change: impl FnOnce(&mut Instance),
+    ) -> Id {
+        let base = commit(store, database, "colors", |wc| {
+            wc.instances_data[0].relationships.insert(
+                "colors".to_string(),
+                RelationshipSelection::SimpleIds(vec!["red".to_string()]),
+            );
+        })
+        .await;
+        store
+            .upsert_branch(Branch {
+                name: "feature".to_string(),
+                current_commit_hash: Some(base.hash),
+                ..Branch::new_main_branch(database.id.clone(), None)
+            })
+            .await
+            .unwrap();
+        commit(store, database, "main edit", |wc| {
+            main_change(&mut wc.instances_data[0])
+        })
+        .await;
+        commit_on(store, database, "feature", "feature edit", |wc| {
+            feature_change(&mut wc.instances_data[0])
+        })
+        .await;
+
+        let result = BranchOperationsV2::start_merge(
+            store,
+            &database.id,
+            "feature",
+            &database.id,
+            "main",
+            None,
+            MergeOptions {
+                relationship_merge: RelationshipMergeStrategy::Conflict,
+                ..MergeOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.conflicts.len(), 1);
+        result.working_commit_id.unwrap()
+    }
+
+    fn set_colors(instance: &mut Instance, colors: &[&str]) {
+        instance.relationships.insert(
+            "colors".to_string(),
+            RelationshipSelection::SimpleIds(colors.iter().map(|c| c.to_string()).collect()),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_union_resolution_keeps_both_sides_ids() {
+        let (store, database) = setup().await;
+        let wc_id = conflicting_merge(
+            &store,
+            &database,
+            |part| set_colors(part, &["red", "blue"]),
+            |part| set_colors(part, &["red", "green"]),
+        )
+        .await;
+
+        BranchOperationsV2::resolve_conflicts(
+            &store,
+            &wc_id,
+            HashMap::from([(0, ConflictResolution::Union)]),
+        )
+        .await
+        .unwrap();
+
+        let wc = store.get_working_commit(&wc_id).await.unwrap().unwrap();
+        assert_eq!(wc.status, WorkingCommitStatus::Active);
+        assert_eq!(
+            wc.instances_data[0].relationships["colors"],
+            RelationshipSelection::SimpleIds(vec![
+                "red".to_string(),
+                "blue".to_string(),
+                "green".to_string()
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_union_resolution_rejected_for_property_conflict() {
+        let (store, database) = setup().await;
+        let wc_id = conflicting_merge(
+            &store,
+            &database,
+            |part| set_price(part, 12.0),
+            |part| set_price(part, 15.0),
+        )
+        .await;
+
+        let union = BranchOperationsV2::resolve_conflicts(
+            &store,
+            &wc_id,
+            HashMap::from([(0, ConflictResolution::Union)]),
+        )
+        .await;
+        assert!(union.is_err());
+        // Nothing was recorded; picking a side still works
+        let wc = store.get_working_commit(&wc_id).await.unwrap().unwrap();
+        assert!(wc.merge_state.unwrap().resolutions.is_empty());
+
+        BranchOperationsV2::resolve_conflicts(
+            &store,
+            &wc_id,
+            HashMap::from([(0, ConflictResolution::UseRight)]),
+        )
+        .await
+        .unwrap();
+        let wc = store.get_working_commit(&wc_id).await.unwrap().unwrap();
+        assert_eq!(price(&wc.instances_data[0]), Some(15.0));
+    }
 }
@@ -0,0 +1,196 @@
+use crate::logic::Expander;
+use crate::model::{BackReference, Id, Instance};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Outcome of planning the deletion of instances from a branch's instance set
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeletionPlan {
+    /// Instances to remove, the requested instances first and cascaded ones after them
+    Delete(Vec<Id>),
+    /// Requested instances still referenced from outside the set, with their referrers, when
+    /// cascading was not requested
+    Referenced(BTreeMap<Id, Vec<BackReference>>),
+}
+
+/// Work out which instances deleting `target_ids` removes.
+///
+/// Without `cascade` the targets are deleted alone, unless instances outside the set still
+/// point at them. With `cascade` their relationships are followed up to `max_depth` levels and
+/// every instance referenced only by instances already being deleted goes too, so anything
+/// still referenced from elsewhere survives. As on expand, only explicit relationship
+/// selections count as references; schema default pools don't.
+pub async fn plan_deletion(
+    target_ids: &[Id],
+    instances: &[Instance],
+    cascade: bool,
+    max_depth: usize,
+) -> DeletionPlan {
+    if !cascade {
+        let mut referenced_by = BTreeMap::new();
+        for target_id in target_ids {
+            let referrers: Vec<BackReference> =
+                Expander::resolve_inverse_relationships(target_id, instances)
+                    .await
+                    .into_iter()
+                    .filter(|r| !target_ids.contains(&r.instance_id))
+                    .collect();
+            if !referrers.is_empty() {
+                referenced_by.insert(target_id.clone(), referrers);
+            }
+        }
+        if !referenced_by.is_empty() {
+            return DeletionPlan::Referenced(referenced_by);
+        }
+        return DeletionPlan::Delete(target_ids.to_vec());
+    }
+
+    // Forward edges and their inverse, self-references left out
+    let mut children: HashMap<&str, Vec<Id>> = HashMap::new();
+    let mut referrers: HashMap<Id, HashSet<&str>> = HashMap::new();
+    for instance in instances {
+        for selection in instance.relationships.values() {
+            let Ok(resolved) =
+                Expander::resolve_selection_enhanced_with_branch(instances, selection).await
+            else {
+                continue;
+            };
+            for child in resolved.materialized_ids {
+                if child == instance.id {
+                    continue;
+                }
+                referrers
+                    .entry(child.clone())
+                    .or_default()
+                    .insert(instance.id.as_str());
+                children
+                    .entry(instance.id.as_str())
+                    .or_default()
+                    .push(child);
+            }
+        }
+    }
+
+    let mut deleted: Vec<(Id, usize)> = target_ids.iter().map(|id| (id.clone(), 0)).collect();
+    let mut deleted_ids: HashSet<Id> = target_ids.iter().cloned().collect();
+    // Repeat until stable: a child shared by two deleted siblings only becomes orphaned once
+    // both are in the set
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let mut index = 0;
+        while index < deleted.len() {
+            let (parent, depth) = deleted[index].clone();
+            index += 1;
+            if depth >= max_depth {
+                continue;
+            }
+            for child in children.get(parent.as_str()).into_iter().flatten() {
+                if deleted_ids.contains(child) || !instances.iter().any(|i| &i.id == child) {
+                    continue;
+                }
+                let orphaned = referrers
+                    .get(child)
+                    .is_none_or(|r| r.iter().all(|id| deleted_ids.contains(*id)));
+                if orphaned {
+                    deleted_ids.insert(child.clone());
+                    deleted.push((child.clone(), depth + 1));
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    DeletionPlan::Delete(deleted.into_iter().map(|(id, _)| id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::RelationshipSelection;
+
+    fn instance(id: &str, children: &[&str]) -> Instance {
+        let mut instance = Instance {
+            id: id.to_string(),
+            class_id: "class-node".to_string(),
+            ..Instance::default()
+        };
+        if !children.is_empty() {
+            instance.relationships.insert(
+                "children".to_string(),
+                RelationshipSelection::SimpleIds(children.iter().map(|c| c.to_string()).collect()),
+            );
+        }
+        instance
+    }
+
+    #[tokio::test]
+    async fn test_cascade_removes_two_level_chain() {
+        let instances = vec![
+            instance("table", &["leg"]),
+            instance("leg", &["screw"]),
+            instance("screw", &[]),
+            instance("lamp", &[]),
+        ];
+        let plan = plan_deletion(&["table".to_string()], &instances, true, 10).await;
+        assert_eq!(
+            plan,
+            DeletionPlan::Delete(vec![
+                "table".to_string(),
+                "leg".to_string(),
+                "screw".to_string()
+            ])
+        );
+
+        // The depth limit stops the cascade after the first level
+        let plan = plan_deletion(&["table".to_string()], &instances, true, 1).await;
+        assert_eq!(
+            plan,
+            DeletionPlan::Delete(vec!["table".to_string(), "leg".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_without_cascade_reports_referrers() {
+        let instances = vec![
+            instance("table", &["leg"]),
+            instance("shelf", &["leg"]),
+            instance("leg", &[]),
+        ];
+        let DeletionPlan::Referenced(mut referenced) =
+            plan_deletion(&["leg".to_string()], &instances, false, 10).await
+        else {
+            panic!("expected the referenced instance to be refused");
+        };
+        let referenced_by = referenced.remove("leg").unwrap();
+        assert!(referenced.is_empty());
+        let referrers: Vec<&str> = referenced_by
+            .iter()
+            .map(|r| r.instance_id.as_str())
+            .collect();
+        assert_eq!(referrers, vec!["table", "shelf"]);
+        assert!(referenced_by.iter().all(|r| r.relationship == "children"));
+
+        // Nothing points at the table, so it goes on its own
+        assert_eq!(
+            plan_deletion(&["table".to_string()], &instances, false, 10).await,
+            DeletionPlan::Delete(vec!["table".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cascade_keeps_instances_referenced_elsewhere() {
+        let instances = vec![
+            instance("table", &["leg", "top"]),
+            instance("top", &["screw"]),
+            instance("shelf", &["leg"]),
+            instance("leg", &["screw"]),
+            instance("screw", &[]),
+        ];
+        // The leg is shared with the shelf, and the screw is held by the surviving leg
+        let plan = plan_deletion(&["table".to_string()], &instances, true, 10).await;
+        assert_eq!(
+            plan,
+            DeletionPlan::Delete(vec!["table".to_string(), "top".to_string()])
+        );
+    }
+}
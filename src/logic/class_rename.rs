@@ -0,0 +1,292 @@
+//! Renaming a schema class together with everything that refers to it by ID
+//!
+//! Instances name their class in `class_id`, relationships name the classes they can point
+//! at in `targets` (and, for `all`, the class they must cover in `universe`), and filters (default pools and instance relationship selections) name
+//! them in `type`. A rename rewrites all of them so no instance is left orphaned.
+
+use serde::Serialize;
+
+use crate::model::{
+    DefaultPool, Id, Instance, InstanceFilter, RelationshipSelection, Schema, SelectionSpec,
+};
+
+/// Why a class can't be renamed
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ClassRenameError {
+    #[error("Class '{0}' not found")]
+    NotFound(Id),
+    #[error("Class '{0}' already exists")]
+    Collision(Id),
+    #[error("New class ID must not be empty")]
+    EmptyId,
+}
+
+/// What a rename rewrote besides the class itself
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ClassRenameSummary {
+    /// Instances whose `class_id` now names the new ID
+    pub instances_updated: usize,
+    /// Relationship definitions whose `targets`, `universe` or default pool named the old ID
+    pub relationships_updated: usize,
+    /// Instances with a relationship selection filtering on the old ID
+    pub selections_updated: usize,
+}
+
+/// Rename class `old_id` to `new_id` in `schema` and rewrite every reference to it in the
+/// schema and in `instances`
+pub fn rename_class(
+    schema: &mut Schema,
+    instances: &mut [Instance],
+    old_id: &str,
+    new_id: &str,
+) -> Result<ClassRenameSummary, ClassRenameError> {
+    if new_id.trim().is_empty() {
+        return Err(ClassRenameError::EmptyId);
+    }
+    if !schema.classes.iter().any(|class| class.id == old_id) {
+        return Err(ClassRenameError::NotFound(old_id.to_string()));
+    }
+    if old_id != new_id && schema.classes.iter().any(|class| class.id == new_id) {
+        return Err(ClassRenameError::Collision(new_id.to_string()));
+    }
+
+    let mut summary = ClassRenameSummary::default();
+    for class in &mut schema.classes {
+        if class.id == old_id {
+            class.id = new_id.to_string();
+        }
+        for relationship in &mut class.relationships {
+            let mut changed = rename_in_types(&mut relationship.targets, old_id, new_id);
+            if let Some(universe) = relationship.universe.as_mut().filter(|u| *u == old_id) {
+                *universe = new_id.to_string();
+                changed = true;
+            }
+            if let DefaultPool::Filter { types, filter } = &mut relationship.default_pool {
+                if let Some(types) = types {
+                    changed |= rename_in_types(types, old_id, new_id);
+                }
+                if let Some(filter) = filter {
+                    changed |= rename_in_filter(filter, old_id, new_id);
+                }
+            }
+            if changed {
+                summary.relationships_updated += 1;
+            }
+        }
+    }
+
+    for instance in instances {
+        if instance.class_id == old_id {
+            instance.class_id = new_id.to_string();
+            summary.instances_updated += 1;
+        }
+        let mut changed = false;
+        for selection in instance.relationships.values_mut() {
+            changed |= rename_in_selection(selection, old_id, new_id);
+        }
+        if changed {
+            summary.selections_updated += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+fn rename_in_types(types: &mut [String], old_id: &str, new_id: &str) -> bool {
+    let mut changed = false;
+    for class_id in types.iter_mut().filter(|class_id| *class_id == old_id) {
+        *class_id = new_id.to_string();
+        changed = true;
+    }
+    changed
+}
+
+fn rename_in_filter(filter: &mut InstanceFilter, old_id: &str, new_id: &str) -> bool {
+    filter
+        .types
+        .as_mut()
+        .is_some_and(|types| rename_in_types(types, old_id, new_id))
+}
+
+fn rename_in_selection(selection: &mut RelationshipSelection, old_id: &str, new_id: &str) -> bool {
+    match selection {
+        RelationshipSelection::Filter { filter } => rename_in_filter(filter, old_id, new_id),
+        RelationshipSelection::PoolBased { pool, selection } => {
+            let pool_changed = pool
+                .as_mut()
+                .is_some_and(|pool| rename_in_filter(pool, old_id, new_id));
+            let selection_changed = match selection {
+                Some(SelectionSpec::Filter(filter)) => rename_in_filter(filter, old_id, new_id),
+                _ => false,
+            };
+            pool_changed || selection_changed
+        }
+        RelationshipSelection::SimpleIds(_)
+        | RelationshipSelection::Ids { .. }
+        | RelationshipSelection::All => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ClassDef, Quantifier, RelationshipDef, SelectionType};
+
+    fn relationship(id: &str, targets: &[&str], default_pool: DefaultPool) -> RelationshipDef {
+        RelationshipDef {
+            id: id.to_string(),
+            name: id.to_string(),
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            quantifier: Quantifier::Any,
+            universe: None,
+            selection: SelectionType::ExplicitOrFilter,
+            default_pool,
+            inverse_of: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    fn schema() -> Schema {
+        let class = |id: &str, relationships: Vec<RelationshipDef>| ClassDef {
+            id: id.to_string(),
+            name: id.to_string(),
+            relationships,
+            ..ClassDef::default()
+        };
+        Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![
+                class("class-part", vec![]),
+                class("class-color", vec![]),
+                class(
+                    "class-bundle",
+                    vec![
+                        relationship("parts", &["class-part", "class-color"], DefaultPool::All),
+                        relationship(
+                            "cheap_parts",
+                            &["class-color"],
+                            DefaultPool::Filter {
+                                types: Some(vec!["class-part".to_string()]),
+                                filter: None,
+                            },
+                        ),
+                        relationship("colors", &["class-color"], DefaultPool::All),
+                        RelationshipDef {
+                            quantifier: Quantifier::All,
+                            universe: Some("class-part".to_string()),
+                            ..relationship("all_parts", &[], DefaultPool::All)
+                        },
+                    ],
+                ),
+            ],
+        }
+    }
+
+    fn instance(id: &str, class_id: &str) -> Instance {
+        Instance {
+            id: id.to_string(),
+            class_id: class_id.to_string(),
+            ..Instance::default()
+        }
+    }
+
+    #[test]
+    fn test_rename_rewrites_instances_and_targets() {
+        let mut schema = schema();
+        let mut bundle = instance("bundle-1", "class-bundle");
+        bundle.relationships.insert(
+            "parts".to_string(),
+            RelationshipSelection::Filter {
+                filter: InstanceFilter {
+                    types: Some(vec!["class-part".to_string()]),
+                    where_clause: None,
+                    sort: None,
+                    limit: None,
+                },
+            },
+        );
+        bundle.relationships.insert(
+            "colors".to_string(),
+            RelationshipSelection::SimpleIds(vec!["red".to_string()]),
+        );
+        let mut instances = vec![
+            instance("part-1", "class-part"),
+            instance("part-2", "class-part"),
+            instance("red", "class-color"),
+            bundle,
+        ];
+
+        let summary =
+            rename_class(&mut schema, &mut instances, "class-part", "class-component").unwrap();
+        assert_eq!(
+            summary,
+            ClassRenameSummary {
+                instances_updated: 2,
+                relationships_updated: 3,
+                selections_updated: 1,
+            }
+        );
+
+        let ids: Vec<&str> = schema.classes.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["class-component", "class-color", "class-bundle"]);
+        let bundle_class = &schema.classes[2];
+        assert_eq!(
+            bundle_class.relationships[0].targets,
+            vec!["class-component", "class-color"]
+        );
+        assert_eq!(
+            bundle_class.relationships[1].default_pool,
+            DefaultPool::Filter {
+                types: Some(vec!["class-component".to_string()]),
+                filter: None,
+            }
+        );
+        assert_eq!(bundle_class.relationships[2].targets, vec!["class-color"]);
+        assert_eq!(
+            bundle_class.relationships[3].universe.as_deref(),
+            Some("class-component")
+        );
+
+        let class_ids: Vec<&str> = instances.iter().map(|i| i.class_id.as_str()).collect();
+        assert_eq!(
+            class_ids,
+            vec![
+                "class-component",
+                "class-component",
+                "class-color",
+                "class-bundle"
+            ]
+        );
+        match &instances[3].relationships["parts"] {
+            RelationshipSelection::Filter { filter } => {
+                assert_eq!(filter.types, Some(vec!["class-component".to_string()]))
+            }
+            other => panic!("unexpected selection {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rename_guards() {
+        let mut schema = schema();
+        let original = schema.clone();
+        let mut instances = vec![instance("part-1", "class-part")];
+
+        assert_eq!(
+            rename_class(&mut schema, &mut instances, "class-part", "class-color"),
+            Err(ClassRenameError::Collision("class-color".to_string()))
+        );
+        assert_eq!(
+            rename_class(&mut schema, &mut instances, "class-missing", "class-new"),
+            Err(ClassRenameError::NotFound("class-missing".to_string()))
+        );
+        assert_eq!(
+            rename_class(&mut schema, &mut instances, "class-part", " "),
+            Err(ClassRenameError::EmptyId)
+        );
+        // Nothing changed on failure
+        assert_eq!(schema, original);
+        assert_eq!(instances[0].class_id, "class-part");
+    }
+}
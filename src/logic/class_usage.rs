@@ -0,0 +1,367 @@
+//! Impact analysis for schema classes
+//!
+//! Answers "what depends on this class?" before a breaking schema change: how many
+//! instances exist, which relationships target the class, and which derived properties
+//! read its properties.
+
+use crate::model::{BoolExpr, ClassDef, Expr, Id, Instance, Predicate, Schema};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// A relationship on some class whose targets include the analyzed class
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelationshipReference {
+    pub class_id: Id,
+    pub class_name: String,
+    pub relationship_id: Id,
+    pub relationship_name: String,
+    /// Whether the relationship is a computed back-reference (`inverse_of`)
+    pub inverse: bool,
+    /// Number of instances of the referencing class that set this relationship explicitly
+    pub explicit_instance_count: usize,
+}
+
+/// A derived property whose expression reads properties of the analyzed class
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DerivedReference {
+    pub class_id: Id,
+    pub class_name: String,
+    pub derived_id: Id,
+    pub derived_name: String,
+    /// Properties of the analyzed class read by the expression
+    pub properties: Vec<String>,
+}
+
+/// Combined usage report for one class
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassUsageReport {
+    pub class_id: Id,
+    pub class_name: String,
+    pub instance_count: usize,
+    pub referenced_by: Vec<RelationshipReference>,
+    pub derived_references: Vec<DerivedReference>,
+}
+
+/// Number of instances of the class
+pub fn count_class_instances(instances: &[Instance], class_id: &str) -> usize {
+    instances.iter().filter(|i| i.class_id == class_id).count()
+}
+
+/// Every relationship in the schema that can point at the class
+pub fn find_relationships_targeting(
+    schema: &Schema,
+    instances: &[Instance],
+    class_id: &str,
+) -> Vec<RelationshipReference> {
+    let mut references = Vec::new();
+    for class_def in &schema.classes {
+        for rel_def in &class_def.relationships {
+            if !rel_def.targets.iter().any(|t| t == class_id) {
+                continue;
+            }
+            let explicit_instance_count = instances
+                .iter()
+                .filter(|i| i.class_id == class_def.id)
+                .filter(|i| {
+                    i.relationships.contains_key(&rel_def.id)
+                        || i.relationships.contains_key(&rel_def.name)
+                })
+                .count();
+            references.push(RelationshipReference {
+                class_id: class_def.id.clone(),
+                class_name: class_def.name.clone(),
+                relationship_id: rel_def.id.clone(),
+                relationship_name: rel_def.name.clone(),
+                inverse: rel_def.is_inverse(),
+                explicit_instance_count,
+            });
+        }
+    }
+    references
+}
+
+/// Every derived property in the schema that reads a property of the class, either on its
+/// own instance or through a relationship targeting the class
+pub fn find_derived_references(schema: &Schema, class_id: &str) -> Vec<DerivedReference> {
+    let Some(target) = schema.classes.iter().find(|c| c.id == class_id) else {
+        return Vec::new();
+    };
+    let is_target_property = |prop: &str| {
+        target
+            .properties
+            .iter()
+            .any(|p| p.name == prop || p.id == prop)
+    };
+
+    let mut references = Vec::new();
+    for class_def in &schema.classes {
+        for derived in &class_def.derived {
+            let mut refs = Vec::new();
+            if let Some(expr) = &derived.expr {
+                collect_expr_refs(expr, &mut refs);
+            } else if let Some(fn_short) = &derived.fn_short {
+                // Shorthand aggregates read the property on the instance and every relationship
+                refs.push((None, fn_short.property.as_str()));
+                for rel_def in &class_def.relationships {
+                    refs.push((Some(rel_def.name.as_str()), fn_short.property.as_str()));
+                }
+            }
+
+            let properties: BTreeSet<String> = refs
+                .into_iter()
+                .filter(|(rel, prop)| {
+                    reads_class(class_def, *rel, class_id) && is_target_property(prop)
+                })
+                .map(|(_, prop)| prop.to_string())
+                .collect();
+            if !properties.is_empty() {
+                references.push(DerivedReference {
+                    class_id: class_def.id.clone(),
+                    class_name: class_def.name.clone(),
+                    derived_id: derived.id.clone(),
+                    derived_name: derived.name.clone(),
+                    properties: properties.into_iter().collect(),
+                });
+            }
+        }
+    }
+    references
+}
+
+/// Combine instance count, referencing relationships and derived references for a class.
+/// Returns `None` when the class is not in the schema.
+pub fn analyze_class_usage(
+    schema: &Schema,
+    instances: &[Instance],
+    class_id: &str,
+) -> Option<ClassUsageReport> {
+    let class_def = schema.classes.iter().find(|c| c.id == class_id)?;
+    Some(ClassUsageReport {
+        class_id: class_def.id.clone(),
+        class_name: class_def.name.clone(),
+        instance_count: count_class_instances(instances, class_id),
+        referenced_by: find_relationships_targeting(schema, instances, class_id),
+        derived_references: find_derived_references(schema, class_id),
+    })
+}
+
+/// Whether a property read on `owner` (directly, or through relationship `rel`) lands on
+/// instances of `class_id`
+fn reads_class(owner: &ClassDef, rel: Option<&str>, class_id: &str) -> bool {
+    match rel {
+        None => owner.id == class_id,
+        Some(rel) => owner
+            .relationships
+            .iter()
+            .filter(|r| r.id == rel || r.name == rel)
+            .any(|r| r.targets.iter().any(|t| t == class_id)),
+    }
+}
+
+/// Collect `(relationship, property)` pairs read by an expression; `None` means the
+/// instance's own property
+fn collect_expr_refs<'a>(expr: &'a Expr, refs: &mut Vec<(Option<&'a str>, &'a str)>) {
+    match expr {
+        Expr::Add { left, right }
+        | Expr::Sub { left, right }
+        | Expr::Mul { left, right }
+        | Expr::Div { left, right }
+        | Expr::Eq { left, right }
+        | Expr::Ne { left, right }
+        | Expr::Lt { left, right }
+        | Expr::Lte { left, right }
+        | Expr::Gt { left, right }
+        | Expr::Gte { left, right } => {
+            collect_expr_refs(left, refs);
+            collect_expr_refs(right, refs);
+        }
+        Expr::Abs { expr } => collect_expr_refs(expr, refs),
+        Expr::Pow { base, exp } => {
+            collect_expr_refs(base, refs);
+            collect_expr_refs(exp, refs);
+        }
+        Expr::Prop { prop } => refs.push((None, prop)),
+        Expr::RelProp { rel, prop } => refs.push((Some(rel), prop)),
+        Expr::Sum { over, prop, r#where } => {
+            refs.push((Some(over), prop));
+            if let Some(cond) = r#where {
+                collect_bool_refs(cond, Some(over), refs);
+            }
+        }
+        Expr::Count { over, r#where } => {
+            if let Some(cond) = r#where {
+                collect_bool_refs(cond, Some(over), refs);
+            }
+        }
+        Expr::If {
+            cond,
+            then,
+            r#else,
+        } => {
+            collect_bool_refs(cond, None, refs);
+            collect_expr_refs(then, refs);
+            collect_expr_refs(r#else, refs);
+        }
+        Expr::Cond { if_, then, else_ } => {
+            collect_expr_refs(if_, refs);
+            collect_expr_refs(then, refs);
+            collect_expr_refs(else_, refs);
+        }
+        Expr::Concat { parts, .. } => {
+            for part in parts {
+                collect_expr_refs(part, refs);
+            }
+        }
+        Expr::LitNumber { .. } | Expr::LitBool { .. } | Expr::LitString { .. } => {}
+    }
+}
+
+/// Collect property reads of a condition evaluated against `rel` targets (or the instance)
+fn collect_bool_refs<'a>(
+    cond: &'a BoolExpr,
+    rel: Option<&'a str>,
+    refs: &mut Vec<(Option<&'a str>, &'a str)>,
+) {
+    let predicates = match cond {
+        BoolExpr::SimpleAll { .. } => return,
+        BoolExpr::All { predicates }
+        | BoolExpr::Any { predicates }
+        | BoolExpr::None { predicates } => predicates,
+    };
+    for predicate in predicates {
+        match predicate {
+            Predicate::PropEq { prop, .. }
+            | Predicate::PropNe { prop, .. }
+            | Predicate::PropGt { prop, .. }
+            | Predicate::PropLt { prop, .. }
+            | Predicate::PropContains { prop, .. } => refs.push((rel, prop)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{
+        DataType, DefaultPool, DerivedDef, PropertyDef, Quantifier, RelationshipDef,
+        RelationshipSelection, SelectionType,
+    };
+
+    fn property(name: &str) -> PropertyDef {
+        PropertyDef {
+            id: format!("prop-{}", name),
+            name: name.to_string(),
+            data_type: DataType::Number,
+            required: None,
+            value: None,
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
+        }
+    }
+
+    fn schema() -> Schema {
+        let part = ClassDef {
+            id: "class-part".to_string(),
+            name: "Part".to_string(),
+            properties: vec![property("price"), property("weight")],
+            ..ClassDef::default()
+        };
+        let bundle = ClassDef {
+            id: "class-bundle".to_string(),
+            name: "Bundle".to_string(),
+            properties: vec![property("price")],
+            relationships: vec![RelationshipDef {
+                id: "rel-parts".to_string(),
+                name: "parts".to_string(),
+                targets: vec!["class-part".to_string()],
+                quantifier: Quantifier::AtLeast(1),
+                universe: None,
+                selection: SelectionType::ExplicitOrFilter,
+                default_pool: DefaultPool::All,
+                inverse_of: None,
+                min: None,
+                max: None,
+            }],
+            derived: vec![
+                DerivedDef {
+                    id: "derived-total".to_string(),
+                    name: "total".to_string(),
+                    data_type: DataType::Number,
+                    expr: Some(Expr::Add {
+                        left: Box::new(Expr::Prop {
+                            prop: "price".to_string(),
+                        }),
+                        right: Box::new(Expr::Sum {
+                            over: "parts".to_string(),
+                            prop: "weight".to_string(),
+                            r#where: None,
+                        }),
+                    }),
+                    fn_short: None,
+                    materialize: None,
+                },
+                DerivedDef {
+                    id: "derived-own".to_string(),
+                    name: "own".to_string(),
+                    data_type: DataType::Number,
+                    expr: Some(Expr::Prop {
+                        prop: "price".to_string(),
+                    }),
+                    fn_short: None,
+                    materialize: None,
+                },
+            ],
+            ..ClassDef::default()
+        };
+        Schema {
+            id: "schema".to_string(),
+            classes: vec![part, bundle],
+            description: None,
+        }
+    }
+
+    fn instance(id: &str, class_id: &str) -> Instance {
+        Instance {
+            id: id.to_string(),
+            class_id: class_id.to_string(),
+            ..Instance::default()
+        }
+    }
+
+    #[test]
+    fn test_class_usage_combines_all_analyses() {
+        let mut bundle = instance("bundle-1", "class-bundle");
+        bundle.relationships.insert(
+            "parts".to_string(),
+            RelationshipSelection::SimpleIds(vec!["part-1".to_string()]),
+        );
+        let instances = vec![
+            instance("part-1", "class-part"),
+            instance("part-2", "class-part"),
+            bundle,
+            instance("bundle-2", "class-bundle"),
+        ];
+
+        let report = analyze_class_usage(&schema(), &instances, "class-part").unwrap();
+        assert_eq!(report.instance_count, 2);
+
+        assert_eq!(report.referenced_by.len(), 1);
+        assert_eq!(report.referenced_by[0].relationship_id, "rel-parts");
+        assert_eq!(report.referenced_by[0].explicit_instance_count, 1);
+
+        // `total` reads part weight through `parts`; its own `price` read is on the bundle
+        assert_eq!(report.derived_references.len(), 1);
+        assert_eq!(report.derived_references[0].derived_id, "derived-total");
+        assert_eq!(report.derived_references[0].properties, vec!["weight"]);
+
+        let bundle_report = analyze_class_usage(&schema(), &instances, "class-bundle").unwrap();
+        assert!(bundle_report.referenced_by.is_empty());
+        assert_eq!(bundle_report.derived_references.len(), 2);
+
+        assert!(analyze_class_usage(&schema(), &instances, "class-missing").is_none());
+    }
+}
@@ -0,0 +1,102 @@
+//! Instance and schema counts of a commit
+//!
+//! Counting only reads the commit's instance list and schema; relationships are not
+//! resolved. Commits never change, so the counts are cached by commit hash and a branch
+//! whose head hasn't moved is answered without loading its commit again.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::model::{CommitData, Id};
+
+/// Counts of one commit's instances and schema
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CommitStats {
+    pub instance_count: usize,
+    /// Instances per class ID; schema classes without instances count zero
+    pub class_counts: BTreeMap<Id, usize>,
+    /// Classes in the schema
+    pub class_count: usize,
+    /// Properties over all schema classes
+    pub property_count: usize,
+}
+
+impl CommitStats {
+    pub fn from_commit_data(commit_data: &CommitData) -> Self {
+        let mut class_counts: BTreeMap<Id, usize> = commit_data
+            .schema
+            .classes
+            .iter()
+            .map(|class_def| (class_def.id.clone(), 0))
+            .collect();
+        for instance in &commit_data.instances {
+            *class_counts.entry(instance.class_id.clone()).or_default() += 1;
+        }
+
+        Self {
+            instance_count: commit_data.instances.len(),
+            class_counts,
+            class_count: commit_data.schema.classes.len(),
+            property_count: commit_data
+                .schema
+                .classes
+                .iter()
+                .map(|class_def| class_def.properties.len())
+                .sum(),
+        }
+    }
+}
+
+/// Number of commits whose stats are kept before the oldest is dropped
+const COMMIT_STATS_CACHE_CAPACITY: usize = 1024;
+
+/// Stats by commit hash, shared by all requests. Entries never go stale because commits are
+/// immutable; the oldest one is dropped once the cache is full.
+#[derive(Debug, Default)]
+pub struct CommitStatsCache {
+    state: Mutex<CommitStatsCacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CommitStatsCacheState {
+    stats: HashMap<String, CommitStats>,
+    /// Cached hashes, oldest first
+    order: VecDeque<String>,
+}
+
+impl CommitStatsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, commit_hash: &str) -> Option<CommitStats> {
+        self.lock().stats.get(commit_hash).cloned()
+    }
+
+    pub fn insert(&self, commit_hash: &str, stats: CommitStats) {
+        let mut state = self.lock();
+        if state.stats.insert(commit_hash.to_string(), stats).is_some() {
+            return;
+        }
+        state.order.push_back(commit_hash.to_string());
+        if state.order.len() > COMMIT_STATS_CACHE_CAPACITY {
+            if let Some(oldest) = state.order.pop_front() {
+                state.stats.remove(&oldest);
+            }
+        }
+    }
+
+    /// Number of commits with cached stats
+    pub fn len(&self) -> usize {
+        self.lock().stats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CommitStatsCacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,323 @@
+//! Materializing derived properties into committed instances
+//!
+//! Derived properties are computed on every read unless marked `materialize`. Those are
+//! computed when a working commit becomes a commit and stored in the instance's `materialized`
+//! values, with a fingerprint of the instance's inputs in `materialized_inputs`. Reads use a
+//! stored value while the inputs still match the fingerprint and recompute it otherwise, until
+//! the next commit materializes it again. The values are kept out of `properties`, so an
+//! instance carried into the next working commit still validates against its class.
+//!
+//! The inputs are everything evaluation can reach: the instance's own properties, domain and
+//! relationships, and the properties and domains of the instances it selects by ID.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use crate::logic::SimpleEvaluator;
+use crate::model::{
+    DerivedDef, Instance, PropertyValue, RelationshipSelection, Schema, TypedValue,
+};
+
+/// Store the value of every materialized derived property in `instances`, replacing values
+/// stored by an earlier commit and dropping those of properties no longer materialized.
+/// Returns the number of values stored, or the first evaluation error such as a cycle between
+/// derived properties.
+pub fn materialize_derived_properties(
+    schema: &Schema,
+    instances: &mut [Instance],
+) -> Result<usize> {
+    for instance in instances.iter_mut() {
+        instance.materialized.clear();
+        instance.materialized_inputs = None;
+    }
+    let snapshot = instances.to_vec();
+
+    let mut stored = 0;
+    for instance in instances.iter_mut() {
+        let Some(class_def) = schema.get_class_by_id(&instance.class_id) else {
+            continue;
+        };
+        let materialized: Vec<&DerivedDef> = class_def
+            .derived
+            .iter()
+            .filter(|d| d.is_materialized())
+            .collect();
+        if materialized.is_empty() {
+            continue;
+        }
+
+        let names: Vec<String> = materialized.iter().map(|d| d.name.clone()).collect();
+        let values =
+            SimpleEvaluator::evaluate_derived_properties(instance, schema, &names, &snapshot)
+                .with_context(|| {
+                    format!(
+                        "Failed to materialize derived properties of instance '{}'",
+                        instance.id
+                    )
+                })?;
+        let fingerprint = inputs_fingerprint(instance, &snapshot);
+        for derived_def in materialized {
+            if let Some(value) = values.get(&derived_def.name) {
+                instance.materialized.insert(
+                    derived_def.name.clone(),
+                    TypedValue {
+                        value: value.clone(),
+                        data_type: derived_def.data_type.clone(),
+                    },
+                );
+                stored += 1;
+            }
+        }
+        instance.materialized_inputs = Some(fingerprint);
+    }
+    Ok(stored)
+}
+
+/// The stored value of a materialized derived property, or `None` when it isn't stored or
+/// its inputs changed since it was computed
+pub fn materialized_value(
+    derived_def: &DerivedDef,
+    instance: &Instance,
+    configuration: &[Instance],
+) -> Option<serde_json::Value> {
+    if !derived_def.is_materialized() {
+        return None;
+    }
+    let fingerprint = instance.materialized_inputs.as_ref()?;
+    let stored = instance.materialized.get(&derived_def.name)?;
+    (*fingerprint == inputs_fingerprint(instance, configuration)).then(|| stored.value.clone())
+}
+
+/// SHA-256 over the instance's inputs and those of the instances it selects by ID
+fn inputs_fingerprint(instance: &Instance, configuration: &[Instance]) -> String {
+    let related: BTreeMap<&str, serde_json::Value> = instance
+        .relationships
+        .values()
+        .flat_map(|selection| match selection {
+            RelationshipSelection::SimpleIds(ids) | RelationshipSelection::Ids { ids } => {
+                ids.as_slice()
+            }
+            _ => &[],
+        })
+        .filter_map(|id| configuration.iter().find(|i| i.id == *id))
+        .map(|related| (related.id.as_str(), own_inputs(related)))
+        .collect();
+    let inputs = serde_json::json!({
+        "instance": own_inputs(instance),
+        "related": related,
+    });
+
+    let mut hasher = Sha256::new();
+    hasher.update(inputs.to_string());
+    hex::encode(hasher.finalize())
+}
+
+/// An instance's properties, domain and relationships
+fn own_inputs(instance: &Instance) -> serde_json::Value {
+    let properties: BTreeMap<&String, &PropertyValue> = instance.properties.iter().collect();
+    let relationships: BTreeMap<&String, &RelationshipSelection> =
+        instance.relationships.iter().collect();
+    serde_json::json!({
+        "properties": properties,
+        "domain": instance.domain,
+        "relationships": relationships,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ClassDef, DataType, Domain, Expr};
+
+    fn derived(name: &str, expr: Expr, materialize: Option<bool>) -> DerivedDef {
+        DerivedDef {
+            id: name.to_string(),
+            name: name.to_string(),
+            data_type: DataType::Number,
+            expr: Some(expr),
+            fn_short: None,
+            materialize,
+        }
+    }
+
+    fn schema() -> Schema {
+        let doubled = Expr::Mul {
+            left: Box::new(Expr::Prop {
+                prop: "price".to_string(),
+            }),
+            right: Box::new(Expr::LitNumber { value: 2.0 }),
+        };
+        let total = Expr::Sum {
+            over: "parts".to_string(),
+            prop: "price".to_string(),
+            r#where: None,
+        };
+        Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![
+                ClassDef {
+                    id: "class-part".to_string(),
+                    derived: vec![
+                        derived("doubled", doubled.clone(), Some(true)),
+                        derived("preview", doubled, None),
+                    ],
+                    ..ClassDef::default()
+                },
+                ClassDef {
+                    id: "class-bundle".to_string(),
+                    derived: vec![derived("total", total, Some(true))],
+                    ..ClassDef::default()
+                },
+            ],
+        }
+    }
+
+    fn instance(id: &str, class_id: &str, price: i32) -> Instance {
+        let mut instance = Instance {
+            id: id.to_string(),
+            class_id: class_id.to_string(),
+            domain: Some(Domain::constant(1)),
+            ..Instance::default()
+        };
+        instance.properties.insert(
+            "price".to_string(),
+            PropertyValue::Literal(TypedValue::number(price)),
+        );
+        instance
+    }
+
+    fn stored(instance: &Instance, name: &str) -> Option<serde_json::Value> {
+        instance
+            .materialized
+            .get(name)
+            .map(|typed| typed.value.clone())
+    }
+
+    fn as_number(value: Option<serde_json::Value>) -> Option<f64> {
+        value.and_then(|value| value.as_f64())
+    }
+
+    #[test]
+    fn test_materialize_stores_only_marked_properties() {
+        let schema = schema();
+        let mut bundle = instance("bundle-1", "class-bundle", 1);
+        bundle.relationships.insert(
+            "parts".to_string(),
+            RelationshipSelection::SimpleIds(vec!["part-1".to_string()]),
+        );
+        let mut instances = vec![instance("part-1", "class-part", 10), bundle];
+
+        assert_eq!(
+            materialize_derived_properties(&schema, &mut instances).unwrap(),
+            2
+        );
+        assert_eq!(as_number(stored(&instances[0], "doubled")), Some(20.0));
+        assert!(!instances[0].materialized.contains_key("preview"));
+        assert_eq!(as_number(stored(&instances[1], "total")), Some(11.0));
+        // The stored values are not properties of the instance
+        assert!(!instances[0].properties.contains_key("doubled"));
+
+        // Committing again replaces the stored values
+        assert_eq!(
+            materialize_derived_properties(&schema, &mut instances).unwrap(),
+            2
+        );
+        assert_eq!(as_number(stored(&instances[0], "doubled")), Some(20.0));
+    }
+
+    #[test]
+    fn test_materialized_value_goes_stale_with_inputs() {
+        let schema = schema();
+        let mut bundle = instance("bundle-1", "class-bundle", 1);
+        bundle.relationships.insert(
+            "parts".to_string(),
+            RelationshipSelection::SimpleIds(vec!["part-1".to_string()]),
+        );
+        let mut instances = vec![instance("part-1", "class-part", 10), bundle];
+        materialize_derived_properties(&schema, &mut instances).unwrap();
+        let total = &schema.classes[1].derived[0];
+        let doubled = &schema.classes[0].derived[0];
+
+        let fresh = |instances: &[Instance], index: usize, derived_def: &DerivedDef| {
+            as_number(materialized_value(
+                derived_def,
+                &instances[index],
+                instances,
+            ))
+        };
+        assert_eq!(fresh(&instances, 0, doubled), Some(20.0));
+        assert_eq!(fresh(&instances, 1, total), Some(11.0));
+
+        // A related instance's input changing invalidates the bundle but not unrelated values
+        instances[0].properties.insert(
+            "price".to_string(),
+            PropertyValue::Literal(TypedValue::number(15)),
+        );
+        assert_eq!(fresh(&instances, 0, doubled), None);
+        assert_eq!(fresh(&instances, 1, total), None);
+
+        // Reads recompute the stale value
+        let values = SimpleEvaluator::evaluate_derived_properties(
+            &instances[1],
+            &schema,
+            &["total".to_string()],
+            &instances,
+        )
+        .unwrap();
+        assert_eq!(as_number(values.get("total").cloned()), Some(16.0));
+    }
+
+    #[test]
+    fn test_materialization_dropped_when_turned_off() {
+        let mut schema = schema();
+        let mut instances = vec![instance("part-1", "class-part", 10)];
+        materialize_derived_properties(&schema, &mut instances).unwrap();
+        assert!(instances[0].materialized.contains_key("doubled"));
+
+        schema.classes[0].derived[0].materialize = Some(false);
+        assert_eq!(
+            materialize_derived_properties(&schema, &mut instances).unwrap(),
+            0
+        );
+        assert!(instances[0].materialized.is_empty());
+        assert!(instances[0].materialized_inputs.is_none());
+        assert!(instances[0].properties.contains_key("price"));
+    }
+
+    #[test]
+    fn test_materialization_reports_cycles() {
+        let mut schema = schema();
+        let prop = |name: &str| {
+            Box::new(Expr::Prop {
+                prop: name.to_string(),
+            })
+        };
+        schema.classes[0].derived = vec![
+            derived(
+                "a",
+                Expr::Add {
+                    left: prop("b"),
+                    right: prop("price"),
+                },
+                Some(true),
+            ),
+            derived(
+                "b",
+                Expr::Add {
+                    left: prop("a"),
+                    right: prop("price"),
+                },
+                None,
+            ),
+        ];
+        let mut instances = vec![instance("part-1", "class-part", 10)];
+
+        let error = materialize_derived_properties(&schema, &mut instances).unwrap_err();
+        assert!(format!("{:#}", error).contains("part-1"));
+        assert!(error
+            .chain()
+            .any(|e| e.is::<crate::logic::DerivedEvaluationError>()));
+    }
+}
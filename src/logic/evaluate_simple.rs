@@ -1,6 +1,22 @@
 use anyhow::{anyhow, Result};
+use std::cmp::Ordering;
 
-use crate::model::{Expr, Instance, PropertyValue, RuleSet, Schema};
+use crate::model::{ClassDef, DerivedDef, Expr, Instance, PropertyValue, RuleSet, Schema};
+
+/// Errors that stop derived property evaluation instead of skipping the property
+#[derive(Debug, thiserror::Error)]
+pub enum DerivedEvaluationError {
+    /// Derived properties reference each other in a loop; `path` starts and ends with the
+    /// same property
+    #[error("Cycle in derived properties: {}", path.join(" -> "))]
+    Cycle { path: Vec<String> },
+}
+
+/// Derived properties an expression may refer to, and the ones currently being computed
+struct DerivedScope<'a> {
+    class_def: Option<&'a ClassDef>,
+    computing: Vec<String>,
+}
 
 pub struct SimpleEvaluator;
 
@@ -129,16 +145,32 @@ impl SimpleEvaluator {
                     .iter()
                     .find(|d| d.name == *derived_prop_name)
                 {
+                    if let Some(value) =
+                        crate::logic::materialized_value(derived_def, instance, configuration)
+                    {
+                        derived_values.insert(derived_prop_name.clone(), value);
+                        continue;
+                    }
                     // Get the expression using the new method that handles fn_short
-                    if let Some(expr) = derived_def.get_expr(class_def) {
-                        match Self::evaluate_derived_expr(&expr, instance, configuration) {
-                            Ok(value) => {
-                                derived_values.insert(derived_prop_name.clone(), value);
-                            }
-                            Err(_e) => {
-                                // Log error but continue with other properties
-                                // Failed to evaluate derived property
-                            }
+                    let mut scope = DerivedScope {
+                        class_def: Some(class_def),
+                        computing: Vec::new(),
+                    };
+                    match Self::evaluate_derived_property(
+                        derived_def,
+                        instance,
+                        configuration,
+                        &mut scope,
+                    ) {
+                        Ok(Some(value)) => {
+                            derived_values.insert(derived_prop_name.clone(), value);
+                        }
+                        Ok(None) => {}
+                        // A cycle is a schema error, not a missing value
+                        Err(e) if e.is::<DerivedEvaluationError>() => return Err(e),
+                        Err(_e) => {
+                            // Log error but continue with other properties
+                            // Failed to evaluate derived property
                         }
                     }
                 }
@@ -148,20 +180,106 @@ impl SimpleEvaluator {
         Ok(derived_values)
     }
 
+    /// Evaluate a class constraint against an instance; the expression must yield a boolean
+    pub fn evaluate_constraint(expr: &Expr, instance: &Instance) -> Result<bool> {
+        Self::constraint_holds(Self::evaluate_derived_expr(expr, instance, &[])?)
+    }
+
+    /// Evaluate a solve-time constraint against a solved instance. Aggregates only count the
+    /// selected instances of `configuration`, and the expression may name derived properties
+    /// of the instance's class.
+    pub fn evaluate_configuration_constraint(
+        expr: &Expr,
+        instance: &Instance,
+        schema: &Schema,
+        configuration: &[Instance],
+    ) -> Result<bool> {
+        let mut scope = DerivedScope {
+            class_def: schema.get_class_by_id(&instance.class_id),
+            computing: Vec::new(),
+        };
+        Self::constraint_holds(Self::evaluate_in_scope(
+            expr,
+            instance,
+            configuration,
+            &mut scope,
+        )?)
+    }
+
+    fn constraint_holds(value: serde_json::Value) -> Result<bool> {
+        match value {
+            serde_json::Value::Bool(holds) => Ok(holds),
+            other => Err(anyhow!("Constraint must evaluate to a boolean, got {}", other)),
+        }
+    }
+
     /// Simple derived expression evaluator
     fn evaluate_derived_expr(
         expr: &Expr,
         context: &Instance,
         configuration: &[Instance],
+    ) -> Result<serde_json::Value> {
+        let mut scope = DerivedScope {
+            class_def: None,
+            computing: Vec::new(),
+        };
+        Self::evaluate_in_scope(expr, context, configuration, &mut scope)
+    }
+
+    /// Evaluate one derived property, refusing to re-enter a property that is already being
+    /// computed further up the chain
+    fn evaluate_derived_property(
+        derived_def: &DerivedDef,
+        context: &Instance,
+        configuration: &[Instance],
+        scope: &mut DerivedScope,
+    ) -> Result<Option<serde_json::Value>> {
+        if let Some(start) = scope.computing.iter().position(|name| *name == derived_def.name) {
+            let mut path = scope.computing[start..].to_vec();
+            path.push(derived_def.name.clone());
+            return Err(DerivedEvaluationError::Cycle { path }.into());
+        }
+        let Some(expr) = scope.class_def.and_then(|class_def| derived_def.get_expr(class_def))
+        else {
+            return Ok(None);
+        };
+
+        scope.computing.push(derived_def.name.clone());
+        let value = Self::evaluate_in_scope(&expr, context, configuration, scope);
+        scope.computing.pop();
+        value.map(Some)
+    }
+
+    fn evaluate_in_scope(
+        expr: &Expr,
+        context: &Instance,
+        configuration: &[Instance],
+        scope: &mut DerivedScope,
     ) -> Result<serde_json::Value> {
         match expr {
             Expr::LitNumber { value } => Ok(serde_json::Value::Number(
                 serde_json::Number::from_f64(*value).unwrap(),
             )),
-            Expr::Prop { prop } => Self::get_property_value(context, prop),
+            Expr::LitBool { value } => Ok(serde_json::Value::Bool(*value)),
+            Expr::LitString { value } => Ok(serde_json::Value::String(value.clone())),
+            Expr::Prop { prop } => {
+                // Instance properties win; otherwise the name may refer to another derived
+                // property of the same class
+                let derived_def = scope
+                    .class_def
+                    .filter(|_| !context.properties.contains_key(prop))
+                    .and_then(|class_def| class_def.derived.iter().find(|d| d.name == *prop));
+                match derived_def {
+                    Some(derived_def) => {
+                        Self::evaluate_derived_property(derived_def, context, configuration, scope)?
+                            .ok_or_else(|| anyhow!("Derived property '{}' has no expression", prop))
+                    }
+                    None => Self::get_property_value(context, prop),
+                }
+            }
             Expr::Add { left, right } => {
-                let left_val = Self::evaluate_derived_expr(left, context, configuration)?;
-                let right_val = Self::evaluate_derived_expr(right, context, configuration)?;
+                let left_val = Self::evaluate_in_scope(left, context, configuration, scope)?;
+                let right_val = Self::evaluate_in_scope(right, context, configuration, scope)?;
 
                 let left_num = Self::json_to_number(&left_val)?;
                 let right_num = Self::json_to_number(&right_val)?;
@@ -172,8 +290,8 @@ impl SimpleEvaluator {
                 ))
             }
             Expr::Sub { left, right } => {
-                let left_val = Self::evaluate_derived_expr(left, context, configuration)?;
-                let right_val = Self::evaluate_derived_expr(right, context, configuration)?;
+                let left_val = Self::evaluate_in_scope(left, context, configuration, scope)?;
+                let right_val = Self::evaluate_in_scope(right, context, configuration, scope)?;
 
                 let left_num = Self::json_to_number(&left_val)?;
                 let right_num = Self::json_to_number(&right_val)?;
@@ -183,6 +301,97 @@ impl SimpleEvaluator {
                     serde_json::Number::from_f64(result).unwrap(),
                 ))
             }
+            Expr::Mul { left, right } | Expr::Div { left, right } => {
+                let left_val = Self::evaluate_in_scope(left, context, configuration, scope)?;
+                let right_val = Self::evaluate_in_scope(right, context, configuration, scope)?;
+
+                let left_num = Self::json_to_number(&left_val)?;
+                let right_num = Self::json_to_number(&right_val)?;
+                if matches!(expr, Expr::Mul { .. }) {
+                    Self::number_to_json(left_num * right_num)
+                } else if right_num == 0.0 {
+                    Err(anyhow!("Division by zero"))
+                } else {
+                    Self::number_to_json(left_num / right_num)
+                }
+            }
+            Expr::Abs { expr } => {
+                let value = Self::evaluate_in_scope(expr, context, configuration, scope)?;
+                Self::number_to_json(Self::json_to_number(&value)?.abs())
+            }
+            Expr::Pow { base, exp } => {
+                let base_val = Self::evaluate_in_scope(base, context, configuration, scope)?;
+                let exp_val = Self::evaluate_in_scope(exp, context, configuration, scope)?;
+
+                let base_num = Self::json_to_number(&base_val)?;
+                let exp_num = Self::json_to_number(&exp_val)?;
+                if base_num < 0.0 && exp_num.fract() != 0.0 {
+                    return Err(anyhow!(
+                        "Cannot raise negative base {} to fractional exponent {}",
+                        base_num,
+                        exp_num
+                    ));
+                }
+                if base_num == 0.0 && exp_num < 0.0 {
+                    return Err(anyhow!(
+                        "Cannot raise zero to negative exponent {}",
+                        exp_num
+                    ));
+                }
+
+                // 0^0 is 1, matching powf
+                Self::number_to_json(base_num.powf(exp_num))
+            }
+            Expr::Cond { if_, then, else_ } => {
+                let branch = match Self::evaluate_in_scope(if_, context, configuration, scope)? {
+                    serde_json::Value::Bool(true) => then,
+                    serde_json::Value::Bool(false) => else_,
+                    other => {
+                        return Err(anyhow!("Condition must evaluate to a boolean, got {}", other))
+                    }
+                };
+                Self::evaluate_in_scope(branch, context, configuration, scope)
+            }
+            Expr::Concat { parts, separator } => {
+                let rendered = parts
+                    .iter()
+                    .map(|part| {
+                        Self::evaluate_in_scope(part, context, configuration, scope)
+                            .map(|value| Self::json_to_display_string(&value))
+                    })
+                    .collect::<Result<Vec<String>>>()?;
+                Ok(serde_json::Value::String(
+                    rendered.join(separator.as_deref().unwrap_or("")),
+                ))
+            }
+            Expr::Eq { left, right } | Expr::Ne { left, right } => {
+                let left_val = Self::evaluate_in_scope(left, context, configuration, scope)?;
+                let right_val = Self::evaluate_in_scope(right, context, configuration, scope)?;
+
+                let equal = match Self::compare_json(&left_val, &right_val) {
+                    Some(ordering) => ordering == Ordering::Equal,
+                    None => left_val == right_val,
+                };
+                Ok(serde_json::Value::Bool(equal == matches!(expr, Expr::Eq { .. })))
+            }
+            Expr::Lt { left, right }
+            | Expr::Lte { left, right }
+            | Expr::Gt { left, right }
+            | Expr::Gte { left, right } => {
+                let left_val = Self::evaluate_in_scope(left, context, configuration, scope)?;
+                let right_val = Self::evaluate_in_scope(right, context, configuration, scope)?;
+
+                let ordering = Self::compare_json(&left_val, &right_val).ok_or_else(|| {
+                    anyhow!("Cannot compare {} with {}", left_val, right_val)
+                })?;
+                let holds = match expr {
+                    Expr::Lt { .. } => ordering.is_lt(),
+                    Expr::Lte { .. } => ordering.is_le(),
+                    Expr::Gt { .. } => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                };
+                Ok(serde_json::Value::Bool(holds))
+            }
             Expr::Sum {
                 over,
                 prop,
@@ -272,6 +481,39 @@ impl SimpleEvaluator {
         }
     }
 
+    /// Order two values: numbers (and booleans) numerically, strings lexicographically.
+    /// `None` when the values aren't comparable.
+    fn compare_json(left: &serde_json::Value, right: &serde_json::Value) -> Option<Ordering> {
+        match (left, right) {
+            (serde_json::Value::String(l), serde_json::Value::String(r)) => Some(l.cmp(r)),
+            _ => {
+                let left_num = Self::json_to_number(left).ok()?;
+                let right_num = Self::json_to_number(right).ok()?;
+                left_num.partial_cmp(&right_num)
+            }
+        }
+    }
+
+    /// Convert a computed number to JSON, rejecting overflow and NaN
+    fn number_to_json(value: f64) -> Result<serde_json::Value> {
+        serde_json::Number::from_f64(value)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| anyhow!("Arithmetic result is not finite: {}", value))
+    }
+
+    /// Render a value for string concatenation: strings as-is, null as empty, numbers without
+    /// a trailing `.0` when integral, anything else in its JSON form
+    fn json_to_display_string(value: &serde_json::Value) -> String {
+        match value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Null => String::new(),
+            serde_json::Value::Number(n) if n.is_f64() => {
+                n.as_f64().map_or_else(|| n.to_string(), |f| f.to_string())
+            }
+            other => other.to_string(),
+        }
+    }
+
     /// Convert JSON value to number
     fn json_to_number(value: &serde_json::Value) -> Result<f64> {
         match value {
@@ -284,3 +526,337 @@ impl SimpleEvaluator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(value: f64) -> Box<Expr> {
+        Box::new(Expr::LitNumber { value })
+    }
+
+    fn eval(expr: Expr) -> Result<f64> {
+        let mut instance = Instance::default();
+        instance.properties.insert(
+            "name".to_string(),
+            PropertyValue::Literal(crate::model::TypedValue::string("sofa".to_string())),
+        );
+        let value = SimpleEvaluator::evaluate_derived_expr(&expr, &instance, &[])?;
+        SimpleEvaluator::json_to_number(&value)
+    }
+
+    fn pow(base: f64, exp: f64) -> Result<f64> {
+        eval(Expr::Pow {
+            base: num(base),
+            exp: num(exp),
+        })
+    }
+
+    #[test]
+    fn test_abs() {
+        assert_eq!(eval(Expr::Abs { expr: num(-3.5) }).unwrap(), 3.5);
+        assert_eq!(eval(Expr::Abs { expr: num(2.0) }).unwrap(), 2.0);
+
+        let not_a_number = Expr::Abs {
+            expr: Box::new(Expr::Prop {
+                prop: "name".to_string(),
+            }),
+        };
+        assert!(eval(not_a_number).is_err());
+    }
+
+    #[test]
+    fn test_pow_edge_cases() {
+        assert_eq!(pow(2.0, 10.0).unwrap(), 1024.0);
+        assert_eq!(pow(0.0, 0.0).unwrap(), 1.0);
+        assert_eq!(pow(-2.0, 3.0).unwrap(), -8.0);
+        assert_eq!(pow(-2.0, -2.0).unwrap(), 0.25);
+        assert_eq!(pow(9.0, 0.5).unwrap(), 3.0);
+
+        // Negative base with fractional exponent has no real result
+        assert!(pow(-8.0, 1.0 / 3.0).is_err());
+        // Division by zero and overflow are errors rather than infinities
+        assert!(pow(0.0, -1.0).is_err());
+        assert!(pow(10.0, 400.0).is_err());
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let name = || {
+            Box::new(Expr::Prop {
+                prop: "name".to_string(),
+            })
+        };
+        let sofa = || {
+            Box::new(Expr::LitString {
+                value: "sofa".to_string(),
+            })
+        };
+
+        assert_eq!(eval(Expr::Lt { left: num(1.0), right: num(2.0) }).unwrap(), 1.0);
+        assert_eq!(eval(Expr::Gte { left: num(1.0), right: num(2.0) }).unwrap(), 0.0);
+        assert_eq!(eval(Expr::Lte { left: num(2.0), right: num(2.0) }).unwrap(), 1.0);
+        assert_eq!(eval(Expr::Eq { left: name(), right: sofa() }).unwrap(), 1.0);
+        assert_eq!(eval(Expr::Ne { left: name(), right: num(1.0) }).unwrap(), 1.0);
+
+        // Ordering a string against a number is an error, not false
+        assert!(eval(Expr::Gt { left: name(), right: num(1.0) }).is_err());
+    }
+
+    fn cond(if_: Expr, then: Box<Expr>, else_: Box<Expr>) -> Expr {
+        Expr::Cond {
+            if_: Box::new(if_),
+            then,
+            else_,
+        }
+    }
+
+    #[test]
+    fn test_cond_branches() {
+        // if featured then price * 1.2 else price
+        let featured = |value| Expr::LitBool { value };
+        let markup = || {
+            Box::new(Expr::Mul {
+                left: num(10.0),
+                right: num(1.2),
+            })
+        };
+        assert_eq!(eval(cond(featured(true), markup(), num(10.0))).unwrap(), 12.0);
+        assert_eq!(eval(cond(featured(false), markup(), num(10.0))).unwrap(), 10.0);
+
+        // The branch not taken is never evaluated
+        let invalid = Box::new(Expr::Pow {
+            base: num(-1.0),
+            exp: num(0.5),
+        });
+        assert_eq!(eval(cond(featured(true), num(1.0), invalid)).unwrap(), 1.0);
+
+        // Nested: if 1 < 2 then (if false then 1 else 2) else 3
+        let inner = cond(featured(false), num(1.0), num(2.0));
+        let outer = cond(
+            Expr::Lt {
+                left: num(1.0),
+                right: num(2.0),
+            },
+            Box::new(inner),
+            num(3.0),
+        );
+        assert_eq!(eval(outer).unwrap(), 2.0);
+
+        let json = serde_json::json!({
+            "type": "cond",
+            "if": { "type": "lit_bool", "value": true },
+            "then": { "type": "lit_number", "value": 1 },
+            "else": { "type": "lit_number", "value": 0 }
+        });
+        let parsed: Expr = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap()["if"], json["if"]);
+        assert_eq!(eval(parsed).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_cond_requires_boolean_condition() {
+        let err = eval(cond(Expr::LitNumber { value: 1.0 }, num(1.0), num(0.0))).unwrap_err();
+        assert!(err.to_string().contains("boolean"));
+    }
+
+    fn concat(parts: Vec<Expr>, separator: Option<&str>) -> Expr {
+        Expr::Concat {
+            parts,
+            separator: separator.map(str::to_string),
+        }
+    }
+
+    fn eval_string(expr: Expr) -> String {
+        let mut instance = Instance::default();
+        instance.properties.insert(
+            "name".to_string(),
+            PropertyValue::Literal(crate::model::TypedValue::string("Table".to_string())),
+        );
+        instance.properties.insert(
+            "finish".to_string(),
+            PropertyValue::Literal(crate::model::TypedValue {
+                value: serde_json::Value::Null,
+                data_type: crate::model::DataType::String,
+            }),
+        );
+        match SimpleEvaluator::evaluate_derived_expr(&expr, &instance, &[]).unwrap() {
+            serde_json::Value::String(s) => s,
+            other => panic!("expected a string, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_concat_mixed_types() {
+        let expr = concat(
+            vec![
+                Expr::LitString {
+                    value: "Oak".to_string(),
+                },
+                Expr::Prop {
+                    prop: "name".to_string(),
+                },
+                Expr::LitNumber { value: 2.0 },
+                Expr::LitNumber { value: 0.5 },
+                Expr::LitBool { value: true },
+                Expr::Prop {
+                    prop: "finish".to_string(),
+                },
+            ],
+            None,
+        );
+        assert_eq!(eval_string(expr), "OakTable20.5true");
+    }
+
+    #[test]
+    fn test_concat_separator_and_nesting() {
+        let color = concat(
+            vec![
+                Expr::LitString {
+                    value: "Red".to_string(),
+                },
+                Expr::LitString {
+                    value: "Oak".to_string(),
+                },
+            ],
+            Some(" "),
+        );
+        let name = Expr::Prop {
+            prop: "name".to_string(),
+        };
+        assert_eq!(
+            eval_string(concat(vec![color.clone(), name], Some(" "))),
+            "Red Oak Table"
+        );
+
+        // Nested inside a comparison
+        let matches = Expr::Eq {
+            left: Box::new(color),
+            right: Box::new(Expr::LitString {
+                value: "Red Oak".to_string(),
+            }),
+        };
+        assert_eq!(eval(matches).unwrap(), 1.0);
+
+        let json = serde_json::json!({
+            "type": "concat",
+            "parts": [{ "type": "lit_string", "value": "a" }, { "type": "lit_number", "value": 1 }],
+            "separator": "-"
+        });
+        let parsed: Expr = serde_json::from_value(json).unwrap();
+        assert_eq!(eval_string(parsed), "a-1");
+    }
+
+    fn derived(name: &str, expr: Expr) -> crate::model::DerivedDef {
+        crate::model::DerivedDef {
+            id: name.to_string(),
+            name: name.to_string(),
+            data_type: crate::model::DataType::Number,
+            expr: Some(expr),
+            fn_short: None,
+            materialize: None,
+        }
+    }
+
+    fn prop(name: &str) -> Box<Expr> {
+        Box::new(Expr::Prop {
+            prop: name.to_string(),
+        })
+    }
+
+    fn evaluate_derived(
+        derived: Vec<crate::model::DerivedDef>,
+        requested: &str,
+    ) -> Result<std::collections::HashMap<String, serde_json::Value>> {
+        let class_def = crate::model::ClassDef {
+            id: "class-table".to_string(),
+            derived,
+            ..crate::model::ClassDef::default()
+        };
+        let schema = Schema {
+            id: "schema".to_string(),
+            classes: vec![class_def],
+            description: None,
+        };
+        let mut instance = Instance {
+            id: "table-1".to_string(),
+            class_id: "class-table".to_string(),
+            ..Instance::default()
+        };
+        instance.properties.insert(
+            "price".to_string(),
+            PropertyValue::Literal(crate::model::TypedValue {
+                value: serde_json::json!(100.0),
+                data_type: crate::model::DataType::Number,
+            }),
+        );
+        SimpleEvaluator::evaluate_derived_properties(
+            &instance,
+            &schema,
+            &[requested.to_string()],
+            &[],
+        )
+    }
+
+    fn cycle_path(err: anyhow::Error) -> Vec<String> {
+        match err.downcast::<DerivedEvaluationError>().unwrap() {
+            DerivedEvaluationError::Cycle { path } => path,
+        }
+    }
+
+    #[test]
+    fn test_derived_self_reference_is_a_cycle() {
+        let total = derived(
+            "totalPrice",
+            Expr::Add {
+                left: prop("totalPrice"),
+                right: num(1.0),
+            },
+        );
+        let err = evaluate_derived(vec![total], "totalPrice").unwrap_err();
+        assert_eq!(cycle_path(err), vec!["totalPrice", "totalPrice"]);
+    }
+
+    #[test]
+    fn test_derived_two_step_cycle() {
+        let total = derived(
+            "totalPrice",
+            Expr::Add {
+                left: prop("price"),
+                right: prop("markup"),
+            },
+        );
+        let markup = derived(
+            "markup",
+            Expr::Mul {
+                left: prop("totalPrice"),
+                right: num(0.1),
+            },
+        );
+        let err = evaluate_derived(vec![total, markup], "totalPrice").unwrap_err();
+        assert_eq!(cycle_path(err), vec!["totalPrice", "markup", "totalPrice"]);
+    }
+
+    #[test]
+    fn test_deep_acyclic_derived_chain() {
+        // step0 = price + 1, stepN = step(N-1) + 1
+        let mut chain = vec![derived(
+            "step0",
+            Expr::Add {
+                left: prop("price"),
+                right: num(1.0),
+            },
+        )];
+        for i in 1..50 {
+            chain.push(derived(
+                &format!("step{}", i),
+                Expr::Add {
+                    left: prop(&format!("step{}", i - 1)),
+                    right: num(1.0),
+                },
+            ));
+        }
+        let values = evaluate_derived(chain, "step49").unwrap();
+        assert_eq!(values["step49"].as_f64(), Some(150.0));
+    }
+}
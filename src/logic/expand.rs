@@ -1,14 +1,76 @@
+use crate::logic::pool_resolution::{PoolCache, PoolKey, PoolSource};
 use crate::model::{
-    ExpandedInstance, Id, Instance, PropertyValue, RelationshipSelection, ResolutionDetails,
-    ResolutionMethod, ResolvedRelationship, Schema,
+    BackReference, ExpandedInstance, Id, IncludedInstance, Instance, InstanceReference,
+    PropertyValue, RelationshipSelection, ResolutionDetails, ResolutionMethod,
+    ResolvedRelationship, Schema,
 };
 use crate::store::traits::Store;
 use anyhow::Result;
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::Instant;
 
 pub struct Expander;
 
+/// How a single relationship named in the `expand` query parameter should be rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpandMode {
+    /// Ids plus full resolution details
+    Full,
+    /// Ids and a count only; resolution details are dropped
+    Count,
+}
+
+/// Parsed `expand` parameter, e.g. `components:count,size`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpandSpec {
+    /// Relationship id or name -> mode; relationships not listed keep full output
+    pub modes: HashMap<String, ExpandMode>,
+    /// `inverse` was listed: fill `referenced_by` with the instances pointing at this one
+    pub inverse: bool,
+}
+
+impl ExpandSpec {
+    pub fn parse(expand: Option<&str>) -> Result<Self> {
+        let mut modes = HashMap::new();
+        let mut inverse = false;
+        for entry in expand.unwrap_or_default().split(',') {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            if entry == "inverse" {
+                inverse = true;
+                continue;
+            }
+            let (name, mode) = match entry.split_once(':') {
+                Some((name, "count")) => (name, ExpandMode::Count),
+                Some((name, "full")) => (name, ExpandMode::Full),
+                Some((_, mode)) => {
+                    return Err(anyhow::anyhow!(
+                        "Unknown expand mode '{}' in '{}'. Expected 'count' or 'full'",
+                        mode,
+                        entry
+                    ))
+                }
+                None => (entry, ExpandMode::Full),
+            };
+            modes.insert(name.to_string(), mode);
+        }
+        Ok(Self { modes, inverse })
+    }
+
+    fn mode_for(&self, rel_def: &crate::model::RelationshipDef) -> ExpandMode {
+        self.modes
+            .get(&rel_def.id)
+            .or_else(|| self.modes.get(&rel_def.name))
+            .copied()
+            .unwrap_or(ExpandMode::Full)
+    }
+}
+
 impl Expander {
     pub async fn expand_instance(
         instance: &Instance,
@@ -16,13 +78,245 @@ impl Expander {
         schema: &Schema,
     ) -> Result<ExpandedInstance> {
         // Simple expansion - just resolve relationships using schema default pools
-        Self::expand_simple(instance, schema, other_instances).await
+        Self::expand_simple(instance, schema, other_instances, None).await
+    }
+
+    /// Expand an instance, rendering relationships marked `:count` as ids and a count only
+    pub async fn expand_instance_with_spec(
+        instance: &Instance,
+        other_instances: &[Instance],
+        schema: &Schema,
+        spec: &ExpandSpec,
+    ) -> Result<ExpandedInstance> {
+        Self::expand_instance_with_cache(instance, other_instances, schema, spec, None).await
+    }
+
+    /// `expand_instance_with_spec`, resolving relationship pools through `cache` so instances
+    /// expanded in the same request share them. `other_instances` must be the branch state
+    /// the cache was created for.
+    pub async fn expand_instance_with_cache(
+        instance: &Instance,
+        other_instances: &[Instance],
+        schema: &Schema,
+        spec: &ExpandSpec,
+        cache: Option<&PoolCache>,
+    ) -> Result<ExpandedInstance> {
+        let mut expanded = Self::expand_simple(instance, schema, other_instances, cache).await?;
+        Self::apply_expand_spec(&mut expanded, schema, spec);
+        if spec.inverse {
+            expanded.referenced_by =
+                Self::resolve_inverse_relationships(&instance.id, other_instances).await;
+        }
+        Ok(expanded)
+    }
+
+    /// Expand each of `selected` against the branch state `instances`, `concurrency` at a
+    /// time on the runtime's worker threads. Only `selected` is expanded, so a list expands
+    /// just the page it returns. Results are returned in input order. A `concurrency` of 1
+    /// expands inline without spawning tasks, which is faster on a single core.
+    pub async fn expand_all(
+        selected: Vec<Instance>,
+        instances: Arc<Vec<Instance>>,
+        schema: Arc<Schema>,
+        spec: Arc<ExpandSpec>,
+        cache: Arc<PoolCache>,
+        concurrency: usize,
+    ) -> Vec<Result<ExpandedInstance>> {
+        if concurrency <= 1 {
+            let mut expanded = Vec::with_capacity(selected.len());
+            for instance in &selected {
+                expanded.push(
+                    Self::expand_instance_with_cache(
+                        instance,
+                        &instances,
+                        &schema,
+                        &spec,
+                        Some(&cache),
+                    )
+                    .await,
+                );
+            }
+            return expanded;
+        }
+
+        futures_util::stream::iter(selected)
+            .map(|instance| {
+                let instances = Arc::clone(&instances);
+                let schema = Arc::clone(&schema);
+                let spec = Arc::clone(&spec);
+                let cache = Arc::clone(&cache);
+                // Spawned when `buffered` pulls it, so at most `concurrency` run at once
+                tokio::spawn(async move {
+                    Self::expand_instance_with_cache(
+                        &instance,
+                        &instances,
+                        &schema,
+                        &spec,
+                        Some(&cache),
+                    )
+                    .await
+                })
+            })
+            .buffered(concurrency)
+            .map(|joined| {
+                joined.unwrap_or_else(|e| {
+                    Err(anyhow::anyhow!("Instance expansion task failed: {}", e))
+                })
+            })
+            .collect()
+            .await
+    }
+
+    /// Fill `expanded.included` with the instances its relationships resolve to, each with
+    /// its own related instances included, `depth` levels deep. An instance is expanded once
+    /// per path: one that is already expanded higher up the same path is included as an
+    /// `InstanceReference` stub, so a relationship cycle ends there, while an instance
+    /// reached through several paths is expanded under each. IDs with no instance in
+    /// `other_instances` are skipped, as are relationships expanded in count mode, which
+    /// only report their ids.
+    pub async fn include_related(
+        expanded: &mut ExpandedInstance,
+        other_instances: &[Instance],
+        schema: &Schema,
+        spec: &ExpandSpec,
+        cache: Option<&PoolCache>,
+        depth: usize,
+    ) -> Result<()> {
+        let mut path = vec![expanded.id.clone()];
+        Self::include_related_on_path(
+            expanded,
+            other_instances,
+            schema,
+            spec,
+            cache,
+            depth,
+            &mut path,
+        )
+        .await
+    }
+
+    /// `include_related` below the instances of `path`, which ends with `expanded`
+    fn include_related_on_path<'a>(
+        expanded: &'a mut ExpandedInstance,
+        other_instances: &'a [Instance],
+        schema: &'a Schema,
+        spec: &'a ExpandSpec,
+        cache: Option<&'a PoolCache>,
+        depth: usize,
+        path: &'a mut Vec<Id>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if depth == 0 {
+                return Ok(());
+            }
+            let mut seen = HashSet::new();
+            for id in related_ids(expanded) {
+                if !seen.insert(id.clone()) {
+                    continue;
+                }
+                let Some(instance) = other_instances.iter().find(|i| i.id == id) else {
+                    continue;
+                };
+                if path.contains(&id) {
+                    expanded
+                        .included
+                        .push(IncludedInstance::Reference(InstanceReference {
+                            id,
+                            class_id: instance.class_id.clone(),
+                            cycle: true,
+                        }));
+                    continue;
+                }
+                let mut related = Self::expand_instance_with_cache(
+                    instance,
+                    other_instances,
+                    schema,
+                    spec,
+                    cache,
+                )
+                .await?;
+                path.push(id);
+                Self::include_related_on_path(
+                    &mut related,
+                    other_instances,
+                    schema,
+                    spec,
+                    cache,
+                    depth - 1,
+                    path,
+                )
+                .await?;
+                path.pop();
+                expanded
+                    .included
+                    .push(IncludedInstance::Expanded(Box::new(related)));
+            }
+            Ok(())
+        })
+    }
+
+    /// Find every instance whose explicit relationship selections resolve to `instance_id`,
+    /// together with the relationship it is referenced through.
+    ///
+    /// Every selection in the branch is resolved, so this is linear in the branch size per
+    /// call. Relationships left to their schema default pool are not counted, and selections
+    /// that fail to resolve are skipped.
+    pub async fn resolve_inverse_relationships(
+        instance_id: &str,
+        instances: &[Instance],
+    ) -> Vec<BackReference> {
+        let mut referenced_by = Vec::new();
+        for referrer in instances {
+            let mut relationship_names: Vec<&String> = referrer.relationships.keys().collect();
+            relationship_names.sort();
+            for relationship_name in relationship_names {
+                let selection = &referrer.relationships[relationship_name];
+                let Ok(resolved) =
+                    Self::resolve_selection_enhanced_with_branch(instances, selection).await
+                else {
+                    continue;
+                };
+                if resolved.materialized_ids.iter().any(|id| id == instance_id) {
+                    referenced_by.push(BackReference {
+                        instance_id: referrer.id.clone(),
+                        class_id: referrer.class_id.clone(),
+                        relationship: relationship_name.clone(),
+                    });
+                }
+            }
+        }
+        referenced_by
+    }
+
+    /// Reduce relationships requested in count mode to their ids and count
+    pub fn apply_expand_spec(expanded: &mut ExpandedInstance, schema: &Schema, spec: &ExpandSpec) {
+        if spec.modes.is_empty() {
+            return;
+        }
+        let Some(class_def) = schema.get_class_by_id(&expanded.class_id) else {
+            return;
+        };
+
+        for rel_def in &class_def.relationships {
+            if spec.mode_for(rel_def) != ExpandMode::Count {
+                continue;
+            }
+            if let Some(resolved) = expanded.relationships.get_mut(&rel_def.id) {
+                // Failed entries keep their details so the error stays explainable
+                if resolved.is_failed() {
+                    continue;
+                }
+                resolved.count = Some(resolved.materialized_ids.len());
+                resolved.resolution_details = None;
+            }
+        }
     }
 
     async fn expand_simple(
         instance: &Instance,
         schema: &Schema,
         other_instances: &[Instance],
+        cache: Option<&PoolCache>,
     ) -> Result<ExpandedInstance> {
         let mut expanded_props = HashMap::new();
 
@@ -61,16 +355,52 @@ impl Expander {
             // Check if instance has explicit relationship data
             let instance_relationship = instance.relationships.get(relationship_name);
 
-            let resolved_rel = if let Some(existing_selection) = instance_relationship {
+            // A failing relationship is recorded on its own entry so the rest still resolve
+            let resolved_rel = if let Some(forward) = &rel_def.inverse_of {
+                // Back-references are always computed, never read from the instance
+                Self::resolve_inverse_relationship(
+                    instance,
+                    rel_def,
+                    forward,
+                    schema,
+                    other_instances,
+                )
+                .await
+                .unwrap_or_else(|e| {
+                    ResolvedRelationship::failed(e.to_string(), serde_json::to_value(rel_def).ok())
+                })
+            } else if let Some(existing_selection) = instance_relationship {
                 // Use existing instance relationship selection
-                Self::resolve_selection_enhanced_with_branch(other_instances, existing_selection)
-                    .await?
+                Self::resolve_selection_with_cache(other_instances, existing_selection, cache)
+                    .await
+                    .unwrap_or_else(|e| {
+                        ResolvedRelationship::failed(
+                            e.to_string(),
+                            serde_json::to_value(existing_selection).ok(),
+                        )
+                    })
+            } else if let Some(missing) = rel_def
+                .targets
+                .iter()
+                .find(|target| schema.get_class_by_id(target).is_none())
+            {
+                ResolvedRelationship::failed(
+                    format!(
+                        "Target class '{}' of relationship '{}' not found in schema",
+                        missing, rel_def.name
+                    ),
+                    serde_json::to_value(rel_def).ok(),
+                )
             } else {
                 // No explicit relationship data - resolve using schema default pool
-                let resolved_relationship =
-                    Self::resolve_relationship_from_schema(other_instances, rel_def).await?;
-
-                resolved_relationship
+                Self::resolve_relationship_from_schema_with_cache(other_instances, rel_def, cache)
+                    .await
+                    .unwrap_or_else(|e| {
+                        ResolvedRelationship::failed(
+                            e.to_string(),
+                            serde_json::to_value(rel_def).ok(),
+                        )
+                    })
             };
 
             expanded_rels.insert(relationship_name.clone(), resolved_rel);
@@ -83,11 +413,14 @@ impl Expander {
             properties: expanded_props,
             relationships: expanded_rels,
             included: Vec::new(),
+            referenced_by: Vec::new(),
             local_domains: instance.local_domains.clone(),
             created_by: instance.created_by.clone(),
             created_at: instance.created_at,
             updated_by: instance.updated_by.clone(),
             updated_at: instance.updated_at,
+            deleted_at: instance.deleted_at,
+            deleted_by: instance.deleted_by.clone(),
         })
     }
 
@@ -126,17 +459,24 @@ impl Expander {
     pub async fn resolve_relationship_from_schema(
         other_instances: &[Instance],
         rel_def: &crate::model::RelationshipDef,
+    ) -> Result<ResolvedRelationship> {
+        Self::resolve_relationship_from_schema_with_cache(other_instances, rel_def, None).await
+    }
+
+    /// `resolve_relationship_from_schema`, reusing the default pool from `cache` when present
+    pub async fn resolve_relationship_from_schema_with_cache(
+        other_instances: &[Instance],
+        rel_def: &crate::model::RelationshipDef,
+        cache: Option<&PoolCache>,
     ) -> Result<ResolvedRelationship> {
         use crate::logic::pool_resolution::{PoolResolver, SelectionResult};
         use crate::model::Quantifier;
 
         let start_time = Instant::now();
 
-        // Step 1: Get all instances and resolve effective pool
-        let instances = other_instances.to_vec();
-        let effective_pool = PoolResolver::resolve_effective_pool(
-            &instances, rel_def, None, // No instance override
-        )?;
+        // Step 1: Resolve the effective pool from the schema default, no instance override
+        let effective_pool =
+            PoolResolver::resolve_effective_pool_cached(other_instances, rel_def, None, cache)?;
 
         // Step 2: For default pool resolution, show the full pool as unresolved
         // This allows the frontend/user to see all available options and make selections
@@ -190,6 +530,103 @@ impl Expander {
                 resolution_time_us: Some(elapsed.as_micros() as u64),
                 notes,
             }),
+            error: None,
+            count: None,
+        })
+    }
+
+    /// Resolve an `inverse_of` relationship by scanning the forward relationship of every
+    /// instance of the target class(es) and keeping those that point back at `instance`.
+    ///
+    /// This is O(n) forward resolutions per expanded instance, where n is the number of
+    /// target-class instances, so expanding a whole branch is O(n * m). Forward relationships
+    /// left to an unresolved schema default pool are not counted as references.
+    pub async fn resolve_inverse_relationship(
+        instance: &Instance,
+        rel_def: &crate::model::RelationshipDef,
+        forward_name: &str,
+        schema: &Schema,
+        other_instances: &[Instance],
+    ) -> Result<ResolvedRelationship> {
+        let start_time = Instant::now();
+
+        let mut materialized_ids: Vec<Id> = Vec::new();
+        let mut scanned = 0;
+        for target_class_id in &rel_def.targets {
+            let target_class = schema.get_class_by_id(target_class_id).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Target class '{}' of relationship '{}' not found in schema",
+                    target_class_id,
+                    rel_def.name
+                )
+            })?;
+            let forward_def = target_class
+                .relationships
+                .iter()
+                .find(|r| r.id == forward_name || r.name == forward_name)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Relationship '{}' is inverse of '{}', which is not defined on class '{}'",
+                        rel_def.name,
+                        forward_name,
+                        target_class.name
+                    )
+                })?;
+            if forward_def.is_inverse() {
+                return Err(anyhow::anyhow!(
+                    "Relationship '{}' cannot be the inverse of another inverse relationship '{}'",
+                    rel_def.name,
+                    forward_def.name
+                ));
+            }
+
+            for candidate in other_instances
+                .iter()
+                .filter(|i| &i.class_id == target_class_id)
+            {
+                scanned += 1;
+                let selection = candidate
+                    .relationships
+                    .get(&forward_def.id)
+                    .or_else(|| candidate.relationships.get(&forward_def.name));
+                let forward = match selection {
+                    Some(selection) => {
+                        Self::resolve_selection_enhanced_with_branch(other_instances, selection)
+                            .await?
+                    }
+                    None => Self::resolve_relationship_from_schema(other_instances, forward_def)
+                        .await?,
+                };
+                if forward.resolution_method == ResolutionMethod::SchemaDefaultResolved {
+                    continue;
+                }
+                if forward.materialized_ids.contains(&instance.id)
+                    && !materialized_ids.contains(&candidate.id)
+                {
+                    materialized_ids.push(candidate.id.clone());
+                }
+            }
+        }
+
+        let elapsed = start_time.elapsed();
+
+        Ok(ResolvedRelationship {
+            resolution_details: Some(ResolutionDetails {
+                original_definition: Some(serde_json::to_value(rel_def).unwrap_or_default()),
+                resolved_from: Some(format!("inverse_of:{}", forward_name)),
+                filter_description: None,
+                total_pool_size: Some(scanned),
+                filtered_out_count: Some(scanned - materialized_ids.len()),
+                resolution_time_us: Some(elapsed.as_micros() as u64),
+                notes: vec![format!(
+                    "Scanned {} instances for back-references via '{}'",
+                    scanned, forward_name
+                )],
+            }),
+            materialized_ids,
+            resolution_method: ResolutionMethod::InverseResolved,
+            error: None,
+            count: None,
         })
     }
 
@@ -205,6 +642,15 @@ impl Expander {
     pub async fn resolve_selection_enhanced_with_branch(
         other_instances: &[Instance],
         selection: &RelationshipSelection,
+    ) -> Result<ResolvedRelationship> {
+        Self::resolve_selection_with_cache(other_instances, selection, None).await
+    }
+
+    /// `resolve_selection_enhanced_with_branch`, reusing filtered pools from `cache` when present
+    pub async fn resolve_selection_with_cache(
+        other_instances: &[Instance],
+        selection: &RelationshipSelection,
+        cache: Option<&PoolCache>,
     ) -> Result<ResolvedRelationship> {
         let start_time = Instant::now();
 
@@ -238,7 +684,7 @@ impl Expander {
             RelationshipSelection::PoolBased { pool, selection } => {
                 // Resolve the pool first
                 let pool_instances = if let Some(pool_filter) = pool {
-                    Self::resolve_pool_filter(other_instances, pool_filter).await?
+                    Self::resolve_pool_filter(other_instances, pool_filter, cache)?
                 } else {
                     Vec::new() // No pool filter means we'd need all instances (branch context needed)
                 };
@@ -274,7 +720,7 @@ impl Expander {
                     }
                     Some(crate::model::SelectionSpec::Filter(filter)) => {
                         let filtered_ids =
-                            Self::resolve_pool_filter(other_instances, filter).await?;
+                            Self::resolve_pool_filter(other_instances, filter, cache)?;
                         (
                             filtered_ids.clone(),
                             ResolutionMethod::PoolFilterResolved,
@@ -324,7 +770,7 @@ impl Expander {
                 )
             }
             RelationshipSelection::Filter { filter } => {
-                let filtered_ids = Self::resolve_pool_filter(other_instances, filter).await?;
+                let filtered_ids = Self::resolve_pool_filter(other_instances, filter, cache)?;
                 (
                     filtered_ids.clone(),
                     ResolutionMethod::DynamicSelectorResolved,
@@ -371,6 +817,8 @@ impl Expander {
             materialized_ids: ids,
             resolution_method: method,
             resolution_details: final_details,
+            error: None,
+            count: None,
         })
     }
 
@@ -384,32 +832,40 @@ impl Expander {
         Ok(resolved.materialized_ids)
     }
 
-    async fn resolve_pool_filter(
+    fn resolve_pool_filter(
+        other_instances: &[Instance],
+        filter: &crate::model::InstanceFilter,
+        cache: Option<&PoolCache>,
+    ) -> Result<Vec<Id>> {
+        match cache {
+            Some(cache) => cache
+                .get_or_resolve(PoolKey::new(PoolSource::ExpanderFilter, filter), || {
+                    Self::filter_pool(other_instances, filter)
+                }),
+            None => Self::filter_pool(other_instances, filter),
+        }
+    }
+
+    fn filter_pool(
         other_instances: &[Instance],
         filter: &crate::model::InstanceFilter,
     ) -> Result<Vec<Id>> {
         // Get instances from ONLY the specified branch - NEVER cross database boundaries!
 
         if let Some(types) = &filter.types {
-            let mut matching_instances = other_instances
-                .iter()
-                .filter(|i| {
-                    let containes_type = types.contains(&i.class_id);
-                    if !containes_type {
-                        return false;
-                    }
-                    // If there's a where_clause, apply it
-                    if let Some(where_clause) = &filter.where_clause {
-                        return crate::logic::InstanceFilterEvaluator::evaluate_filter(
-                            i,
-                            where_clause,
-                        )
-                        .unwrap_or(false);
+            let mut matching_instances = Vec::new();
+            for i in other_instances {
+                if !types.contains(&i.class_id) {
+                    continue;
+                }
+                // If there's a where_clause, apply it (invalid filters are reported, not skipped)
+                if let Some(where_clause) = &filter.where_clause {
+                    if !crate::logic::InstanceFilterEvaluator::evaluate_filter(i, where_clause)? {
+                        continue;
                     }
-                    true
-                })
-                .cloned()
-                .collect::<Vec<_>>();
+                }
+                matching_instances.push(i.clone());
+            }
 
             // Apply sorting if present
             if let Some(sort_field) = &filter.sort {
@@ -510,6 +966,682 @@ impl Expander {
         let mut seen = HashSet::new();
         expanded
             .included
-            .retain(|instance| seen.insert(instance.id.clone()));
+            .retain(|instance| seen.insert(instance.id().clone()));
+    }
+}
+
+/// Resolved IDs of every relationship, in relationship name order
+fn related_ids(expanded: &ExpandedInstance) -> Vec<Id> {
+    let mut names: Vec<&String> = expanded
+        .relationships
+        .iter()
+        .filter(|(_, resolved)| resolved.count.is_none())
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+    names
+        .into_iter()
+        .flat_map(|name| {
+            expanded.relationships[name]
+                .materialized_ids
+                .iter()
+                .cloned()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::{FilterExpr, JsonPath};
+    use crate::model::{ClassDef, DefaultPool, InstanceFilter, Quantifier, RelationshipDef};
+
+    fn relationship(id: &str, targets: &[&str]) -> RelationshipDef {
+        RelationshipDef {
+            id: id.to_string(),
+            name: id.to_string(),
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            quantifier: Quantifier::Any,
+            universe: None,
+            selection: crate::model::SelectionType::ExplicitOrFilter,
+            default_pool: DefaultPool::All,
+            inverse_of: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expand_reports_failed_relationships_individually() {
+        let schema = Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![
+                ClassDef {
+                    id: "class-car".to_string(),
+                    name: "Car".to_string(),
+                    relationships: vec![
+                        relationship("explicit", &["class-color"]),
+                        relationship("bad-filter", &["class-color"]),
+                        relationship("default-pool", &["class-color"]),
+                        relationship("missing-target", &["class-missing"]),
+                    ],
+                    ..ClassDef::default()
+                },
+                ClassDef {
+                    id: "class-color".to_string(),
+                    name: "Color".to_string(),
+                    ..ClassDef::default()
+                },
+            ],
+        };
+
+        let red = Instance {
+            id: "red".to_string(),
+            class_id: "class-color".to_string(),
+            ..Instance::default()
+        };
+        let mut car = Instance {
+            id: "car".to_string(),
+            class_id: "class-car".to_string(),
+            ..Instance::default()
+        };
+        car.relationships.insert(
+            "explicit".to_string(),
+            RelationshipSelection::SimpleIds(vec!["red".to_string()]),
+        );
+        car.relationships.insert(
+            "bad-filter".to_string(),
+            RelationshipSelection::Filter {
+                filter: InstanceFilter {
+                    types: Some(vec!["class-color".to_string()]),
+                    where_clause: Some(FilterExpr::Exists {
+                        exists: JsonPath("price".to_string()),
+                    }),
+                    sort: None,
+                    limit: None,
+                },
+            },
+        );
+
+        let all_instances = vec![car.clone(), red];
+        let expanded = Expander::expand_instance(&car, &all_instances, &schema)
+            .await
+            .expect("expansion should succeed even with broken relationships");
+
+        let explicit = &expanded.relationships["explicit"];
+        assert!(!explicit.is_failed());
+        assert_eq!(explicit.materialized_ids, vec!["red".to_string()]);
+
+        let default_pool = &expanded.relationships["default-pool"];
+        assert!(!default_pool.is_failed());
+        assert_eq!(default_pool.materialized_ids, vec!["red".to_string()]);
+
+        let bad_filter = &expanded.relationships["bad-filter"];
+        assert!(bad_filter.is_failed());
+        assert!(bad_filter.error.as_deref().unwrap().contains("Invalid JSON path"));
+        assert!(bad_filter.materialized_ids.is_empty());
+
+        let missing_target = &expanded.relationships["missing-target"];
+        assert!(missing_target.is_failed());
+        assert!(missing_target
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("class-missing"));
+    }
+
+    #[tokio::test]
+    async fn test_expand_resolves_inverse_relationship_from_forward_side() {
+        let mut used_by = relationship("usedBy", &["class-product"]);
+        used_by.inverse_of = Some("components".to_string());
+        let schema = Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![
+                ClassDef {
+                    id: "class-product".to_string(),
+                    name: "Product".to_string(),
+                    relationships: vec![relationship("components", &["class-component"])],
+                    ..ClassDef::default()
+                },
+                ClassDef {
+                    id: "class-component".to_string(),
+                    name: "Component".to_string(),
+                    relationships: vec![used_by],
+                    ..ClassDef::default()
+                },
+            ],
+        };
+
+        let wheel = Instance {
+            id: "wheel".to_string(),
+            class_id: "class-component".to_string(),
+            ..Instance::default()
+        };
+        let product = |id: &str, components: &[&str]| {
+            let mut instance = Instance {
+                id: id.to_string(),
+                class_id: "class-product".to_string(),
+                ..Instance::default()
+            };
+            instance.relationships.insert(
+                "components".to_string(),
+                RelationshipSelection::SimpleIds(
+                    components.iter().map(|c| c.to_string()).collect(),
+                ),
+            );
+            instance
+        };
+        // A product with no explicit selection only has an unresolved default pool
+        let unselected = Instance {
+            id: "unselected".to_string(),
+            class_id: "class-product".to_string(),
+            ..Instance::default()
+        };
+
+        let all_instances = vec![
+            wheel.clone(),
+            product("bike", &["wheel"]),
+            product("cart", &["wheel", "axle"]),
+            product("boat", &["hull"]),
+            unselected,
+        ];
+        let expanded = Expander::expand_instance(&wheel, &all_instances, &schema)
+            .await
+            .unwrap();
+
+        let used_by = &expanded.relationships["usedBy"];
+        assert!(!used_by.is_failed());
+        assert_eq!(used_by.resolution_method, ResolutionMethod::InverseResolved);
+        assert_eq!(
+            used_by.materialized_ids,
+            vec!["bike".to_string(), "cart".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expand_count_mode_keeps_ids_and_drops_details() {
+        let schema = Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![
+                ClassDef {
+                    id: "class-product".to_string(),
+                    name: "Product".to_string(),
+                    relationships: vec![
+                        relationship("components", &["class-component"]),
+                        relationship("extras", &["class-component"]),
+                    ],
+                    ..ClassDef::default()
+                },
+                ClassDef {
+                    id: "class-component".to_string(),
+                    name: "Component".to_string(),
+                    ..ClassDef::default()
+                },
+            ],
+        };
+        let component = |id: &str| Instance {
+            id: id.to_string(),
+            class_id: "class-component".to_string(),
+            ..Instance::default()
+        };
+        let mut product = Instance {
+            id: "bike".to_string(),
+            class_id: "class-product".to_string(),
+            ..Instance::default()
+        };
+        product.relationships.insert(
+            "components".to_string(),
+            RelationshipSelection::SimpleIds(vec!["wheel".to_string(), "frame".to_string()]),
+        );
+        let all_instances = vec![product.clone(), component("wheel"), component("frame")];
+
+        let spec = ExpandSpec::parse(Some("components:count, extras")).unwrap();
+        let expanded =
+            Expander::expand_instance_with_spec(&product, &all_instances, &schema, &spec)
+                .await
+                .unwrap();
+
+        let components = &expanded.relationships["components"];
+        assert_eq!(components.count, Some(2));
+        assert_eq!(components.materialized_ids.len(), 2);
+        assert!(components.resolution_details.is_none());
+
+        let extras = &expanded.relationships["extras"];
+        assert_eq!(extras.count, None);
+        assert!(extras.resolution_details.is_some());
+
+        assert!(ExpandSpec::parse(Some("components:bodies")).is_err());
+    }
+
+    fn referrer(id: &str, class_id: &str, relationship: &str, targets: &[&str]) -> Instance {
+        let mut instance = Instance {
+            id: id.to_string(),
+            class_id: class_id.to_string(),
+            ..Instance::default()
+        };
+        instance.relationships.insert(
+            relationship.to_string(),
+            RelationshipSelection::SimpleIds(targets.iter().map(|t| t.to_string()).collect()),
+        );
+        instance
+    }
+
+    fn chair(id: &str) -> Instance {
+        Instance {
+            id: id.to_string(),
+            class_id: "class-chair".to_string(),
+            ..Instance::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inverse_single_back_reference() {
+        let instances = vec![
+            chair("chair-1"),
+            chair("chair-2"),
+            referrer("table-1", "class-table", "chairs", &["chair-1"]),
+        ];
+        let referenced_by = Expander::resolve_inverse_relationships("chair-1", &instances).await;
+        assert_eq!(
+            referenced_by,
+            vec![BackReference {
+                instance_id: "table-1".to_string(),
+                class_id: "class-table".to_string(),
+                relationship: "chairs".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inverse_multiple_referrers() {
+        let mut room = referrer("room-1", "class-room", "seating", &["chair-1"]);
+        room.relationships.insert(
+            "spares".to_string(),
+            RelationshipSelection::SimpleIds(vec!["chair-1".to_string()]),
+        );
+        let instances = vec![
+            chair("chair-1"),
+            referrer("table-1", "class-table", "chairs", &["chair-1", "chair-2"]),
+            referrer("table-2", "class-table", "chairs", &["chair-2"]),
+            room,
+        ];
+        let referenced_by = Expander::resolve_inverse_relationships("chair-1", &instances).await;
+        let pairs: Vec<(&str, &str)> = referenced_by
+            .iter()
+            .map(|r| (r.instance_id.as_str(), r.relationship.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("table-1", "chairs"),
+                ("room-1", "seating"),
+                ("room-1", "spares")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inverse_unreferenced_instance() {
+        let schema = Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![ClassDef {
+                id: "class-chair".to_string(),
+                name: "Chair".to_string(),
+                ..ClassDef::default()
+            }],
+        };
+        let lonely = chair("chair-3");
+        let instances = vec![
+            lonely.clone(),
+            referrer("table-1", "class-table", "chairs", &["chair-1"]),
+        ];
+        let spec = ExpandSpec::parse(Some("inverse")).unwrap();
+        assert!(spec.inverse && spec.modes.is_empty());
+        let expanded = Expander::expand_instance_with_spec(&lonely, &instances, &schema, &spec)
+            .await
+            .unwrap();
+        assert!(expanded.referenced_by.is_empty());
+    }
+
+    /// Chairs whose `legs` relationship resolves from one shared pool of legs: all but the
+    /// last chair use the schema default pool, the last one an explicit pool filter
+    fn shared_pool_branch(chairs: usize, legs: usize) -> (Schema, Vec<Instance>) {
+        let schema = Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![
+                ClassDef {
+                    id: "class-chair".to_string(),
+                    name: "Chair".to_string(),
+                    relationships: vec![relationship("legs", &["class-leg"])],
+                    ..ClassDef::default()
+                },
+                ClassDef {
+                    id: "class-leg".to_string(),
+                    name: "Leg".to_string(),
+                    ..ClassDef::default()
+                },
+            ],
+        };
+        let mut instances: Vec<Instance> = (0..legs)
+            .map(|i| Instance {
+                id: format!("leg-{}", i),
+                class_id: "class-leg".to_string(),
+                ..Instance::default()
+            })
+            .collect();
+        instances.extend((0..chairs).map(|i| Instance {
+            id: format!("chair-{}", i),
+            class_id: "class-chair".to_string(),
+            ..Instance::default()
+        }));
+        if let Some(last) = instances.last_mut() {
+            last.relationships.insert(
+                "legs".to_string(),
+                RelationshipSelection::PoolBased {
+                    pool: Some(InstanceFilter {
+                        types: Some(vec!["class-leg".to_string()]),
+                        where_clause: None,
+                        sort: None,
+                        limit: Some(2),
+                    }),
+                    selection: None,
+                },
+            );
+        }
+        (schema, instances)
+    }
+
+    #[tokio::test]
+    async fn test_pool_cache_gives_identical_results() {
+        let (schema, instances) = shared_pool_branch(5, 4);
+        let spec = ExpandSpec::default();
+        let cache = PoolCache::new("commit-1");
+
+        for instance in &instances {
+            let uncached =
+                Expander::expand_instance_with_spec(instance, &instances, &schema, &spec)
+                    .await
+                    .unwrap();
+            let cached = Expander::expand_instance_with_cache(
+                instance,
+                &instances,
+                &schema,
+                &spec,
+                Some(&cache),
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                cached.relationships.keys().collect::<HashSet<_>>(),
+                uncached.relationships.keys().collect::<HashSet<_>>()
+            );
+            for (name, relationship) in &uncached.relationships {
+                let cached_relationship = &cached.relationships[name];
+                assert_eq!(
+                    cached_relationship.materialized_ids,
+                    relationship.materialized_ids
+                );
+                assert_eq!(
+                    cached_relationship.resolution_method,
+                    relationship.resolution_method
+                );
+            }
+        }
+
+        // One default pool and one explicit pool filter, however many chairs share them
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pool_cache_is_busted_by_commit_change() {
+        use crate::logic::pool_resolution::PoolResolver;
+
+        let rel_def = relationship("legs", &["class-leg"]);
+        let (_, mut instances) = shared_pool_branch(0, 1);
+        let cache = PoolCache::new("commit-1");
+        let resolve = |instances: &[Instance]| {
+            PoolResolver::resolve_effective_pool_cached(instances, &rel_def, None, Some(&cache))
+                .unwrap()
+        };
+        assert_eq!(resolve(&instances), vec!["leg-0"]);
+
+        // Same commit: the cached pool is served even though the instances differ
+        instances.extend(shared_pool_branch(0, 2).1.pop());
+        assert_eq!(resolve(&instances), vec!["leg-0"]);
+
+        cache.set_commit("commit-1");
+        assert_eq!(resolve(&instances), vec!["leg-0"]);
+        cache.set_commit("commit-2");
+        assert_eq!(resolve(&instances), vec!["leg-0", "leg-1"]);
+    }
+
+    /// Timing comparison behind the numbers in the `PoolCache` docs; run with
+    /// `cargo test --release -- --ignored test_pool_cache_speedup`
+    #[tokio::test]
+    #[ignore = "timing measurement, not a correctness check"]
+    async fn test_pool_cache_speedup_on_shared_pool() {
+        let (schema, instances) = shared_pool_branch(1000, 5000);
+        let spec = ExpandSpec::default();
+
+        let start = Instant::now();
+        for instance in &instances {
+            Expander::expand_instance_with_spec(instance, &instances, &schema, &spec)
+                .await
+                .unwrap();
+        }
+        let uncached = start.elapsed();
+
+        let cache = PoolCache::new("commit-1");
+        let start = Instant::now();
+        for instance in &instances {
+            Expander::expand_instance_with_cache(
+                instance,
+                &instances,
+                &schema,
+                &spec,
+                Some(&cache),
+            )
+            .await
+            .unwrap();
+        }
+        let cached = start.elapsed();
+
+        assert!(cached < uncached);
+    }
+
+    /// Branch of `class-node` instances linked through `next`, given as (id, next ids)
+    fn node_graph(edges: &[(&str, &[&str])]) -> (Schema, Vec<Instance>) {
+        let schema = Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![ClassDef {
+                id: "class-node".to_string(),
+                relationships: vec![relationship("next", &["class-node"])],
+                ..ClassDef::default()
+            }],
+        };
+        let instances = edges
+            .iter()
+            .map(|(id, next)| referrer(id, "class-node", "next", next))
+            .collect();
+        (schema, instances)
+    }
+
+    async fn include_from(
+        (schema, instances): &(Schema, Vec<Instance>),
+        root: &str,
+        depth: usize,
+    ) -> ExpandedInstance {
+        let root = instances.iter().find(|i| i.id == root).unwrap();
+        let mut expanded = Expander::expand_instance(root, instances, schema)
+            .await
+            .unwrap();
+        Expander::include_related(
+            &mut expanded,
+            instances,
+            schema,
+            &ExpandSpec::default(),
+            None,
+            depth,
+        )
+        .await
+        .unwrap();
+        expanded
+    }
+
+    fn as_expanded(included: &IncludedInstance) -> &ExpandedInstance {
+        match included {
+            IncludedInstance::Expanded(expanded) => expanded,
+            IncludedInstance::Reference(reference) => {
+                panic!("expected {} to be expanded", reference.id)
+            }
+        }
+    }
+
+    fn cycle_stub(id: &str) -> IncludedInstance {
+        IncludedInstance::Reference(InstanceReference {
+            id: id.to_string(),
+            class_id: "class-node".to_string(),
+            cycle: true,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_include_related_skips_count_mode_relationships() {
+        let (schema, instances) = node_graph(&[("node-a", &["node-b"]), ("node-b", &[])]);
+        let spec = ExpandSpec::parse(Some("next:count")).unwrap();
+        let mut expanded =
+            Expander::expand_instance_with_spec(&instances[0], &instances, &schema, &spec)
+                .await
+                .unwrap();
+        Expander::include_related(&mut expanded, &instances, &schema, &spec, None, 3)
+            .await
+            .unwrap();
+
+        assert_eq!(expanded.relationships["next"].count, Some(1));
+        assert!(expanded.included.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_include_related_stops_at_depth() {
+        let graph = node_graph(&[
+            ("node-a", &["node-b"]),
+            ("node-b", &["node-c"]),
+            ("node-c", &[]),
+        ]);
+        let expanded = include_from(&graph, "node-a", 1).await;
+
+        let node_b = as_expanded(&expanded.included[0]);
+        assert_eq!(node_b.id, "node-b");
+        assert!(node_b.included.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_include_related_two_node_cycle() {
+        let graph = node_graph(&[("node-a", &["node-b"]), ("node-b", &["node-a"])]);
+        // Far deeper than the cycle: it ends at the first repeat of an instance on the path
+        let expanded = include_from(&graph, "node-a", 1000).await;
+
+        assert_eq!(expanded.included.len(), 1);
+        let node_b = as_expanded(&expanded.included[0]);
+        assert_eq!(node_b.id, "node-b");
+        assert_eq!(node_b.included, vec![cycle_stub("node-a")]);
+        assert_eq!(
+            serde_json::to_value(&node_b.included[0]).unwrap(),
+            serde_json::json!({"id": "node-a", "class": "class-node", "cycle": true})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_include_related_self_reference() {
+        let graph = node_graph(&[("node-a", &["node-a"])]);
+        let expanded = include_from(&graph, "node-a", 5).await;
+
+        assert_eq!(expanded.included, vec![cycle_stub("node-a")]);
+    }
+
+    #[tokio::test]
+    async fn test_include_related_diamond_expands_shared_child_per_path() {
+        let graph = node_graph(&[
+            ("node-a", &["node-b", "node-c"]),
+            ("node-b", &["node-d"]),
+            ("node-c", &["node-d"]),
+            ("node-d", &[]),
+        ]);
+        let expanded = include_from(&graph, "node-a", 3).await;
+
+        let branches: Vec<&ExpandedInstance> = expanded.included.iter().map(as_expanded).collect();
+        let ids: Vec<&str> = branches.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, vec!["node-b", "node-c"]);
+        for branch in branches {
+            // node-d is on neither path above it, so each path expands it once
+            assert_eq!(branch.included.len(), 1);
+            assert_eq!(as_expanded(&branch.included[0]).id, "node-d");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_expand_all_keeps_input_order() {
+        let (schema, instances) = shared_pool_branch(50, 3);
+        let instances = Arc::new(instances);
+        let expanded = Expander::expand_all(
+            instances.to_vec(),
+            Arc::clone(&instances),
+            Arc::new(schema),
+            Arc::new(ExpandSpec::default()),
+            Arc::new(PoolCache::new("commit-1")),
+            4,
+        )
+        .await;
+
+        let ids: Vec<Id> = expanded.into_iter().map(|e| e.unwrap().id).collect();
+        let expected: Vec<Id> = instances.iter().map(|i| i.id.clone()).collect();
+        assert_eq!(ids, expected);
+    }
+
+    /// Timing comparison behind the list expansion notes in the README; run with
+    /// `cargo test --release -- --ignored test_expand_all_speedup`. The gain depends on the
+    /// cores available, so it is only asserted with more than one.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    #[ignore = "timing measurement, not a correctness check"]
+    async fn test_expand_all_speedup_on_large_list() {
+        let (schema, instances) = shared_pool_branch(1000, 5000);
+        let spec = ExpandSpec::default();
+
+        let cache = PoolCache::new("commit-1");
+        let start = Instant::now();
+        for instance in &instances {
+            Expander::expand_instance_with_cache(
+                instance,
+                &instances,
+                &schema,
+                &spec,
+                Some(&cache),
+            )
+            .await
+            .unwrap();
+        }
+        let sequential = start.elapsed();
+
+        let start = Instant::now();
+        Expander::expand_all(
+            instances.clone(),
+            Arc::new(instances),
+            Arc::new(schema),
+            Arc::new(spec),
+            Arc::new(PoolCache::new("commit-1")),
+            8,
+        )
+        .await;
+        let concurrent = start.elapsed();
+
+        if std::thread::available_parallelism().map_or(1, |n| n.get()) > 1 {
+            assert!(concurrent < sequential);
+        }
     }
 }
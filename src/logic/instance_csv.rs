@@ -0,0 +1,418 @@
+use crate::model::{ClassDef, DataType, Id, Instance, PropertyValue, TypedValue};
+use std::collections::HashMap;
+
+/// Column holding the instance id, ahead of the class's property columns
+pub const CSV_ID_COLUMN: &str = "id";
+
+/// Header line for a class export: the id column, then one column per property name.
+/// Relationships are left out.
+pub fn csv_header(class_def: &ClassDef) -> String {
+    let columns = std::iter::once(CSV_ID_COLUMN).chain(
+        class_def
+            .properties
+            .iter()
+            .map(|property| property.name.as_str()),
+    );
+    csv_line(columns)
+}
+
+/// One CSV line for an instance, with a cell per header column. Properties the instance
+/// doesn't set (and conditional values, which have no single literal) are left empty so
+/// every row stays aligned with the header.
+pub fn csv_row(instance: &Instance, class_def: &ClassDef) -> String {
+    let cells: Vec<String> = std::iter::once(instance.id.clone())
+        .chain(class_def.properties.iter().map(|property| {
+            match instance
+                .properties
+                .get(&property.id)
+                .or_else(|| instance.properties.get(&property.name))
+            {
+                Some(PropertyValue::Literal(typed_value)) => csv_cell(&typed_value.value),
+                _ => String::new(),
+            }
+        }))
+        .collect();
+    csv_line(cells.iter().map(String::as_str))
+}
+
+/// String form of a literal: strings unquoted, null empty, objects and arrays as JSON
+fn csv_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Join fields into a CRLF-terminated line, quoting fields that contain a separator, a
+/// quote or a line break (RFC 4180)
+fn csv_line<'a>(fields: impl Iterator<Item = &'a str>) -> String {
+    let mut line = fields
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// One data row of an imported CSV, with its cells coerced to the class's property types
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvImportRow {
+    /// Line the row starts on, counting the header as line 1
+    pub line: usize,
+    /// Value of the `id` column, when present and non-empty
+    pub instance_id: Option<Id>,
+    /// Coerced properties keyed by property name; empty cells are left out
+    pub properties: HashMap<String, PropertyValue>,
+    /// Cells that couldn't be coerced, or a cell count that doesn't match the header
+    pub errors: Vec<String>,
+}
+
+/// Parse a CSV document whose header names the class's properties (and optionally `id`)
+/// into one row per record. Problems with a single row are reported on that row; an
+/// `Err` means the document as a whole can't be imported: no header, a header naming a
+/// column the class doesn't have, or a quote that is never closed.
+pub fn parse_csv_import(text: &str, class_def: &ClassDef) -> Result<Vec<CsvImportRow>, String> {
+    let mut records = csv_records(text.strip_prefix('\u{feff}').unwrap_or(text))?.into_iter();
+    let (_, header) = records
+        .next()
+        .ok_or_else(|| "CSV document has no header row".to_string())?;
+
+    let mut columns = Vec::with_capacity(header.len());
+    for (index, name) in header.iter().enumerate() {
+        let name = name.trim();
+        if header[..index].iter().any(|other| other.trim() == name) {
+            return Err(format!("Column '{}' appears more than once", name));
+        }
+        if name == CSV_ID_COLUMN {
+            columns.push(None);
+            continue;
+        }
+        match class_def.properties.iter().find(|p| p.name == name) {
+            Some(property) => columns.push(Some(property)),
+            None => {
+                return Err(format!(
+                    "Unknown column '{}': class '{}' has no such property",
+                    name, class_def.id
+                ))
+            }
+        }
+    }
+
+    Ok(records
+        .map(|(line, cells)| {
+            let mut row = CsvImportRow {
+                line,
+                instance_id: None,
+                properties: HashMap::new(),
+                errors: Vec::new(),
+            };
+            if cells.len() != columns.len() {
+                row.errors.push(format!(
+                    "Expected {} cells but found {}",
+                    columns.len(),
+                    cells.len()
+                ));
+                return row;
+            }
+            for (column, cell) in columns.iter().zip(cells) {
+                match column {
+                    None if !cell.is_empty() => row.instance_id = Some(cell),
+                    None => {}
+                    Some(_) if cell.is_empty() => {}
+                    Some(property) => match coerce_cell(&cell, &property.data_type) {
+                        Ok(value) => {
+                            row.properties.insert(
+                                property.name.clone(),
+                                PropertyValue::Literal(TypedValue {
+                                    value,
+                                    data_type: property.data_type.clone(),
+                                }),
+                            );
+                        }
+                        Err(expected) => row.errors.push(format!(
+                            "Column '{}': '{}' is not {}",
+                            property.name, cell, expected
+                        )),
+                    },
+                }
+            }
+            row
+        })
+        .collect())
+}
+
+/// Read a cell as the given type, the inverse of `csv_cell`. On failure returns a
+/// description of what was expected.
+fn coerce_cell(cell: &str, data_type: &DataType) -> Result<serde_json::Value, &'static str> {
+    match data_type {
+        DataType::String => Ok(serde_json::Value::String(cell.to_string())),
+        DataType::Number => {
+            let trimmed = cell.trim();
+            if let Ok(integer) = trimmed.parse::<i64>() {
+                return Ok(integer.into());
+            }
+            if let Ok(integer) = trimmed.parse::<u64>() {
+                return Ok(integer.into());
+            }
+            trimmed
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(serde_json::Value::Number)
+                .ok_or("a number")
+        }
+        DataType::Boolean => match cell.trim().to_ascii_lowercase().as_str() {
+            "true" => Ok(serde_json::Value::Bool(true)),
+            "false" => Ok(serde_json::Value::Bool(false)),
+            _ => Err("a boolean (true or false)"),
+        },
+        DataType::Object => match serde_json::from_str(cell) {
+            Ok(value @ serde_json::Value::Object(_)) => Ok(value),
+            _ => Err("a JSON object"),
+        },
+        DataType::Array => match serde_json::from_str(cell) {
+            Ok(value @ serde_json::Value::Array(_)) => Ok(value),
+            _ => Err("a JSON array"),
+        },
+        DataType::StringList => match serde_json::from_str::<Vec<String>>(cell) {
+            Ok(items) => Ok(items.into()),
+            Err(_) => Err("a JSON array of strings"),
+        },
+        DataType::Json => serde_json::from_str(cell).map_err(|_| "valid JSON"),
+    }
+}
+
+/// Split a CSV document into records (RFC 4180), each with the line it starts on.
+/// Blank lines are skipped.
+fn csv_records(text: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                fields.push(std::mem::take(&mut field));
+                let record = std::mem::take(&mut fields);
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push((record_line, record));
+                }
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!(
+            "Line {}: quoted field is never closed",
+            record_line
+        ));
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((record_line, fields));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{DataType, PropertyDef, TypedValue};
+
+    fn property(id: &str, name: &str, data_type: &str) -> PropertyDef {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "data_type": data_type
+        }))
+        .unwrap()
+    }
+
+    fn chair_class() -> ClassDef {
+        ClassDef {
+            id: "class-chair".to_string(),
+            name: "Chair".to_string(),
+            properties: vec![
+                property("prop-name", "name", "string"),
+                property("prop-price", "price", "number"),
+                property("prop-stackable", "stackable", "boolean"),
+                property("prop-tags", "tags", "string-list"),
+            ],
+            ..ClassDef::default()
+        }
+    }
+
+    fn chair(id: &str, values: &[(&str, serde_json::Value, DataType)]) -> Instance {
+        let mut instance = Instance {
+            id: id.to_string(),
+            class_id: "class-chair".to_string(),
+            ..Instance::default()
+        };
+        for (key, value, data_type) in values {
+            instance.properties.insert(
+                key.to_string(),
+                PropertyValue::Literal(TypedValue {
+                    value: value.clone(),
+                    data_type: data_type.clone(),
+                }),
+            );
+        }
+        instance
+    }
+
+    #[test]
+    fn test_csv_export_mixed_property_types() {
+        let class_def = chair_class();
+        assert_eq!(csv_header(&class_def), "id,name,price,stackable,tags\r\n");
+
+        let instance = chair(
+            "chair-1",
+            &[
+                (
+                    "name",
+                    serde_json::json!("Oslo, \"classic\""),
+                    DataType::String,
+                ),
+                ("prop-price", serde_json::json!(129.5), DataType::Number),
+                ("stackable", serde_json::json!(true), DataType::Boolean),
+                (
+                    "tags",
+                    serde_json::json!(["oak", "nordic"]),
+                    DataType::StringList,
+                ),
+            ],
+        );
+        assert_eq!(
+            csv_row(&instance, &class_def),
+            "chair-1,\"Oslo, \"\"classic\"\"\",129.5,true,\"[\"\"oak\"\",\"\"nordic\"\"]\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_import_round_trips_export() {
+        let class_def = chair_class();
+        let exported = chair(
+            "chair-1",
+            &[
+                (
+                    "name",
+                    serde_json::json!("Oslo, \"classic\""),
+                    DataType::String,
+                ),
+                ("price", serde_json::json!(129.5), DataType::Number),
+                ("stackable", serde_json::json!(true), DataType::Boolean),
+                (
+                    "tags",
+                    serde_json::json!(["oak", "nordic"]),
+                    DataType::StringList,
+                ),
+            ],
+        );
+        let text =
+            csv_header(&class_def) + &csv_row(&exported, &class_def) + "\r\n,Bergen,80,,\r\n";
+
+        let rows = parse_csv_import(&text, &class_def).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[0].instance_id.as_deref(), Some("chair-1"));
+        assert_eq!(rows[0].properties, exported.properties);
+        assert!(rows[0].errors.is_empty());
+
+        // Blank lines are skipped but still counted, and empty cells are left out
+        assert_eq!(rows[1].line, 4);
+        assert_eq!(rows[1].instance_id, None);
+        assert_eq!(rows[1].properties.len(), 2);
+        assert_eq!(
+            rows[1].properties.get("price"),
+            Some(&PropertyValue::Literal(TypedValue {
+                value: serde_json::json!(80),
+                data_type: DataType::Number,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_csv_import_reports_bad_cells_per_row() {
+        let class_def = chair_class();
+        let text = "id,price,stackable\nchair-1,12.5,true\nchair-2,cheap,maybe\nchair-3,7\n";
+        let rows = parse_csv_import(text, &class_def).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].errors.is_empty());
+        assert_eq!(rows[1].line, 3);
+        assert_eq!(
+            rows[1].errors,
+            vec![
+                "Column 'price': 'cheap' is not a number".to_string(),
+                "Column 'stackable': 'maybe' is not a boolean (true or false)".to_string(),
+            ]
+        );
+        assert_eq!(
+            rows[2].errors,
+            vec!["Expected 3 cells but found 2".to_string()]
+        );
+
+        let err = parse_csv_import("id,colour\nchair-1,red\n", &class_def).unwrap_err();
+        assert!(err.contains("Unknown column 'colour'"));
+        assert!(parse_csv_import("id,name\nchair-1,\"Oslo\n", &class_def).is_err());
+    }
+
+    #[test]
+    fn test_csv_import_keeps_large_integers_exact() {
+        for (cell, expected) in [
+            ("9223372036854775807", serde_json::json!(i64::MAX)),
+            ("18446744073709551615", serde_json::json!(u64::MAX)),
+            ("-9007199254740993", serde_json::json!(-9007199254740993_i64)),
+        ] {
+            assert_eq!(coerce_cell(cell, &DataType::Number), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_csv_export_leaves_missing_properties_empty() {
+        let class_def = chair_class();
+        let instance = chair(
+            "chair-2",
+            &[
+                ("price", serde_json::json!(80), DataType::Number),
+                ("stackable", serde_json::Value::Null, DataType::Boolean),
+            ],
+        );
+        let row = csv_row(&instance, &class_def);
+        assert_eq!(row, "chair-2,,80,,\r\n");
+        assert_eq!(
+            row.split(',').count(),
+            csv_header(&class_def).split(',').count()
+        );
+    }
+}
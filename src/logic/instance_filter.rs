@@ -2,20 +2,31 @@ use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::model::{Instance, PropertyValue};
+use crate::logic::{PoolResolver, SelectionResult};
+use crate::model::{Instance, PropertyValue, RelationshipSelection, Schema, SelectionSpec};
 
 /// Complex filter expression that can be deserialized from JSON
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum FilterExpr {
-    /// Logical AND - all conditions must be true
-    All { all: Vec<FilterExpr> },
-    /// Logical OR - any condition must be true
-    Any { any: Vec<FilterExpr> },
+    /// Logical AND - all conditions must be true (`and` is accepted as an alias).
+    /// An empty list is true; evaluation stops at the first false condition.
+    All {
+        #[serde(alias = "and")]
+        all: Vec<FilterExpr>,
+    },
+    /// Logical OR - any condition must be true (`or` is accepted as an alias).
+    /// An empty list is false; evaluation stops at the first true condition.
+    Any {
+        #[serde(alias = "or")]
+        any: Vec<FilterExpr>,
+    },
     /// Logical NOT - condition must be false
     Not { not: Box<FilterExpr> },
     /// Equality check
     Eq { eq: (JsonPath, Value) },
+    /// Case-insensitive equality check for strings; other values compare exactly
+    EqI { eq_i: (JsonPath, Value) },
     /// Not equal check
     Ne { ne: (JsonPath, Value) },
     /// Greater than check
@@ -26,16 +37,39 @@ pub enum FilterExpr {
     Lt { lt: (JsonPath, Value) },
     /// Less than or equal check
     Lte { lte: (JsonPath, Value) },
+    /// Inclusive range check: `[path, lower, upper]`. Numeric when the value and both
+    /// bounds coerce to numbers, lexical when they are all strings (e.g. RFC3339 dates).
+    /// Reversed bounds are swapped.
+    Between { between: (JsonPath, Value, Value) },
     /// Check if value is in a list
     In { r#in: (JsonPath, Vec<Value>) },
     /// Check if value is not in a list
     NotIn { not_in: (JsonPath, Vec<Value>) },
-    /// Check if string contains substring
+    /// Check if the value contains a substring. Numbers and booleans are matched on their
+    /// string form, so a numeric SKU can be searched by digits.
     Contains { contains: (JsonPath, String) },
+    /// Case-insensitive `contains`
+    ContainsI { contains_i: (JsonPath, String) },
+    /// Check if the value's string form starts with a prefix
+    StartsWith { starts_with: (JsonPath, String) },
+    /// Case-insensitive `starts_with`
+    StartsWithI { starts_with_i: (JsonPath, String) },
+    /// Check if the value's string form ends with a suffix
+    EndsWith { ends_with: (JsonPath, String) },
+    /// Case-insensitive `ends_with`
+    EndsWithI { ends_with_i: (JsonPath, String) },
     /// Check if property exists
     Exists { exists: JsonPath },
     /// Check if property does not exist
     NotExists { not_exists: JsonPath },
+    /// Check if the value is null: an absent property and an explicit JSON null both count.
+    /// Use `exists`/`not_exists` to tell the two apart.
+    IsNull { is_null: JsonPath },
+    /// Check if the property is set to a non-null value
+    IsNotNull { is_not_null: JsonPath },
+    /// Check if an array contains a scalar. When the path names a relationship rather than
+    /// an array property, the relationship's selection is resolved to instance IDs first.
+    ArrayContains { array_contains: (JsonPath, Value) },
 }
 
 /// JSON path for accessing instance properties
@@ -68,12 +102,188 @@ impl JsonPath {
                     }
                 }
             } else {
-                Ok(None)
+                Ok(Self::extract_nested(instance, prop_name))
             }
         } else {
             Err(anyhow!("Invalid JSON path: {}", path))
         }
     }
+
+    /// Resolve paths like `$.dimensions.width`, `$.tags[0]` or `$.metadata.items.2.sku` into
+    /// the structured value of a literal property. Array elements are addressed by `[n]` or
+    /// by a numeric segment; a missing segment or out-of-range index yields `None`.
+    fn extract_nested(instance: &Instance, path: &str) -> Option<Value> {
+        let segments = Self::parse_segments(path)?;
+        let (PathSegment::Key(prop_name), rest) = segments.split_first()? else {
+            return None;
+        };
+        let PropertyValue::Literal(typed_val) = instance.properties.get(*prop_name)? else {
+            return None;
+        };
+
+        let mut current = &typed_val.value;
+        for segment in rest {
+            current = match (current, segment) {
+                (Value::Object(map), PathSegment::Key(key)) => map.get(*key)?,
+                (Value::Array(items), PathSegment::Index(index)) => items.get(*index)?,
+                (Value::Array(items), PathSegment::Key(key)) => {
+                    items.get(key.parse::<usize>().ok()?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(current.clone())
+    }
+
+    /// Point a path into property `old` (`$.old`, `$.old.width`, `$.old[0]`) at property
+    /// `new` instead, keeping any nested segments; false for paths into other properties
+    pub fn rename_property(&mut self, old: &str, new: &str) -> bool {
+        let Some(rest) = self.0.strip_prefix("$.").and_then(|path| path.strip_prefix(old)) else {
+            return false;
+        };
+        if !(rest.is_empty() || rest.starts_with('.') || rest.starts_with('[')) {
+            return false;
+        }
+        self.0 = format!("$.{}{}", new, rest);
+        true
+    }
+
+    /// Split `a.b[0][1].c` into keys and indices; `None` for malformed brackets
+    fn parse_segments(path: &str) -> Option<Vec<PathSegment<'_>>> {
+        let mut segments = Vec::new();
+        for part in path.split('.') {
+            let (key, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+            if !key.is_empty() {
+                segments.push(PathSegment::Key(key));
+            } else if indices.is_empty() {
+                return None;
+            }
+            while !indices.is_empty() {
+                let (index, rest) = indices.strip_prefix('[')?.split_once(']')?;
+                segments.push(PathSegment::Index(index.parse().ok()?));
+                indices = rest;
+            }
+        }
+        Some(segments)
+    }
+}
+
+/// One step of a nested property path
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+impl FilterExpr {
+    /// All property paths referenced anywhere in this expression
+    pub fn paths(&self) -> Vec<&JsonPath> {
+        match self {
+            FilterExpr::All { all: exprs } | FilterExpr::Any { any: exprs } => {
+                exprs.iter().flat_map(|e| e.paths()).collect()
+            }
+            FilterExpr::Not { not } => not.paths(),
+            FilterExpr::Eq { eq: (path, _) }
+            | FilterExpr::EqI { eq_i: (path, _) }
+            | FilterExpr::Ne { ne: (path, _) }
+            | FilterExpr::Gt { gt: (path, _) }
+            | FilterExpr::Gte { gte: (path, _) }
+            | FilterExpr::Lt { lt: (path, _) }
+            | FilterExpr::Lte { lte: (path, _) }
+            | FilterExpr::Between { between: (path, _, _) } => vec![path],
+            FilterExpr::In { r#in: (path, _) } | FilterExpr::NotIn { not_in: (path, _) } => {
+                vec![path]
+            }
+            FilterExpr::Contains { contains: (path, _) }
+            | FilterExpr::ContainsI { contains_i: (path, _) }
+            | FilterExpr::StartsWith { starts_with: (path, _) }
+            | FilterExpr::StartsWithI { starts_with_i: (path, _) }
+            | FilterExpr::EndsWith { ends_with: (path, _) }
+            | FilterExpr::EndsWithI { ends_with_i: (path, _) } => vec![path],
+            FilterExpr::Exists { exists: path } | FilterExpr::NotExists { not_exists: path } => {
+                vec![path]
+            }
+            FilterExpr::IsNull { is_null: path } | FilterExpr::IsNotNull { is_not_null: path } => {
+                vec![path]
+            }
+            FilterExpr::ArrayContains { array_contains: (path, _) } => vec![path],
+        }
+    }
+
+    /// Mutable access to every property path, in the same order as `paths`
+    pub fn paths_mut(&mut self) -> Vec<&mut JsonPath> {
+        match self {
+            FilterExpr::All { all: exprs } | FilterExpr::Any { any: exprs } => {
+                exprs.iter_mut().flat_map(|e| e.paths_mut()).collect()
+            }
+            FilterExpr::Not { not } => not.paths_mut(),
+            FilterExpr::Eq { eq: (path, _) }
+            | FilterExpr::EqI { eq_i: (path, _) }
+            | FilterExpr::Ne { ne: (path, _) }
+            | FilterExpr::Gt { gt: (path, _) }
+            | FilterExpr::Gte { gte: (path, _) }
+            | FilterExpr::Lt { lt: (path, _) }
+            | FilterExpr::Lte { lte: (path, _) }
+            | FilterExpr::Between { between: (path, _, _) } => vec![path],
+            FilterExpr::In { r#in: (path, _) } | FilterExpr::NotIn { not_in: (path, _) } => {
+                vec![path]
+            }
+            FilterExpr::Contains { contains: (path, _) }
+            | FilterExpr::ContainsI { contains_i: (path, _) }
+            | FilterExpr::StartsWith { starts_with: (path, _) }
+            | FilterExpr::StartsWithI { starts_with_i: (path, _) }
+            | FilterExpr::EndsWith { ends_with: (path, _) }
+            | FilterExpr::EndsWithI { ends_with_i: (path, _) } => vec![path],
+            FilterExpr::Exists { exists: path } | FilterExpr::NotExists { not_exists: path } => {
+                vec![path]
+            }
+            FilterExpr::IsNull { is_null: path } | FilterExpr::IsNotNull { is_not_null: path } => {
+                vec![path]
+            }
+            FilterExpr::ArrayContains { array_contains: (path, _) } => vec![path],
+        }
+    }
+
+    /// Refuse an expression that reads a property marked `encrypted` on any class of the
+    /// schema. Encrypted values are never filterable, whichever store or endpoint runs the
+    /// filter.
+    pub fn check_filterable(&self, schema: &Schema) -> Result<()> {
+        let encrypted: Vec<String> = schema
+            .classes
+            .iter()
+            .flat_map(|c| crate::store::encryption::encrypted_property_keys(schema, &c.id))
+            .collect();
+        if encrypted.is_empty() {
+            return Ok(());
+        }
+        match self.paths().into_iter().find(|path| {
+            encrypted
+                .iter()
+                .any(|key| path.0.strip_prefix("$.") == Some(key.as_str()))
+        }) {
+            Some(path) => Err(anyhow!(
+                "Property '{}' is encrypted and cannot be used in filters",
+                path.0.trim_start_matches("$.")
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Which string test a text operator applies
+#[derive(Debug, Clone, Copy)]
+enum TextMatch {
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+/// What relationship selections are resolved against: the instances that pools and
+/// filter selections draw from, and the schema that defines each relationship
+#[derive(Debug, Clone, Copy)]
+pub struct FilterContext<'a> {
+    pub instances: &'a [Instance],
+    pub schema: &'a Schema,
 }
 
 /// Instance filter evaluator
@@ -88,12 +298,45 @@ impl InstanceFilterEvaluator {
             .collect()
     }
     
-    /// Evaluate filter expression against a single instance
+    /// Filter a list of instances, resolving relationship selections against `context`
+    pub fn filter_instances_with_context(
+        instances: Vec<Instance>,
+        filter: &FilterExpr,
+        context: &FilterContext,
+    ) -> Vec<Instance> {
+        instances
+            .into_iter()
+            .filter(|instance| {
+                Self::evaluate_filter_with_context(instance, filter, context).unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Evaluate filter expression against a single instance. Without a context only
+    /// relationships selected by explicit IDs can be resolved for `array_contains`.
     pub fn evaluate_filter(instance: &Instance, filter: &FilterExpr) -> Result<bool> {
+        Self::evaluate(instance, filter, None)
+    }
+
+    /// Evaluate filter expression against a single instance, resolving relationship
+    /// selections against `context`
+    pub fn evaluate_filter_with_context(
+        instance: &Instance,
+        filter: &FilterExpr,
+        context: &FilterContext,
+    ) -> Result<bool> {
+        Self::evaluate(instance, filter, Some(context))
+    }
+
+    fn evaluate(
+        instance: &Instance,
+        filter: &FilterExpr,
+        context: Option<&FilterContext>,
+    ) -> Result<bool> {
         match filter {
             FilterExpr::All { all } => {
                 for expr in all {
-                    if !Self::evaluate_filter(instance, expr)? {
+                    if !Self::evaluate(instance, expr, context)? {
                         return Ok(false);
                     }
                 }
@@ -102,7 +345,7 @@ impl InstanceFilterEvaluator {
             
             FilterExpr::Any { any } => {
                 for expr in any {
-                    if Self::evaluate_filter(instance, expr)? {
+                    if Self::evaluate(instance, expr, context)? {
                         return Ok(true);
                     }
                 }
@@ -110,7 +353,7 @@ impl InstanceFilterEvaluator {
             }
             
             FilterExpr::Not { not } => {
-                Ok(!Self::evaluate_filter(instance, not)?)
+                Ok(!Self::evaluate(instance, not, context)?)
             }
             
             FilterExpr::Eq { eq: (path, value) } => {
@@ -118,6 +361,16 @@ impl InstanceFilterEvaluator {
                 Ok(extracted.as_ref() == Some(value))
             }
             
+            FilterExpr::EqI { eq_i: (path, value) } => {
+                let extracted = path.extract(instance)?;
+                Ok(match (extracted, value) {
+                    (Some(Value::String(l)), Value::String(r)) => {
+                        l.to_lowercase() == r.to_lowercase()
+                    }
+                    (extracted, value) => extracted.as_ref() == Some(value),
+                })
+            }
+            
             FilterExpr::Ne { ne: (path, value) } => {
                 let extracted = path.extract(instance)?;
                 Ok(extracted.as_ref() != Some(value))
@@ -143,10 +396,18 @@ impl InstanceFilterEvaluator {
                 Self::compare_values(extracted.as_ref(), value, |a, b| a <= b)
             }
             
+            FilterExpr::Between { between: (path, lower, upper) } => {
+                let extracted = path.extract(instance)?;
+                match extracted {
+                    Some(val) => Self::in_range(&val, lower, upper),
+                    None => Ok(false),
+                }
+            }
+            
             FilterExpr::In { r#in: (path, values) } => {
                 let extracted = path.extract(instance)?;
                 match extracted {
-                    Some(val) => Ok(values.contains(&val)),
+                    Some(val) => Ok(values.iter().any(|v| Self::values_equal(&val, v))),
                     None => Ok(false),
                 }
             }
@@ -154,17 +415,33 @@ impl InstanceFilterEvaluator {
             FilterExpr::NotIn { not_in: (path, values) } => {
                 let extracted = path.extract(instance)?;
                 match extracted {
-                    Some(val) => Ok(!values.contains(&val)),
+                    Some(val) => Ok(!values.iter().any(|v| Self::values_equal(&val, v))),
                     None => Ok(true), // If property doesn't exist, it's not in the list
                 }
             }
             
             FilterExpr::Contains { contains: (path, substring) } => {
-                let extracted = path.extract(instance)?;
-                match extracted {
-                    Some(Value::String(s)) => Ok(s.contains(substring)),
-                    _ => Ok(false),
-                }
+                Self::match_text(path.extract(instance)?, substring, TextMatch::Contains, false)
+            }
+            
+            FilterExpr::ContainsI { contains_i: (path, substring) } => {
+                Self::match_text(path.extract(instance)?, substring, TextMatch::Contains, true)
+            }
+            
+            FilterExpr::StartsWith { starts_with: (path, prefix) } => {
+                Self::match_text(path.extract(instance)?, prefix, TextMatch::StartsWith, false)
+            }
+            
+            FilterExpr::StartsWithI { starts_with_i: (path, prefix) } => {
+                Self::match_text(path.extract(instance)?, prefix, TextMatch::StartsWith, true)
+            }
+            
+            FilterExpr::EndsWith { ends_with: (path, suffix) } => {
+                Self::match_text(path.extract(instance)?, suffix, TextMatch::EndsWith, false)
+            }
+            
+            FilterExpr::EndsWithI { ends_with_i: (path, suffix) } => {
+                Self::match_text(path.extract(instance)?, suffix, TextMatch::EndsWith, true)
             }
             
             FilterExpr::Exists { exists: path } => {
@@ -176,9 +453,134 @@ impl InstanceFilterEvaluator {
                 let extracted = path.extract(instance)?;
                 Ok(extracted.is_none())
             }
+            
+            FilterExpr::IsNull { is_null: path } => {
+                let extracted = path.extract(instance)?;
+                Ok(extracted.is_none_or(|v| v.is_null()))
+            }
+            
+            FilterExpr::IsNotNull { is_not_null: path } => {
+                let extracted = path.extract(instance)?;
+                Ok(extracted.is_some_and(|v| !v.is_null()))
+            }
+            
+            FilterExpr::ArrayContains { array_contains: (path, value) } => {
+                if let Some(Value::Array(items)) = path.extract(instance)? {
+                    return Ok(items.iter().any(|item| Self::values_equal(item, value)));
+                }
+                let ids = Self::relationship_ids(instance, path, context)?;
+                Ok(ids.iter().any(|id| Self::values_equal(&Value::String(id.clone()), value)))
+            }
+        }
+    }
+
+    /// Resolve the relationship named by `path` to the IDs it selects. Unresolved
+    /// selections (left to the solver) and unknown relationships select nothing.
+    fn relationship_ids(
+        instance: &Instance,
+        path: &JsonPath,
+        context: Option<&FilterContext>,
+    ) -> Result<Vec<String>> {
+        let Some(name) = path.0.strip_prefix("$.") else {
+            return Err(anyhow!("Invalid JSON path: {}", path.0));
+        };
+        let Some(selection) = instance.relationships.get(name) else {
+            return Ok(Vec::new());
+        };
+        let relationship_def = context.and_then(|context| {
+            context
+                .schema
+                .get_class_by_id(&instance.class_id)
+                .and_then(|class| class.relationships.iter().find(|rel| rel.name == name))
+        });
+
+        match (context, relationship_def) {
+            (Some(context), Some(relationship_def)) => {
+                match PoolResolver::resolve_relationship(
+                    context.instances,
+                    relationship_def,
+                    selection,
+                )? {
+                    SelectionResult::Resolved(ids) => Ok(ids),
+                    SelectionResult::Unresolved(_) => Ok(Vec::new()),
+                }
+            }
+            _ => Ok(match selection {
+                RelationshipSelection::SimpleIds(ids) | RelationshipSelection::Ids { ids } => {
+                    ids.clone()
+                }
+                RelationshipSelection::PoolBased {
+                    selection: Some(SelectionSpec::Ids(ids)),
+                    ..
+                } => ids.clone(),
+                _ => Vec::new(),
+            }),
         }
     }
     
+    /// Apply a string test to the string form of a value: strings as they are, numbers and
+    /// booleans as written in JSON. Null, missing, object and array values never match.
+    fn match_text(
+        value: Option<Value>,
+        needle: &str,
+        mode: TextMatch,
+        case_insensitive: bool,
+    ) -> Result<bool> {
+        let mut text = match value {
+            Some(Value::String(s)) => s,
+            Some(value @ (Value::Number(_) | Value::Bool(_))) => value.to_string(),
+            _ => return Ok(false),
+        };
+        let mut needle = needle.to_string();
+        if case_insensitive {
+            text = text.to_lowercase();
+            needle = needle.to_lowercase();
+        }
+        Ok(match mode {
+            TextMatch::Contains => text.contains(&needle),
+            TextMatch::StartsWith => text.starts_with(&needle),
+            TextMatch::EndsWith => text.ends_with(&needle),
+        })
+    }
+
+    /// Membership equality for `In`/`NotIn`: numbers and numeric strings compare by value
+    /// (so `"40"` matches `40`), everything else must be equal as JSON
+    fn values_equal(left: &Value, right: &Value) -> bool {
+        match (left, right) {
+            (Value::Number(_), Value::Number(_) | Value::String(_))
+            | (Value::String(_), Value::Number(_)) => {
+                Self::compare_values(Some(left), right, |a, b| a == b).unwrap_or(false)
+            }
+            _ => left == right,
+        }
+    }
+
+    /// Inclusive range membership for `Between`, swapping reversed bounds
+    fn in_range(value: &Value, lower: &Value, upper: &Value) -> Result<bool> {
+        let is_numeric = |v: &Value| match v {
+            Value::Number(_) => true,
+            Value::String(s) => s.parse::<f64>().is_ok(),
+            _ => false,
+        };
+        if is_numeric(value) && is_numeric(lower) && is_numeric(upper) {
+            let (lower, upper) = if Self::compare_values(Some(lower), upper, |a, b| a > b)? {
+                (upper, lower)
+            } else {
+                (lower, upper)
+            };
+            return Ok(Self::compare_values(Some(value), lower, |a, b| a >= b)?
+                && Self::compare_values(Some(value), upper, |a, b| a <= b)?);
+        }
+
+        match (value, lower, upper) {
+            (Value::String(value), Value::String(lower), Value::String(upper)) => {
+                let (lower, upper) = if lower > upper { (upper, lower) } else { (lower, upper) };
+                Ok(lower <= value && value <= upper)
+            }
+            _ => Ok(false),
+        }
+    }
+
     /// Compare two JSON values using a comparison function
     fn compare_values<F>(left: Option<&Value>, right: &Value, cmp: F) -> Result<bool>
     where
@@ -225,6 +627,102 @@ impl InstanceFilterEvaluator {
     }
 }
 
+/// Direction of one sort key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// Ordered sort keys, each a property path and direction
+pub type SortSpec = Vec<(JsonPath, SortDirection)>;
+
+/// Parse a sort specification like `price:asc,name:desc`. The direction defaults to `asc`,
+/// and keys without a `$.` prefix are treated as property paths.
+pub fn parse_sort_spec(spec: &str) -> Result<SortSpec> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(|key| {
+            let (path, direction) = match key.rsplit_once(':') {
+                Some((path, "asc")) => (path, SortDirection::Asc),
+                Some((path, "desc")) => (path, SortDirection::Desc),
+                Some((_, direction)) => {
+                    return Err(anyhow!(
+                        "Invalid sort direction '{}' (expected 'asc' or 'desc')",
+                        direction
+                    ))
+                }
+                None => (key, SortDirection::Asc),
+            };
+            if path.is_empty() || path == "$." {
+                return Err(anyhow!("Invalid sort key '{}'", key));
+            }
+            let path = if path.starts_with("$.") {
+                path.to_string()
+            } else {
+                format!("$.{}", path)
+            };
+            Ok((JsonPath(path), direction))
+        })
+        .collect()
+}
+
+/// Stable multi-key sort. Numbers sort numerically and strings lexically (numbers before
+/// strings when a key mixes them); missing or null values sort last in either direction.
+pub fn sort_instances(instances: &mut [Instance], sort: &[(JsonPath, SortDirection)]) {
+    if sort.is_empty() {
+        return;
+    }
+    let key = |instance: &Instance| -> Vec<Option<Value>> {
+        sort.iter()
+            .map(|(path, _)| path.extract(instance).ok().flatten().filter(|v| !v.is_null()))
+            .collect()
+    };
+    let mut keyed: Vec<(Vec<Option<Value>>, Instance)> = instances
+        .iter()
+        .map(|instance| (key(instance), instance.clone()))
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| {
+        for ((a, b), (_, direction)) in a.iter().zip(b).zip(sort) {
+            let ordering = match (a, b) {
+                (Some(a), Some(b)) => {
+                    let ordering = compare_sort_values(a, b);
+                    match direction {
+                        SortDirection::Asc => ordering,
+                        SortDirection::Desc => ordering.reverse(),
+                    }
+                }
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            };
+            if ordering != std::cmp::Ordering::Equal {
+                return ordering;
+            }
+        }
+        std::cmp::Ordering::Equal
+    });
+    for (slot, (_, instance)) in instances.iter_mut().zip(keyed) {
+        *slot = instance;
+    }
+}
+
+fn compare_sort_values(a: &Value, b: &Value) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => {
+            let (a, b) = (a.as_f64().unwrap_or(f64::NAN), b.as_f64().unwrap_or(f64::NAN));
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Number(_), _) => Ordering::Less,
+        (_, Value::Number(_)) => Ordering::Greater,
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
 /// Convert from generic JSON value to typed FilterExpr
 pub fn parse_filter_expr(value: Value) -> Result<FilterExpr> {
     serde_json::from_value(value)
@@ -237,6 +735,27 @@ pub fn filter_instances(instances: Vec<Instance>, filter: &FilterExpr) -> Vec<In
     InstanceFilterEvaluator::filter_instances(instances, filter)
 }
 
+/// Filter instances, resolving relationship selections for `array_contains` against the
+/// given instances and schema
+pub fn filter_instances_with_context(
+    instances: Vec<Instance>,
+    filter: &FilterExpr,
+    context: &FilterContext,
+) -> Vec<Instance> {
+    InstanceFilterEvaluator::filter_instances_with_context(instances, filter, context)
+}
+
+/// Filter instances, then order them by the given sort keys (see `sort_instances`)
+pub fn filter_instances_sorted(
+    instances: Vec<Instance>,
+    filter: &FilterExpr,
+    sort: &[(JsonPath, SortDirection)],
+) -> Vec<Instance> {
+    let mut instances = filter_instances(instances, filter);
+    sort_instances(&mut instances, sort);
+    instances
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,6 +797,10 @@ mod tests {
             created_at: chrono::Utc::now(),
             updated_by: "test".to_string(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
         }
     }
     
@@ -366,4 +889,426 @@ mod tests {
         let filtered = filter_instances(instances, &filter);
         assert_eq!(filtered.len(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_nested_path_into_json_property() {
+        let metadata = serde_json::json!({
+            "vendor": {"sku": "AB-12", "lead_days": 14},
+            "tags": ["oak", "outdoor"]
+        });
+        let mut instance = create_test_instance("inst1", "Furniture", vec![
+            ("metadata", metadata.clone()),
+            ("dimensions", serde_json::json!({"width": 80})),
+        ]);
+        if let Some(PropertyValue::Literal(typed)) = instance.properties.get_mut("metadata") {
+            typed.data_type = DataType::Json;
+        }
+
+        let filter: FilterExpr = serde_json::from_value(serde_json::json!({
+            "all": [
+                {"eq": ["$.metadata.vendor.sku", "AB-12"]},
+                {"lt": ["$.metadata.vendor.lead_days", 30]},
+                {"eq": ["$.metadata.tags.1", "outdoor"]},
+                {"not_exists": "$.metadata.vendor.color"}
+            ]
+        }))
+        .unwrap();
+        assert!(InstanceFilterEvaluator::evaluate_filter(&instance, &filter).unwrap());
+
+        let whole = JsonPath("$.metadata".to_string()).extract(&instance).unwrap();
+        assert_eq!(whole, Some(metadata));
+
+        // Structured values of other property types are traversed too
+        let path = JsonPath("$.dimensions.width".to_string());
+        assert_eq!(path.extract(&instance).unwrap(), Some(serde_json::json!(80)));
+    }
+
+    #[test]
+    fn test_nested_path_segments_and_indices() {
+        let instance = create_test_instance("inst1", "Furniture", vec![
+            ("dimensions", serde_json::json!({"seat": {"width": 55, "depth": 50}})),
+            ("tags", serde_json::json!(["oak", "outdoor"])),
+            ("parts", serde_json::json!([{"sku": "LEG", "sizes": [10, 12]}])),
+            ("name", Value::String("Bench".to_string())),
+        ]);
+        let extract = |path: &str| JsonPath(path.to_string()).extract(&instance).unwrap();
+
+        // Two levels deep
+        assert_eq!(extract("$.dimensions.seat.width"), Some(serde_json::json!(55)));
+        // Missing middle segment
+        assert_eq!(extract("$.dimensions.back.width"), None);
+        assert_eq!(extract("$.dimensions.seat.width.value"), None);
+
+        // Bracket indexing, chained and mixed with keys
+        assert_eq!(extract("$.tags[0]"), Some(serde_json::json!("oak")));
+        assert_eq!(extract("$.parts[0].sku"), Some(serde_json::json!("LEG")));
+        assert_eq!(extract("$.parts[0].sizes[1]"), Some(serde_json::json!(12)));
+        // Out-of-range and malformed indices
+        assert_eq!(extract("$.tags[2]"), None);
+        assert_eq!(extract("$.parts[0].sizes[5]"), None);
+        assert_eq!(extract("$.tags[x]"), None);
+        assert_eq!(extract("$.tags[0"), None);
+
+        // Indexing into scalars or unknown properties
+        assert_eq!(extract("$.name[0]"), None);
+        assert_eq!(extract("$.missing.width"), None);
+
+        let filter = parse_filter_expr(serde_json::json!(
+            {"all": [{"gte": ["$.dimensions.seat.width", 50]}, {"eq": ["$.tags[1]", "outdoor"]}]}
+        ))
+        .unwrap();
+        assert!(InstanceFilterEvaluator::evaluate_filter(&instance, &filter).unwrap());
+    }
+
+    #[test]
+    fn test_in_and_not_in_membership() {
+        let instance = create_test_instance("inst1", "Shoe", vec![
+            ("color", Value::String("red".to_string())),
+            ("size", serde_json::json!(40)),
+            ("in_stock", Value::Bool(true)),
+        ]);
+        let matches = |filter: serde_json::Value| {
+            let filter = parse_filter_expr(filter).unwrap();
+            InstanceFilterEvaluator::evaluate_filter(&instance, &filter).unwrap()
+        };
+
+        assert!(matches(serde_json::json!({"in": ["$.color", ["red", "blue"]]})));
+        assert!(!matches(serde_json::json!({"in": ["$.color", ["green"]]})));
+        assert!(matches(serde_json::json!({"not_in": ["$.color", ["green", "blue"]]})));
+
+        // Numbers coerce like the comparison operators
+        assert!(matches(serde_json::json!({"in": ["$.size", [38, "40"]]})));
+        assert!(matches(serde_json::json!({"in": ["$.size", [40.0]]})));
+        assert!(!matches(serde_json::json!({"in": ["$.size", [38, "42"]]})));
+        assert!(!matches(serde_json::json!({"not_in": ["$.size", ["40"]]})));
+
+        assert!(matches(serde_json::json!({"in": ["$.in_stock", [true]]})));
+        assert!(!matches(serde_json::json!({"in": ["$.in_stock", ["true", 1]]})));
+
+        // An empty list never matches `in`, and a missing property is never in the list
+        assert!(!matches(serde_json::json!({"in": ["$.color", []]})));
+        assert!(matches(serde_json::json!({"not_in": ["$.color", []]})));
+        assert!(!matches(serde_json::json!({"in": ["$.missing", ["red"]]})));
+        assert!(matches(serde_json::json!({"not_in": ["$.missing", ["red"]]})));
+    }
+
+    #[test]
+    fn test_is_null_and_is_not_null() {
+        let absent = create_test_instance("absent", "Chair", vec![]);
+        let explicit_null = create_test_instance("null", "Chair", vec![("color", Value::Null)]);
+        let set = create_test_instance("set", "Chair", vec![("color", serde_json::json!("red"))]);
+        let matches = |instance: &Instance, filter: serde_json::Value| {
+            let filter = parse_filter_expr(filter).unwrap();
+            InstanceFilterEvaluator::evaluate_filter(instance, &filter).unwrap()
+        };
+        let is_null = serde_json::json!({"is_null": "$.color"});
+        let is_not_null = serde_json::json!({"is_not_null": "$.color"});
+
+        assert!(matches(&absent, is_null.clone()));
+        assert!(!matches(&absent, is_not_null.clone()));
+
+        // An explicit null counts as null, although the property exists
+        assert!(matches(&explicit_null, is_null.clone()));
+        assert!(!matches(&explicit_null, is_not_null.clone()));
+        assert!(matches(&explicit_null, serde_json::json!({"exists": "$.color"})));
+
+        assert!(!matches(&set, is_null));
+        assert!(matches(&set, is_not_null));
+    }
+
+    #[test]
+    fn test_string_operators() {
+        let instance = create_test_instance("inst1", "Product", vec![
+            ("name", Value::String("Oak Dining Table".to_string())),
+            ("sku", serde_json::json!(509912)),
+        ]);
+        let matches = |filter: serde_json::Value| {
+            let filter = parse_filter_expr(filter).unwrap();
+            InstanceFilterEvaluator::evaluate_filter(&instance, &filter).unwrap()
+        };
+
+        // Substrings, with numbers matched on their string form
+        assert!(matches(serde_json::json!({"contains": ["$.name", "Dining"]})));
+        assert!(matches(serde_json::json!({"contains": ["$.sku", "99"]})));
+        assert!(!matches(serde_json::json!({"contains": ["$.name", "dining"]})));
+        assert!(matches(serde_json::json!({"contains_i": ["$.name", "dining"]})));
+
+        // Prefixes and suffixes
+        assert!(matches(serde_json::json!({"starts_with": ["$.name", "Oak"]})));
+        assert!(matches(serde_json::json!({"starts_with": ["$.sku", "509"]})));
+        assert!(matches(serde_json::json!({"ends_with": ["$.name", "Table"]})));
+        assert!(matches(serde_json::json!({"starts_with_i": ["$.name", "OAK"]})));
+        assert!(matches(serde_json::json!({"ends_with_i": ["$.name", "table"]})));
+
+        // Non-matching text and missing properties
+        assert!(!matches(serde_json::json!({"starts_with": ["$.name", "Table"]})));
+        assert!(!matches(serde_json::json!({"ends_with": ["$.sku", "99"]})));
+        assert!(!matches(serde_json::json!({"contains": ["$.color", "red"]})));
+    }
+
+    #[test]
+    fn test_case_insensitive_equality() {
+        let instance = create_test_instance("inst1", "Color", vec![
+            ("name", Value::String("red".to_string())),
+            ("label", Value::String("Crème Brûlée".to_string())),
+            ("shade", serde_json::json!(40)),
+        ]);
+        let matches = |filter: serde_json::Value| {
+            let filter = parse_filter_expr(filter).unwrap();
+            InstanceFilterEvaluator::evaluate_filter(&instance, &filter).unwrap()
+        };
+
+        assert!(matches(serde_json::json!({"eq_i": ["$.name", "Red"]})));
+        assert!(matches(serde_json::json!({"eq_i": ["$.name", "RED"]})));
+        assert!(!matches(serde_json::json!({"eq": ["$.name", "Red"]})));
+        assert!(!matches(serde_json::json!({"eq_i": ["$.name", "Redd"]})));
+
+        // Accented letters fold too
+        assert!(matches(serde_json::json!({"eq_i": ["$.label", "CRÈME BRÛLÉE"]})));
+
+        // Non-strings compare exactly, without lowercasing or numeric coercion
+        assert!(matches(serde_json::json!({"eq_i": ["$.shade", 40]})));
+        assert!(!matches(serde_json::json!({"eq_i": ["$.shade", "40"]})));
+        assert!(!matches(serde_json::json!({"eq_i": ["$.missing", "red"]})));
+    }
+
+    #[test]
+    fn test_between_range() {
+        let instance = create_test_instance("inst1", "Product", vec![
+            ("price", serde_json::json!(50)),
+            ("code", Value::String("m".to_string())),
+            ("released", Value::String("2024-03-15T10:00:00Z".to_string())),
+        ]);
+        let matches = |filter: serde_json::Value| {
+            let filter = parse_filter_expr(filter).unwrap();
+            InstanceFilterEvaluator::evaluate_filter(&instance, &filter).unwrap()
+        };
+
+        // Bounds are inclusive and numeric strings coerce
+        assert!(matches(serde_json::json!({"between": ["$.price", "10", "50"]})));
+        assert!(matches(serde_json::json!({"between": ["$.price", 50, 60]})));
+        assert!(matches(serde_json::json!({"between": ["$.price", 49.5, 50.5]})));
+        assert!(!matches(serde_json::json!({"between": ["$.price", 10, 49.99]})));
+        assert!(!matches(serde_json::json!({"between": ["$.price", 51, "60"]})));
+
+        // Reversed bounds are normalized
+        assert!(matches(serde_json::json!({"between": ["$.price", 60, 10]})));
+        assert!(matches(serde_json::json!({"between": ["$.code", "z", "a"]})));
+
+        // RFC3339 timestamps compare lexically
+        assert!(matches(serde_json::json!(
+            {"between": ["$.released", "2024-01-01T00:00:00Z", "2024-12-31T23:59:59Z"]}
+        )));
+        assert!(matches(serde_json::json!(
+            {"between": ["$.released", "2024-03-15T10:00:00Z", "2024-03-16T00:00:00Z"]}
+        )));
+        assert!(!matches(serde_json::json!(
+            {"between": ["$.released", "2023-01-01T00:00:00Z", "2023-12-31T23:59:59Z"]}
+        )));
+
+        // Mixed kinds and missing properties never match
+        assert!(!matches(serde_json::json!({"between": ["$.price", "a", "z"]})));
+        assert!(!matches(serde_json::json!({"between": ["$.missing", 0, 100]})));
+    }
+
+    #[test]
+    fn test_and_or_not_combinators() {
+        let red = create_test_instance("red", "Shoe", vec![
+            ("color", Value::String("red".to_string())),
+            ("price", serde_json::json!(40)),
+        ]);
+        let blue = create_test_instance("blue", "Shoe", vec![
+            ("color", Value::String("blue".to_string())),
+            ("price", serde_json::json!(80)),
+        ]);
+        let green = create_test_instance("green", "Shoe", vec![
+            ("color", Value::String("green".to_string())),
+            ("price", serde_json::json!(20)),
+        ]);
+        let evaluate = |instance: &Instance, filter: serde_json::Value| {
+            let filter = parse_filter_expr(filter).unwrap();
+            InstanceFilterEvaluator::evaluate_filter(instance, &filter)
+        };
+
+        // (color = red OR color = blue) AND price < 50, nested three levels deep
+        let filter = serde_json::json!({"and": [
+            {"or": [
+                {"eq": ["$.color", "red"]},
+                {"and": [{"eq": ["$.color", "blue"]}, {"not": {"gte": ["$.price", 100]}}]}
+            ]},
+            {"lt": ["$.price", 50]}
+        ]});
+        assert!(evaluate(&red, filter.clone()).unwrap());
+        assert!(!evaluate(&blue, filter.clone()).unwrap());
+        assert!(!evaluate(&green, filter).unwrap());
+
+        // `and`/`or` are the same expressions as `all`/`any`
+        assert_eq!(
+            parse_filter_expr(serde_json::json!({"and": [{"exists": "$.color"}]})).ok(),
+            parse_filter_expr(serde_json::json!({"all": [{"exists": "$.color"}]})).ok()
+        );
+
+        // Empty AND is true, empty OR is false
+        assert!(evaluate(&red, serde_json::json!({"and": []})).unwrap());
+        assert!(!evaluate(&red, serde_json::json!({"or": []})).unwrap());
+
+        // A false AND member (or true OR member) short-circuits before an invalid path errors
+        let invalid = serde_json::json!({"eq": ["color", "red"]});
+        assert!(evaluate(&red, invalid.clone()).is_err());
+        assert!(!evaluate(&blue, serde_json::json!(
+            {"and": [{"eq": ["$.color", "red"]}, invalid.clone()]}
+        )).unwrap());
+        assert!(evaluate(&red, serde_json::json!(
+            {"or": [{"eq": ["$.color", "red"]}, invalid]}
+        )).unwrap());
+    }
+
+    #[test]
+    fn test_parse_sort_spec() {
+        let sort = parse_sort_spec("price:asc, name:desc,$.size").unwrap();
+        assert_eq!(
+            sort,
+            vec![
+                (JsonPath("$.price".to_string()), SortDirection::Asc),
+                (JsonPath("$.name".to_string()), SortDirection::Desc),
+                (JsonPath("$.size".to_string()), SortDirection::Asc),
+            ]
+        );
+        assert!(parse_sort_spec("price:up").is_err());
+        assert!(parse_sort_spec(":desc").is_err());
+        assert!(parse_sort_spec("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_multi_key_sort_with_missing_values() {
+        let shoe = |id: &str, price: Option<f64>, name: Option<&str>| {
+            let mut props = Vec::new();
+            if let Some(price) = price {
+                props.push(("price", serde_json::json!(price)));
+            }
+            if let Some(name) = name {
+                props.push(("name", Value::String(name.to_string())));
+            }
+            create_test_instance(id, "Shoe", props)
+        };
+        let instances = vec![
+            shoe("a", Some(20.0), Some("beta")),
+            shoe("b", None, Some("alpha")),
+            shoe("c", Some(5.0), Some("gamma")),
+            shoe("d", Some(20.0), Some("alpha")),
+            shoe("e", Some(100.0), None),
+            shoe("f", Some(20.0), Some("beta")),
+        ];
+        let ids = |instances: &[Instance]| -> Vec<String> {
+            instances.iter().map(|i| i.id.clone()).collect()
+        };
+
+        // Numeric (not lexical) order, ties broken by name desc, equal keys keep input order
+        let mut sorted = instances.clone();
+        sort_instances(&mut sorted, &parse_sort_spec("price:asc,name:desc").unwrap());
+        assert_eq!(ids(&sorted), vec!["c", "a", "f", "d", "e", "b"]);
+
+        // Missing prices stay last when descending too
+        let mut sorted = instances.clone();
+        sort_instances(&mut sorted, &parse_sort_spec("price:desc").unwrap());
+        assert_eq!(ids(&sorted), vec!["e", "a", "d", "f", "c", "b"]);
+
+        let mut sorted = instances.clone();
+        sort_instances(&mut sorted, &parse_sort_spec("name").unwrap());
+        assert_eq!(ids(&sorted), vec!["b", "d", "a", "f", "c", "e"]);
+
+        let filter = parse_filter_expr(serde_json::json!({"exists": "$.price"})).unwrap();
+        let sorted = filter_instances_sorted(
+            instances,
+            &filter,
+            &parse_sort_spec("price:desc").unwrap(),
+        );
+        assert_eq!(ids(&sorted), vec!["e", "a", "d", "f", "c"]);
+    }
+
+    #[test]
+    fn test_array_contains_relationship_ids() {
+        let schema: Schema = serde_json::from_value(serde_json::json!({
+            "id": "schema-1",
+            "classes": [{
+                "id": "class-chair",
+                "name": "Chair",
+                "properties": [],
+                "derived": [],
+                "relationships": [{
+                    "id": "rel-legs",
+                    "name": "legs",
+                    "targets": ["class-leg"],
+                    "quantifier": "any"
+                }]
+            }]
+        }))
+        .unwrap();
+        let legs = vec![
+            create_test_instance("leg-oak", "class-leg", vec![("wood", serde_json::json!("oak"))]),
+            create_test_instance("leg-pine", "class-leg", vec![("wood", serde_json::json!("pine"))]),
+        ];
+        let context = FilterContext { instances: &legs, schema: &schema };
+
+        // Selected by a filter, so the IDs only exist once resolved against the pool
+        let mut oak_chair = create_test_instance("chair-1", "class-chair", vec![]);
+        oak_chair.relationships.insert(
+            "legs".to_string(),
+            serde_json::from_value(serde_json::json!({
+                "filter": { "type": ["class-leg"], "where": { "eq": ["$.wood", "oak"] } }
+            }))
+            .unwrap(),
+        );
+        let mut bare_chair = create_test_instance("chair-2", "class-chair", vec![]);
+        bare_chair
+            .relationships
+            .insert("legs".to_string(), RelationshipSelection::SimpleIds(Vec::new()));
+
+        let contains = |instance: &Instance, id: &str| {
+            let filter = parse_filter_expr(serde_json::json!({"array_contains": ["$.legs", id]}))
+                .unwrap();
+            InstanceFilterEvaluator::evaluate_filter_with_context(instance, &filter, &context)
+                .unwrap()
+        };
+
+        assert!(contains(&oak_chair, "leg-oak"));
+        assert!(!contains(&oak_chair, "leg-pine"));
+        assert!(!contains(&bare_chair, "leg-oak"));
+
+        // Explicit ID lists resolve without a context too
+        let mut pine_chair = create_test_instance("chair-3", "class-chair", vec![]);
+        pine_chair.relationships.insert(
+            "legs".to_string(),
+            RelationshipSelection::SimpleIds(vec!["leg-pine".to_string()]),
+        );
+        let filter =
+            parse_filter_expr(serde_json::json!({"array_contains": ["$.legs", "leg-pine"]}))
+                .unwrap();
+        assert!(InstanceFilterEvaluator::evaluate_filter(&pine_chair, &filter).unwrap());
+    }
+
+    #[test]
+    fn test_encrypted_properties_are_not_filterable() {
+        let schema: Schema = serde_json::from_value(serde_json::json!({
+            "id": "schema-1",
+            "classes": [{
+                "id": "class-part",
+                "name": "Part",
+                "properties": [
+                    {"id": "prop-cost", "name": "cost", "data_type": "number", "encrypted": true},
+                    {"id": "prop-price", "name": "price", "data_type": "number"}
+                ],
+                "derived": [],
+                "relationships": []
+            }]
+        }))
+        .unwrap();
+        let check = |filter: Value| parse_filter_expr(filter).unwrap().check_filterable(&schema);
+
+        assert!(check(serde_json::json!({"gt": ["$.price", 10]})).is_ok());
+        for path in ["$.cost", "$.prop-cost"] {
+            let error = check(serde_json::json!({"not": {"lt": [path, 10]}})).unwrap_err();
+            assert!(error.to_string().contains("is encrypted"));
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Timeline of one instance over a branch's commits
+//!
+//! Each commit is compared with the next older one in the walk, so the caller loads one
+//! commit past the entries it wants and drops the last entry; without an older commit the
+//! oldest entry counts as the instance's creation. Only properties are compared:
+//! relationship edits show up as `unchanged`.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::model::{Commit, Instance, PropertyValue};
+
+/// What a commit did to the instance, compared with its parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceHistoryEvent {
+    Created,
+    Changed,
+    Unchanged,
+    Deleted,
+    /// Neither in this commit nor in its parent
+    Absent,
+}
+
+/// The instance as of one commit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstanceHistoryEntry {
+    pub commit_hash: String,
+    pub message: Option<String>,
+    pub author: Option<String>,
+    pub created_at: String,
+    pub event: InstanceHistoryEvent,
+    /// Property values at this commit, limited to the projected property if one was given;
+    /// None while the instance doesn't exist
+    pub properties: Option<BTreeMap<String, PropertyValue>>,
+}
+
+/// One entry per commit of `snapshots`, which pair each commit with the instance as stored
+/// in it and run newest first. With `property` set, snapshots hold only that property and
+/// `changed` means that property changed.
+pub fn instance_history(
+    snapshots: &[(Commit, Option<Instance>)],
+    property: Option<&str>,
+) -> Vec<InstanceHistoryEntry> {
+    let properties: Vec<Option<BTreeMap<String, PropertyValue>>> = snapshots
+        .iter()
+        .map(|(_, instance)| {
+            instance.as_ref().map(|instance| {
+                instance
+                    .properties
+                    .iter()
+                    .filter(|(name, _)| property.is_none_or(|property| *name == property))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect()
+            })
+        })
+        .collect();
+
+    snapshots
+        .iter()
+        .enumerate()
+        .map(|(index, (commit, _))| {
+            let parent = properties.get(index + 1).and_then(Option::as_ref);
+            let event = match (&properties[index], parent) {
+                (Some(_), None) => InstanceHistoryEvent::Created,
+                (Some(current), Some(parent)) if current == parent => {
+                    InstanceHistoryEvent::Unchanged
+                }
+                (Some(_), Some(_)) => InstanceHistoryEvent::Changed,
+                (None, Some(_)) => InstanceHistoryEvent::Deleted,
+                (None, None) => InstanceHistoryEvent::Absent,
+            };
+            InstanceHistoryEntry {
+                commit_hash: commit.hash.clone(),
+                message: commit.message.clone(),
+                author: commit.author.clone(),
+                created_at: commit.created_at.clone(),
+                event,
+                properties: properties[index].clone(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{CommitData, DataType, Schema, TypedValue};
+
+    fn commit(message: &str) -> Commit {
+        let data = CommitData {
+            schema: Schema {
+                id: "schema".to_string(),
+                classes: vec![],
+                description: None,
+            },
+            instances: vec![],
+        };
+        Commit::new(
+            "db".to_string(),
+            None,
+            data,
+            None,
+            Some(message.to_string()),
+        )
+    }
+
+    fn literal(value: serde_json::Value, data_type: DataType) -> PropertyValue {
+        PropertyValue::Literal(TypedValue { value, data_type })
+    }
+
+    fn part(price: f64, name: &str) -> Instance {
+        let mut instance = Instance {
+            id: "part-1".to_string(),
+            class_id: "class-part".to_string(),
+            ..Instance::default()
+        };
+        instance.properties.insert(
+            "price".to_string(),
+            literal(serde_json::json!(price), DataType::Number),
+        );
+        instance.properties.insert(
+            "name".to_string(),
+            literal(serde_json::json!(name), DataType::String),
+        );
+        instance
+    }
+
+    /// Snapshots from oldest to newest, returned newest first as the walk produces them
+    fn snapshots(history: Vec<Option<Instance>>) -> Vec<(Commit, Option<Instance>)> {
+        let mut snapshots: Vec<_> = history
+            .into_iter()
+            .enumerate()
+            .map(|(index, instance)| (commit(&format!("commit {}", index)), instance))
+            .collect();
+        snapshots.reverse();
+        snapshots
+    }
+
+    fn events(entries: &[InstanceHistoryEntry]) -> Vec<InstanceHistoryEvent> {
+        entries.iter().map(|entry| entry.event).collect()
+    }
+
+    #[test]
+    fn test_instance_created_mid_history() {
+        let entries =
+            instance_history(&snapshots(vec![None, None, Some(part(10.0, "bolt"))]), None);
+
+        use InstanceHistoryEvent::*;
+        assert_eq!(events(&entries), vec![Created, Absent, Absent]);
+        assert_eq!(entries[0].message.as_deref(), Some("commit 2"));
+        let properties = entries[0].properties.as_ref().unwrap();
+        assert_eq!(properties.len(), 2);
+        assert!(entries[1].properties.is_none());
+    }
+
+    #[test]
+    fn test_property_changed_twice() {
+        let history = snapshots(vec![
+            Some(part(10.0, "bolt")),
+            Some(part(12.0, "bolt")),
+            Some(part(12.0, "hex bolt")),
+            Some(part(15.0, "hex bolt")),
+        ]);
+
+        use InstanceHistoryEvent::*;
+        let entries = instance_history(&history, None);
+        assert_eq!(events(&entries), vec![Changed, Changed, Changed, Created]);
+
+        // Projected on price, the rename no longer counts as a change
+        let entries = instance_history(&history, Some("price"));
+        assert_eq!(events(&entries), vec![Changed, Unchanged, Changed, Created]);
+        let prices: Vec<_> = entries
+            .iter()
+            .map(|entry| {
+                let properties = entry.properties.as_ref().unwrap();
+                assert_eq!(properties.len(), 1);
+                properties["price"].clone()
+            })
+            .collect();
+        assert_eq!(
+            prices,
+            [15.0, 12.0, 12.0, 10.0]
+                .map(|price| literal(serde_json::json!(price), DataType::Number))
+        );
+    }
+
+    #[test]
+    fn test_deleted_then_recreated_instance() {
+        let entries = instance_history(
+            &snapshots(vec![
+                Some(part(10.0, "bolt")),
+                None,
+                None,
+                Some(part(20.0, "bolt")),
+            ]),
+            Some("price"),
+        );
+
+        use InstanceHistoryEvent::*;
+        assert_eq!(events(&entries), vec![Created, Absent, Deleted, Created]);
+        assert!(entries[1].properties.is_none());
+        assert!(entries[2].properties.is_none());
+    }
+}
@@ -0,0 +1,56 @@
+//! Full-text search over resolved instance properties
+//!
+//! A query matches a property when its value is a string containing the query,
+//! case-insensitively. Strings nested in array or object values count too, but a property
+//! counts once no matter how many of its nested strings match. Instances are ranked by
+//! how many of their properties match.
+
+use serde::Serialize;
+
+use crate::model::ExpandedInstance;
+
+/// An instance with at least one matching property
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub instance: ExpandedInstance,
+    /// Names of the matching properties, sorted
+    pub matched_properties: Vec<String>,
+}
+
+/// Instances with a property containing `query`, most matching properties first. Ties keep
+/// the order of `instances`. `query` must not be empty; every instance would match it.
+pub fn search_instances(instances: Vec<ExpandedInstance>, query: &str) -> Vec<SearchHit> {
+    let needle = query.to_lowercase();
+    let mut hits: Vec<SearchHit> = instances
+        .into_iter()
+        .filter_map(|instance| {
+            let mut matched_properties: Vec<String> = instance
+                .properties
+                .iter()
+                .filter(|(_, value)| value_contains(value, &needle))
+                .map(|(name, _)| name.clone())
+                .collect();
+            if matched_properties.is_empty() {
+                return None;
+            }
+            matched_properties.sort();
+            Some(SearchHit {
+                instance,
+                matched_properties,
+            })
+        })
+        .collect();
+    hits.sort_by_key(|hit| std::cmp::Reverse(hit.matched_properties.len()));
+    hits
+}
+
+/// Whether `value` is or contains a string containing the lowercased `needle`; stops at the
+/// first match
+fn value_contains(value: &serde_json::Value, needle: &str) -> bool {
+    match value {
+        serde_json::Value::String(text) => text.to_lowercase().contains(needle),
+        serde_json::Value::Array(items) => items.iter().any(|item| value_contains(item, needle)),
+        serde_json::Value::Object(map) => map.values().any(|item| value_contains(item, needle)),
+        _ => false,
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::model::{Branch, Commit, CommitTag, Id, WorkingCommit, WorkingCommitStatus};
+
+/// Kind of history corruption found by the integrity check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityIssueKind {
+    /// A commit's `parent_hash` points at a commit that doesn't exist in the database
+    MissingParent,
+    /// A branch head points at a commit that doesn't exist in the database
+    MissingBranchHead,
+    /// A tag references a commit that doesn't exist in the database
+    MissingTagCommit,
+    /// An open working commit is based on a commit that doesn't exist in the database
+    MissingWorkingCommitBase,
+}
+
+/// A single problem found in a database's commit history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub kind: IntegrityIssueKind,
+    /// What is broken: a commit hash, branch name, tag ID or working commit ID
+    pub subject: String,
+    /// The commit hash that could not be found
+    pub missing_hash: String,
+    pub message: String,
+    /// Whether repair mode can fix this issue without rewriting history
+    pub fixable: bool,
+    /// Set when repair mode was requested: whether the issue was fixed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repaired: Option<bool>,
+}
+
+/// Result of checking a database's commit history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub database_id: Id,
+    pub healthy: bool,
+    pub commits_checked: usize,
+    pub branches_checked: usize,
+    pub tags_checked: usize,
+    pub working_commits_checked: usize,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+/// Check that every parent, branch head, tag and open working commit of a database
+/// references a commit that exists in that database
+pub fn check_history_integrity(
+    database_id: &Id,
+    commits: &[Commit],
+    branches: &[Branch],
+    tags: &[CommitTag],
+    working_commits: &[WorkingCommit],
+) -> IntegrityReport {
+    let known: HashSet<&str> = commits.iter().map(|c| c.hash.as_str()).collect();
+    let mut issues = Vec::new();
+
+    for commit in commits {
+        if let Some(parent) = &commit.parent_hash {
+            if !known.contains(parent.as_str()) {
+                issues.push(IntegrityIssue {
+                    kind: IntegrityIssueKind::MissingParent,
+                    subject: commit.hash.clone(),
+                    missing_hash: parent.clone(),
+                    message: format!(
+                        "Commit '{}' has parent '{}', which does not exist in this database",
+                        commit.hash, parent
+                    ),
+                    // The parent hash is part of the commit hash, so it can't be rewritten
+                    fixable: false,
+                    repaired: None,
+                });
+            }
+        }
+    }
+
+    for branch in branches {
+        if let Some(head) = &branch.current_commit_hash {
+            if !known.contains(head.as_str()) {
+                issues.push(IntegrityIssue {
+                    kind: IntegrityIssueKind::MissingBranchHead,
+                    subject: branch.name.clone(),
+                    missing_hash: head.clone(),
+                    message: format!(
+                        "Branch '{}' points at commit '{}', which does not exist in this database",
+                        branch.name, head
+                    ),
+                    fixable: false,
+                    repaired: None,
+                });
+            }
+        }
+    }
+
+    for tag in tags {
+        if !known.contains(tag.commit_hash.as_str()) {
+            issues.push(IntegrityIssue {
+                kind: IntegrityIssueKind::MissingTagCommit,
+                subject: tag.id.to_string(),
+                missing_hash: tag.commit_hash.clone(),
+                message: format!(
+                    "Tag '{}' references commit '{}', which does not exist in this database",
+                    tag.tag_name, tag.commit_hash
+                ),
+                fixable: true,
+                repaired: None,
+            });
+        }
+    }
+
+    for working_commit in working_commits {
+        let is_open = working_commit.status != WorkingCommitStatus::Abandoned;
+        let base = working_commit.based_on_hash.as_str();
+        if is_open && !base.is_empty() && !known.contains(base) {
+            issues.push(IntegrityIssue {
+                kind: IntegrityIssueKind::MissingWorkingCommitBase,
+                subject: working_commit.id.clone(),
+                missing_hash: base.to_string(),
+                message: format!(
+                    "Working commit '{}' is based on commit '{}', which does not exist in this database",
+                    working_commit.id, base
+                ),
+                fixable: true,
+                repaired: None,
+            });
+        }
+    }
+
+    IntegrityReport {
+        database_id: database_id.clone(),
+        healthy: issues.is_empty(),
+        commits_checked: commits.len(),
+        branches_checked: branches.len(),
+        tags_checked: tags.len(),
+        working_commits_checked: working_commits.len(),
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{CommitData, Schema, TagType};
+
+    fn commit(parent: Option<&str>, message: &str) -> Commit {
+        let data = CommitData {
+            schema: Schema {
+                id: "schema".to_string(),
+                classes: vec![],
+                description: None,
+            },
+            instances: vec![],
+        };
+        Commit::new(
+            "db".to_string(),
+            parent.map(str::to_string),
+            data,
+            None,
+            Some(message.to_string()),
+        )
+    }
+
+    fn tag(commit_hash: &str) -> CommitTag {
+        CommitTag {
+            id: 7,
+            commit_hash: commit_hash.to_string(),
+            tag_type: TagType::Version,
+            tag_name: "v1".to_string(),
+            tag_description: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            created_by: None,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_healthy_history_has_no_issues() {
+        let root = commit(None, "root");
+        let child = commit(Some(&root.hash), "child");
+        let mut main = Branch::new_main_branch("db".to_string(), None);
+        main.current_commit_hash = Some(child.hash.clone());
+        let working = WorkingCommit::new("db".to_string(), Some("main".into()), &child, None)
+            .unwrap();
+
+        let report = check_history_integrity(
+            &"db".to_string(),
+            &[child.clone(), root],
+            &[main],
+            &[tag(&child.hash)],
+            &[working],
+        );
+        assert!(report.healthy);
+        assert_eq!(report.commits_checked, 2);
+    }
+
+    #[test]
+    fn test_reports_dangling_references() {
+        let root = commit(None, "root");
+        let child = commit(Some(&root.hash), "child");
+        let mut main = Branch::new_main_branch("db".to_string(), None);
+        main.current_commit_hash = Some(root.hash.clone());
+        let mut working = WorkingCommit::new("db".to_string(), Some("main".into()), &root, None)
+            .unwrap();
+        let mut abandoned = working.clone();
+        abandoned.status = WorkingCommitStatus::Abandoned;
+        working.based_on_hash = "gone".to_string();
+        abandoned.based_on_hash = "gone".to_string();
+
+        // Only the child survived, e.g. after a partial delete
+        let report = check_history_integrity(
+            &"db".to_string(),
+            std::slice::from_ref(&child),
+            &[main],
+            &[tag(&root.hash)],
+            &[working, abandoned],
+        );
+        assert!(!report.healthy);
+        let kinds: Vec<_> = report.issues.iter().map(|i| (i.kind, i.fixable)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (IntegrityIssueKind::MissingParent, false),
+                (IntegrityIssueKind::MissingBranchHead, false),
+                (IntegrityIssueKind::MissingTagCommit, true),
+                (IntegrityIssueKind::MissingWorkingCommitBase, true),
+            ]
+        );
+        assert_eq!(report.issues[0].subject, child.hash);
+        assert_eq!(report.issues[0].missing_hash, root.hash);
+    }
+}
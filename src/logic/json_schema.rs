@@ -0,0 +1,161 @@
+use crate::model::{ClassDef, DataType};
+use serde_json::{json, Map, Value};
+
+/// Dialect the generated schemas declare in `$schema`
+pub const JSON_SCHEMA_DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Translate a class definition into a draft-07 JSON Schema for an object of property
+/// values keyed by property name, the simple value format instances accept. Required
+/// properties are listed in `required`; `pattern`, `min` and `max` carry over as
+/// `pattern`, `minimum` and `maximum`, and a property's constant value as `default`.
+/// Patterns are anchored as `^(?:pattern)$`, since validation matches them in full and
+/// JSON Schema's `pattern` matches anywhere in the string.
+/// Derived properties are included as `readOnly`, since they are computed, never sent.
+pub fn class_json_schema(class_def: &ClassDef) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+
+    for property in &class_def.properties {
+        let mut schema = data_type_schema(&property.data_type);
+        if let Some(pattern) = &property.pattern {
+            schema.insert("pattern".to_string(), json!(format!("^(?:{})$", pattern)));
+        }
+        if let Some(min) = property.min {
+            schema.insert("minimum".to_string(), json!(min));
+        }
+        if let Some(max) = property.max {
+            schema.insert("maximum".to_string(), json!(max));
+        }
+        if let Some(value) = &property.value {
+            schema.insert("default".to_string(), value.clone());
+        }
+        if property.required.unwrap_or(false) {
+            required.push(json!(property.name));
+        }
+        properties.insert(property.name.clone(), Value::Object(schema));
+    }
+
+    for derived in &class_def.derived {
+        let mut schema = data_type_schema(&derived.data_type);
+        schema.insert("readOnly".to_string(), json!(true));
+        properties.insert(derived.name.clone(), Value::Object(schema));
+    }
+
+    let mut schema = Map::new();
+    schema.insert("$schema".to_string(), json!(JSON_SCHEMA_DRAFT_07));
+    schema.insert("title".to_string(), json!(class_def.name));
+    if let Some(description) = &class_def.description {
+        schema.insert("description".to_string(), json!(description));
+    }
+    schema.insert("type".to_string(), json!("object"));
+    schema.insert("properties".to_string(), Value::Object(properties));
+    if !required.is_empty() {
+        schema.insert("required".to_string(), Value::Array(required));
+    }
+    Value::Object(schema)
+}
+
+/// JSON type keywords for a data type; `json` values may be anything, so get no keywords
+fn data_type_schema(data_type: &DataType) -> Map<String, Value> {
+    let schema = match data_type {
+        DataType::String => json!({ "type": "string" }),
+        DataType::Number => json!({ "type": "number" }),
+        DataType::Boolean => json!({ "type": "boolean" }),
+        DataType::Object => json!({ "type": "object" }),
+        DataType::Array => json!({ "type": "array" }),
+        DataType::StringList => json!({ "type": "array", "items": { "type": "string" } }),
+        DataType::Json => json!({}),
+    };
+    match schema {
+        Value::Object(map) => map,
+        _ => unreachable!("schema literals are objects"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{DerivedDef, PropertyDef};
+
+    fn property(value: Value) -> PropertyDef {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_json_schema_lists_required_properties() {
+        let class_def = ClassDef {
+            id: "class-chair".to_string(),
+            name: "Chair".to_string(),
+            description: Some("Something to sit on".to_string()),
+            properties: vec![
+                property(json!({
+                    "id": "prop-name",
+                    "name": "name",
+                    "data_type": "string",
+                    "required": true,
+                    "pattern": "[A-Z].*"
+                })),
+                property(json!({ "id": "prop-tags", "name": "tags", "data_type": "string-list" })),
+                property(json!({
+                    "id": "prop-stackable",
+                    "name": "stackable",
+                    "data_type": "boolean",
+                    "required": false,
+                    "value": false
+                })),
+            ],
+            ..ClassDef::default()
+        };
+
+        assert_eq!(
+            class_json_schema(&class_def),
+            json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "title": "Chair",
+                "description": "Something to sit on",
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "pattern": "^(?:[A-Z].*)$" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "stackable": { "type": "boolean", "default": false }
+                },
+                "required": ["name"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_schema_number_range_and_derived() {
+        let class_def = ClassDef {
+            id: "class-part".to_string(),
+            name: "Part".to_string(),
+            properties: vec![property(json!({
+                "id": "prop-price",
+                "name": "price",
+                "data_type": "number",
+                "min": 0.0,
+                "max": 500.0
+            }))],
+            derived: vec![DerivedDef {
+                id: "derived-total".to_string(),
+                name: "total".to_string(),
+                data_type: DataType::Number,
+                expr: None,
+                fn_short: None,
+                materialize: None,
+            }],
+            ..ClassDef::default()
+        };
+
+        let schema = class_json_schema(&class_def);
+        assert_eq!(
+            schema["properties"]["price"],
+            json!({ "type": "number", "minimum": 0.0, "maximum": 500.0 })
+        );
+        assert_eq!(
+            schema["properties"]["total"],
+            json!({ "type": "number", "readOnly": true })
+        );
+        assert!(schema.get("required").is_none());
+    }
+}
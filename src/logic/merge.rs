@@ -2,54 +2,83 @@ use anyhow::{anyhow, Result};
 use std::collections::{HashMap, HashSet};
 
 use crate::model::merge::{
-    ChangeOp, CommitDiff, FieldChange, MergeConflict,
-    MergeResult,
+    ChangeOp, CommitDiff, ConflictType, FieldChange, MergeConflict,
+    MergeOptions, MergeResult, MergeStrategy, RelationshipMergeStrategy,
 };
-use crate::model::{ClassDef, CommitData, Instance, Schema};
+use crate::model::{ClassDef, CommitData, Id, Instance, Schema};
 use crate::store::traits::Store;
 
 /// Implements three-way merge algorithm for commits
 pub struct MergeEngine;
 
 impl MergeEngine {
-    /// Find the common ancestor of two commits
+    /// Newest commit of `left_commit`'s history that is also in `right_commit`'s, within
+    /// the database. `None` when the histories are disjoint.
     pub async fn find_common_ancestor<S: Store>(
         store: &S,
+        database_id: &Id,
         left_commit: &str,
         right_commit: &str,
     ) -> Result<Option<String>> {
-        // Build ancestor sets for both commits
-        let left_ancestors = Self::get_ancestor_chain(store, left_commit).await?;
-        let right_ancestors = Self::get_ancestor_chain(store, right_commit).await?;
-
-        // Find the most recent common ancestor
-        for ancestor in &left_ancestors {
-            if right_ancestors.contains(ancestor) {
-                return Ok(Some(ancestor.clone()));
+        let right_history: HashSet<String> =
+            Self::database_history(store, database_id, right_commit)
+                .await?
+                .into_iter()
+                .collect();
+        Ok(Self::database_history(store, database_id, left_commit)
+            .await?
+            .into_iter()
+            .find(|hash| right_history.contains(hash)))
+    }
+
+    /// Lowest common ancestor of the heads of `branch_a` and `branch_b`: the newest commit of
+    /// `branch_b`'s history that is also in `branch_a`'s. `None` when the histories are
+    /// disjoint or either branch has no commits yet; errors when a branch doesn't exist.
+    pub async fn merge_base<S: Store>(
+        store: &S,
+        database_id: &Id,
+        branch_a: &str,
+        branch_b: &str,
+    ) -> Result<Option<String>> {
+        let mut heads = Vec::with_capacity(2);
+        for branch_name in [branch_a, branch_b] {
+            let branch = store
+                .get_branch(database_id, branch_name)
+                .await?
+                .ok_or_else(|| anyhow!("Branch '{}' not found", branch_name))?;
+            match branch.current_commit_hash {
+                Some(head) => heads.push(head),
+                None => return Ok(None),
             }
         }
 
-        Ok(None)
+        Self::find_common_ancestor(store, database_id, &heads[1], &heads[0]).await
     }
 
-    /// Get the chain of ancestors for a commit (including the commit itself)
-    async fn get_ancestor_chain<S: Store>(
+    /// Commits from `head` back to the root, newest first. Stops at a commit that is missing,
+    /// belongs to another database, or was already visited in a looping parent chain.
+    async fn database_history<S: Store>(
         store: &S,
-        commit_hash: &str,
+        database_id: &Id,
+        head: &str,
     ) -> Result<Vec<String>> {
-        let mut ancestors = vec![commit_hash.to_string()];
-        let mut current = commit_hash.to_string();
-
-        while let Some(commit) = store.get_commit(&current).await? {
-            if let Some(parent) = commit.parent_hash {
-                ancestors.push(parent.clone());
-                current = parent;
-            } else {
+        let mut history = Vec::new();
+        let mut seen = HashSet::new();
+        let mut current = Some(head.to_string());
+        while let Some(hash) = current.take() {
+            if !seen.insert(hash.clone()) {
                 break;
             }
+            let Some(commit) = store.get_commit(&hash).await? else {
+                break;
+            };
+            if &commit.database_id != database_id {
+                break;
+            }
+            history.push(hash);
+            current = commit.parent_hash;
         }
-
-        Ok(ancestors)
+        Ok(history)
     }
 
     /// Perform a three-way merge
@@ -58,6 +87,24 @@ impl MergeEngine {
         base_commit: &str,
         left_commit: &str,
         right_commit: &str,
+    ) -> Result<MergeResult> {
+        Self::three_way_merge_with_options(
+            store,
+            base_commit,
+            left_commit,
+            right_commit,
+            &MergeOptions::default(),
+        )
+        .await
+    }
+
+    /// Perform a three-way merge with explicit merge options
+    pub async fn three_way_merge_with_options<S: Store>(
+        store: &S,
+        base_commit: &str,
+        left_commit: &str,
+        right_commit: &str,
+        options: &MergeOptions,
     ) -> Result<MergeResult> {
         // Load all three commits
         let base = store.get_commit(base_commit).await?
@@ -80,7 +127,7 @@ impl MergeEngine {
         let right_diff = Self::compute_diff(&base_data, &right_data)?;
 
         // Merge the diffs
-        Ok(Self::merge_diffs(left_diff, right_diff))
+        Ok(Self::merge_diffs_with_options(left_diff, right_diff, options))
     }
 
     /// Compute diff between two commit data states
@@ -217,6 +264,18 @@ impl MergeEngine {
             );
         }
 
+        // Compare business-rule constraints
+        if from.constraints != to.constraints {
+            field_changes.insert(
+                "constraints".to_string(),
+                FieldChange {
+                    field_path: vec!["constraints".to_string()],
+                    old_value: Some(serde_json::to_value(&from.constraints)?),
+                    new_value: Some(serde_json::to_value(&to.constraints)?),
+                },
+            );
+        }
+
         if field_changes.is_empty() {
             Ok(None)
         } else {
@@ -365,6 +424,15 @@ impl MergeEngine {
 
     /// Merge two diffs to produce a final result
     pub fn merge_diffs(left_diff: CommitDiff, right_diff: CommitDiff) -> MergeResult {
+        Self::merge_diffs_with_options(left_diff, right_diff, &MergeOptions::default())
+    }
+
+    /// Merge two diffs using the given options
+    pub fn merge_diffs_with_options(
+        left_diff: CommitDiff,
+        right_diff: CommitDiff,
+        options: &MergeOptions,
+    ) -> MergeResult {
         let mut conflicts = Vec::new();
         let mut merged_operations = Vec::new();
         let mut processed_right = HashSet::new();
@@ -373,10 +441,30 @@ impl MergeEngine {
         for left_op in left_diff.operations {
             let (left_type, left_id) = left_op.resource_info();
             let mut found_conflict = false;
+            let mut auto_merged = None;
 
             // Check against all right operations
             for (idx, right_op) in right_diff.operations.iter().enumerate() {
                 if let Some(conflict_type) = left_op.conflicts_with(right_op) {
+                    // Concurrent instance patches may still compose field by field
+                    if conflict_type == ConflictType::ModifyModify {
+                        if let Some(merged_op) =
+                            Self::try_merge_instance_patches(&left_op, right_op, options)
+                        {
+                            auto_merged = Some(merged_op);
+                            processed_right.insert(idx);
+                            break;
+                        }
+                    }
+
+                    if let Some(resolved_op) =
+                        Self::resolve_with_strategy(&left_op, right_op, &options.strategy)
+                    {
+                        auto_merged = Some(resolved_op);
+                        processed_right.insert(idx);
+                        break;
+                    }
+
                     let (_, _right_id) = right_op.resource_info();
                     
                     // Create conflict description
@@ -401,7 +489,9 @@ impl MergeEngine {
                 }
             }
 
-            if !found_conflict {
+            if let Some(merged_op) = auto_merged {
+                merged_operations.push(merged_op);
+            } else if !found_conflict {
                 // No conflict, include left operation
                 merged_operations.push(left_op);
             }
@@ -422,6 +512,368 @@ impl MergeEngine {
         }
     }
 
+    /// Try to combine two patches of the same instance without a conflict
+    /// Overlapping fields must agree, except `relationships`, which is merged per relationship
+    fn try_merge_instance_patches(
+        left_op: &ChangeOp,
+        right_op: &ChangeOp,
+        options: &MergeOptions,
+    ) -> Option<ChangeOp> {
+        let (
+            ChangeOp::PatchInstance {
+                instance_id,
+                field_changes: left_changes,
+            },
+            ChangeOp::PatchInstance {
+                field_changes: right_changes,
+                ..
+            },
+        ) = (left_op, right_op)
+        else {
+            return None;
+        };
+
+        let mut merged_changes = left_changes.clone();
+        for (field, right_change) in right_changes {
+            match left_changes.get(field) {
+                None => {
+                    merged_changes.insert(field.clone(), right_change.clone());
+                }
+                Some(left_change) if left_change.new_value == right_change.new_value => {}
+                Some(left_change) if field == "relationships" => {
+                    let merged = Self::merge_relationship_maps(
+                        left_change.old_value.as_ref(),
+                        left_change.new_value.as_ref(),
+                        right_change.new_value.as_ref(),
+                        &options.relationship_merge,
+                    )?;
+                    merged_changes.insert(
+                        field.clone(),
+                        FieldChange {
+                            field_path: left_change.field_path.clone(),
+                            old_value: left_change.old_value.clone(),
+                            new_value: Some(merged),
+                        },
+                    );
+                }
+                Some(_) => return None,
+            }
+        }
+
+        Some(ChangeOp::PatchInstance {
+            instance_id: instance_id.clone(),
+            field_changes: merged_changes,
+        })
+    }
+
+    /// Settle a conflict by `strategy`: `None` leaves it for manual resolution. The winning
+    /// side's operation replaces the other one, except that two patches of the same instance
+    /// are combined property by property and relationship by relationship.
+    fn resolve_with_strategy(
+        left_op: &ChangeOp,
+        right_op: &ChangeOp,
+        strategy: &MergeStrategy,
+    ) -> Option<ChangeOp> {
+        let prefer_left = match strategy {
+            MergeStrategy::Recursive => return None,
+            MergeStrategy::Ours => true,
+            MergeStrategy::Theirs => false,
+        };
+        let resolved = Self::combine_instance_patches(left_op, right_op, prefer_left);
+        Some(resolved.unwrap_or_else(|| {
+            if prefer_left {
+                left_op.clone()
+            } else {
+                right_op.clone()
+            }
+        }))
+    }
+
+    /// Combine two patches of the same instance, taking the preferred side's value for every
+    /// field, property or relationship both sides changed. `None` unless both are instance
+    /// patches.
+    fn combine_instance_patches(
+        left_op: &ChangeOp,
+        right_op: &ChangeOp,
+        prefer_left: bool,
+    ) -> Option<ChangeOp> {
+        let (
+            ChangeOp::PatchInstance {
+                instance_id,
+                field_changes: left_changes,
+            },
+            ChangeOp::PatchInstance {
+                field_changes: right_changes,
+                ..
+            },
+        ) = (left_op, right_op)
+        else {
+            return None;
+        };
+
+        let mut combined = left_changes.clone();
+        for (field, right_change) in right_changes {
+            let Some(left_change) = left_changes.get(field) else {
+                combined.insert(field.clone(), right_change.clone());
+                continue;
+            };
+            let change = if field == "properties" || field == "relationships" {
+                FieldChange {
+                    field_path: left_change.field_path.clone(),
+                    old_value: left_change.old_value.clone(),
+                    new_value: Some(Self::merge_maps_preferring(
+                        left_change.old_value.as_ref(),
+                        left_change.new_value.as_ref(),
+                        right_change.new_value.as_ref(),
+                        prefer_left,
+                    )),
+                }
+            } else if prefer_left {
+                left_change.clone()
+            } else {
+                right_change.clone()
+            };
+            combined.insert(field.clone(), change);
+        }
+
+        Some(ChangeOp::PatchInstance {
+            instance_id: instance_id.clone(),
+            field_changes: combined,
+        })
+    }
+
+    /// Three-way merge of two JSON maps key by key; a key both sides changed differently takes
+    /// the preferred side's value, including its removal
+    fn merge_maps_preferring(
+        base: Option<&serde_json::Value>,
+        left: Option<&serde_json::Value>,
+        right: Option<&serde_json::Value>,
+        prefer_left: bool,
+    ) -> serde_json::Value {
+        let empty = serde_json::Map::new();
+        let base = base.and_then(|v| v.as_object()).unwrap_or(&empty);
+        let left = left.and_then(|v| v.as_object()).unwrap_or(&empty);
+        let right = right.and_then(|v| v.as_object()).unwrap_or(&empty);
+
+        let mut keys: Vec<&String> = base.keys().chain(left.keys()).chain(right.keys()).collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut merged = serde_json::Map::new();
+        for key in keys {
+            let (b, l, r) = (base.get(key), left.get(key), right.get(key));
+            let value = if l == r || r == b {
+                l
+            } else if l == b || !prefer_left {
+                r
+            } else {
+                l
+            };
+            if let Some(value) = value {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+        serde_json::Value::Object(merged)
+    }
+
+    /// Union resolution of two conflicting patches of the same instance: every relationship
+    /// selection both sides changed gets the ids of both, left ones first and without
+    /// duplicates. `None` when the conflict isn't limited to `SimpleIds`/`Ids` selections,
+    /// e.g. both sides changed a property or one side removed the relationship.
+    pub fn union_relationship_patches(left_op: &ChangeOp, right_op: &ChangeOp) -> Option<ChangeOp> {
+        let (
+            ChangeOp::PatchInstance {
+                instance_id,
+                field_changes: left_changes,
+            },
+            ChangeOp::PatchInstance {
+                field_changes: right_changes,
+                ..
+            },
+        ) = (left_op, right_op)
+        else {
+            return None;
+        };
+
+        let mut combined = left_changes.clone();
+        for (field, right_change) in right_changes {
+            let Some(left_change) = left_changes.get(field) else {
+                combined.insert(field.clone(), right_change.clone());
+                continue;
+            };
+            if left_change.new_value == right_change.new_value {
+                continue;
+            }
+            if field != "relationships" {
+                return None;
+            }
+
+            let empty = serde_json::Map::new();
+            let base = left_change
+                .old_value
+                .as_ref()
+                .and_then(|v| v.as_object())
+                .unwrap_or(&empty);
+            let left = left_change
+                .new_value
+                .as_ref()
+                .and_then(|v| v.as_object())
+                .unwrap_or(&empty);
+            let right = right_change
+                .new_value
+                .as_ref()
+                .and_then(|v| v.as_object())
+                .unwrap_or(&empty);
+
+            let mut keys: Vec<&String> =
+                base.keys().chain(left.keys()).chain(right.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            let mut merged = serde_json::Map::new();
+            for key in keys {
+                let (b, l, r) = (base.get(key), left.get(key), right.get(key));
+                let value = if l == r || r == b {
+                    l.cloned()
+                } else if l == b {
+                    r.cloned()
+                } else {
+                    // Both changed it; a removal on either side can't be combined
+                    let (l, r) = (l?, r?);
+                    let mut ids: Vec<String> = Vec::new();
+                    for id in Self::selection_ids(l)?
+                        .into_iter()
+                        .chain(Self::selection_ids(r)?)
+                    {
+                        if !ids.contains(&id) {
+                            ids.push(id);
+                        }
+                    }
+                    // Keep the left side's selection shape
+                    Some(if l.is_array() {
+                        serde_json::json!(ids)
+                    } else {
+                        serde_json::json!({ "ids": ids })
+                    })
+                };
+                if let Some(value) = value {
+                    merged.insert(key.clone(), value);
+                }
+            }
+
+            combined.insert(
+                field.clone(),
+                FieldChange {
+                    field_path: left_change.field_path.clone(),
+                    old_value: left_change.old_value.clone(),
+                    new_value: Some(serde_json::Value::Object(merged)),
+                },
+            );
+        }
+
+        Some(ChangeOp::PatchInstance {
+            instance_id: instance_id.clone(),
+            field_changes: combined,
+        })
+    }
+
+    /// Three-way merge of two relationship maps, relationship by relationship
+    fn merge_relationship_maps(
+        base: Option<&serde_json::Value>,
+        left: Option<&serde_json::Value>,
+        right: Option<&serde_json::Value>,
+        strategy: &RelationshipMergeStrategy,
+    ) -> Option<serde_json::Value> {
+        if *strategy == RelationshipMergeStrategy::Conflict {
+            return None;
+        }
+
+        let empty = serde_json::Map::new();
+        let base = base.and_then(|v| v.as_object()).unwrap_or(&empty);
+        let left = left.and_then(|v| v.as_object()).unwrap_or(&empty);
+        let right = right.and_then(|v| v.as_object()).unwrap_or(&empty);
+
+        let mut keys: Vec<&String> = left.keys().chain(right.keys()).collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut merged = serde_json::Map::new();
+        for key in keys {
+            let (b, l, r) = (base.get(key), left.get(key), right.get(key));
+            let value = if l == r || r == b {
+                l.cloned()
+            } else if l == b {
+                r.cloned()
+            } else {
+                // Both sides changed this relationship - only id lists can be combined
+                Some(Self::merge_id_selections(b, l?, r?, strategy)?)
+            };
+            if let Some(value) = value {
+                merged.insert(key.clone(), value);
+            }
+        }
+
+        Some(serde_json::Value::Object(merged))
+    }
+
+    /// Set-aware merge of two `SimpleIds`/`Ids` selections against their common base
+    fn merge_id_selections(
+        base: Option<&serde_json::Value>,
+        left: &serde_json::Value,
+        right: &serde_json::Value,
+        strategy: &RelationshipMergeStrategy,
+    ) -> Option<serde_json::Value> {
+        let base_ids = match base {
+            Some(value) => Self::selection_ids(value)?,
+            None => Vec::new(),
+        };
+        let left_ids = Self::selection_ids(left)?;
+        let right_ids = Self::selection_ids(right)?;
+
+        let merged_ids: Vec<String> = match strategy {
+            RelationshipMergeStrategy::Union => {
+                // One side removing an id the other side kept is a genuine disagreement
+                let removed_left_kept_right = base_ids
+                    .iter()
+                    .any(|id| !left_ids.contains(id) && right_ids.contains(id));
+                let removed_right_kept_left = base_ids
+                    .iter()
+                    .any(|id| !right_ids.contains(id) && left_ids.contains(id));
+                if removed_left_kept_right || removed_right_kept_left {
+                    return None;
+                }
+                let mut ids = left_ids.clone();
+                ids.extend(right_ids.into_iter().filter(|id| !left_ids.contains(id)));
+                ids
+            }
+            RelationshipMergeStrategy::Intersection => left_ids
+                .into_iter()
+                .filter(|id| right_ids.contains(id))
+                .collect(),
+            RelationshipMergeStrategy::Conflict => return None,
+        };
+
+        // Keep the left side's selection shape
+        if left.is_array() {
+            Some(serde_json::json!(merged_ids))
+        } else {
+            Some(serde_json::json!({ "ids": merged_ids }))
+        }
+    }
+
+    /// Extract ids from a serialized `SimpleIds` or `Ids` selection
+    fn selection_ids(value: &serde_json::Value) -> Option<Vec<String>> {
+        let ids = match value {
+            serde_json::Value::Array(_) => value,
+            serde_json::Value::Object(obj) if obj.len() == 1 => obj.get("ids")?,
+            _ => return None,
+        };
+        ids.as_array()?
+            .iter()
+            .map(|id| id.as_str().map(|s| s.to_string()))
+            .collect()
+    }
+
     /// Apply a merge result to create a new commit data
     pub fn apply_merge_result(
         base_data: &CommitData,
@@ -499,6 +951,11 @@ impl MergeEngine {
                         class.domain_constraint = serde_json::from_value(new_val.clone())?;
                     }
                 }
+                "constraints" => {
+                    if let Some(new_val) = &change.new_value {
+                        class.constraints = serde_json::from_value(new_val.clone())?;
+                    }
+                }
                 _ => {
                     // Ignore unknown fields
                 }
@@ -541,4 +998,405 @@ impl MergeEngine {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{
+        Branch, DataType, Database, NewCommit, NewWorkingCommit, PropertyValue,
+        RelationshipSelection, TypedValue,
+    };
+    use crate::store::traits::{BranchStore, CommitStore, DatabaseStore, WorkingCommitStore};
+    use crate::store::InMemoryStore;
+    use serde_json::json;
+
+    fn commit_data(colors: &[&str]) -> CommitData {
+        let mut car = Instance {
+            id: "car".to_string(),
+            class_id: "class-car".to_string(),
+            ..Instance::default()
+        };
+        car.relationships.insert(
+            "colors".to_string(),
+            RelationshipSelection::SimpleIds(colors.iter().map(|c| c.to_string()).collect()),
+        );
+        CommitData {
+            schema: Schema {
+                id: "schema".to_string(),
+                classes: vec![],
+                description: None,
+            },
+            instances: vec![car],
+        }
+    }
+
+    fn merged_colors(base: &CommitData, result: &MergeResult) -> RelationshipSelection {
+        let merged = MergeEngine::apply_merge_result(base, result).unwrap();
+        merged.instances[0].relationships["colors"].clone()
+    }
+
+    fn merge(
+        base: &CommitData,
+        left: &[&str],
+        right: &[&str],
+        strategy: RelationshipMergeStrategy,
+    ) -> MergeResult {
+        let left_diff = MergeEngine::compute_diff(base, &commit_data(left)).unwrap();
+        let right_diff = MergeEngine::compute_diff(base, &commit_data(right)).unwrap();
+        MergeEngine::merge_diffs_with_options(
+            left_diff,
+            right_diff,
+            &MergeOptions {
+                relationship_merge: strategy,
+                ..MergeOptions::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_union_merge_of_additive_id_edits() {
+        let base = commit_data(&["red"]);
+        let result = merge(
+            &base,
+            &["red", "blue"],
+            &["red", "green"],
+            RelationshipMergeStrategy::Union,
+        );
+
+        assert!(result.success);
+        assert_eq!(
+            merged_colors(&base, &result),
+            RelationshipSelection::SimpleIds(vec![
+                "red".to_string(),
+                "blue".to_string(),
+                "green".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_union_merge_conflicts_when_removed_id_was_kept() {
+        let base = commit_data(&["red", "blue"]);
+        let result = merge(
+            &base,
+            &["blue"],
+            &["red", "blue", "green"],
+            RelationshipMergeStrategy::Union,
+        );
+
+        assert!(!result.success);
+        assert_eq!(result.conflicts[0].conflict_type, ConflictType::ModifyModify);
+    }
+
+    #[test]
+    fn test_intersection_merge_keeps_common_ids() {
+        let base = commit_data(&["red"]);
+        let result = merge(
+            &base,
+            &["red", "blue"],
+            &["red", "green"],
+            RelationshipMergeStrategy::Intersection,
+        );
+
+        assert!(result.success);
+        assert_eq!(
+            merged_colors(&base, &result),
+            RelationshipSelection::SimpleIds(vec!["red".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_conflict_strategy_keeps_whole_relationship_conflict() {
+        let base = commit_data(&["red"]);
+        let result = merge(
+            &base,
+            &["red", "blue"],
+            &["red", "green"],
+            RelationshipMergeStrategy::Conflict,
+        );
+
+        assert!(!result.success);
+        assert_eq!(result.conflicts.len(), 1);
+    }
+
+    /// The car of `commit_data` with literal `price` and `trim` properties
+    fn priced_car(price: f64, trim: &str) -> CommitData {
+        let mut data = commit_data(&["red"]);
+        let properties = [
+            ("price", json!(price), DataType::Number),
+            ("trim", json!(trim), DataType::String),
+        ];
+        for (name, value, data_type) in properties {
+            data.instances[0].properties.insert(
+                name.to_string(),
+                PropertyValue::Literal(TypedValue { value, data_type }),
+            );
+        }
+        data
+    }
+
+    fn merge_with_strategy(
+        base: &CommitData,
+        left: &CommitData,
+        right: &CommitData,
+        strategy: MergeStrategy,
+    ) -> CommitData {
+        let result = MergeEngine::merge_diffs_with_options(
+            MergeEngine::compute_diff(base, left).unwrap(),
+            MergeEngine::compute_diff(base, right).unwrap(),
+            &MergeOptions {
+                strategy,
+                ..MergeOptions::default()
+            },
+        );
+        assert!(result.success, "unexpected conflicts: {:?}", result.conflicts);
+        MergeEngine::apply_merge_result(base, &result).unwrap()
+    }
+
+    fn property(data: &CommitData, name: &str) -> serde_json::Value {
+        match &data.instances[0].properties[name] {
+            PropertyValue::Literal(literal) => literal.value.clone(),
+            other => panic!("unexpected property value {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_recursive_strategy_keeps_property_conflict() {
+        let base = priced_car(10.0, "base");
+        let result = MergeEngine::merge_diffs(
+            MergeEngine::compute_diff(&base, &priced_car(12.0, "base")).unwrap(),
+            MergeEngine::compute_diff(&base, &priced_car(15.0, "base")).unwrap(),
+        );
+        assert!(!result.success);
+        assert_eq!(result.conflicts[0].conflict_type, ConflictType::ModifyModify);
+    }
+
+    #[test]
+    fn test_ours_strategy_keeps_target_property() {
+        let base = priced_car(10.0, "base");
+        let merged = merge_with_strategy(
+            &base,
+            &priced_car(12.0, "base"),
+            &priced_car(15.0, "sport"),
+            MergeStrategy::Ours,
+        );
+        assert_eq!(property(&merged, "price"), json!(12.0));
+        // A property only the source changed still comes through
+        assert_eq!(property(&merged, "trim"), json!("sport"));
+    }
+
+    #[test]
+    fn test_theirs_strategy_keeps_source_property() {
+        let base = priced_car(10.0, "base");
+        let merged = merge_with_strategy(
+            &base,
+            &priced_car(12.0, "luxury"),
+            &priced_car(15.0, "base"),
+            MergeStrategy::Theirs,
+        );
+        assert_eq!(property(&merged, "price"), json!(15.0));
+        assert_eq!(property(&merged, "trim"), json!("luxury"));
+    }
+
+    #[test]
+    fn test_strategy_resolves_schema_conflicts_per_class() {
+        let class = |name: &str, description: Option<&str>| {
+            let mut data = commit_data(&["red"]);
+            data.schema.classes.push(ClassDef {
+                id: "class-car".to_string(),
+                name: name.to_string(),
+                description: description.map(str::to_string),
+                ..ClassDef::default()
+            });
+            data
+        };
+        let base = class("Car", None);
+        let left = class("Automobile", None);
+        let right = class("Vehicle", Some("Anything with wheels"));
+
+        let ours = merge_with_strategy(&base, &left, &right, MergeStrategy::Ours);
+        // The whole target class wins, including fields only the source changed
+        assert_eq!(ours.schema.classes[0].name, "Automobile");
+        assert_eq!(ours.schema.classes[0].description, None);
+
+        let theirs = merge_with_strategy(&base, &left, &right, MergeStrategy::Theirs);
+        assert_eq!(theirs.schema.classes[0].name, "Vehicle");
+        assert_eq!(
+            theirs.schema.classes[0].description.as_deref(),
+            Some("Anything with wheels")
+        );
+    }
+
+    /// Union resolution of the single conflict between the diffs to `left` and `right`
+    fn union_resolution(
+        base: &CommitData,
+        left: &CommitData,
+        right: &CommitData,
+    ) -> Option<CommitData> {
+        let left_diff = MergeEngine::compute_diff(base, left).unwrap();
+        let right_diff = MergeEngine::compute_diff(base, right).unwrap();
+        let union = MergeEngine::union_relationship_patches(
+            &left_diff.operations[0],
+            &right_diff.operations[0],
+        )?;
+        let result = MergeResult {
+            success: true,
+            conflicts: vec![],
+            merged_operations: vec![union],
+            needs_validation: true,
+        };
+        Some(MergeEngine::apply_merge_result(base, &result).unwrap())
+    }
+
+    #[test]
+    fn test_union_resolution_of_disjoint_ids() {
+        let base = commit_data(&["red"]);
+        let merged = union_resolution(
+            &base,
+            &commit_data(&["red", "blue"]),
+            &commit_data(&["red", "green"]),
+        )
+        .unwrap();
+        assert_eq!(
+            merged.instances[0].relationships["colors"],
+            RelationshipSelection::SimpleIds(vec![
+                "red".to_string(),
+                "blue".to_string(),
+                "green".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_union_resolution_of_overlapping_ids() {
+        let base = commit_data(&["red"]);
+        // The source dropped red; the union keeps it because the target still has it
+        let merged = union_resolution(
+            &base,
+            &commit_data(&["red", "blue", "green"]),
+            &commit_data(&["green", "blue", "white"]),
+        )
+        .unwrap();
+        assert_eq!(
+            merged.instances[0].relationships["colors"],
+            RelationshipSelection::SimpleIds(vec![
+                "red".to_string(),
+                "blue".to_string(),
+                "green".to_string(),
+                "white".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_union_resolution_rejects_property_conflict() {
+        let base = priced_car(10.0, "base");
+        let resolved =
+            union_resolution(&base, &priced_car(12.0, "base"), &priced_car(15.0, "base"));
+        assert!(resolved.is_none());
+    }
+
+    async fn history_store() -> (InMemoryStore, Database) {
+        let store = InMemoryStore::new();
+        let database = Database::new("merge-base".to_string(), None);
+        store.upsert_database(database.clone()).await.unwrap();
+        store
+            .upsert_branch(Branch::new_main_branch(database.id.clone(), None))
+            .await
+            .unwrap();
+        (store, database)
+    }
+
+    /// Commits an empty change on `branch` and moves the branch to it
+    async fn commit_on(store: &InMemoryStore, database: &Database, branch: &str) -> String {
+        let wc = store
+            .create_working_commit(&database.id, branch, NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        let commit = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc.id.clone(),
+                message: format!("on {}", branch),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+        store.delete_working_commit(&wc.id).await.unwrap();
+        let mut head = store
+            .get_branch(&database.id, branch)
+            .await
+            .unwrap()
+            .unwrap();
+        head.current_commit_hash = Some(commit.hash.clone());
+        store.upsert_branch(head).await.unwrap();
+        commit.hash
+    }
+
+    async fn branch_at(store: &InMemoryStore, database: &Database, name: &str, head: Option<&str>) {
+        store
+            .upsert_branch(Branch {
+                name: name.to_string(),
+                current_commit_hash: head.map(str::to_string),
+                ..Branch::new_main_branch(database.id.clone(), None)
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_base_of_sibling_branches() {
+        let (store, database) = history_store().await;
+        commit_on(&store, &database, "main").await;
+        let fork = commit_on(&store, &database, "main").await;
+        branch_at(&store, &database, "feature", Some(&fork)).await;
+        commit_on(&store, &database, "main").await;
+        commit_on(&store, &database, "feature").await;
+        commit_on(&store, &database, "feature").await;
+
+        for (a, b) in [("main", "feature"), ("feature", "main")] {
+            let base = MergeEngine::merge_base(&store, &database.id, a, b)
+                .await
+                .unwrap();
+            assert_eq!(base, Some(fork.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge_base_when_one_branch_contains_the_other() {
+        let (store, database) = history_store().await;
+        let old_head = commit_on(&store, &database, "main").await;
+        branch_at(&store, &database, "stale", Some(&old_head)).await;
+        commit_on(&store, &database, "main").await;
+
+        let base = MergeEngine::merge_base(&store, &database.id, "main", "stale")
+            .await
+            .unwrap();
+        assert_eq!(base, Some(old_head));
+    }
+
+    #[tokio::test]
+    async fn test_merge_base_of_disjoint_histories() {
+        let (store, database) = history_store().await;
+        commit_on(&store, &database, "main").await;
+        branch_at(&store, &database, "orphan", None).await;
+        let base = MergeEngine::merge_base(&store, &database.id, "main", "orphan")
+            .await
+            .unwrap();
+        assert_eq!(base, None);
+
+        // A second root commit shares nothing with main
+        commit_on(&store, &database, "orphan").await;
+        let base = MergeEngine::merge_base(&store, &database.id, "main", "orphan")
+            .await
+            .unwrap();
+        assert_eq!(base, None);
+
+        assert!(
+            MergeEngine::merge_base(&store, &database.id, "main", "missing")
+                .await
+                .is_err()
+        );
+    }
+}
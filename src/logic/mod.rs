@@ -6,10 +6,22 @@ pub mod evaluate_simple;
 pub mod analysis;
 pub mod branch_ops;
 pub mod branch_ops_v2;
+pub mod cascade_delete;
+pub mod class_rename;
+pub mod class_usage;
+pub mod commit_stats;
+pub mod derived_materialization;
 pub mod expand;
+pub mod instance_csv;
 pub mod instance_filter;
+pub mod instance_history;
+pub mod instance_search;
+pub mod integrity;
+pub mod json_schema;
 pub mod merge;
 pub mod pool_resolution;
+pub mod property_rename;
+pub mod schema_impact;
 pub mod solve_pipeline;
 
 // pub use validate::*;
@@ -21,8 +33,20 @@ pub use evaluate_simple::*;
 // pub use branch_ops::*;  // Old version - commented out to avoid conflicts
 pub use analysis::*;
 pub use branch_ops_v2::*;
+pub use cascade_delete::*;
+pub use class_rename::*;
+pub use class_usage::*;
+pub use commit_stats::*;
+pub use derived_materialization::*;
 pub use expand::*;
+pub use instance_csv::*;
 pub use instance_filter::*;
+pub use instance_history::*;
+pub use instance_search::*;
+pub use integrity::*;
+pub use json_schema::*;
 pub use merge::*;
 pub use pool_resolution::*;
+pub use property_rename::*;
+pub use schema_impact::*;
 pub use solve_pipeline::{SolveError, SolvePipeline};
@@ -0,0 +1,546 @@
+//! Renaming a class property together with the instance keys and schema references using it
+//!
+//! Instances store property values keyed by the property name, so a rename moves every
+//! value of the class's instances to the new key. Derived expressions and constraints name
+//! properties either bare (`price`) or as JSON paths (`$.price`, `$.dims.width`): those of
+//! the class itself are rewritten, as are the `rel_prop`/`sum`/`count` parts of other classes
+//! that reach the class through a relationship. Default pool filters of relationships
+//! targeting the class have their paths rewritten too, as do the filters of instance
+//! relationship selections through those relationships.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::logic::{FilterExpr, JsonPath};
+use crate::model::{
+    BoolExpr, DefaultPool, Expr, Id, Instance, InstanceFilter, Predicate, RelationshipSelection,
+    Schema, SelectionSpec,
+};
+
+/// Why a property can't be renamed
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum PropertyRenameError {
+    #[error("Class '{0}' not found")]
+    ClassNotFound(Id),
+    #[error("Property '{0}' not found")]
+    PropertyNotFound(String),
+    #[error("Class already has a property or derived property named '{0}'")]
+    Collision(String),
+    #[error("New property name must not be empty")]
+    EmptyName,
+}
+
+/// What a rename rewrote besides the property definition
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PropertyRenameSummary {
+    /// Instances of the class whose value moved to the new key
+    pub instances_updated: usize,
+    /// Derived properties and constraints whose expression named the property
+    pub expressions_updated: usize,
+    /// Relationship default pool filters whose paths named the property
+    pub filters_updated: usize,
+    /// Instances with a relationship selection filtering on the property
+    pub selections_updated: usize,
+}
+
+/// Rename property `property` (matched by name or ID) of class `class_id` to `new_name` and
+/// rewrite every reference to it. An ID equal to the old name is renamed along with it.
+pub fn rename_property(
+    schema: &mut Schema,
+    instances: &mut [Instance],
+    class_id: &str,
+    property: &str,
+    new_name: &str,
+) -> Result<PropertyRenameSummary, PropertyRenameError> {
+    if new_name.trim().is_empty() {
+        return Err(PropertyRenameError::EmptyName);
+    }
+    let class = schema
+        .classes
+        .iter_mut()
+        .find(|class| class.id == class_id)
+        .ok_or_else(|| PropertyRenameError::ClassNotFound(class_id.to_string()))?;
+    let index = class
+        .properties
+        .iter()
+        .position(|p| p.name == property)
+        .or_else(|| class.properties.iter().position(|p| p.id == property))
+        .ok_or_else(|| PropertyRenameError::PropertyNotFound(property.to_string()))?;
+    let old_name = class.properties[index].name.clone();
+    let taken = class
+        .properties
+        .iter()
+        .enumerate()
+        .any(|(i, p)| i != index && (p.name == new_name || p.id == new_name))
+        || class.derived.iter().any(|d| d.name == new_name);
+    if taken {
+        return Err(PropertyRenameError::Collision(new_name.to_string()));
+    }
+    if old_name == new_name {
+        return Ok(PropertyRenameSummary::default());
+    }
+
+    let definition = &mut class.properties[index];
+    if definition.id == old_name {
+        definition.id = new_name.to_string();
+    }
+    definition.name = new_name.to_string();
+
+    let rename = Rename {
+        old: &old_name,
+        new: new_name,
+    };
+    let mut summary = PropertyRenameSummary::default();
+    let mut via_by_class: HashMap<String, HashSet<String>> = HashMap::new();
+    for class in &mut schema.classes {
+        // Relationships of this class that can reach instances of the renamed property's class
+        let via: HashSet<String> = class
+            .relationships
+            .iter()
+            .filter(|r| r.targets.iter().any(|t| t == class_id))
+            .flat_map(|r| [r.id.clone(), r.name.clone()])
+            .collect();
+        let scope = Scope {
+            own: class.id == class_id,
+            via: &via,
+        };
+
+        let derived = class.derived.iter_mut().filter_map(|d| d.expr.as_mut());
+        for expr in derived.chain(class.constraints.iter_mut()) {
+            if rename.expr(expr, &scope) {
+                summary.expressions_updated += 1;
+            }
+        }
+
+        for relationship in &mut class.relationships {
+            if !relationship.targets.iter().any(|t| t == class_id) {
+                continue;
+            }
+            let DefaultPool::Filter {
+                filter: Some(filter),
+                ..
+            } = &mut relationship.default_pool
+            else {
+                continue;
+            };
+            if filter
+                .where_clause
+                .as_mut()
+                .is_some_and(|where_clause| rename.where_clause(where_clause))
+            {
+                summary.filters_updated += 1;
+            }
+        }
+
+        if !via.is_empty() {
+            via_by_class.insert(class.id.clone(), via);
+        }
+    }
+
+    for instance in instances.iter_mut() {
+        if instance.class_id == class_id {
+            if let Some(value) = instance.properties.remove(&old_name) {
+                instance.properties.insert(new_name.to_string(), value);
+                summary.instances_updated += 1;
+            }
+        }
+
+        let Some(via) = via_by_class.get(&instance.class_id) else {
+            continue;
+        };
+        let mut changed = false;
+        for (key, selection) in instance.relationships.iter_mut() {
+            if via.contains(key) {
+                changed |= rename.selection(selection);
+            }
+        }
+        if changed {
+            summary.selections_updated += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Where an expression is evaluated relative to the renamed property's class
+struct Scope<'a> {
+    /// The expression belongs to that class, so bare property names refer to it
+    own: bool,
+    /// Relationship IDs and names leading to instances of that class
+    via: &'a HashSet<String>,
+}
+
+struct Rename<'a> {
+    old: &'a str,
+    new: &'a str,
+}
+
+impl Rename<'_> {
+    /// Rewrite a bare property name or a JSON path into the property
+    fn reference(&self, reference: &mut String) -> bool {
+        if reference == self.old {
+            *reference = self.new.to_string();
+            return true;
+        }
+        let mut path = JsonPath(std::mem::take(reference));
+        let changed = path.rename_property(self.old, self.new);
+        *reference = path.0;
+        changed
+    }
+
+    /// Rewrite the paths of a filter on instances of the renamed class
+    fn where_clause(&self, where_clause: &mut FilterExpr) -> bool {
+        where_clause
+            .paths_mut()
+            .into_iter()
+            .fold(false, |changed, path| {
+                path.rename_property(self.old, self.new) | changed
+            })
+    }
+
+    fn filter(&self, filter: &mut InstanceFilter) -> bool {
+        filter
+            .where_clause
+            .as_mut()
+            .is_some_and(|where_clause| self.where_clause(where_clause))
+    }
+
+    /// Rewrite the filters of an instance's selection through a relationship to the class
+    fn selection(&self, selection: &mut RelationshipSelection) -> bool {
+        match selection {
+            RelationshipSelection::Filter { filter } => self.filter(filter),
+            RelationshipSelection::PoolBased { pool, selection } => {
+                let pool_changed = pool.as_mut().is_some_and(|pool| self.filter(pool));
+                let selection_changed = match selection {
+                    Some(SelectionSpec::Filter(filter)) => self.filter(filter),
+                    _ => false,
+                };
+                pool_changed || selection_changed
+            }
+            RelationshipSelection::SimpleIds(_)
+            | RelationshipSelection::Ids { .. }
+            | RelationshipSelection::All => false,
+        }
+    }
+
+    fn expr(&self, expr: &mut Expr, scope: &Scope) -> bool {
+        match expr {
+            Expr::Add { left, right }
+            | Expr::Sub { left, right }
+            | Expr::Mul { left, right }
+            | Expr::Div { left, right }
+            | Expr::Eq { left, right }
+            | Expr::Ne { left, right }
+            | Expr::Lt { left, right }
+            | Expr::Lte { left, right }
+            | Expr::Gt { left, right }
+            | Expr::Gte { left, right } => {
+                // Both sides, without short-circuiting
+                self.expr(left, scope) | self.expr(right, scope)
+            }
+            Expr::Abs { expr } => self.expr(expr, scope),
+            Expr::Pow { base, exp } => self.expr(base, scope) | self.expr(exp, scope),
+            Expr::LitNumber { .. } | Expr::LitBool { .. } | Expr::LitString { .. } => false,
+            Expr::Prop { prop } => scope.own && self.reference(prop),
+            Expr::RelProp { rel, prop } => scope.via.contains(rel.as_str()) && self.reference(prop),
+            Expr::Sum {
+                over,
+                prop,
+                r#where,
+            } => {
+                if !scope.via.contains(over.as_str()) {
+                    return false;
+                }
+                let changed = self.reference(prop);
+                changed | r#where.as_mut().is_some_and(|w| self.bool_expr(w))
+            }
+            Expr::Count { over, r#where } => {
+                scope.via.contains(over.as_str())
+                    && r#where.as_mut().is_some_and(|w| self.bool_expr(w))
+            }
+            Expr::If { cond, then, r#else } => {
+                let cond_changed = scope.own && self.bool_expr(cond);
+                cond_changed | self.expr(then, scope) | self.expr(r#else, scope)
+            }
+            Expr::Cond { if_, then, else_ } => {
+                self.expr(if_, scope) | self.expr(then, scope) | self.expr(else_, scope)
+            }
+            Expr::Concat { parts, .. } => parts
+                .iter_mut()
+                .fold(false, |changed, part| self.expr(part, scope) | changed),
+        }
+    }
+
+    /// Rewrite the property predicates of a condition on instances of the renamed class
+    fn bool_expr(&self, expr: &mut BoolExpr) -> bool {
+        let predicates = match expr {
+            BoolExpr::SimpleAll { .. } => return false,
+            BoolExpr::All { predicates }
+            | BoolExpr::Any { predicates }
+            | BoolExpr::None { predicates } => predicates,
+        };
+        predicates
+            .iter_mut()
+            .fold(false, |changed, predicate| match predicate {
+                Predicate::PropEq { prop, .. }
+                | Predicate::PropNe { prop, .. }
+                | Predicate::PropGt { prop, .. }
+                | Predicate::PropLt { prop, .. }
+                | Predicate::PropContains { prop, .. } => self.reference(prop) | changed,
+                Predicate::Has { .. }
+                | Predicate::Count { .. }
+                | Predicate::HasTargets { .. }
+                | Predicate::IncludesUniverse { .. } => changed,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ClassDef, DataType, PropertyValue, TypedValue};
+    use serde_json::json;
+
+    fn class(id: &str, value: serde_json::Value) -> ClassDef {
+        let mut class: ClassDef = serde_json::from_value(value).unwrap();
+        class.id = id.to_string();
+        class
+    }
+
+    fn schema() -> Schema {
+        let part = class(
+            "class-part",
+            json!({
+                "id": "",
+                "name": "Part",
+                "properties": [
+                    { "id": "price", "name": "price", "data_type": "number" },
+                    { "id": "prop-name", "name": "name", "data_type": "string" }
+                ],
+                "relationships": [],
+                "derived": [{
+                    "id": "doubled",
+                    "name": "doubled",
+                    "data_type": "number",
+                    "expr": {
+                        "type": "mul",
+                        "left": { "type": "prop", "prop": "$.price" },
+                        "right": { "type": "lit_number", "value": 2.0 }
+                    }
+                }],
+                "description": null,
+                "constraints": [{
+                    "type": "gte",
+                    "left": { "type": "prop", "prop": "price" },
+                    "right": { "type": "lit_number", "value": 0.0 }
+                }]
+            }),
+        );
+        let bundle = class(
+            "class-bundle",
+            json!({
+                "id": "",
+                "name": "Bundle",
+                "properties": [
+                    { "id": "price", "name": "price", "data_type": "number" }
+                ],
+                "relationships": [{
+                    "id": "parts",
+                    "name": "parts",
+                    "targets": ["class-part"],
+                    "quantifier": "any",
+                    "default_pool": {
+                        "mode": "filter",
+                        "type": ["class-part"],
+                        "where": { "where": { "lt": ["$.price", 25] } }
+                    }
+                }],
+                "derived": [
+                    {
+                        "id": "parts_total",
+                        "name": "parts_total",
+                        "data_type": "number",
+                        "expr": { "type": "sum", "over": "parts", "prop": "price", "where": null }
+                    },
+                    {
+                        "id": "own_price",
+                        "name": "own_price",
+                        "data_type": "number",
+                        "expr": { "type": "prop", "prop": "price" }
+                    }
+                ],
+                "description": null
+            }),
+        );
+        Schema {
+            id: "schema".to_string(),
+            description: None,
+            classes: vec![part, bundle],
+        }
+    }
+
+    fn instance(id: &str, class_id: &str, price: f64) -> Instance {
+        let mut instance = Instance {
+            id: id.to_string(),
+            class_id: class_id.to_string(),
+            ..Instance::default()
+        };
+        instance.properties.insert(
+            "price".to_string(),
+            PropertyValue::Literal(TypedValue {
+                value: json!(price),
+                data_type: DataType::Number,
+            }),
+        );
+        instance
+    }
+
+    fn derived_expr(schema: &Schema, class: usize, derived: usize) -> serde_json::Value {
+        serde_json::to_value(&schema.classes[class].derived[derived].expr).unwrap()
+    }
+
+    #[test]
+    fn test_rename_rekeys_instances_and_expressions() {
+        let mut schema = schema();
+        let mut bundle = instance("bundle-1", "class-bundle", 5.0);
+        bundle.relationships.insert(
+            "parts".to_string(),
+            serde_json::from_value(json!({
+                "pool": { "type": ["class-part"], "where": { "gt": ["$.price", 5] } },
+                "selection": { "where": { "lt": ["$.price", 20] } }
+            }))
+            .unwrap(),
+        );
+        let mut instances = vec![
+            instance("part-1", "class-part", 10.0),
+            instance("part-2", "class-part", 30.0),
+            bundle,
+        ];
+
+        let summary =
+            rename_property(&mut schema, &mut instances, "class-part", "price", "cost").unwrap();
+        assert_eq!(
+            summary,
+            PropertyRenameSummary {
+                instances_updated: 2,
+                // doubled and the constraint on the part, parts_total on the bundle
+                expressions_updated: 3,
+                filters_updated: 1,
+                selections_updated: 1,
+            }
+        );
+
+        let definition = &schema.classes[0].properties[0];
+        assert_eq!(
+            (definition.id.as_str(), definition.name.as_str()),
+            ("cost", "cost")
+        );
+        assert_eq!(definition.data_type, DataType::Number);
+
+        for instance in &instances[..2] {
+            assert!(!instance.properties.contains_key("price"));
+            assert!(matches!(
+                &instance.properties["cost"],
+                PropertyValue::Literal(TypedValue {
+                    data_type: DataType::Number,
+                    ..
+                })
+            ));
+        }
+        assert_eq!(
+            serde_json::to_value(&instances[1].properties["cost"]).unwrap(),
+            json!({ "value": 30.0, "type": "number" })
+        );
+        // The bundle's own price is a different property
+        assert!(instances[2].properties.contains_key("price"));
+
+        assert_eq!(derived_expr(&schema, 0, 0)["left"]["prop"], "$.cost");
+        assert_eq!(
+            serde_json::to_value(&schema.classes[0].constraints[0]).unwrap()["left"]["prop"],
+            "cost"
+        );
+        assert_eq!(derived_expr(&schema, 1, 0)["prop"], "cost");
+        assert_eq!(derived_expr(&schema, 1, 1)["prop"], "price");
+        let DefaultPool::Filter {
+            filter: Some(filter),
+            ..
+        } = &schema.classes[1].relationships[0].default_pool
+        else {
+            panic!("default pool is not a filter");
+        };
+        let paths: Vec<&str> = filter
+            .where_clause
+            .as_ref()
+            .unwrap()
+            .paths()
+            .into_iter()
+            .map(|path| path.0.as_str())
+            .collect();
+        assert_eq!(paths, vec!["$.cost"]);
+
+        let RelationshipSelection::PoolBased {
+            pool: Some(pool),
+            selection: Some(SelectionSpec::Filter(selection)),
+        } = &instances[2].relationships["parts"]
+        else {
+            panic!("selection is not pool based");
+        };
+        for filter in [pool, selection] {
+            let paths: Vec<&str> = filter
+                .where_clause
+                .as_ref()
+                .unwrap()
+                .paths()
+                .into_iter()
+                .map(|path| path.0.as_str())
+                .collect();
+            assert_eq!(paths, vec!["$.cost"]);
+        }
+    }
+
+    #[test]
+    fn test_rename_guards() {
+        let mut schema = schema();
+        let original = schema.clone();
+        let mut instances = vec![instance("part-1", "class-part", 10.0)];
+
+        use PropertyRenameError::*;
+        let mut rename = |class_id: &str, property: &str, new_name: &str| {
+            rename_property(&mut schema, &mut instances, class_id, property, new_name)
+        };
+        assert_eq!(
+            rename("class-part", "price", "name"),
+            Err(Collision("name".to_string()))
+        );
+        assert_eq!(
+            rename("class-part", "price", "doubled"),
+            Err(Collision("doubled".to_string()))
+        );
+        assert_eq!(
+            rename("class-part", "weight", "mass"),
+            Err(PropertyNotFound("weight".to_string()))
+        );
+        assert_eq!(
+            rename("class-missing", "price", "cost"),
+            Err(ClassNotFound("class-missing".to_string()))
+        );
+        assert_eq!(rename("class-part", "price", ""), Err(EmptyName));
+        assert_eq!(schema, original);
+        assert!(instances[0].properties.contains_key("price"));
+
+        // Matched by ID, only the name changes when the ID differs from it
+        rename_property(
+            &mut schema,
+            &mut instances,
+            "class-part",
+            "prop-name",
+            "title",
+        )
+        .unwrap();
+        let definition = &schema.classes[0].properties[1];
+        assert_eq!(
+            (definition.id.as_str(), definition.name.as_str()),
+            ("prop-name", "title")
+        );
+    }
+}
@@ -0,0 +1,440 @@
+//! Dry-run impact analysis for schema changes
+//!
+//! Validates the current instances against both the current and a proposed schema and
+//! reports, per changed class, which instances would start failing validation. Nothing is
+//! staged; the proposed schema only lives for the duration of the analysis. A staged
+//! schema can also be checked on its own, with the failures summarized per error type.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::logic::{SimpleValidator, ValidationError, ValidationErrorType};
+use crate::model::{ClassDef, Id, Instance, Schema};
+use crate::store::traits::Store;
+
+/// How a class differs between the current and the proposed schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaChangeKind {
+    Added,
+    Removed,
+    Modified,
+    /// The class itself is unchanged, but its instances fail because of a change elsewhere,
+    /// e.g. a relationship target class was removed
+    Indirect,
+}
+
+/// An instance that would become invalid, with the errors it does not have today
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceImpact {
+    pub instance_id: Id,
+    pub errors: Vec<ValidationError>,
+}
+
+/// Impact of the proposed schema on one class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassImpact {
+    pub class_id: Id,
+    pub class_name: String,
+    pub change: SchemaChangeKind,
+    /// Number of instances of this class in the branch
+    pub instance_count: usize,
+    /// Number of those instances that would gain validation errors
+    pub newly_invalid_count: usize,
+    /// Up to `sample_size` of the newly invalid instances
+    pub sample: Vec<InstanceImpact>,
+}
+
+/// Result of a schema impact dry-run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaImpactReport {
+    pub instances_checked: usize,
+    pub newly_invalid_count: usize,
+    pub changes: Vec<ClassImpact>,
+}
+
+/// Instances failing validation with one type of error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorTypeImpact {
+    pub error_type: ValidationErrorType,
+    /// Number of errors of this type across all instances
+    pub error_count: usize,
+    /// Number of instances with at least one error of this type
+    pub instance_count: usize,
+    /// Up to `sample_size` IDs of those instances
+    pub sample_instance_ids: Vec<Id>,
+}
+
+/// Validation failures of a set of instances against one schema, grouped by error type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaErrorSummary {
+    pub instances_checked: usize,
+    pub invalid_count: usize,
+    /// Error types ordered by the number of instances they affect, most first
+    pub error_types: Vec<ErrorTypeImpact>,
+}
+
+/// Classes that were added, removed or modified between two schemas, sorted by class ID.
+/// Audit fields are ignored when comparing.
+pub fn diff_schema_classes(current: &Schema, proposed: &Schema) -> Vec<(Id, SchemaChangeKind)> {
+    let current_classes: BTreeMap<&str, &ClassDef> =
+        current.classes.iter().map(|c| (c.id.as_str(), c)).collect();
+    let proposed_classes: BTreeMap<&str, &ClassDef> =
+        proposed.classes.iter().map(|c| (c.id.as_str(), c)).collect();
+
+    let mut changes = Vec::new();
+    for (id, class_def) in &current_classes {
+        match proposed_classes.get(id) {
+            None => changes.push((id.to_string(), SchemaChangeKind::Removed)),
+            Some(proposed_def) if !same_definition(class_def, proposed_def) => {
+                changes.push((id.to_string(), SchemaChangeKind::Modified))
+            }
+            Some(_) => {}
+        }
+    }
+    for id in proposed_classes.keys() {
+        if !current_classes.contains_key(id) {
+            changes.push((id.to_string(), SchemaChangeKind::Added));
+        }
+    }
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+    changes
+}
+
+/// Validate `instances` against both schemas and report instances that would gain errors
+pub async fn analyze_schema_impact<S: Store>(
+    store: &S,
+    current: &Schema,
+    proposed: &Schema,
+    instances: &[Instance],
+    sample_size: usize,
+) -> Result<SchemaImpactReport> {
+    let before = validate_all(store, current, instances).await?;
+    let after = validate_all(store, proposed, instances).await?;
+
+    let mut changes: Vec<ClassImpact> = diff_schema_classes(current, proposed)
+        .into_iter()
+        .map(|(class_id, change)| {
+            let class_name = proposed
+                .get_class_by_id(&class_id)
+                .or_else(|| current.get_class_by_id(&class_id))
+                .map(|c| c.name.clone())
+                .unwrap_or_default();
+            ClassImpact {
+                instance_count: count_instances(instances, &class_id),
+                class_id,
+                class_name,
+                change,
+                newly_invalid_count: 0,
+                sample: Vec::new(),
+            }
+        })
+        .collect();
+
+    let mut newly_invalid_count = 0;
+    for instance in instances {
+        let existing = before.get(&instance.id).map(Vec::as_slice).unwrap_or_default();
+        let errors: Vec<ValidationError> = after
+            .get(&instance.id)
+            .into_iter()
+            .flatten()
+            .filter(|e| !existing.iter().any(|x| x.message == e.message))
+            .cloned()
+            .collect();
+        if errors.is_empty() {
+            continue;
+        }
+        newly_invalid_count += 1;
+
+        let position = match changes.iter().position(|c| c.class_id == instance.class_id) {
+            Some(position) => position,
+            None => {
+                let class_name = current
+                    .get_class_by_id(&instance.class_id)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_default();
+                changes.push(ClassImpact {
+                    class_id: instance.class_id.clone(),
+                    class_name,
+                    change: SchemaChangeKind::Indirect,
+                    instance_count: count_instances(instances, &instance.class_id),
+                    newly_invalid_count: 0,
+                    sample: Vec::new(),
+                });
+                changes.len() - 1
+            }
+        };
+        let impact = &mut changes[position];
+        impact.newly_invalid_count += 1;
+        if impact.sample.len() < sample_size {
+            impact.sample.push(InstanceImpact {
+                instance_id: instance.id.clone(),
+                errors,
+            });
+        }
+    }
+
+    Ok(SchemaImpactReport {
+        instances_checked: instances.len(),
+        newly_invalid_count,
+        changes,
+    })
+}
+
+/// Validate `instances` against `schema` and count the failures per error type, keeping
+/// a sample of the affected instance IDs rather than every error
+pub async fn summarize_schema_errors<S: Store>(
+    store: &S,
+    schema: &Schema,
+    instances: &[Instance],
+    sample_size: usize,
+) -> Result<SchemaErrorSummary> {
+    let errors = validate_all(store, schema, instances).await?;
+
+    let mut invalid_count = 0;
+    let mut error_types: Vec<ErrorTypeImpact> = Vec::new();
+    for instance in instances {
+        let Some(instance_errors) = errors.get(&instance.id).filter(|e| !e.is_empty()) else {
+            continue;
+        };
+        invalid_count += 1;
+
+        let mut seen: Vec<&ValidationErrorType> = Vec::new();
+        for error in instance_errors {
+            let position = match error_types
+                .iter()
+                .position(|t| t.error_type == error.error_type)
+            {
+                Some(position) => position,
+                None => {
+                    error_types.push(ErrorTypeImpact {
+                        error_type: error.error_type.clone(),
+                        error_count: 0,
+                        instance_count: 0,
+                        sample_instance_ids: Vec::new(),
+                    });
+                    error_types.len() - 1
+                }
+            };
+            let impact = &mut error_types[position];
+            impact.error_count += 1;
+            if !seen.contains(&&error.error_type) {
+                seen.push(&error.error_type);
+                impact.instance_count += 1;
+                if impact.sample_instance_ids.len() < sample_size {
+                    impact.sample_instance_ids.push(instance.id.clone());
+                }
+            }
+        }
+    }
+    error_types.sort_by_key(|impact| std::cmp::Reverse(impact.instance_count));
+
+    Ok(SchemaErrorSummary {
+        instances_checked: instances.len(),
+        invalid_count,
+        error_types,
+    })
+}
+
+/// Validation errors per instance ID, including relationship resolution
+async fn validate_all<S: Store>(
+    store: &S,
+    schema: &Schema,
+    instances: &[Instance],
+) -> Result<HashMap<Id, Vec<ValidationError>>> {
+    let mut errors = HashMap::new();
+    for instance in instances {
+        let mut result = SimpleValidator::validate_instance(store, instance, schema).await?;
+        if let Some(class_def) = schema.get_class_by_id(&instance.class_id) {
+            SimpleValidator::validate_relationship_resolution(
+                instance,
+                class_def,
+                instances,
+                &mut result,
+            );
+        }
+        errors.insert(instance.id.clone(), result.errors);
+    }
+    Ok(errors)
+}
+
+fn count_instances(instances: &[Instance], class_id: &str) -> usize {
+    instances.iter().filter(|i| i.class_id == class_id).count()
+}
+
+fn same_definition(a: &ClassDef, b: &ClassDef) -> bool {
+    let mut b = b.clone();
+    b.created_by = a.created_by.clone();
+    b.created_at = a.created_at;
+    b.updated_by = a.updated_by.clone();
+    b.updated_at = a.updated_at;
+    *a == b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{DataType, PropertyDef, PropertyValue, TypedValue};
+    use crate::store::InMemoryStore;
+    use serde_json::json;
+
+    fn property(name: &str, required: bool) -> PropertyDef {
+        PropertyDef {
+            id: format!("prop-{}", name),
+            name: name.to_string(),
+            data_type: DataType::Number,
+            required: Some(required),
+            value: None,
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
+        }
+    }
+
+    fn part(id: &str, price: Option<f64>) -> Instance {
+        let mut instance = Instance {
+            id: id.to_string(),
+            class_id: "class-part".to_string(),
+            ..Instance::default()
+        };
+        if let Some(price) = price {
+            instance.properties.insert(
+                "price".to_string(),
+                PropertyValue::Literal(TypedValue {
+                    value: json!(price),
+                    data_type: DataType::Number,
+                }),
+            );
+        }
+        instance
+    }
+
+    #[tokio::test]
+    async fn test_reports_newly_invalid_instances_per_class() {
+        let current = Schema {
+            id: "schema".to_string(),
+            classes: vec![
+                ClassDef {
+                    id: "class-part".to_string(),
+                    name: "Part".to_string(),
+                    properties: vec![property("price", false)],
+                    ..ClassDef::default()
+                },
+                ClassDef {
+                    id: "class-old".to_string(),
+                    name: "Old".to_string(),
+                    ..ClassDef::default()
+                },
+            ],
+            description: None,
+        };
+        let mut proposed = current.clone();
+        proposed.classes[0].properties = vec![property("price", true)];
+        proposed.classes[0].updated_at = chrono::Utc::now();
+        proposed.classes.remove(1);
+
+        let instances = vec![
+            part("part-1", Some(10.0)),
+            part("part-2", None),
+            part("part-3", None),
+        ];
+        let store = InMemoryStore::new();
+        let report = analyze_schema_impact(&store, &current, &proposed, &instances, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(report.instances_checked, 3);
+        assert_eq!(report.newly_invalid_count, 2);
+        assert_eq!(report.changes.len(), 2);
+
+        let part_impact = &report.changes[1];
+        assert_eq!(part_impact.class_id, "class-part");
+        assert_eq!(part_impact.change, SchemaChangeKind::Modified);
+        assert_eq!(part_impact.instance_count, 3);
+        assert_eq!(part_impact.newly_invalid_count, 2);
+        assert_eq!(part_impact.sample.len(), 1);
+        assert_eq!(part_impact.sample[0].instance_id, "part-2");
+
+        let removed = &report.changes[0];
+        assert_eq!(removed.change, SchemaChangeKind::Removed);
+        assert_eq!(removed.newly_invalid_count, 0);
+    }
+
+    #[test]
+    fn test_diff_ignores_audit_fields() {
+        let current = Schema {
+            id: "schema".to_string(),
+            classes: vec![ClassDef::default()],
+            description: None,
+        };
+        let mut proposed = current.clone();
+        proposed.classes[0].updated_by = "someone-else".to_string();
+        assert!(diff_schema_classes(&current, &proposed).is_empty());
+
+        proposed.classes.push(ClassDef {
+            id: "class-new".to_string(),
+            ..ClassDef::default()
+        });
+        assert_eq!(
+            diff_schema_classes(&current, &proposed),
+            vec![("class-new".to_string(), SchemaChangeKind::Added)]
+        );
+    }
+
+    fn staged_schema(properties: Vec<PropertyDef>) -> Schema {
+        Schema {
+            id: "schema".to_string(),
+            classes: vec![ClassDef {
+                id: "class-part".to_string(),
+                name: "Part".to_string(),
+                properties,
+                ..ClassDef::default()
+            }],
+            description: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_summary_for_retyped_property() {
+        let mut price = property("price", false);
+        price.data_type = DataType::String;
+        let schema = staged_schema(vec![price]);
+
+        // Only instances that have a price are broken by the retype
+        let instances = vec![
+            part("part-1", Some(10.0)),
+            part("part-2", None),
+            part("part-3", Some(12.0)),
+        ];
+        let store = InMemoryStore::new();
+        let summary = summarize_schema_errors(&store, &schema, &instances, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.instances_checked, 3);
+        assert_eq!(summary.invalid_count, 2);
+        assert_eq!(summary.error_types.len(), 1);
+        let impact = &summary.error_types[0];
+        assert_eq!(impact.error_type, ValidationErrorType::TypeMismatch);
+        assert_eq!(impact.instance_count, 2);
+        assert_eq!(impact.error_count, 2);
+        assert_eq!(impact.sample_instance_ids, vec!["part-1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_error_summary_for_additive_change_is_empty() {
+        let schema = staged_schema(vec![property("price", false), property("weight", false)]);
+        let instances = vec![part("part-1", Some(10.0)), part("part-2", None)];
+        let store = InMemoryStore::new();
+        let summary = summarize_schema_errors(&store, &schema, &instances, 10)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.instances_checked, 2);
+        assert_eq!(summary.invalid_count, 0);
+        assert!(summary.error_types.is_empty());
+    }
+}
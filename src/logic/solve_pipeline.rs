@@ -1,15 +1,18 @@
 use crate::class;
+use crate::logic::SimpleEvaluator;
 use crate::model::{
-    generate_configuration_id, CommitData, ConfigurationArtifact, DefaultPool, Domain, Id,
-    Instance, InstanceFilter, NewConfigurationArtifact, PipelinePhase, Quantifier, RelationshipDef,
-    RelationshipSelection, Schema, SelectionSpec, SolveMetadata, SolveStatistics, SolverInfo,
+    generate_configuration_id, CommitData, ConfigurationArtifact, ConstraintViolation, DefaultPool,
+    Domain, Expr, Id, Instance, InstanceFilter, IssueSeverity, NewConfigurationArtifact,
+    ObjectiveBreakdown, ObjectiveContribution, ObjectiveDirection, PipelinePhase, PropertyValue,
+    Quantifier, RelationshipDef, RelationshipSelection, Schema, SelectionSpec, SolveIssue,
+    SolveMetadata, SolveStatistics, SolverInfo,
 };
 use anyhow::Result;
 use itertools::Itertools;
 use pldag::Pldag;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::{DefaultHasher, Hash, Hasher};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Error type for solve pipeline failures
 #[derive(Debug, thiserror::Error)]
@@ -18,6 +21,17 @@ pub enum SolveError {
     #[error("No solution found for objective(s): {objectives}. The constraints may be unsatisfiable or contradictory. Please review your class definitions, relationship quantifiers, and instance relationships.")]
     UnsatisfiableConstraints { objectives: String },
 
+    /// The solve ran longer than the request's `timeout_ms`
+    #[error("Solve timed out after {elapsed_ms}ms during {phase} (limit {timeout_ms}ms)")]
+    Timeout {
+        timeout_ms: u64,
+        elapsed_ms: u64,
+        /// Phase that had just run when the limit was noticed
+        phase: String,
+        /// Partial artifact with the elapsed time and the timings of the phases that ran
+        artifact: Box<ConfigurationArtifact>,
+    },
+
     /// Other errors (internal errors, data not found, etc.)
     #[error(transparent)]
     Other(#[from] anyhow::Error),
@@ -28,6 +42,36 @@ impl SolveError {
     pub fn is_unsatisfiable(&self) -> bool {
         matches!(self, SolveError::UnsatisfiableConstraints { .. })
     }
+
+    /// Check if the solve was abandoned after running past its timeout
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, SolveError::Timeout { .. })
+    }
+}
+
+/// Time budget of one solve, checked between pipeline phases and while resolving
+/// relationships, so a slow phase is cut short instead of running to the end
+struct SolveDeadline {
+    start: Instant,
+    timeout: Option<Duration>,
+}
+
+impl SolveDeadline {
+    fn new(start: Instant, timeout_ms: Option<u64>) -> Self {
+        Self {
+            start,
+            timeout: timeout_ms.map(Duration::from_millis),
+        }
+    }
+
+    fn unbounded() -> Self {
+        Self::new(Instant::now(), None)
+    }
+
+    fn passed(&self) -> bool {
+        self.timeout
+            .is_some_and(|timeout| self.start.elapsed() > timeout)
+    }
 }
 
 /// Helper function to determine the type string for a JSON value
@@ -91,7 +135,17 @@ impl<'a> SolvePipeline<'a> {
         F: FnOnce(&mut Pldag, &IdMappings) -> Result<(), SolveError>,
     {
         let start_time = Instant::now();
+        let deadline = SolveDeadline::new(start_time, request.timeout_ms);
         let mut phase_timings = Vec::new();
+        let check_deadline = |phase: &str, phase_timings: &[(&str, Duration)]| {
+            self.check_deadline(
+                &deadline,
+                &request,
+                &target_instance_id,
+                phase,
+                phase_timings,
+            )
+        };
 
         // Step 1: Get instances and schema from commit data
         let phase_start = Instant::now();
@@ -107,6 +161,7 @@ impl<'a> SolvePipeline<'a> {
             })?
             .clone();
         phase_timings.push(("fetch_data", phase_start.elapsed()));
+        check_deadline("fetch_data", &phase_timings)?;
 
         // Step 2: Build dependency tree and filter instances early
         let phase_start = Instant::now();
@@ -116,22 +171,47 @@ impl<'a> SolvePipeline<'a> {
             .filter(|inst| dependencies.contains(&inst.id))
             .collect();
         phase_timings.push(("filter_dependencies", phase_start.elapsed()));
+        check_deadline("filter_dependencies", &phase_timings)?;
 
         // Step 3: Resolve all pool filters and materialize relationships for filtered instances
         let phase_start = Instant::now();
-        let resolved_instances = self.resolve_all_relationships(instances, schema)?;
+        let resolved = self.resolve_all_relationships_within(instances, schema, &deadline)?;
         phase_timings.push(("resolve_relationships", phase_start.elapsed()));
+        let Some(resolved_instances) = resolved else {
+            return Err(self.timeout_error(
+                &deadline,
+                &request,
+                &target_instance_id,
+                "resolve_relationships",
+                &phase_timings,
+            ));
+        };
+        check_deadline("resolve_relationships", &phase_timings)?;
 
         // Step 4: Setup Pldag model
         let phase_start = Instant::now();
         let (mut model, id_mappings) =
             self.setup_pldag_model(&target_instance_id, &resolved_instances, schema)?;
         phase_timings.push(("setup_pldag", phase_start.elapsed()));
+        check_deadline("setup_pldag", &phase_timings)?;
 
         // Step 4.5: Apply custom constraints
         let phase_start = Instant::now();
         constraint_fn(&mut model, &id_mappings)?;
         phase_timings.push(("apply_constraints", phase_start.elapsed()));
+        check_deadline("apply_constraints", &phase_timings)?;
+
+        // Step 4.6: Fold the property objective into every objective set
+        let property_values = request
+            .objective
+            .as_ref()
+            .map(|objective| property_values(&objective.property, &resolved_instances));
+        let objective_sets = match (&request.objective, &property_values) {
+            (Some(objective), Some(values)) => {
+                add_property_objective(objective_sets, objective.direction, values)
+            }
+            _ => objective_sets,
+        };
 
         // Step 5: Map all objectives and solve with Pldag
         let phase_start = Instant::now();
@@ -142,6 +222,7 @@ impl<'a> SolvePipeline<'a> {
             &id_mappings,
         )?;
         phase_timings.push(("solve", phase_start.elapsed()));
+        check_deadline("solve", &phase_timings)?;
 
         // Step 5.5: Evaluate derived properties for all instances
         let instance_class = schema
@@ -155,7 +236,8 @@ impl<'a> SolvePipeline<'a> {
         let elapsed = start_time.elapsed();
         let total_time = std::cmp::max(1, elapsed.as_micros() / 1000) as u64;
 
-        for ((objective_id, _), solution) in objective_sets.iter().zip(solutions.iter()) {
+        for ((objective_id, objective), solution) in objective_sets.iter().zip(solutions.iter()) {
+            check_deadline("compile_artifacts", &phase_timings)?;
             let mut artifact = self.compile_artifact(
                 request.clone(),
                 target_instance_id.clone(),
@@ -166,6 +248,15 @@ impl<'a> SolvePipeline<'a> {
                 &phase_timings,
             )?;
 
+            if let Some(values) = &property_values {
+                artifact.objective_value = Some(objective_value(values, solution));
+            }
+
+            if request.include_objective_breakdown {
+                artifact.objective_breakdown =
+                    Some(objective_breakdown(objective, solution, &resolved_instances));
+            }
+
             // Only calculate derived properties if requested
             if let Some(requested_props) = &derived_properties {
                 if !requested_props.is_empty() {
@@ -203,20 +294,112 @@ impl<'a> SolvePipeline<'a> {
                 }
             }
 
+            // Step 6.5: Run the request's constraint checks against the solved target
+            if let Some(target) = artifact
+                .configuration
+                .iter()
+                .find(|inst| inst.id == target_instance_id)
+            {
+                artifact.constraint_violations = constraint_violations(
+                    &request.constraint_checks,
+                    target,
+                    &artifact.configuration,
+                    schema,
+                );
+            }
+
             results.push((objective_id.clone(), artifact));
         }
 
         Ok(results)
     }
 
+    /// `Err(SolveError::Timeout)` once `deadline` has passed, with `phase` as the phase that
+    /// had just run
+    fn check_deadline(
+        &self,
+        deadline: &SolveDeadline,
+        request: &NewConfigurationArtifact,
+        target_instance_id: &Id,
+        phase: &str,
+        phase_timings: &[(&str, Duration)],
+    ) -> Result<(), SolveError> {
+        if deadline.passed() {
+            return Err(self.timeout_error(
+                deadline,
+                request,
+                target_instance_id,
+                phase,
+                phase_timings,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Timeout error carrying an artifact with no configuration, the elapsed time and the
+    /// timings of the phases that ran
+    fn timeout_error(
+        &self,
+        deadline: &SolveDeadline,
+        request: &NewConfigurationArtifact,
+        target_instance_id: &Id,
+        phase: &str,
+        phase_timings: &[(&str, Duration)],
+    ) -> SolveError {
+        let timeout_ms = deadline.timeout.unwrap_or_default().as_millis() as u64;
+        let elapsed_ms = deadline.start.elapsed().as_millis() as u64;
+
+        let mut artifact = ConfigurationArtifact::new(
+            generate_configuration_id(
+                request.resolution_context.commit_hash.as_ref(),
+                &HashMap::new(),
+                target_instance_id,
+            ),
+            request.resolution_context.clone(),
+            request.user_metadata.clone(),
+        );
+        artifact.solve_metadata.total_time_ms = elapsed_ms;
+        artifact.solve_metadata.pipeline_phases = pipeline_phases(phase_timings);
+        artifact.solve_metadata.issues.push(SolveIssue {
+            severity: IssueSeverity::Critical,
+            message: format!(
+                "Solve timed out after {}ms during {} (limit {}ms)",
+                elapsed_ms, phase, timeout_ms
+            ),
+            component: Some(target_instance_id.clone()),
+            context: None,
+        });
+
+        SolveError::Timeout {
+            timeout_ms,
+            elapsed_ms,
+            phase: phase.to_string(),
+            artifact: Box::new(artifact),
+        }
+    }
+
     // Remove fetch_commit_data method as we now have commit data directly
 
     /// Step 2: Resolve all relationships to concrete instance IDs
     pub fn resolve_all_relationships(
         &self,
-        mut instances: Vec<Instance>,
+        instances: Vec<Instance>,
         schema: &Schema,
     ) -> Result<Vec<Instance>> {
+        Ok(self
+            .resolve_all_relationships_within(instances, schema, &SolveDeadline::unbounded())?
+            .expect("an unbounded deadline never passes"))
+    }
+
+    /// `resolve_all_relationships`, giving up with `None` once `deadline` has passed. The
+    /// deadline is checked before each instance, as pool filters over a large branch can make
+    /// this the longest phase.
+    fn resolve_all_relationships_within(
+        &self,
+        mut instances: Vec<Instance>,
+        schema: &Schema,
+        deadline: &SolveDeadline,
+    ) -> Result<Option<Vec<Instance>>> {
         // Create a map of all instances by type for quick lookup
         let mut instances_by_type: HashMap<String, Vec<Instance>> = HashMap::new();
         for instance in &instances {
@@ -228,6 +411,10 @@ impl<'a> SolvePipeline<'a> {
 
         // Process each instance
         for instance in instances.iter_mut() {
+            if deadline.passed() {
+                return Ok(None);
+            }
+
             // Get class definition
             let class_def = schema.get_class_by_id(&instance.class_id).ok_or_else(|| {
                 anyhow::anyhow!("Class {} not found in schema", instance.class_id)
@@ -235,6 +422,11 @@ impl<'a> SolvePipeline<'a> {
 
             // Process each relationship in the class definition
             for rel_def in &class_def.relationships {
+                // Inverse relationships are derived from the forward side, not chosen by the solver
+                if rel_def.is_inverse() {
+                    continue;
+                }
+
                 // Check if instance already has this relationship
                 let selection = instance
                     .relationships
@@ -257,7 +449,7 @@ impl<'a> SolvePipeline<'a> {
             }
         }
 
-        Ok(instances)
+        Ok(Some(instances))
     }
 
     /// Create a selection from default pool definition
@@ -1017,7 +1209,7 @@ impl<'a> SolvePipeline<'a> {
         solution: HashMap<String, i64>,
         id_mappings: &IdMappings,
         total_time_ms: u64,
-        phase_timings: &[(&str, std::time::Duration)],
+        phase_timings: &[(&str, Duration)],
     ) -> Result<ConfigurationArtifact> {
         // Update instance domains based on solution. Exclude the instance if not in solution.
         let solution_instances: Vec<Instance> = instances
@@ -1054,15 +1246,7 @@ impl<'a> SolvePipeline<'a> {
         // Set metadata
         artifact.solve_metadata = SolveMetadata {
             total_time_ms,
-            pipeline_phases: phase_timings
-                .iter()
-                .map(|(name, duration)| PipelinePhase {
-                    name: name.to_string(),
-                    // Convert duration to milliseconds, ensure at least 1ms for very fast operations
-                    duration_ms: std::cmp::max(1, duration.as_micros() / 1000) as u64,
-                    details: None,
-                })
-                .collect(),
+            pipeline_phases: pipeline_phases(phase_timings),
             solver_info: Some(SolverInfo {
                 name: "pldag".to_string(),
                 version: None,
@@ -1084,6 +1268,140 @@ impl<'a> SolvePipeline<'a> {
     }
 }
 
+/// Numeric value at the property path `path` of each instance that has one. The first
+/// segment names the property, further dot-separated segments walk into its object value.
+fn property_values(path: &str, instances: &[Instance]) -> HashMap<Id, f64> {
+    let mut segments = path.split('.');
+    let property = segments.next().unwrap_or_default();
+    let fields: Vec<&str> = segments.collect();
+
+    instances
+        .iter()
+        .filter_map(|instance| {
+            let Some(PropertyValue::Literal(typed_value)) = instance.properties.get(property)
+            else {
+                return None;
+            };
+            fields
+                .iter()
+                .try_fold(&typed_value.value, |value, field| value.get(field))
+                .and_then(|value| value.as_f64())
+                .map(|value| (instance.id.clone(), value))
+        })
+        .collect()
+}
+
+/// `objective_sets` with each instance's property value added to its weight. The solver
+/// maximizes, so values are negated to minimize.
+fn add_property_objective(
+    objective_sets: Vec<(String, HashMap<String, f64>)>,
+    direction: ObjectiveDirection,
+    values: &HashMap<Id, f64>,
+) -> Vec<(String, HashMap<String, f64>)> {
+    let sign = match direction {
+        ObjectiveDirection::Minimize => -1.0,
+        ObjectiveDirection::Maximize => 1.0,
+    };
+    objective_sets
+        .into_iter()
+        .map(|(id, mut weights)| {
+            for (instance_id, value) in values {
+                *weights.entry(instance_id.clone()).or_default() += sign * value;
+            }
+            (id, weights)
+        })
+        .collect()
+}
+
+/// Property objective achieved by `solution`: each selected instance's value times its
+/// solution value
+fn objective_value(values: &HashMap<Id, f64>, solution: &HashMap<String, i64>) -> f64 {
+    solution
+        .iter()
+        .filter_map(|(id, selected)| values.get(id).map(|value| value * *selected as f64))
+        .sum()
+}
+
+/// Constraints that do not hold for the solved `target`. A constraint that cannot be
+/// evaluated, for example because it names a missing property, counts as violated.
+fn constraint_violations(
+    constraints: &[Expr],
+    target: &Instance,
+    configuration: &[Instance],
+    schema: &Schema,
+) -> Vec<ConstraintViolation> {
+    constraints
+        .iter()
+        .enumerate()
+        .filter_map(|(index, constraint)| {
+            let message = match SimpleEvaluator::evaluate_configuration_constraint(
+                constraint,
+                target,
+                schema,
+                configuration,
+            ) {
+                Ok(true) => return None,
+                Ok(false) => format!("Constraint {} is not satisfied", index),
+                Err(e) => format!("Constraint {} could not be evaluated: {}", index, e),
+            };
+            Some(ConstraintViolation {
+                index,
+                constraint: constraint.clone(),
+                message,
+            })
+        })
+        .collect()
+}
+
+/// Phase timings as recorded in artifact metadata
+fn pipeline_phases(phase_timings: &[(&str, Duration)]) -> Vec<PipelinePhase> {
+    phase_timings
+        .iter()
+        .map(|(name, duration)| PipelinePhase {
+            name: name.to_string(),
+            // Convert duration to milliseconds, ensure at least 1ms for very fast operations
+            duration_ms: std::cmp::max(1, duration.as_micros() / 1000) as u64,
+            details: None,
+        })
+        .collect()
+}
+
+/// Split a solution's objective value into the contribution of each selected instance
+pub fn objective_breakdown(
+    objective: &HashMap<String, f64>,
+    solution: &HashMap<String, i64>,
+    instances: &[Instance],
+) -> ObjectiveBreakdown {
+    let mut contributions: Vec<ObjectiveContribution> = instances
+        .iter()
+        .filter_map(|instance| {
+            let weight = *objective.get(&instance.id)?;
+            let value = *solution.get(&instance.id)?;
+            if weight == 0.0 || value == 0 {
+                return None;
+            }
+            Some(ObjectiveContribution {
+                instance_id: instance.id.clone(),
+                class_id: instance.class_id.clone(),
+                weight,
+                value,
+                contribution: weight * value as f64,
+            })
+        })
+        .collect();
+    contributions.sort_by(|a, b| {
+        b.contribution
+            .abs()
+            .total_cmp(&a.contribution.abs())
+            .then_with(|| a.instance_id.cmp(&b.instance_id))
+    });
+
+    ObjectiveBreakdown {
+        total: contributions.iter().map(|c| c.contribution).sum(),
+        contributions,
+    }
+}
+
 /// Helper struct to manage ID mappings between our system and Pldag
 pub struct IdMappings {
     /// Our instance ID -> Pldag variable ID
@@ -1126,3 +1444,425 @@ impl IdMappings {
         self.our_to_pldag.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(id: &str, class_id: &str) -> Instance {
+        Instance {
+            id: id.to_string(),
+            class_id: class_id.to_string(),
+            ..Instance::default()
+        }
+    }
+
+    #[test]
+    fn test_objective_breakdown_lists_selected_weighted_instances() {
+        let instances = vec![
+            instance("chair", "class-chair"),
+            instance("leg-oak", "class-leg"),
+            instance("leg-steel", "class-leg"),
+            instance("cushion", "class-cushion"),
+        ];
+        let objective = HashMap::from([
+            ("leg-oak".to_string(), -40.0),
+            ("leg-steel".to_string(), -25.0),
+            ("cushion".to_string(), -15.5),
+        ]);
+        // The chair has no weight and the steel leg was not selected
+        let solution = HashMap::from([
+            ("chair".to_string(), 1),
+            ("leg-oak".to_string(), 1),
+            ("leg-steel".to_string(), 0),
+            ("cushion".to_string(), 1),
+        ]);
+
+        let breakdown = objective_breakdown(&objective, &solution, &instances);
+        let ids: Vec<_> = breakdown
+            .contributions
+            .iter()
+            .map(|c| c.instance_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["leg-oak", "cushion"]);
+        assert_eq!(breakdown.contributions[0].class_id, "class-leg");
+        assert_eq!(breakdown.contributions[1].contribution, -15.5);
+        assert_eq!(breakdown.total, -55.5);
+    }
+
+    fn chair_commit() -> CommitData {
+        CommitData {
+            schema: Schema {
+                id: "schema".to_string(),
+                classes: vec![crate::model::ClassDef {
+                    id: "class-chair".to_string(),
+                    name: "Chair".to_string(),
+                    ..crate::model::ClassDef::default()
+                }],
+                description: None,
+            },
+            instances: vec![instance("chair", "class-chair")],
+        }
+    }
+
+    fn solve_request(timeout_ms: Option<u64>) -> NewConfigurationArtifact {
+        NewConfigurationArtifact {
+            resolution_context: crate::model::ResolutionContext {
+                database_id: "db".to_string(),
+                branch_id: "main".to_string(),
+                commit_hash: None,
+                policies: crate::model::ResolutionPolicies::default(),
+                metadata: None,
+            },
+            user_metadata: None,
+            include_objective_breakdown: false,
+            timeout_ms,
+            objective: None,
+            constraint_checks: Vec::new(),
+        }
+    }
+
+    #[test]
+    #[ignore = "runs the GLPK solver"]
+    fn test_fast_solve_finishes_within_timeout() {
+        let commit = chair_commit();
+        let results = SolvePipeline::new(&commit)
+            .solve_instance_with_multiple_objectives_and_derived_properties(
+                solve_request(Some(60_000)),
+                "chair".to_string(),
+                vec![("default".to_string(), HashMap::new())],
+                None,
+            )
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.solve_metadata.issues.is_empty());
+    }
+
+    #[test]
+    fn test_slow_solve_times_out_with_partial_artifact() {
+        let commit = chair_commit();
+        let error = SolvePipeline::new(&commit)
+            .solve_instance_with_constraints(
+                solve_request(Some(10)),
+                "chair".to_string(),
+                vec![("default".to_string(), HashMap::new())],
+                None,
+                |_model, _mappings| {
+                    std::thread::sleep(Duration::from_millis(50));
+                    Ok(())
+                },
+            )
+            .unwrap_err();
+
+        assert!(error.is_timeout());
+        let SolveError::Timeout {
+            timeout_ms,
+            elapsed_ms,
+            phase,
+            artifact,
+        } = error
+        else {
+            unreachable!()
+        };
+        assert_eq!(timeout_ms, 10);
+        assert!(elapsed_ms >= 50);
+        // The checkpoint right after the slow phase stops the solve before the solver runs
+        assert_eq!(phase, "apply_constraints");
+        assert_eq!(artifact.solve_metadata.total_time_ms, elapsed_ms);
+        let phases: Vec<_> = artifact
+            .solve_metadata
+            .pipeline_phases
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(phases.last(), Some(&"apply_constraints"));
+        assert!(!phases.contains(&"solve"));
+        assert!(artifact.configuration.is_empty());
+        assert_eq!(
+            artifact.solve_metadata.issues[0].severity,
+            IssueSeverity::Critical
+        );
+    }
+
+    #[test]
+    fn test_resolution_checks_the_deadline() {
+        let commit = chair_commit();
+        let pipeline = SolvePipeline::new(&commit);
+
+        let generous = SolveDeadline::new(Instant::now(), Some(60_000));
+        let resolved = pipeline
+            .resolve_all_relationships_within(commit.instances.clone(), &commit.schema, &generous)
+            .unwrap();
+        assert_eq!(resolved.map(|instances| instances.len()), Some(1));
+
+        let passed = SolveDeadline::new(Instant::now() - Duration::from_millis(20), Some(10));
+        let resolved = pipeline
+            .resolve_all_relationships_within(commit.instances.clone(), &commit.schema, &passed)
+            .unwrap();
+        assert!(resolved.is_none());
+    }
+
+    fn with_property(mut instance: Instance, name: &str, value: serde_json::Value) -> Instance {
+        instance.properties.insert(
+            name.to_string(),
+            PropertyValue::Literal(crate::model::TypedValue {
+                value,
+                data_type: crate::model::DataType::Number,
+            }),
+        );
+        instance
+    }
+
+    #[test]
+    fn test_property_values_follow_the_path() {
+        let instances = vec![
+            with_property(
+                instance("leg-oak", "class-leg"),
+                "price",
+                serde_json::json!(40),
+            ),
+            with_property(
+                instance("leg-steel", "class-leg"),
+                "price",
+                serde_json::json!(25.5),
+            ),
+            with_property(
+                instance("leg-name", "class-leg"),
+                "price",
+                serde_json::json!("n/a"),
+            ),
+            with_property(
+                instance("cushion", "class-cushion"),
+                "dimensions",
+                serde_json::json!({"width": 50}),
+            ),
+            instance("chair", "class-chair"),
+        ];
+
+        let prices = property_values("price", &instances);
+        assert_eq!(
+            prices,
+            HashMap::from([
+                ("leg-oak".to_string(), 40.0),
+                ("leg-steel".to_string(), 25.5)
+            ])
+        );
+        let widths = property_values("dimensions.width", &instances);
+        assert_eq!(widths, HashMap::from([("cushion".to_string(), 50.0)]));
+    }
+
+    #[test]
+    fn test_property_objective_weights_follow_the_direction() {
+        let values = HashMap::from([
+            ("leg-oak".to_string(), 40.0),
+            ("leg-steel".to_string(), 25.0),
+        ]);
+        let sets = vec![(
+            "default".to_string(),
+            HashMap::from([("leg-oak".to_string(), 5.0)]),
+        )];
+
+        let minimized = add_property_objective(sets.clone(), ObjectiveDirection::Minimize, &values);
+        assert_eq!(minimized[0].1["leg-oak"], -35.0);
+        assert_eq!(minimized[0].1["leg-steel"], -25.0);
+
+        let maximized = add_property_objective(sets, ObjectiveDirection::Maximize, &values);
+        assert_eq!(maximized[0].1["leg-oak"], 45.0);
+        assert_eq!(maximized[0].1["leg-steel"], 25.0);
+    }
+
+    #[test]
+    fn test_objective_value_sums_selected_instances() {
+        let values = HashMap::from([
+            ("leg-oak".to_string(), 40.0),
+            ("leg-steel".to_string(), 25.0),
+            ("cushion".to_string(), 15.5),
+        ]);
+        let solution = HashMap::from([
+            ("chair".to_string(), 1),
+            ("leg-oak".to_string(), 0),
+            ("leg-steel".to_string(), 4),
+            ("cushion".to_string(), 1),
+        ]);
+        assert_eq!(objective_value(&values, &solution), 115.5);
+    }
+
+    /// A chair with exactly one of two legs, priced 10 and 30
+    fn leg_choice_commit() -> CommitData {
+        let mut chair = instance("chair", "class-chair");
+        chair.relationships.insert(
+            "legs".to_string(),
+            RelationshipSelection::SimpleIds(vec!["leg-cheap".to_string(), "leg-dear".to_string()]),
+        );
+        CommitData {
+            schema: Schema {
+                id: "schema".to_string(),
+                classes: vec![
+                    crate::model::ClassDef {
+                        id: "class-chair".to_string(),
+                        name: "Chair".to_string(),
+                        relationships: vec![RelationshipDef {
+                            id: "legs".to_string(),
+                            name: "legs".to_string(),
+                            targets: vec!["class-leg".to_string()],
+                            quantifier: Quantifier::One,
+                            universe: None,
+                            selection: crate::model::SelectionType::ExplicitOrFilter,
+                            default_pool: DefaultPool::All,
+                            inverse_of: None,
+                            min: None,
+                            max: None,
+                        }],
+                        ..crate::model::ClassDef::default()
+                    },
+                    crate::model::ClassDef {
+                        id: "class-leg".to_string(),
+                        name: "Leg".to_string(),
+                        ..crate::model::ClassDef::default()
+                    },
+                ],
+                description: None,
+            },
+            instances: vec![
+                chair,
+                with_property(
+                    instance("leg-cheap", "class-leg"),
+                    "price",
+                    serde_json::json!(10),
+                ),
+                with_property(
+                    instance("leg-dear", "class-leg"),
+                    "price",
+                    serde_json::json!(30),
+                ),
+            ],
+        }
+    }
+
+    fn solve_for_price(direction: ObjectiveDirection) -> ConfigurationArtifact {
+        let commit = leg_choice_commit();
+        let mut request = solve_request(None);
+        request.objective = Some(crate::model::PropertyObjective {
+            property: "price".to_string(),
+            direction,
+        });
+        let mut results = SolvePipeline::new(&commit)
+            .solve_instance_with_multiple_objectives_and_derived_properties(
+                request,
+                "chair".to_string(),
+                vec![("default".to_string(), HashMap::new())],
+                None,
+            )
+            .unwrap();
+        results.remove(0).1
+    }
+
+    #[test]
+    #[ignore = "runs the GLPK solver"]
+    fn test_minimize_picks_the_cheapest_leg() {
+        let artifact = solve_for_price(ObjectiveDirection::Minimize);
+        assert_eq!(artifact.objective_value, Some(10.0));
+        assert!(artifact.configuration.iter().any(|i| i.id == "leg-cheap"));
+        assert!(!artifact.configuration.iter().any(|i| i.id == "leg-dear"));
+    }
+
+    #[test]
+    #[ignore = "runs the GLPK solver"]
+    fn test_maximize_picks_the_dearest_leg() {
+        let artifact = solve_for_price(ObjectiveDirection::Maximize);
+        assert_eq!(artifact.objective_value, Some(30.0));
+        assert!(artifact.configuration.iter().any(|i| i.id == "leg-dear"));
+        assert!(!artifact.configuration.iter().any(|i| i.id == "leg-cheap"));
+    }
+
+    /// `leg_choice_commit` solved with the cheap leg selected
+    fn cheap_leg_configuration() -> Vec<Instance> {
+        leg_choice_commit()
+            .instances
+            .into_iter()
+            .map(|mut inst| {
+                let selected = if inst.id == "leg-dear" { 0 } else { 1 };
+                inst.domain = Some(Domain::new(selected, selected));
+                inst
+            })
+            .collect()
+    }
+
+    fn budget_constraint(budget: f64) -> Expr {
+        serde_json::from_value(serde_json::json!({
+            "type": "lte",
+            "left": { "type": "sum", "over": "legs", "prop": "price" },
+            "right": { "type": "lit_number", "value": budget }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_budget_constraint_within_budget_is_satisfied() {
+        let commit = leg_choice_commit();
+        let configuration = cheap_leg_configuration();
+        let violations = constraint_violations(
+            &[budget_constraint(20.0)],
+            &configuration[0],
+            &configuration,
+            &commit.schema,
+        );
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn test_budget_constraint_over_budget_is_violated() {
+        let commit = leg_choice_commit();
+        let configuration = cheap_leg_configuration();
+        let constraints = [budget_constraint(20.0), budget_constraint(5.0)];
+        let violations = constraint_violations(
+            &constraints,
+            &configuration[0],
+            &configuration,
+            &commit.schema,
+        );
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].index, 1);
+        assert_eq!(violations[0].constraint, constraints[1]);
+        assert_eq!(violations[0].message, "Constraint 1 is not satisfied");
+    }
+
+    #[test]
+    fn test_constraint_on_missing_property_is_violated() {
+        let commit = leg_choice_commit();
+        let configuration = cheap_leg_configuration();
+        let constraint = serde_json::from_value(serde_json::json!({
+            "type": "gt",
+            "left": { "type": "prop", "prop": "weight" },
+            "right": { "type": "lit_number", "value": 0 }
+        }))
+        .unwrap();
+        let violations = constraint_violations(
+            &[constraint],
+            &configuration[0],
+            &configuration,
+            &commit.schema,
+        );
+        assert!(violations[0]
+            .message
+            .starts_with("Constraint 0 could not be evaluated"));
+    }
+
+    #[test]
+    #[ignore = "runs the GLPK solver"]
+    fn test_solve_reports_violated_constraints() {
+        let commit = leg_choice_commit();
+        let mut request = solve_request(None);
+        request.constraint_checks = vec![budget_constraint(5.0)];
+        let results = SolvePipeline::new(&commit)
+            .solve_instance_with_multiple_objectives_and_derived_properties(
+                request,
+                "chair".to_string(),
+                vec![("default".to_string(), HashMap::new())],
+                None,
+            )
+            .unwrap();
+        assert_eq!(results[0].1.constraint_violations.len(), 1);
+        assert!(!results[0].1.structured_summary().complete);
+    }
+}
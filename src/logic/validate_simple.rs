@@ -1,7 +1,9 @@
 use anyhow::{anyhow, Result};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::logic::SimpleEvaluator;
 use crate::model::{ClassDef, DataType, Id, Instance, PropertyValue, Schema};
 use crate::store::traits::Store;
 
@@ -32,7 +34,7 @@ pub struct ValidationWarning {
     pub property_name: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ValidationErrorType {
     TypeMismatch,
     MissingRequiredProperty,
@@ -41,6 +43,10 @@ pub enum ValidationErrorType {
     ClassNotFound,
     RelationshipError,
     ValueTypeInconsistency,
+    /// The schema itself is broken, e.g. a property pattern that isn't a valid regex
+    InvalidSchema,
+    /// A `unique` property value is shared by several instances of the class
+    DuplicateValue,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +54,24 @@ pub enum ValidationWarningType {
     UnusedProperty,
     ConditionalPropertySkipped,
     RelationshipNotValidated,
+    /// A schema property sets `min`/`max` but its data type isn't `number`, so the bounds are
+    /// never checked
+    RangeOnNonNumericProperty,
+}
+
+/// Compiled property patterns, shared by the instances of one validation pass
+#[derive(Default)]
+struct PatternCache {
+    compiled: HashMap<String, Result<Regex, String>>,
+}
+
+impl PatternCache {
+    /// The full-match regex for `pattern`, or why it doesn't compile
+    fn get(&mut self, pattern: &str) -> &Result<Regex, String> {
+        self.compiled.entry(pattern.to_string()).or_insert_with(|| {
+            Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| e.to_string())
+        })
+    }
 }
 
 pub struct SimpleValidator;
@@ -88,11 +112,31 @@ impl SimpleValidator {
         let instances = store
             .list_instances_for_branch(database_id, branch_name, None)
             .await?;
-        result.instance_count = instances.len();
+
+        Self::validate_instances(store, &instances, &schema).await
+    }
+
+    /// Validate a set of instances against a schema, including relationship resolution.
+    /// Also used for states that are not stored on a branch, such as a simulated merge.
+    pub async fn validate_instances<S: Store>(
+        store: &S,
+        instances: &[Instance],
+        schema: &Schema,
+    ) -> Result<ValidationResult> {
+        let mut result = ValidationResult {
+            valid: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            instance_count: instances.len(),
+            validated_instances: Vec::new(),
+        };
+        result.warnings = Self::schema_warnings(schema);
+        let mut patterns = PatternCache::default();
 
         // Validate each instance
-        for instance in &instances {
-            let instance_result = Self::validate_instance(store, instance, &schema).await;
+        for instance in instances {
+            let instance_result =
+                Self::validate_instance_with(store, instance, schema, &mut patterns).await;
             result.validated_instances.push(instance.id.clone());
 
             match instance_result {
@@ -118,25 +162,137 @@ impl SimpleValidator {
         }
 
         // Additional validation: Check that all relationships resolve to at least one instance
-        for instance in &instances {
+        for instance in instances {
             if let Some(class_def) = schema.get_class_by_id(&instance.class_id) {
-                Self::validate_relationship_resolution(
-                    instance,
-                    class_def,
-                    &instances,
-                    &mut result,
-                );
+                Self::validate_relationship_resolution(instance, class_def, instances, &mut result);
             }
         }
 
+        Self::validate_branch_uniqueness(instances, schema, &mut result);
+
         Ok(result)
     }
 
+    /// Flag values of `unique` properties that appear on more than one instance of a class.
+    /// Every conflicting instance gets an error listing all of them; nulls never conflict.
+    pub fn validate_branch_uniqueness(
+        instances: &[Instance],
+        schema: &Schema,
+        result: &mut ValidationResult,
+    ) {
+        for class_def in &schema.classes {
+            for prop_def in class_def
+                .properties
+                .iter()
+                .filter(|p| p.unique == Some(true))
+            {
+                // Serialized value -> instances holding it, in instance order; `positions`
+                // indexes `holders` by value so each lookup is constant time
+                let mut holders: Vec<(String, Vec<&Id>)> = Vec::new();
+                let mut positions: HashMap<String, usize> = HashMap::new();
+                for instance in instances.iter().filter(|i| i.class_id == class_def.id) {
+                    let value = instance
+                        .properties
+                        .get(&prop_def.id)
+                        .or_else(|| instance.properties.get(&prop_def.name));
+                    let Some(PropertyValue::Literal(typed_value)) = value else {
+                        continue;
+                    };
+                    if typed_value.value.is_null() {
+                        continue;
+                    }
+                    let key = typed_value.value.to_string();
+                    match positions.get(&key) {
+                        Some(&position) => holders[position].1.push(&instance.id),
+                        None => {
+                            positions.insert(key.clone(), holders.len());
+                            holders.push((key, vec![&instance.id]));
+                        }
+                    }
+                }
+
+                for (value, ids) in holders.iter().filter(|(_, ids)| ids.len() > 1) {
+                    let all_ids = ids
+                        .iter()
+                        .map(|id| id.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    for id in ids {
+                        result.valid = false;
+                        result.errors.push(ValidationError {
+                            instance_id: id.to_string(),
+                            error_type: ValidationErrorType::DuplicateValue,
+                            message: format!(
+                                "Value {} of unique property '{}' in class '{}' is shared by \
+                                 instances: {}",
+                                value, prop_def.name, class_def.name, all_ids
+                            ),
+                            property_name: Some(prop_def.id.clone()),
+                            expected: Some("Unique value".to_string()),
+                            actual: Some(all_ids.clone()),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Problems in the schema itself that don't invalidate any instance
+    pub fn schema_warnings(schema: &Schema) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+        for class_def in &schema.classes {
+            for prop_def in &class_def.properties {
+                if prop_def.data_type != DataType::Number
+                    && (prop_def.min.is_some() || prop_def.max.is_some())
+                {
+                    warnings.push(ValidationWarning {
+                        instance_id: "N/A".to_string(),
+                        warning_type: ValidationWarningType::RangeOnNonNumericProperty,
+                        message: format!(
+                            "Property '{}' of class '{}' sets min/max but has type {:?}; \
+                             the range is ignored",
+                            prop_def.name, class_def.name, prop_def.data_type
+                        ),
+                        property_name: Some(prop_def.id.clone()),
+                    });
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Errors in `after` that `before` does not have, matched by instance and message
+    pub fn introduced_errors(
+        before: &ValidationResult,
+        after: &ValidationResult,
+    ) -> Vec<ValidationError> {
+        after
+            .errors
+            .iter()
+            .filter(|e| {
+                !before
+                    .errors
+                    .iter()
+                    .any(|b| b.instance_id == e.instance_id && b.message == e.message)
+            })
+            .cloned()
+            .collect()
+    }
+
     /// Validate a single instance against the schema
     pub async fn validate_instance<S: Store>(
         store: &S,
         instance: &Instance,
         schema: &Schema,
+    ) -> Result<ValidationResult> {
+        Self::validate_instance_with(store, instance, schema, &mut PatternCache::default()).await
+    }
+
+    async fn validate_instance_with<S: Store>(
+        store: &S,
+        instance: &Instance,
+        schema: &Schema,
+        patterns: &mut PatternCache,
     ) -> Result<ValidationResult> {
         let mut result = ValidationResult {
             valid: true,
@@ -167,7 +323,10 @@ impl SimpleValidator {
         };
 
         // Validate properties
-        Self::validate_instance_properties(instance, class_def, &mut result);
+        Self::validate_instance_properties(instance, class_def, patterns, &mut result);
+
+        // Validate business-rule constraints
+        Self::validate_instance_constraints(instance, class_def, &mut result);
 
         // Validate relationships
         Self::validate_instance_relationships(store, instance, class_def, schema, &mut result)
@@ -194,6 +353,7 @@ impl SimpleValidator {
     fn validate_instance_properties(
         instance: &Instance,
         class_def: &ClassDef,
+        patterns: &mut PatternCache,
         result: &mut ValidationResult,
     ) {
         // Create lookup maps for both property IDs and names (for backward compatibility)
@@ -301,6 +461,64 @@ impl SimpleValidator {
                                 });
                             }
                         }
+
+                        // Check numeric range
+                        if prop_def.data_type == DataType::Number
+                            && prop_def.in_range(&typed_value.value) == Some(false)
+                        {
+                            let range = prop_def.range_description().unwrap_or_default();
+                            result.valid = false;
+                            result.errors.push(ValidationError {
+                                instance_id: instance.id.clone(),
+                                error_type: ValidationErrorType::InvalidValue,
+                                message: format!(
+                                    "Value {} for property '{}' (ID: {}) must be {}",
+                                    typed_value.value, prop_def.name, prop_def.id, range
+                                ),
+                                property_name: Some(prop_key.clone()),
+                                expected: Some(range),
+                                actual: Some(typed_value.value.to_string()),
+                            });
+                        }
+
+                        // Check string pattern
+                        if let (Some(pattern), Some(value)) =
+                            (&prop_def.pattern, typed_value.value.as_str())
+                        {
+                            match patterns.get(pattern) {
+                                Ok(regex) if regex.is_match(value) => {}
+                                Ok(_) => {
+                                    result.valid = false;
+                                    result.errors.push(ValidationError {
+                                        instance_id: instance.id.clone(),
+                                        error_type: ValidationErrorType::InvalidValue,
+                                        message: format!(
+                                            "Value '{}' for property '{}' (ID: {}) does not match \
+                                             pattern '{}'",
+                                            value, prop_def.name, prop_def.id, pattern
+                                        ),
+                                        property_name: Some(prop_key.clone()),
+                                        expected: Some(pattern.clone()),
+                                        actual: Some(value.to_string()),
+                                    });
+                                }
+                                Err(e) => {
+                                    result.valid = false;
+                                    result.errors.push(ValidationError {
+                                        instance_id: instance.id.clone(),
+                                        error_type: ValidationErrorType::InvalidSchema,
+                                        message: format!(
+                                            "Property '{}' (ID: {}) of class '{}' has an invalid \
+                                             pattern '{}': {}",
+                                            prop_def.name, prop_def.id, class_def.name, pattern, e
+                                        ),
+                                        property_name: Some(prop_key.clone()),
+                                        expected: Some("Valid regular expression".to_string()),
+                                        actual: Some(pattern.clone()),
+                                    });
+                                }
+                            }
+                        }
                     }
                     PropertyValue::Conditional(rule_set) => {
                         // Validate that all relationships referenced in conditional rules exist in the class definition
@@ -313,6 +531,37 @@ impl SimpleValidator {
         }
     }
 
+    /// Check the class's boolean constraint expressions; a constraint that evaluates to false
+    /// or can't be evaluated is reported as an `InvalidValue` error naming its index
+    fn validate_instance_constraints(
+        instance: &Instance,
+        class_def: &ClassDef,
+        result: &mut ValidationResult,
+    ) {
+        for (index, constraint) in class_def.constraints.iter().enumerate() {
+            let message = match SimpleEvaluator::evaluate_constraint(constraint, instance) {
+                Ok(true) => continue,
+                Ok(false) => format!(
+                    "Constraint {} of class '{}' is not satisfied",
+                    index, class_def.name
+                ),
+                Err(e) => format!(
+                    "Constraint {} of class '{}' could not be evaluated: {}",
+                    index, class_def.name, e
+                ),
+            };
+            result.valid = false;
+            result.errors.push(ValidationError {
+                instance_id: instance.id.clone(),
+                error_type: ValidationErrorType::InvalidValue,
+                message,
+                property_name: None,
+                expected: serde_json::to_string(constraint).ok(),
+                actual: None,
+            });
+        }
+    }
+
     async fn validate_instance_relationships<S: Store>(
         store: &S,
         instance: &Instance,
@@ -372,6 +621,55 @@ impl SimpleValidator {
             }
         }
 
+        // Inverse relationships are computed and must mirror a forward relationship
+        for rel_def in &class_def.relationships {
+            let Some(forward) = &rel_def.inverse_of else {
+                continue;
+            };
+
+            if instance.relationships.contains_key(&rel_def.id)
+                || instance.relationships.contains_key(&rel_def.name)
+            {
+                result.valid = false;
+                result.errors.push(ValidationError {
+                    instance_id: instance.id.clone(),
+                    error_type: ValidationErrorType::RelationshipError,
+                    message: format!(
+                        "Relationship '{}' is the inverse of '{}' and cannot be set on instances",
+                        rel_def.name, forward
+                    ),
+                    property_name: Some(rel_def.name.clone()),
+                    expected: None,
+                    actual: None,
+                });
+            }
+
+            for target_class_id in &rel_def.targets {
+                let Some(target_class) = schema.get_class_by_id(target_class_id) else {
+                    continue;
+                };
+                if !target_class
+                    .relationships
+                    .iter()
+                    .any(|r| (&r.id == forward || &r.name == forward) && !r.is_inverse())
+                {
+                    result.valid = false;
+                    result.errors.push(ValidationError {
+                        instance_id: instance.id.clone(),
+                        error_type: ValidationErrorType::RelationshipError,
+                        message: format!(
+                            "Relationship '{}' is the inverse of '{}', which is not a forward \
+                             relationship on class '{}'",
+                            rel_def.name, forward, target_class.name
+                        ),
+                        property_name: Some(rel_def.name.clone()),
+                        expected: Some("Forward relationship on target class".to_string()),
+                        actual: Some(forward.clone()),
+                    });
+                }
+            }
+        }
+
         // Note: Relationship resolution validation is now handled by validate_relationship_resolution()
         // which is called separately with access to all instances. This allows proper pool resolution
         // and filter evaluation. The old warnings about "complex validation not yet implemented" have
@@ -388,6 +686,10 @@ impl SimpleValidator {
     ) {
         use crate::logic::pool_resolution::{PoolResolver, SelectionResult};
 
+        Self::validate_relationship_cardinality(instance, class_def, all_instances, result);
+        Self::validate_relationship_logic(instance, class_def, all_instances, result);
+        Self::validate_base_target_count(instance, class_def, all_instances, result);
+
         // Build relationship definition lookup
         let schema_rels_by_name: HashMap<String, &crate::model::RelationshipDef> = class_def
             .relationships
@@ -408,6 +710,15 @@ impl SimpleValidator {
                 .or_else(|| schema_rels_by_id.get(rel_key));
 
             if let Some(rel_def) = rel_def {
+                Self::validate_selection_in_pool(
+                    instance,
+                    rel_key,
+                    rel_def,
+                    relationship_selection,
+                    all_instances,
+                    result,
+                );
+
                 // Try to resolve the relationship to see if it produces any instances
                 match PoolResolver::resolve_relationship(
                     all_instances,
@@ -415,6 +726,17 @@ impl SimpleValidator {
                     relationship_selection,
                 ) {
                     Ok(selection_result) => {
+                        if let SelectionResult::Resolved(ids) = &selection_result {
+                            Self::validate_target_classes(
+                                instance,
+                                rel_key,
+                                rel_def,
+                                ids,
+                                all_instances,
+                                result,
+                            );
+                        }
+
                         // Check if the resolved relationship is empty
                         let is_empty = match &selection_result {
                             SelectionResult::Resolved(ids) => ids.is_empty(),
@@ -485,6 +807,331 @@ impl SimpleValidator {
         }
     }
 
+    /// Check `min`/`max` cardinality against the resolved targets of each relationship. A
+    /// relationship missing from the instance counts as zero targets; one still left to the
+    /// solver (unresolved) can only fail when its whole pool is smaller than `min`.
+    fn validate_relationship_cardinality(
+        instance: &Instance,
+        class_def: &ClassDef,
+        all_instances: &[Instance],
+        result: &mut ValidationResult,
+    ) {
+        use crate::logic::pool_resolution::{PoolResolver, SelectionResult};
+
+        for rel_def in &class_def.relationships {
+            let Some(expected) = rel_def.cardinality_description() else {
+                continue;
+            };
+            if rel_def.is_inverse() {
+                continue;
+            }
+            let selection = instance
+                .relationships
+                .get(&rel_def.name)
+                .or_else(|| instance.relationships.get(&rel_def.id));
+
+            let count = match selection {
+                None => 0,
+                Some(selection) => {
+                    match PoolResolver::resolve_relationship(all_instances, rel_def, selection) {
+                        Ok(SelectionResult::Resolved(ids)) => ids.len(),
+                        Ok(SelectionResult::Unresolved(pool)) => {
+                            if rel_def.min.is_none_or(|min| pool.len() >= min) {
+                                continue;
+                            }
+                            pool.len()
+                        }
+                        // Resolution failures are reported by `validate_relationship_resolution`
+                        Err(_) => continue,
+                    }
+                }
+            };
+            if rel_def.allows_target_count(count) {
+                continue;
+            }
+
+            result.valid = false;
+            result.errors.push(ValidationError {
+                instance_id: instance.id.clone(),
+                error_type: ValidationErrorType::RelationshipError,
+                message: format!(
+                    "Relationship '{}' has {} target(s) but requires {}",
+                    rel_def.name, count, expected
+                ),
+                property_name: Some(rel_def.name.clone()),
+                expected: Some(expected),
+                actual: Some(format!("{} target(s)", count)),
+            });
+        }
+    }
+
+    /// Check an `imply` or `equiv` base operation over the class's two forward relationships:
+    /// `imply` requires the second relationship to have a target whenever the first has one,
+    /// `equiv` requires both or neither to have one. A relationship missing from the instance
+    /// has no targets; one still left to the solver (unresolved) skips the check, as does a
+    /// class without exactly two forward relationships.
+    fn validate_relationship_logic(
+        instance: &Instance,
+        class_def: &ClassDef,
+        all_instances: &[Instance],
+        result: &mut ValidationResult,
+    ) {
+        use crate::model::BaseOp;
+
+        if !matches!(class_def.base.op, BaseOp::Imply | BaseOp::Equiv) {
+            return;
+        }
+        let operands: Vec<&crate::model::RelationshipDef> = class_def
+            .relationships
+            .iter()
+            .filter(|r| !r.is_inverse())
+            .collect();
+        let [source, target] = operands[..] else {
+            return;
+        };
+
+        let has_targets = |rel_def: &crate::model::RelationshipDef| {
+            Self::resolved_target_count(instance, rel_def, all_instances).map(|count| count > 0)
+        };
+        let (Some(source_selected), Some(target_selected)) =
+            (has_targets(source), has_targets(target))
+        else {
+            return;
+        };
+
+        let (holds, expected) = if class_def.base.op == BaseOp::Imply {
+            (
+                !source_selected || target_selected,
+                format!("'{}' selected whenever '{}' is", target.name, source.name),
+            )
+        } else {
+            (
+                source_selected == target_selected,
+                format!(
+                    "'{}' and '{}' both selected or both empty",
+                    source.name, target.name
+                ),
+            )
+        };
+        if holds {
+            return;
+        }
+
+        let describe = |rel_def: &crate::model::RelationshipDef, selected: bool| {
+            let state = if selected { "selected" } else { "empty" };
+            format!("'{}' {}", rel_def.name, state)
+        };
+        result.valid = false;
+        result.errors.push(ValidationError {
+            instance_id: instance.id.clone(),
+            error_type: ValidationErrorType::RelationshipError,
+            message: format!(
+                "Class '{}' requires {}, but {} and {}",
+                class_def.name,
+                expected,
+                describe(source, source_selected),
+                describe(target, target_selected)
+            ),
+            property_name: Some(target.name.clone()),
+            expected: Some(expected),
+            actual: Some(format!(
+                "{}, {}",
+                describe(source, source_selected),
+                describe(target, target_selected)
+            )),
+        });
+    }
+
+    /// Check an `atleast`, `atmost` or `exactly` base operation against the number of forward
+    /// relationships whose quantifier holds on their resolved targets, with `base.val` as the
+    /// bound (0 when unset). This is what the solver counts; `optional` relationships add no
+    /// constraint there and aren't counted here. The check is skipped while any relationship is
+    /// left to the solver.
+    fn validate_base_target_count(
+        instance: &Instance,
+        class_def: &ClassDef,
+        all_instances: &[Instance],
+        result: &mut ValidationResult,
+    ) {
+        use crate::model::{BaseOp, Quantifier};
+
+        let bound = class_def.base.val.unwrap_or(0).max(0) as usize;
+        let (holds, expected): (fn(usize, usize) -> bool, String) = match class_def.base.op {
+            BaseOp::AtLeast => (|count, bound| count >= bound, format!("at least {}", bound)),
+            BaseOp::AtMost => (|count, bound| count <= bound, format!("at most {}", bound)),
+            BaseOp::Exactly => (|count, bound| count == bound, format!("exactly {}", bound)),
+            _ => return,
+        };
+        let Some(count) = class_def
+            .relationships
+            .iter()
+            .filter(|r| !r.is_inverse() && r.quantifier != Quantifier::Optional)
+            .map(|rel_def| {
+                Self::resolved_target_count(instance, rel_def, all_instances)
+                    .map(|targets| Self::quantifier_holds(&rel_def.quantifier, targets) as usize)
+            })
+            .sum::<Option<usize>>()
+        else {
+            return;
+        };
+        if holds(count, bound) {
+            return;
+        }
+
+        result.valid = false;
+        result.errors.push(ValidationError {
+            instance_id: instance.id.clone(),
+            error_type: ValidationErrorType::RelationshipError,
+            message: format!(
+                "Class '{}' satisfied relationships: expected {}, found {}",
+                class_def.name, expected, count
+            ),
+            property_name: None,
+            expected: Some(format!("{} relationship(s)", expected)),
+            actual: Some(format!("{} relationship(s)", count)),
+        });
+    }
+
+    /// Whether `quantifier` holds when all of a relationship's `targets` resolved targets are
+    /// selected, as the solver's relationship constraint would evaluate it.
+    fn quantifier_holds(quantifier: &crate::model::Quantifier, targets: usize) -> bool {
+        use crate::model::Quantifier;
+
+        match quantifier {
+            Quantifier::One => targets == 1,
+            Quantifier::Exactly(n) => targets == *n,
+            Quantifier::AtLeast(n) => targets >= *n,
+            Quantifier::AtMost(n) => targets <= *n,
+            Quantifier::Range(min, max) => (*min..=*max).contains(&targets),
+            Quantifier::Any => targets >= 1,
+            Quantifier::All | Quantifier::Optional => true,
+        }
+    }
+
+    /// Number of targets `rel_def` resolves to on `instance`, zero when the instance doesn't
+    /// set it. `None` when the selection is left to the solver or fails to resolve.
+    fn resolved_target_count(
+        instance: &Instance,
+        rel_def: &crate::model::RelationshipDef,
+        all_instances: &[Instance],
+    ) -> Option<usize> {
+        use crate::logic::pool_resolution::{PoolResolver, SelectionResult};
+
+        let selection = instance
+            .relationships
+            .get(&rel_def.name)
+            .or_else(|| instance.relationships.get(&rel_def.id));
+        match selection {
+            None => Some(0),
+            Some(selection) => {
+                match PoolResolver::resolve_relationship(all_instances, rel_def, selection) {
+                    Ok(SelectionResult::Resolved(ids)) => Some(ids.len()),
+                    // Resolution failures are reported by `validate_relationship_resolution`
+                    Ok(SelectionResult::Unresolved(_)) | Err(_) => None,
+                }
+            }
+        }
+    }
+
+    /// Check that the resolved targets are instances of the relationship's target classes; a
+    /// relationship without `targets` allows any class. Ids that aren't instances at all are
+    /// left to the pool check.
+    fn validate_target_classes(
+        instance: &Instance,
+        rel_key: &str,
+        rel_def: &crate::model::RelationshipDef,
+        ids: &[Id],
+        all_instances: &[Instance],
+        result: &mut ValidationResult,
+    ) {
+        if rel_def.targets.is_empty() || rel_def.is_inverse() {
+            return;
+        }
+
+        let targets = rel_def.targets.join(", ");
+        for target in ids
+            .iter()
+            .filter_map(|id| all_instances.iter().find(|i| i.id == *id))
+            .filter(|target| !rel_def.targets.contains(&target.class_id))
+        {
+            result.valid = false;
+            result.errors.push(ValidationError {
+                instance_id: instance.id.clone(),
+                error_type: ValidationErrorType::RelationshipError,
+                message: format!(
+                    "Relationship '{}' targets '{}' of class '{}', but only allows {}",
+                    rel_key, target.id, target.class_id, targets
+                ),
+                property_name: Some(rel_key.to_string()),
+                expected: Some(format!("Instance of {}", targets)),
+                actual: Some(format!("'{}' of class '{}'", target.id, target.class_id)),
+            });
+        }
+    }
+
+    /// Check that explicitly listed ids are candidates of the relationship's default pool.
+    /// Pool-based selections are checked by `PoolResolver` against their own pool; with a
+    /// `none` default pool any instance of the target classes may be listed. Instances of
+    /// other classes are reported by `validate_target_classes` instead.
+    fn validate_selection_in_pool(
+        instance: &Instance,
+        rel_key: &str,
+        rel_def: &crate::model::RelationshipDef,
+        selection: &crate::model::RelationshipSelection,
+        all_instances: &[Instance],
+        result: &mut ValidationResult,
+    ) {
+        use crate::logic::pool_resolution::PoolResolver;
+        use crate::model::{DefaultPool, RelationshipSelection};
+
+        let ids = match selection {
+            RelationshipSelection::SimpleIds(ids) | RelationshipSelection::Ids { ids } => ids,
+            _ => return,
+        };
+        if ids.is_empty() || rel_def.is_inverse() {
+            return;
+        }
+
+        let is_target_class =
+            |i: &Instance| rel_def.targets.is_empty() || rel_def.targets.contains(&i.class_id);
+        let pool: Vec<Id> = match rel_def.default_pool {
+            DefaultPool::None => all_instances
+                .iter()
+                .filter(|i| is_target_class(i))
+                .map(|i| i.id.clone())
+                .collect(),
+            _ => match PoolResolver::resolve_effective_pool(all_instances, rel_def, None) {
+                Ok(pool) => pool,
+                // Resolution failures are reported by `validate_relationship_resolution`
+                Err(_) => return,
+            },
+        };
+
+        let wrong_class = |id: &Id| {
+            all_instances
+                .iter()
+                .find(|i| i.id == *id)
+                .is_some_and(|i| !is_target_class(i))
+        };
+        for id in ids
+            .iter()
+            .filter(|id| !pool.contains(id) && !wrong_class(id))
+        {
+            result.valid = false;
+            result.errors.push(ValidationError {
+                instance_id: instance.id.clone(),
+                error_type: ValidationErrorType::RelationshipError,
+                message: format!(
+                    "Relationship '{}' selects '{}', which is not in its pool of allowed instances",
+                    rel_key, id
+                ),
+                property_name: Some(rel_key.to_string()),
+                expected: Some(format!("One of {} pool instance(s)", pool.len())),
+                actual: Some(id.clone()),
+            });
+        }
+    }
+
     fn validate_value_type_consistency_detailed(
         value: &serde_json::Value,
         declared_type: &DataType,
@@ -498,6 +1145,7 @@ impl SimpleValidator {
             (serde_json::Value::Array(arr), DataType::StringList) => arr
                 .iter()
                 .all(|v| matches!(v, serde_json::Value::String(_))),
+            (_, DataType::Json) => true,
             _ => false,
         };
 
@@ -630,3 +1278,788 @@ impl SimpleValidator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Expr, TypedValue};
+
+    fn product(price: f64, discount_price: Option<f64>) -> Instance {
+        let mut instance = Instance {
+            id: "product-1".to_string(),
+            class_id: "class-product".to_string(),
+            ..Instance::default()
+        };
+        let number = |value: f64| {
+            PropertyValue::Literal(TypedValue {
+                value: serde_json::json!(value),
+                data_type: DataType::Number,
+            })
+        };
+        instance.properties.insert("price".to_string(), number(price));
+        if let Some(discount_price) = discount_price {
+            instance
+                .properties
+                .insert("discount_price".to_string(), number(discount_price));
+        }
+        instance
+    }
+
+    fn validate(instance: &Instance) -> ValidationResult {
+        let class_def = ClassDef {
+            id: "class-product".to_string(),
+            name: "Product".to_string(),
+            constraints: vec![Expr::Lt {
+                left: Box::new(Expr::Prop {
+                    prop: "discount_price".to_string(),
+                }),
+                right: Box::new(Expr::Prop {
+                    prop: "price".to_string(),
+                }),
+            }],
+            ..ClassDef::default()
+        };
+        let mut result = ValidationResult {
+            valid: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            instance_count: 1,
+            validated_instances: vec![instance.id.clone()],
+        };
+        SimpleValidator::validate_instance_constraints(instance, &class_def, &mut result);
+        result
+    }
+
+    #[test]
+    fn test_two_property_constraint() {
+        assert!(validate(&product(100.0, Some(80.0))).valid);
+
+        let result = validate(&product(100.0, Some(120.0)));
+        assert!(!result.valid);
+        assert!(matches!(
+            result.errors[0].error_type,
+            ValidationErrorType::InvalidValue
+        ));
+        assert!(result.errors[0].message.contains("Constraint 0"));
+
+        // A constraint over a missing property fails rather than passing silently
+        let result = validate(&product(100.0, None));
+        assert!(!result.valid);
+        assert!(result.errors[0].message.contains("could not be evaluated"));
+    }
+
+    #[tokio::test]
+    async fn test_introduced_errors_ignore_existing_ones() {
+        let schema = Schema {
+            id: "schema".to_string(),
+            classes: vec![ClassDef {
+                id: "class-product".to_string(),
+                name: "Product".to_string(),
+                ..ClassDef::default()
+            }],
+            description: None,
+        };
+        let mut orphan = product(10.0, None);
+        orphan.id = "product-2".to_string();
+        orphan.class_id = "class-missing".to_string();
+        let store = crate::store::InMemoryStore::new();
+
+        let before = SimpleValidator::validate_instances(&store, &[orphan.clone()], &schema)
+            .await
+            .unwrap();
+        assert!(!before.valid);
+
+        let mut moved = product(10.0, None);
+        moved.class_id = "class-gone".to_string();
+        let after = SimpleValidator::validate_instances(&store, &[orphan, moved], &schema)
+            .await
+            .unwrap();
+        assert_eq!(after.instance_count, 2);
+        assert_eq!(after.errors.len(), 2);
+
+        let introduced = SimpleValidator::introduced_errors(&before, &after);
+        assert_eq!(introduced.len(), 1);
+        assert_eq!(introduced[0].instance_id, "product-1");
+    }
+
+    #[tokio::test]
+    async fn test_selected_ids_must_be_in_pool() {
+        use crate::model::{
+            DefaultPool, InstanceFilter, Quantifier, RelationshipDef, RelationshipSelection,
+            SelectionType,
+        };
+
+        let fabric = |id: &str, color: &str| {
+            let mut instance = Instance {
+                id: id.to_string(),
+                class_id: "class-fabric".to_string(),
+                ..Instance::default()
+            };
+            instance.properties.insert(
+                "color".to_string(),
+                PropertyValue::Literal(TypedValue {
+                    value: serde_json::json!(color),
+                    data_type: DataType::String,
+                }),
+            );
+            instance
+        };
+        let sofa = |selection: RelationshipSelection| {
+            let mut instance = Instance {
+                id: "sofa".to_string(),
+                class_id: "class-sofa".to_string(),
+                ..Instance::default()
+            };
+            instance.relationships.insert("fabric".to_string(), selection);
+            instance
+        };
+        let red_only = crate::logic::parse_filter_expr(serde_json::json!(
+            {"eq": ["$.color", "red"]}
+        ))
+        .unwrap();
+        let schema = Schema {
+            id: "schema".to_string(),
+            classes: vec![
+                ClassDef {
+                    id: "class-fabric".to_string(),
+                    name: "Fabric".to_string(),
+                    properties: vec![crate::model::PropertyDef {
+                        id: "prop-color".to_string(),
+                        name: "color".to_string(),
+                        data_type: DataType::String,
+                        required: None,
+                        value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
+                    }],
+                    ..ClassDef::default()
+                },
+                ClassDef {
+                    id: "class-sofa".to_string(),
+                    name: "Sofa".to_string(),
+                    relationships: vec![RelationshipDef {
+                        id: "rel-fabric".to_string(),
+                        name: "fabric".to_string(),
+                        targets: vec!["class-fabric".to_string()],
+                        quantifier: Quantifier::AtLeast(1),
+                        universe: None,
+                        selection: SelectionType::ExplicitOrFilter,
+                        default_pool: DefaultPool::Filter {
+                            types: None,
+                            filter: Some(InstanceFilter {
+                                types: None,
+                                where_clause: Some(red_only),
+                                sort: None,
+                                limit: None,
+                            }),
+                        },
+                        inverse_of: None,
+                        min: None,
+                        max: None,
+                    }],
+                    ..ClassDef::default()
+                },
+            ],
+            description: None,
+        };
+        let store = crate::store::InMemoryStore::new();
+        let validate = |selection: RelationshipSelection| {
+            let instances = vec![fabric("red-1", "red"), fabric("blue-1", "blue"), sofa(selection)];
+            let schema = schema.clone();
+            let store = &store;
+            async move {
+                SimpleValidator::validate_instances(store, &instances, &schema)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let result = validate(RelationshipSelection::SimpleIds(vec!["red-1".to_string()])).await;
+        assert!(result.valid, "{:?}", result.errors);
+
+        let result = validate(RelationshipSelection::Ids {
+            ids: vec!["red-1".to_string(), "blue-1".to_string()],
+        })
+        .await;
+        assert!(!result.valid);
+        assert_eq!(result.errors.len(), 1);
+        assert!(matches!(
+            result.errors[0].error_type,
+            ValidationErrorType::RelationshipError
+        ));
+        assert_eq!(result.errors[0].actual.as_deref(), Some("blue-1"));
+    }
+
+    fn quantity_schema(data_type: DataType) -> Schema {
+        let quantity: crate::model::PropertyDef = serde_json::from_value(serde_json::json!({
+            "id": "prop-quantity",
+            "name": "quantity",
+            "data_type": data_type,
+            "min": 1,
+            "max": 100
+        }))
+        .unwrap();
+        Schema {
+            id: "schema".to_string(),
+            classes: vec![ClassDef {
+                id: "class-line".to_string(),
+                name: "Line".to_string(),
+                properties: vec![quantity],
+                ..ClassDef::default()
+            }],
+            description: None,
+        }
+    }
+
+    async fn validate_quantity(quantity: f64) -> ValidationResult {
+        let mut line = Instance {
+            id: "line-1".to_string(),
+            class_id: "class-line".to_string(),
+            ..Instance::default()
+        };
+        line.properties.insert(
+            "quantity".to_string(),
+            PropertyValue::Literal(TypedValue {
+                value: serde_json::json!(quantity),
+                data_type: DataType::Number,
+            }),
+        );
+        let store = crate::store::InMemoryStore::new();
+        SimpleValidator::validate_instances(&store, &[line], &quantity_schema(DataType::Number))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_numeric_range() {
+        assert!(validate_quantity(1.0).await.valid);
+        assert!(validate_quantity(100.0).await.valid);
+
+        for out_of_range in [0.0, 100.5] {
+            let result = validate_quantity(out_of_range).await;
+            assert!(!result.valid);
+            assert_eq!(result.errors.len(), 1);
+            assert!(matches!(
+                result.errors[0].error_type,
+                ValidationErrorType::InvalidValue
+            ));
+            assert_eq!(result.errors[0].expected.as_deref(), Some("between 1 and 100"));
+        }
+    }
+
+    #[test]
+    fn test_range_fields_are_optional() {
+        let json = serde_json::json!({
+            "id": "prop-name",
+            "name": "name",
+            "data_type": "string"
+        });
+        let prop: crate::model::PropertyDef = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!((prop.min, prop.max), (None, None));
+        assert_eq!(prop.in_range(&serde_json::json!(1e9)), Some(true));
+        assert_eq!(serde_json::to_value(&prop).unwrap(), json);
+
+        assert!(SimpleValidator::schema_warnings(&quantity_schema(DataType::Number)).is_empty());
+        let warnings = SimpleValidator::schema_warnings(&quantity_schema(DataType::String));
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(
+            warnings[0].warning_type,
+            ValidationWarningType::RangeOnNonNumericProperty
+        ));
+    }
+
+    async fn validate_skus(pattern: &str, skus: &[&str]) -> ValidationResult {
+        let sku: crate::model::PropertyDef = serde_json::from_value(serde_json::json!({
+            "id": "prop-sku",
+            "name": "sku",
+            "data_type": "string",
+            "pattern": pattern
+        }))
+        .unwrap();
+        let schema = Schema {
+            id: "schema".to_string(),
+            classes: vec![ClassDef {
+                id: "class-item".to_string(),
+                name: "Item".to_string(),
+                properties: vec![sku],
+                ..ClassDef::default()
+            }],
+            description: None,
+        };
+        let items: Vec<Instance> = skus
+            .iter()
+            .enumerate()
+            .map(|(i, sku)| {
+                let mut item = Instance {
+                    id: format!("item-{}", i),
+                    class_id: "class-item".to_string(),
+                    ..Instance::default()
+                };
+                item.properties.insert(
+                    "sku".to_string(),
+                    PropertyValue::Literal(TypedValue::string(sku.to_string())),
+                );
+                item
+            })
+            .collect();
+        let store = crate::store::InMemoryStore::new();
+        SimpleValidator::validate_instances(&store, &items, &schema)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_string_pattern() {
+        assert!(validate_skus("[A-Z]{3}-\\d{4}", &["ABC-1234", "XYZ-0001"]).await.valid);
+
+        // The whole value must match, not just a substring
+        let result = validate_skus("[A-Z]{3}-\\d{4}", &["ABC-1234", "xABC-12345"]).await;
+        assert!(!result.valid);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].instance_id, "item-1");
+        assert!(matches!(
+            result.errors[0].error_type,
+            ValidationErrorType::InvalidValue
+        ));
+        assert!(result.errors[0].message.contains("[A-Z]{3}-\\d{4}"));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_pattern_is_a_schema_error() {
+        let result = validate_skus("[A-Z", &["ABC"]).await;
+        assert!(!result.valid);
+        assert!(matches!(
+            result.errors[0].error_type,
+            ValidationErrorType::InvalidSchema
+        ));
+        assert_eq!(result.errors[0].actual.as_deref(), Some("[A-Z"));
+    }
+
+    #[tokio::test]
+    async fn test_relationship_cardinality() {
+        use crate::model::{
+            DefaultPool, Quantifier, RelationshipDef, RelationshipSelection, SelectionType,
+        };
+
+        let relationship = |name: &str, min: Option<usize>, max: Option<usize>| RelationshipDef {
+            id: format!("rel-{}", name),
+            name: name.to_string(),
+            targets: vec!["class-leg".to_string()],
+            quantifier: Quantifier::Any,
+            universe: None,
+            selection: SelectionType::ExplicitOrFilter,
+            default_pool: DefaultPool::All,
+            inverse_of: None,
+            min,
+            max,
+        };
+        let schema = Schema {
+            id: "schema".to_string(),
+            classes: vec![
+                ClassDef {
+                    id: "class-leg".to_string(),
+                    name: "Leg".to_string(),
+                    ..ClassDef::default()
+                },
+                ClassDef {
+                    id: "class-table".to_string(),
+                    name: "Table".to_string(),
+                    relationships: vec![
+                        relationship("legs", Some(1), Some(4)),
+                        relationship("spares", None, None),
+                    ],
+                    ..ClassDef::default()
+                },
+            ],
+            description: None,
+        };
+        let legs: Vec<Instance> = (0..5)
+            .map(|i| Instance {
+                id: format!("leg-{}", i),
+                class_id: "class-leg".to_string(),
+                ..Instance::default()
+            })
+            .collect();
+        let validate = |relationships: Vec<(&str, RelationshipSelection)>| {
+            let mut table = Instance {
+                id: "table".to_string(),
+                class_id: "class-table".to_string(),
+                ..Instance::default()
+            };
+            for (name, selection) in relationships {
+                table.relationships.insert(name.to_string(), selection);
+            }
+            let mut instances = legs.clone();
+            instances.push(table);
+            let schema = schema.clone();
+            async move {
+                let store = crate::store::InMemoryStore::new();
+                SimpleValidator::validate_instances(&store, &instances, &schema)
+                    .await
+                    .unwrap()
+            }
+        };
+        let ids = |n: usize| {
+            RelationshipSelection::SimpleIds((0..n).map(|i| format!("leg-{}", i)).collect())
+        };
+
+        // Required relationship left empty
+        let result = validate(vec![]).await;
+        assert!(!result.valid);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].expected.as_deref(), Some("between 1 and 4 target(s)"));
+        assert_eq!(result.errors[0].actual.as_deref(), Some("0 target(s)"));
+
+        // Over capacity, whether listed explicitly or selected as the whole pool
+        for over in [ids(5), RelationshipSelection::All] {
+            let result = validate(vec![("legs", over)]).await;
+            assert!(!result.valid);
+            assert!(matches!(
+                result.errors[0].error_type,
+                ValidationErrorType::RelationshipError
+            ));
+            assert_eq!(result.errors[0].actual.as_deref(), Some("5 target(s)"));
+        }
+
+        // Unbounded relationship may hold any number of targets
+        let result = validate(vec![("legs", ids(4)), ("spares", RelationshipSelection::All)]).await;
+        assert!(result.valid, "unexpected errors: {:?}", result.errors);
+    }
+
+    #[tokio::test]
+    async fn test_relationship_target_classes() {
+        use crate::model::{
+            DefaultPool, Quantifier, RelationshipDef, RelationshipSelection, SelectionType,
+        };
+
+        let relationship = |name: &str, targets: &[&str], default_pool| RelationshipDef {
+            id: format!("rel-{}", name),
+            name: name.to_string(),
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            quantifier: Quantifier::Any,
+            universe: None,
+            selection: SelectionType::ExplicitOrFilter,
+            default_pool,
+            inverse_of: None,
+            min: None,
+            max: None,
+        };
+        let class = |id: &str, relationships: Vec<RelationshipDef>| ClassDef {
+            id: id.to_string(),
+            name: id.to_string(),
+            relationships,
+            ..ClassDef::default()
+        };
+        let schema = Schema {
+            id: "schema".to_string(),
+            classes: vec![
+                class("class-leg", vec![]),
+                class("class-fabric", vec![]),
+                class(
+                    "class-table",
+                    vec![
+                        relationship("legs", &["class-leg"], DefaultPool::All),
+                        relationship("extras", &[], DefaultPool::None),
+                    ],
+                ),
+            ],
+            description: None,
+        };
+        let instance = |id: &str, class_id: &str| Instance {
+            id: id.to_string(),
+            class_id: class_id.to_string(),
+            ..Instance::default()
+        };
+        let validate = |name: &str, ids: &[&str]| {
+            let mut table = instance("table", "class-table");
+            table.relationships.insert(
+                name.to_string(),
+                RelationshipSelection::SimpleIds(ids.iter().map(|id| id.to_string()).collect()),
+            );
+            let instances = vec![
+                instance("leg-1", "class-leg"),
+                instance("fabric-1", "class-fabric"),
+                table,
+            ];
+            let schema = schema.clone();
+            async move {
+                let store = crate::store::InMemoryStore::new();
+                SimpleValidator::validate_instances(&store, &instances, &schema)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let result = validate("legs", &["leg-1"]).await;
+        assert!(result.valid, "unexpected errors: {:?}", result.errors);
+
+        let result = validate("legs", &["leg-1", "fabric-1"]).await;
+        assert!(!result.valid);
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        let error = &result.errors[0];
+        assert_eq!(error.error_type, ValidationErrorType::RelationshipError);
+        assert_eq!(error.property_name.as_deref(), Some("legs"));
+        assert_eq!(
+            error.actual.as_deref(),
+            Some("'fabric-1' of class 'class-fabric'")
+        );
+        assert!(error.message.contains("fabric-1"), "{}", error.message);
+
+        // No targets: any class is allowed
+        let result = validate("extras", &["leg-1", "fabric-1"]).await;
+        assert!(result.valid, "unexpected errors: {:?}", result.errors);
+    }
+
+    /// Errors for a bike whose `frame` and `fork` relationships hold the given targets,
+    /// combined with `op` and `val`; `None` leaves the relationship off the instance
+    fn base_operation_errors(
+        op: crate::model::BaseOp,
+        val: Option<i32>,
+        frame: Option<&[&str]>,
+        fork: Option<&[&str]>,
+    ) -> Vec<ValidationError> {
+        use crate::model::{
+            Base, DefaultPool, Quantifier, RelationshipDef, RelationshipSelection, SelectionType,
+        };
+
+        let relationship = |name: &str| RelationshipDef {
+            id: format!("rel-{}", name),
+            name: name.to_string(),
+            targets: vec!["class-part".to_string()],
+            quantifier: Quantifier::Any,
+            universe: None,
+            selection: SelectionType::ExplicitOrFilter,
+            default_pool: DefaultPool::All,
+            inverse_of: None,
+            min: None,
+            max: None,
+        };
+        let class_def = ClassDef {
+            id: "class-bike".to_string(),
+            name: "Bike".to_string(),
+            relationships: vec![relationship("frame"), relationship("fork")],
+            base: Base { op, val },
+            ..ClassDef::default()
+        };
+        let mut instances: Vec<Instance> = ["part-a", "part-b", "part-c", "part-d"]
+            .iter()
+            .map(|id| Instance {
+                id: id.to_string(),
+                class_id: "class-part".to_string(),
+                ..Instance::default()
+            })
+            .collect();
+        let mut bike = Instance {
+            id: "bike".to_string(),
+            class_id: "class-bike".to_string(),
+            ..Instance::default()
+        };
+        for (name, ids) in [("frame", frame), ("fork", fork)] {
+            if let Some(ids) = ids {
+                let ids = ids.iter().map(|id| id.to_string()).collect();
+                bike.relationships
+                    .insert(name.to_string(), RelationshipSelection::SimpleIds(ids));
+            }
+        }
+        instances.push(bike.clone());
+
+        let mut result = ValidationResult {
+            valid: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            instance_count: 1,
+            validated_instances: vec![bike.id.clone()],
+        };
+        SimpleValidator::validate_relationship_resolution(
+            &bike,
+            &class_def,
+            &instances,
+            &mut result,
+        );
+        result.errors
+    }
+
+    #[test]
+    fn test_imply_satisfied() {
+        use crate::model::BaseOp;
+
+        let errors =
+            base_operation_errors(BaseOp::Imply, None, Some(&["part-a"]), Some(&["part-b"]));
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        // Nothing follows from an empty source
+        let errors = base_operation_errors(BaseOp::Imply, None, None, Some(&["part-b"]));
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+    }
+
+    #[test]
+    fn test_imply_violated() {
+        use crate::model::BaseOp;
+
+        let errors = base_operation_errors(BaseOp::Imply, None, Some(&["part-a"]), Some(&[]));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error_type, ValidationErrorType::RelationshipError);
+        assert_eq!(
+            errors[0].expected.as_deref(),
+            Some("'fork' selected whenever 'frame' is")
+        );
+        assert_eq!(
+            errors[0].actual.as_deref(),
+            Some("'frame' selected, 'fork' empty")
+        );
+    }
+
+    #[test]
+    fn test_equiv_satisfied() {
+        use crate::model::BaseOp;
+
+        let errors =
+            base_operation_errors(BaseOp::Equiv, None, Some(&["part-a"]), Some(&["part-b"]));
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+    }
+
+    #[test]
+    fn test_equiv_violated() {
+        use crate::model::BaseOp;
+
+        for (frame, fork) in [(None, Some(&["part-b"][..])), (Some(&["part-a"][..]), None)] {
+            let errors = base_operation_errors(BaseOp::Equiv, None, frame, fork);
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].error_type, ValidationErrorType::RelationshipError);
+            assert_eq!(
+                errors[0].expected.as_deref(),
+                Some("'frame' and 'fork' both selected or both empty")
+            );
+        }
+    }
+
+    #[test]
+    fn test_relationship_logic_without_selections() {
+        use crate::model::BaseOp;
+
+        for op in [BaseOp::Imply, BaseOp::Equiv] {
+            let errors = base_operation_errors(op, None, None, None);
+            assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        }
+    }
+
+    /// Base operation errors for `val` 1 and zero, one and two satisfied `any` relationships
+    fn target_count_errors(op: crate::model::BaseOp) -> [Vec<ValidationError>; 3] {
+        [
+            base_operation_errors(op.clone(), Some(1), None, None),
+            base_operation_errors(op.clone(), Some(1), Some(&["part-a", "part-b"]), None),
+            base_operation_errors(op, Some(1), Some(&["part-a"]), Some(&["part-c"])),
+        ]
+    }
+
+    #[test]
+    fn test_atleast_target_count() {
+        let [below, at, above] = target_count_errors(crate::model::BaseOp::AtLeast);
+        assert_eq!(below.len(), 1);
+        assert_eq!(below[0].error_type, ValidationErrorType::RelationshipError);
+        assert!(below[0].message.ends_with("expected at least 1, found 0"));
+        assert_eq!(below[0].expected.as_deref(), Some("at least 1 relationship(s)"));
+        assert_eq!(below[0].actual.as_deref(), Some("0 relationship(s)"));
+        assert!(at.is_empty(), "unexpected errors: {:?}", at);
+        assert!(above.is_empty(), "unexpected errors: {:?}", above);
+    }
+
+    #[test]
+    fn test_atmost_target_count() {
+        let [below, at, above] = target_count_errors(crate::model::BaseOp::AtMost);
+        assert!(below.is_empty(), "unexpected errors: {:?}", below);
+        assert!(at.is_empty(), "unexpected errors: {:?}", at);
+        assert_eq!(above.len(), 1);
+        assert!(above[0].message.ends_with("expected at most 1, found 2"));
+        assert_eq!(above[0].actual.as_deref(), Some("2 relationship(s)"));
+    }
+
+    #[test]
+    fn test_exactly_target_count() {
+        let [below, at, above] = target_count_errors(crate::model::BaseOp::Exactly);
+        assert_eq!(below.len(), 1);
+        assert!(below[0].message.ends_with("expected exactly 1, found 0"));
+        assert!(at.is_empty(), "unexpected errors: {:?}", at);
+        assert_eq!(above.len(), 1);
+        assert!(above[0].message.ends_with("expected exactly 1, found 2"));
+        assert_eq!(above[0].expected.as_deref(), Some("exactly 1 relationship(s)"));
+    }
+
+    fn unique_sku_errors(skus: &[Option<&str>]) -> Vec<ValidationError> {
+        let sku: crate::model::PropertyDef = serde_json::from_value(serde_json::json!({
+            "id": "prop-sku",
+            "name": "sku",
+            "data_type": "string",
+            "unique": true
+        }))
+        .unwrap();
+        let schema = Schema {
+            id: "schema".to_string(),
+            classes: vec![ClassDef {
+                id: "class-item".to_string(),
+                name: "Item".to_string(),
+                properties: vec![sku],
+                ..ClassDef::default()
+            }],
+            description: None,
+        };
+        let items: Vec<Instance> = skus
+            .iter()
+            .enumerate()
+            .map(|(i, sku)| {
+                let mut item = Instance {
+                    id: format!("item-{}", i),
+                    class_id: "class-item".to_string(),
+                    ..Instance::default()
+                };
+                let value = sku.map_or(serde_json::Value::Null, |sku| serde_json::json!(sku));
+                item.properties.insert(
+                    "sku".to_string(),
+                    PropertyValue::Literal(TypedValue {
+                        value,
+                        data_type: DataType::String,
+                    }),
+                );
+                item
+            })
+            .collect();
+        let mut result = ValidationResult {
+            valid: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            instance_count: items.len(),
+            validated_instances: Vec::new(),
+        };
+        SimpleValidator::validate_branch_uniqueness(&items, &schema, &mut result);
+        assert_eq!(result.valid, result.errors.is_empty());
+        result.errors
+    }
+
+    #[test]
+    fn test_unique_duplicate_pair() {
+        let errors = unique_sku_errors(&[Some("A-1"), Some("B-2"), Some("A-1")]);
+        let flagged: Vec<&str> = errors.iter().map(|e| e.instance_id.as_str()).collect();
+        assert_eq!(flagged, vec!["item-0", "item-2"]);
+        assert!(matches!(
+            errors[0].error_type,
+            ValidationErrorType::DuplicateValue
+        ));
+        assert_eq!(errors[0].actual.as_deref(), Some("item-0, item-2"));
+    }
+
+    #[test]
+    fn test_unique_three_duplicates() {
+        let errors = unique_sku_errors(&[Some("A-1"), Some("A-1"), Some("A-1")]);
+        assert_eq!(errors.len(), 3);
+        assert!(errors
+            .iter()
+            .all(|e| e.actual.as_deref() == Some("item-0, item-1, item-2")));
+    }
+
+    #[test]
+    fn test_unique_ignores_nulls() {
+        assert!(unique_sku_errors(&[None, None, Some("A-1")]).is_empty());
+    }
+}
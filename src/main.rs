@@ -1,9 +1,12 @@
-use axum::serve;
+use axum::{serve, Extension};
+use oat_db_rust::api::idempotency::IdempotencyCache;
 use oat_db_rust::api::routes::create_router;
 use oat_db_rust::config::AppConfig;
+use oat_db_rust::logic::CommitStatsCache;
 use oat_db_rust::seed;
-use oat_db_rust::store::PostgresStore;
+use oat_db_rust::store::{PostgresStore, PropertyCipher};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 
 #[tokio::main]
@@ -31,7 +34,11 @@ async fn main() -> anyhow::Result<()> {
 
     println!("Connecting to PostgreSQL...");
     let database_url = config.database_url()?;
-    let postgres_store = PostgresStore::new(&database_url).await?;
+    let mut postgres_store = PostgresStore::new(&database_url, &config.database).await?;
+    if let Some(key) = &config.encryption.key {
+        postgres_store = postgres_store.with_property_cipher(PropertyCipher::from_base64_key(key)?);
+        println!("Property encryption enabled");
+    }
 
     println!("Running database migrations...");
     postgres_store.migrate().await?;
@@ -53,7 +60,16 @@ async fn main() -> anyhow::Result<()> {
         println!("Seed data loaded successfully");
     }
 
-    run_server(create_router().with_state(store), &config).await?;
+    let app = create_router()
+        .layer(Extension(config.expand.clone()))
+        .layer(Extension(config.pagination.clone()))
+        .layer(Extension(config.delete.clone()))
+        .layer(Extension(Arc::new(CommitStatsCache::new())))
+        .layer(Extension(Arc::new(IdempotencyCache::new(Duration::from_secs(
+            config.idempotency.ttl_secs,
+        )))))
+        .with_state(store);
+    run_server(app, &config).await?;
 
     Ok(())
 }
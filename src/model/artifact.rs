@@ -1,5 +1,5 @@
 use crate::model::{
-    Id, Instance, ResolutionContext, ResolutionContextMetadata, ResolutionPolicies,
+    Expr, Id, Instance, ResolutionContext, ResolutionContextMetadata, ResolutionPolicies,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -33,6 +33,98 @@ pub struct ConfigurationArtifact {
     /// Maps instance_id -> property_name -> calculated_value
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub derived_properties: HashMap<Id, HashMap<String, serde_json::Value>>,
+
+    /// Per-instance contributions to the objective, present only when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objective_breakdown: Option<ObjectiveBreakdown>,
+
+    /// Sum of the property objective's property over the selected instances, present only
+    /// when the solve had a property objective
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub objective_value: Option<f64>,
+
+    /// Request constraint checks the solved configuration does not satisfy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraint_violations: Vec<ConstraintViolation>,
+}
+
+/// A request constraint check that failed against a solved configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConstraintViolation {
+    /// Position of the constraint in the request's `constraint_checks`
+    pub index: usize,
+
+    /// The constraint as it was requested
+    pub constraint: Expr,
+
+    /// Why the constraint failed: it evaluated to false or could not be evaluated
+    pub message: String,
+}
+
+/// How the objective value of a solution is made up, one line per selected instance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectiveBreakdown {
+    /// Sum of all contributions, i.e. the objective value of the solution
+    pub total: f64,
+
+    /// Contributions of selected instances with a non-zero weight, largest first
+    pub contributions: Vec<ObjectiveContribution>,
+}
+
+/// The contribution of a single selected instance to the objective
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectiveContribution {
+    pub instance_id: Id,
+    pub class_id: Id,
+
+    /// Objective weight of the instance
+    pub weight: f64,
+
+    /// Value the solver assigned to the instance (1 when selected)
+    pub value: i64,
+
+    /// `weight * value`
+    pub contribution: f64,
+}
+
+/// Machine-readable summary of a solve, for clients that should not parse `solve_summary()`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolveSummary {
+    /// Human-readable solve summary, same text as `ConfigurationArtifact::solve_summary()`
+    pub summary: String,
+
+    /// False when the solve reported a critical issue
+    pub feasible: bool,
+
+    /// Whether every instance in the configuration has a constant domain and no request
+    /// constraint is violated
+    pub complete: bool,
+
+    /// Number of instances in the configuration
+    pub instance_count: usize,
+
+    /// Objective value of the solution, present when an objective breakdown was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objective_value: Option<f64>,
+
+    /// Total solve time in milliseconds
+    pub solve_time_ms: u64,
+
+    /// Pipeline phases in execution order with their timings
+    pub phases: Vec<SolvePhaseTiming>,
+
+    /// Whether the solve had any issues, including informational ones
+    pub has_issues: bool,
+
+    /// Issues with severity warning or above
+    pub warnings: Vec<SolveIssue>,
+}
+
+/// Name and duration of a single pipeline phase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolvePhaseTiming {
+    pub name: String,
+    pub duration_ms: u64,
 }
 
 /// Notes about selector resolution (warnings, fallbacks, etc.)
@@ -213,6 +305,44 @@ pub struct NewConfigurationArtifact {
     /// Optional user metadata
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_metadata: Option<ArtifactUserMetadata>,
+
+    /// Whether to attach an objective breakdown to each artifact
+    #[serde(default)]
+    pub include_objective_breakdown: bool,
+
+    /// Milliseconds the solve may take before it is abandoned; unbounded when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+
+    /// Property to minimize or maximize over the selected instances, on top of any
+    /// per-instance objective weights
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub objective: Option<PropertyObjective>,
+
+    /// Boolean expressions checked against the solved target instance after solving; the
+    /// solver doesn't see them, and a solution where any of them is not true is reported as
+    /// incomplete
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraint_checks: Vec<Expr>,
+}
+
+/// Optimization target summed from a numeric property of the selected instances
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropertyObjective {
+    /// Property name; dots address fields inside object values (`dimensions.width`).
+    /// Instances without a numeric value there don't count.
+    pub property: String,
+
+    /// Whether the sum should be as small or as large as possible
+    pub direction: ObjectiveDirection,
+}
+
+/// Direction of a property objective
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectiveDirection {
+    Minimize,
+    Maximize,
 }
 
 /// Instance query request for instance-specific solve operations
@@ -247,7 +377,8 @@ pub struct InstanceQueryRequest {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SimpleInstanceQueryRequest {
     /// Map of property names to objective weights
-    /// Special key "derived_properties" is treated as a comma-separated list
+    /// Special key "derived_properties" is treated as a comma-separated list,
+    /// "objective_breakdown" as a boolean flag and "timeout_ms" as the solve time limit
     #[serde(flatten)]
     pub objectives: HashMap<String, serde_json::Value>,
 }
@@ -258,7 +389,7 @@ impl SimpleInstanceQueryRequest {
         self.objectives
             .iter()
             .filter_map(|(k, v)| {
-                if k == "derived_properties" {
+                if k == "derived_properties" || k == "timeout_ms" {
                     None
                 } else {
                     v.as_f64().map(|weight| (k.clone(), weight))
@@ -267,6 +398,19 @@ impl SimpleInstanceQueryRequest {
             .collect()
     }
 
+    /// Whether the special key "objective_breakdown" is set to true
+    pub fn wants_objective_breakdown(&self) -> bool {
+        self.objectives
+            .get("objective_breakdown")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// The special key "timeout_ms", if it is a non-negative integer
+    pub fn get_timeout_ms(&self) -> Option<u64> {
+        self.objectives.get("timeout_ms").and_then(|v| v.as_u64())
+    }
+
     /// Extract the derived properties list from the request
     pub fn get_derived_properties(&self) -> Option<Vec<String>> {
         self.objectives.get("derived_properties").and_then(|v| {
@@ -384,6 +528,22 @@ pub struct BatchInstanceQueryRequest {
     /// Whether to include detailed solve metadata in responses (default: false for performance)
     #[serde(default)]
     pub include_metadata: bool,
+
+    /// Whether to include a per-instance objective breakdown in each artifact
+    #[serde(default)]
+    pub include_objective_breakdown: bool,
+
+    /// Milliseconds the solve may take before it is abandoned; unbounded when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+
+    /// Property to minimize or maximize in every objective set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub property_objective: Option<PropertyObjective>,
+
+    /// Boolean expressions every solved configuration is checked against after solving
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraint_checks: Vec<Expr>,
 }
 
 /// A single set of objectives for solving
@@ -419,11 +579,19 @@ pub struct ConfigurationResult {
     /// Whether this configuration solved successfully
     pub success: bool,
 
+    /// False when the solution fails one of the request's constraint checks
+    #[serde(default = "complete_by_default")]
+    pub complete: bool,
+
     /// Error message if solution failed
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
 
+fn complete_by_default() -> bool {
+    true
+}
+
 /// Metadata for batch query operations
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BatchQueryMetadata {
@@ -451,6 +619,127 @@ pub struct BatchQueryMetadata {
     pub commit_hash: Option<String>,
 }
 
+/// Request for solving many instances of one branch in a single call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSolveRequest {
+    /// Instances to solve, each with its own objective
+    pub items: Vec<BatchSolveItem>,
+
+    /// Resolution policies for all items
+    #[serde(default)]
+    pub policies: ResolutionPolicies,
+
+    /// Optional user metadata attached to every artifact
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_metadata: Option<ArtifactUserMetadata>,
+
+    /// Optional context metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_metadata: Option<ResolutionContextMetadata>,
+
+    /// Maximum number of items solved in parallel (defaults to 4)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+
+    /// Whether to include a per-instance objective breakdown in each artifact
+    #[serde(default)]
+    pub include_objective_breakdown: bool,
+
+    /// Milliseconds each item's solve may take before it is abandoned; unbounded when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+
+    /// Property to minimize or maximize for every item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub property_objective: Option<PropertyObjective>,
+
+    /// Boolean expressions every item's solved configuration is checked against after solving
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraint_checks: Vec<Expr>,
+}
+
+/// A single instance + objective to solve as part of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSolveItem {
+    /// Caller-chosen identifier echoed back in the result (defaults to the instance ID)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Instance to solve
+    pub instance_id: Id,
+
+    /// Map of instance ID to objective weight
+    #[serde(default)]
+    pub objective: HashMap<String, f64>,
+
+    /// Optional list of derived property names to include in the artifact
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub derived_properties: Option<Vec<String>>,
+}
+
+/// Response for a batch solve, with one result per requested item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSolveResponse {
+    /// Results in the same order as the request items
+    pub results: Vec<BatchSolveItemResult>,
+
+    /// Overall batch metadata
+    pub batch_metadata: BatchSolveMetadata,
+}
+
+/// Outcome of solving one batch item; failures don't fail the batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSolveItemResult {
+    /// The item ID (or instance ID when the item had none)
+    pub id: String,
+
+    /// Instance that was solved
+    pub instance_id: Id,
+
+    /// Whether this item solved successfully
+    pub success: bool,
+
+    /// The resulting configuration artifact, present on success; on timeout, a partial
+    /// artifact with the elapsed time and the phases that ran
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<ConfigurationArtifact>,
+
+    /// Structured summary of `artifact`, present whenever the artifact is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SolveSummary>,
+
+    /// Error message if the item failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Metadata for batch solve operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSolveMetadata {
+    /// Total time for the entire batch operation (milliseconds)
+    pub total_time_ms: u64,
+
+    /// Number of items processed
+    pub items_processed: usize,
+
+    /// Number of successful solutions
+    pub successful_solutions: usize,
+
+    /// Number of failed solutions
+    pub failed_solutions: usize,
+
+    /// Number of items solved in parallel
+    pub max_concurrency: usize,
+
+    /// Database and branch context
+    pub database_id: String,
+    pub branch_id: String,
+
+    /// Commit the batch was solved against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_hash: Option<String>,
+}
+
 impl ConfigurationArtifact {
     /// Create a new configuration artifact
     pub fn new(
@@ -480,6 +769,9 @@ impl ConfigurationArtifact {
             },
             user_metadata,
             derived_properties: HashMap::new(),
+            objective_breakdown: None,
+            objective_value: None,
+            constraint_violations: Vec::new(),
         }
     }
 
@@ -529,6 +821,37 @@ impl ConfigurationArtifact {
         )
     }
 
+    /// Get a structured summary of the solve operation
+    pub fn structured_summary(&self) -> SolveSummary {
+        let metadata = &self.solve_metadata;
+        SolveSummary {
+            summary: self.solve_summary(),
+            feasible: !metadata
+                .issues
+                .iter()
+                .any(|issue| issue.severity == IssueSeverity::Critical),
+            complete: self.is_complete_configuration() && self.constraint_violations.is_empty(),
+            instance_count: self.instance_count(),
+            objective_value: self.objective_breakdown.as_ref().map(|b| b.total),
+            solve_time_ms: metadata.total_time_ms,
+            phases: metadata
+                .pipeline_phases
+                .iter()
+                .map(|phase| SolvePhaseTiming {
+                    name: phase.name.clone(),
+                    duration_ms: phase.duration_ms,
+                })
+                .collect(),
+            has_issues: self.has_issues(),
+            warnings: metadata
+                .issues
+                .iter()
+                .filter(|issue| issue.severity != IssueSeverity::Info)
+                .cloned()
+                .collect(),
+        }
+    }
+
     /// Check if the solve had any issues
     pub fn has_issues(&self) -> bool {
         !self.solve_metadata.issues.is_empty()
@@ -612,6 +935,10 @@ mod tests {
             local_domains: Vec::new(),
             created_by: "test-user".to_string(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
             updated_by: "test-user".to_string(),
         }
     }
@@ -725,6 +1052,62 @@ mod tests {
         assert!(summary.contains("250ms"));
     }
 
+    #[test]
+    fn test_structured_summary() {
+        let resolution_context = ResolutionContext {
+            database_id: "db1".to_string(),
+            branch_id: "branch1".to_string(),
+            commit_hash: None,
+            policies: ResolutionPolicies::default(),
+            metadata: None,
+        };
+
+        let mut artifact =
+            ConfigurationArtifact::new("artifact1".to_string(), resolution_context, None);
+        artifact.add_instance(create_test_instance("queried1", "Product"));
+        artifact.solve_metadata.total_time_ms = 250;
+        artifact.solve_metadata.pipeline_phases = vec![PipelinePhase {
+            name: "solve".to_string(),
+            duration_ms: 200,
+            details: None,
+        }];
+        artifact.solve_metadata.issues = vec![
+            SolveIssue {
+                severity: IssueSeverity::Info,
+                message: "cache hit".to_string(),
+                component: None,
+                context: None,
+            },
+            SolveIssue {
+                severity: IssueSeverity::Warning,
+                message: "empty pool".to_string(),
+                component: Some("queried1".to_string()),
+                context: None,
+            },
+        ];
+        artifact.objective_breakdown = Some(ObjectiveBreakdown {
+            total: 42.5,
+            contributions: Vec::new(),
+        });
+
+        let summary = artifact.structured_summary();
+        assert_eq!(summary.summary, artifact.solve_summary());
+        assert!(summary.feasible);
+        assert!(summary.complete);
+        assert_eq!(summary.instance_count, 1);
+        assert_eq!(summary.objective_value, Some(42.5));
+        assert_eq!(summary.solve_time_ms, 250);
+        assert_eq!(summary.phases[0].name, "solve");
+        assert_eq!(summary.phases[0].duration_ms, 200);
+        assert!(summary.has_issues);
+        assert_eq!(summary.warnings.len(), 1);
+        assert_eq!(summary.warnings[0].message, "empty pool");
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["warnings"][0]["severity"], "warning");
+        assert_eq!(json["phases"][0]["duration_ms"], 200);
+    }
+
     #[test]
     fn test_ilp_statistics() {
         let resolution_context = ResolutionContext {
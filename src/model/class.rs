@@ -1,6 +1,9 @@
-use crate::model::{DerivedDef, Domain, Id, PropertyDef, RelationshipDef};
+use crate::model::{
+    DerivedDef, Domain, Expr, Id, PropertyDef, PropertyValue, RelationshipDef, TypedValue,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Base operation types for class constraints
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -84,6 +87,11 @@ pub struct ClassDef {
     #[serde(default)]
     pub base: Base,
 
+    /// Business rules every instance must satisfy, e.g. `discount_price < price`.
+    /// Each expression must evaluate to a boolean.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<Expr>,
+
     /// Audit fields for tracking who created/modified this class
     #[serde(default = "default_user")]
     pub created_by: String,
@@ -127,6 +135,10 @@ pub struct NewClassDef {
     /// Defaults to {"op": "all", "val": null} for backward compatibility
     #[serde(default)]
     pub base: Base,
+
+    /// Boolean business-rule expressions checked during validation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<Expr>,
 }
 
 /// Class definition update model for PATCH operations
@@ -160,6 +172,10 @@ pub struct ClassDefUpdate {
     /// Base constraint configuration for this class
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base: Option<Base>,
+
+    /// Business-rule constraints (replaces entire constraint list)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<Vec<Expr>>,
 }
 
 impl Default for ClassDef {
@@ -176,6 +192,7 @@ impl Default for ClassDef {
             description: None,
             domain_constraint: Domain::binary(),
             base: Base::default(),
+            constraints: Vec::new(),
             created_by: system_user.clone(),
             created_at: now,
             updated_by: system_user,
@@ -197,6 +214,7 @@ impl ClassDef {
             description: new_class.description,
             domain_constraint: new_class.domain_constraint,
             base: new_class.base,
+            constraints: new_class.constraints,
             created_by: user_id.clone(),
             created_at: now,
             updated_by: user_id,
@@ -227,9 +245,92 @@ impl ClassDef {
         if let Some(base) = update.base {
             self.base = base;
         }
+        if let Some(constraints) = update.constraints {
+            self.constraints = constraints;
+        }
         
         // Update audit fields (preserve created_by/created_at)
         self.updated_by = user_id;
         self.updated_at = Utc::now();
     }
+
+    /// Fill in schema default values for any properties missing from `properties`
+    /// Properties are matched by ID or name; defaults are inserted under the property name
+    pub fn apply_property_defaults(&self, properties: &mut HashMap<String, PropertyValue>) {
+        for prop_def in &self.properties {
+            if properties.contains_key(&prop_def.id) || properties.contains_key(&prop_def.name) {
+                continue;
+            }
+            if let Some(default_value) = &prop_def.value {
+                if default_value.is_null() {
+                    continue;
+                }
+                properties.insert(
+                    prop_def.name.clone(),
+                    PropertyValue::Literal(TypedValue {
+                        value: default_value.clone(),
+                        data_type: prop_def.data_type.clone(),
+                    }),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::DataType;
+
+    fn property(id: &str, name: &str, value: Option<serde_json::Value>) -> PropertyDef {
+        PropertyDef {
+            id: id.to_string(),
+            name: name.to_string(),
+            data_type: DataType::Number,
+            required: None,
+            value,
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_property_defaults_fills_only_missing_properties() {
+        let class_def = ClassDef {
+            properties: vec![
+                property("prop-price", "price", Some(serde_json::json!(100))),
+                property("prop-weight", "weight", Some(serde_json::json!(5))),
+                property("prop-stock", "stock", Some(serde_json::json!(1))),
+                property("prop-width", "width", None),
+            ],
+            ..ClassDef::default()
+        };
+
+        let mut properties = HashMap::new();
+        properties.insert(
+            "price".to_string(),
+            PropertyValue::Literal(TypedValue::number(250)),
+        );
+        properties.insert(
+            "prop-stock".to_string(),
+            PropertyValue::Literal(TypedValue::number(0)),
+        );
+
+        class_def.apply_property_defaults(&mut properties);
+
+        assert_eq!(
+            properties.get("price"),
+            Some(&PropertyValue::Literal(TypedValue::number(250)))
+        );
+        assert_eq!(
+            properties.get("weight"),
+            Some(&PropertyValue::Literal(TypedValue::number(5)))
+        );
+        assert!(!properties.contains_key("stock"));
+        assert!(!properties.contains_key("width"));
+        assert_eq!(properties.len(), 3);
+    }
 }
@@ -28,6 +28,18 @@ pub struct Commit {
     /// Metadata for quick access without decompressing
     pub schema_classes_count: i32,
     pub instances_count: i32,
+
+    /// Branch merged in; set on merge commits only, like the three fields below
+    #[serde(default)]
+    pub source_branch: Option<String>,
+    /// Head of the source branch when the merge started
+    #[serde(default)]
+    pub source_commit_hash: Option<String>,
+    /// Head of the target branch when the merge started
+    #[serde(default)]
+    pub target_commit_hash: Option<String>,
+    #[serde(default)]
+    pub merge_strategy: Option<crate::model::merge::MergeStrategy>,
 }
 
 /// A working commit represents mutable changes being made to a branch
@@ -48,6 +60,11 @@ pub struct WorkingCommit {
     pub created_at: String, // ISO 8601 string
     /// When the working commit was last updated
     pub updated_at: String, // ISO 8601 string
+    /// Incremented every time the working commit is saved. It is part of the `ETag` clients
+    /// send back in `If-Match`, so an edit based on a stale copy is refused instead of
+    /// overwriting someone else's.
+    #[serde(default)]
+    pub version: u64,
 
     /// Current mutable schema (JSON format)
     pub schema_data: Schema,
@@ -60,6 +77,10 @@ pub struct WorkingCommit {
     /// Merge state if this is a merge working commit
     #[serde(skip_serializing_if = "Option::is_none")]
     pub merge_state: Option<crate::model::merge::MergeState>,
+    /// What this working commit merges; outlives `merge_state`, which is cleared once
+    /// conflicts are resolved, and is recorded on the commit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge_metadata: Option<crate::model::merge::MergeMetadata>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -103,6 +124,10 @@ pub struct NewCommit {
     pub message: String,
     /// Author of the commit
     pub author: Option<String>,
+    /// Parent to commit on instead of the working commit's `based_on_hash`, as when squashing
+    /// into the branch head; the working commit itself is left unchanged
+    #[serde(default)]
+    pub based_on_hash: Option<String>,
 }
 
 /// Working commit creation request
@@ -112,6 +137,50 @@ pub struct NewWorkingCommit {
     pub author: Option<String>,
 }
 
+/// Position in a commit listing ordered newest first (`created_at`, then `hash`, descending).
+/// Encodes the ordering key rather than an offset, so pages stay stable while new commits land.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitCursor {
+    pub created_at: String,
+    pub hash: String,
+}
+
+/// One page of a commit listing
+#[derive(Debug, Clone)]
+pub struct CommitPage {
+    pub commits: Vec<Commit>,
+    /// Cursor pointing after the last commit of this page, `None` on the last page
+    pub next_cursor: Option<CommitCursor>,
+}
+
+impl CommitCursor {
+    /// Cursor that resumes the listing right after `commit`
+    pub fn after(commit: &Commit) -> Self {
+        Self {
+            created_at: commit.created_at.clone(),
+            hash: commit.hash.clone(),
+        }
+    }
+
+    /// Opaque URL-safe representation: base64 of the JSON tuple `[created_at, hash]`
+    pub fn encode(&self) -> String {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+        let tuple = serde_json::json!([self.created_at, self.hash]).to_string();
+        URL_SAFE_NO_PAD.encode(tuple)
+    }
+
+    /// Parse a cursor produced by `encode`
+    pub fn decode(cursor: &str) -> Result<Self, String> {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+        let invalid = || format!("Invalid cursor '{}'", cursor);
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let (created_at, hash): (String, String) =
+            serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        chrono::DateTime::parse_from_rfc3339(&created_at).map_err(|_| invalid())?;
+        Ok(Self { created_at, hash })
+    }
+}
+
 impl Commit {
     /// Create a new commit with the given data
     pub fn new(
@@ -142,6 +211,10 @@ impl Commit {
             data_size: serialized.len() as i64,
             schema_classes_count: commit_data.schema.classes.len() as i32,
             instances_count: commit_data.instances.len() as i32,
+            source_branch: None,
+            source_commit_hash: None,
+            target_commit_hash: None,
+            merge_strategy: None,
         }
     }
 
@@ -200,6 +273,13 @@ impl Commit {
         }
     }
 
+    /// Replace the stored data without touching the hash (e.g. after decrypting on read)
+    pub fn replace_data(&mut self, commit_data: &CommitData) {
+        let serialized = serde_json::to_string(commit_data).unwrap();
+        self.data = Self::compress_data(serialized.as_bytes());
+        self.data_size = serialized.len() as i64;
+    }
+
     /// Decompress and deserialize the commit data
     pub fn get_data(&self) -> Result<CommitData, Box<dyn std::error::Error>> {
         let decompressed = Self::decompress_data(&self.data)?;
@@ -256,19 +336,37 @@ impl WorkingCommit {
             author,
             created_at: now.clone(),
             updated_at: now,
+            version: 0,
             schema_data: commit_data.schema,
             instances_data: commit_data.instances,
             status: WorkingCommitStatus::Active,
             merge_state: None,
+            merge_metadata: None,
         })
     }
 
-    /// Convert this working commit into an immutable commit
-    pub fn to_commit(&self, message: String) -> Commit {
-        let commit_data = CommitData {
+    /// The instances that are not soft-deleted: the ones a commit keeps, and the only ones
+    /// relationships, validation and queries see
+    pub fn live_instances(&self) -> Vec<Instance> {
+        self.instances_data
+            .iter()
+            .filter(|instance| !instance.is_deleted())
+            .cloned()
+            .collect()
+    }
+
+    /// Convert this working commit into an immutable commit; soft-deleted instances are
+    /// left out of the snapshot and materialized derived properties are computed into it,
+    /// which fails when one of them can't be evaluated
+    pub fn to_commit(&self, message: String) -> anyhow::Result<Commit> {
+        let mut commit_data = CommitData {
             schema: self.schema_data.clone(),
-            instances: self.instances_data.clone(),
+            instances: self.live_instances(),
         };
+        crate::logic::materialize_derived_properties(
+            &commit_data.schema,
+            &mut commit_data.instances,
+        )?;
 
         // For initial commits, parent_hash should be None
         let parent_hash = if self.based_on_hash.is_empty() {
@@ -277,19 +375,52 @@ impl WorkingCommit {
             Some(self.based_on_hash.clone())
         };
 
-        Commit::new(
+        let mut commit = Commit::new(
             self.database_id.clone(),
             parent_hash,
             commit_data,
             self.author.clone(),
             Some(message),
-        )
+        );
+        if let Some(merge) = &self.merge_metadata {
+            commit.source_branch = Some(merge.source_branch.clone());
+            commit.source_commit_hash = Some(merge.source_commit_hash.clone());
+            commit.target_commit_hash = Some(merge.target_commit_hash.clone());
+            commit.merge_strategy = Some(merge.strategy.clone());
+        }
+        Ok(commit)
     }
 
     /// Update the updated_at timestamp
     pub fn touch(&mut self) {
         self.updated_at = chrono::Utc::now().to_rfc3339();
     }
+
+    /// Whether `user_id` authored this working commit or staged a class or instance edit in it
+    pub fn is_edited_by(&self, user_id: &str) -> bool {
+        if self.author.as_deref() == Some(user_id) {
+            return true;
+        }
+        let Ok(created_at) = chrono::DateTime::parse_from_rfc3339(&self.created_at) else {
+            return false;
+        };
+        let created_at = created_at.with_timezone(&chrono::Utc);
+
+        // Data copied from the base commit keeps its older audit timestamps
+        self.instances_data
+            .iter()
+            .any(|i| i.updated_by == user_id && i.updated_at >= created_at)
+            || self
+                .schema_data
+                .classes
+                .iter()
+                .any(|c| c.updated_by == user_id && c.updated_at >= created_at)
+    }
+
+    /// Whether the working commit can be picked up again (anything not abandoned)
+    pub fn is_recoverable(&self) -> bool {
+        self.status != WorkingCommitStatus::Abandoned
+    }
 }
 
 /// Working commit changes - shows only what has been added, modified, or deleted
@@ -422,6 +553,79 @@ pub struct GranularChanges {
     pub class_changes: Vec<GranularClassChange>,
 }
 
+/// How the two commits of a `CommitChanges` relate in history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffDirection {
+    /// Both hashes name the same commit
+    Same,
+    /// `from` is an ancestor of `to`: the changes replay history
+    Forward,
+    /// `to` is an ancestor of `from`: the changes undo history
+    Reverse,
+    /// Neither is an ancestor of the other, e.g. commits on diverged branches
+    Diverged,
+}
+
+/// Changes between two commits: what turns the `from` snapshot into the `to` snapshot
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommitChanges {
+    pub from_hash: String,
+    pub to_hash: String,
+    pub direction: DiffDirection,
+    /// Changes to schema classes
+    pub schema_changes: SchemaChanges,
+    /// Changes to instances
+    pub instance_changes: InstanceChanges,
+    /// Field-level property and relationship changes per added, modified or deleted instance
+    pub granular_changes: GranularChanges,
+}
+
+impl CommitChanges {
+    /// Diff two commit snapshots by class and instance ID. Entries are sorted by ID so the
+    /// result does not depend on map iteration order.
+    pub fn between(
+        from_hash: String,
+        to_hash: String,
+        direction: DiffDirection,
+        from: &CommitData,
+        to: &CommitData,
+    ) -> Self {
+        let mut schema_changes = WorkingCommit::diff_schemas(&from.schema, &to.schema);
+        schema_changes.added.sort_by(|a, b| a.id.cmp(&b.id));
+        schema_changes.modified.sort_by(|a, b| a.id.cmp(&b.id));
+        schema_changes.deleted.sort();
+
+        let mut instance_changes = WorkingCommit::diff_instances(&from.instances, &to.instances);
+        instance_changes.added.sort_by(|a, b| a.id.cmp(&b.id));
+        instance_changes.modified.sort_by(|a, b| a.id.cmp(&b.id));
+        instance_changes.deleted.sort();
+
+        let mut granular_changes =
+            WorkingCommit::diff_instances_granular(&from.instances, &to.instances);
+        granular_changes
+            .instance_changes
+            .sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+        for change in &mut granular_changes.instance_changes {
+            change
+                .property_changes
+                .sort_by(|a, b| a.property_id.cmp(&b.property_id));
+            change
+                .relationship_changes
+                .sort_by(|a, b| a.relationship_id.cmp(&b.relationship_id));
+        }
+
+        Self {
+            from_hash,
+            to_hash,
+            direction,
+            schema_changes,
+            instance_changes,
+            granular_changes,
+        }
+    }
+}
+
 impl WorkingCommit {
     /// Generate a diff-style view showing only changes compared to the base commit
     pub async fn to_changes<S>(
@@ -822,6 +1026,25 @@ mod tests {
     use crate::model::{DataType, PropertyValue, TypedValue};
     use std::collections::HashMap;
 
+    #[test]
+    fn test_commit_cursor_round_trip() {
+        let cursor = CommitCursor {
+            created_at: "2024-05-01T12:30:00.123456+00:00".to_string(),
+            hash: "abc123".to_string(),
+        };
+        let encoded = cursor.encode();
+        assert!(!encoded.contains('/') && !encoded.contains('+') && !encoded.contains('='));
+        assert_eq!(CommitCursor::decode(&encoded).unwrap(), cursor);
+
+        assert!(CommitCursor::decode("not a cursor").is_err());
+        // Well-formed base64 whose timestamp does not parse is rejected too
+        let bogus = CommitCursor {
+            created_at: "yesterday".to_string(),
+            hash: "abc123".to_string(),
+        };
+        assert!(CommitCursor::decode(&bogus.encode()).is_err());
+    }
+
     #[tokio::test]
     async fn test_granular_change_tracking() {
         // Create a base commit with one instance
@@ -849,6 +1072,10 @@ mod tests {
             relationships: HashMap::new(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
             local_domains: Vec::new(),
             created_by: "test-user".to_string(),
             updated_by: "test-user".to_string(),
@@ -879,6 +1106,10 @@ mod tests {
             relationships: HashMap::new(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
             local_domains: Vec::new(),
             created_by: "test-user".to_string(),
             updated_by: "test-user".to_string(),
@@ -937,6 +1168,10 @@ mod tests {
             relationships: HashMap::new(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
             local_domains: Vec::new(),
             created_by: "test-user".to_string(),
             updated_by: "test-user".to_string(),
@@ -968,6 +1203,10 @@ mod tests {
             relationships: HashMap::new(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: Default::default(),
             local_domains: Vec::new(),
             created_by: "test-user".to_string(),
             updated_by: "test-user".to_string(),
@@ -1005,4 +1244,37 @@ mod tests {
         assert!(added_change.old_value.is_none());
         assert!(added_change.new_value.is_some());
     }
+
+    #[test]
+    fn test_is_edited_by_ignores_edits_from_the_base_commit() {
+        let base_instance = Instance {
+            id: "color-red".to_string(),
+            class_id: "class-color".to_string(),
+            updated_by: "alice".to_string(),
+            updated_at: chrono::Utc::now() - chrono::Duration::hours(1),
+            ..Instance::default()
+        };
+        let base = Commit::new(
+            "db".to_string(),
+            None,
+            CommitData {
+                schema: Schema {
+                    id: "schema".to_string(),
+                    classes: vec![],
+                    description: None,
+                },
+                instances: vec![base_instance],
+            },
+            None,
+            None,
+        );
+        let mut wc = WorkingCommit::new("db".to_string(), Some("main".into()), &base, None)
+            .unwrap();
+        assert!(!wc.is_edited_by("alice"));
+
+        wc.instances_data[0].updated_by = "bob".to_string();
+        wc.instances_data[0].updated_at = chrono::Utc::now();
+        assert!(wc.is_edited_by("bob"));
+        assert!(!wc.is_edited_by("alice"));
+    }
 }
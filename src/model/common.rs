@@ -14,6 +14,8 @@ pub enum DataType {
     Array,
     #[serde(rename = "string-list")]
     StringList,
+    /// Free-form structured value; any JSON is accepted and filters can traverse into it
+    Json,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -124,3 +126,35 @@ pub fn generate_configuration_id(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TypedValue;
+    use serde_json::json;
+
+    #[test]
+    fn test_data_type_serialization_round_trip() {
+        for (data_type, name) in [
+            (DataType::String, "string"),
+            (DataType::Number, "number"),
+            (DataType::StringList, "string-list"),
+            (DataType::Json, "json"),
+        ] {
+            assert_eq!(serde_json::to_value(&data_type).unwrap(), json!(name));
+            let parsed: DataType = serde_json::from_value(json!(name)).unwrap();
+            assert_eq!(parsed, data_type);
+        }
+    }
+
+    #[test]
+    fn test_json_typed_value_round_trip() {
+        let typed = TypedValue {
+            value: json!({"vendor": {"sku": "AB-12", "dims": [1.5, 2, null]}, "active": true}),
+            data_type: DataType::Json,
+        };
+        let serialized = serde_json::to_string(&typed).unwrap();
+        let parsed: TypedValue = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(parsed, typed);
+    }
+}
@@ -8,6 +8,68 @@ pub struct Database {
     pub description: Option<String>,
     pub created_at: String,            // ISO 8601 timestamp
     pub default_branch_name: String, // Name of the main/default branch
+    /// Convention that commit messages must follow; no policy means any message is accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_message_policy: Option<CommitMessagePolicy>,
+}
+
+/// Commit message convention enforced when committing a working commit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct CommitMessagePolicy {
+    /// Regex the message must match, e.g. `^(feat|fix|chore)(\(.+\))?: .+`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The message must start with one of these prefixes, e.g. `["feat:", "fix:"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_prefixes: Vec<String>,
+}
+
+impl CommitMessagePolicy {
+    /// A policy without pattern or prefixes accepts every message
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_none() && self.required_prefixes.is_empty()
+    }
+
+    /// Check that the configured pattern compiles
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(pattern) = &self.pattern {
+            regex::Regex::new(pattern)
+                .map_err(|e| format!("Invalid commit message pattern '{}': {}", pattern, e))?;
+        }
+        Ok(())
+    }
+
+    /// Check a commit message, returning a description of the expected format on failure
+    pub fn check(&self, message: &str) -> Result<(), String> {
+        if !self.required_prefixes.is_empty()
+            && !self
+                .required_prefixes
+                .iter()
+                .any(|prefix| message.starts_with(prefix.as_str()))
+        {
+            return Err(format!(
+                "Commit message must start with one of: {}",
+                self.required_prefixes
+                    .iter()
+                    .map(|p| format!("'{}'", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        if let Some(pattern) = &self.pattern {
+            let regex = regex::Regex::new(pattern)
+                .map_err(|e| format!("Invalid commit message pattern '{}': {}", pattern, e))?;
+            if !regex.is_match(message) {
+                return Err(format!(
+                    "Commit message must match pattern '{}'",
+                    pattern
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Database {
@@ -18,6 +80,7 @@ impl Database {
             description,
             created_at: chrono::Utc::now().to_rfc3339(),
             default_branch_name: "main".to_string(), // Default to main branch
+            commit_message_policy: None,
         }
     }
 
@@ -28,6 +91,7 @@ impl Database {
             description,
             created_at: chrono::Utc::now().to_rfc3339(),
             default_branch_name: "main".to_string(), // Default to main branch
+            commit_message_policy: None,
         }
     }
 }
@@ -134,14 +198,46 @@ pub struct NewDatabase {
     pub id: Id,
     pub name: String,
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_message_policy: Option<CommitMessagePolicy>,
 }
 
 impl NewDatabase {
     /// Convert to a full Database with server-generated fields
     pub fn into_database(self) -> Database {
-        Database::new_with_id(self.id, self.name, self.description)
+        let mut database = Database::new_with_id(self.id, self.name, self.description);
+        database.commit_message_policy = self.commit_message_policy.filter(|p| !p.is_empty());
+        database
     }
 }
 
 // Keep Version as an alias for backward compatibility during migration
 pub type Version = Branch;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_message_policy_prefixes_and_pattern() {
+        let policy = CommitMessagePolicy {
+            pattern: Some(r"^\w+: .{3,}$".to_string()),
+            required_prefixes: vec!["feat:".to_string(), "fix:".to_string()],
+        };
+        assert!(policy.validate().is_ok());
+        assert!(policy.check("feat: add wheels").is_ok());
+
+        let err = policy.check("chore: tidy").unwrap_err();
+        assert!(err.contains("'feat:', 'fix:'"));
+
+        let err = policy.check("fix: x").unwrap_err();
+        assert!(err.contains(r"^\w+: .{3,}$"));
+
+        assert!(CommitMessagePolicy::default().check("anything").is_ok());
+        let invalid = CommitMessagePolicy {
+            pattern: Some("(".to_string()),
+            required_prefixes: Vec::new(),
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
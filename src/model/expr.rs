@@ -20,6 +20,37 @@ pub enum Expr {
         left: Box<Expr>,
         right: Box<Expr>,
     },
+    Abs {
+        expr: Box<Expr>,
+    },
+    Pow {
+        base: Box<Expr>,
+        exp: Box<Expr>,
+    },
+    Eq {
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Ne {
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Lt {
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Lte {
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Gt {
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Gte {
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
     LitNumber {
         value: f64,
     },
@@ -50,6 +81,20 @@ pub enum Expr {
         then: Box<Expr>,
         r#else: Box<Expr>,
     },
+    /// Expression-level branch: `if_` must evaluate to a boolean and only the chosen branch is
+    /// evaluated
+    Cond {
+        #[serde(rename = "if")]
+        if_: Box<Expr>,
+        then: Box<Expr>,
+        #[serde(rename = "else")]
+        else_: Box<Expr>,
+    },
+    /// Stringify each part and join them; null parts render as empty strings
+    Concat {
+        parts: Vec<Expr>,
+        separator: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
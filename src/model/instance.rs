@@ -1,7 +1,7 @@
 use crate::model::{DataType, Domain, Id, RelationshipSelection, RuleSet};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Default user for legacy data migration
 fn default_user() -> String {
@@ -44,6 +44,20 @@ pub struct Instance {
     pub updated_by: String,
     #[serde(default = "default_timestamp")]
     pub updated_at: DateTime<Utc>,
+
+    /// Tombstone set by a soft delete; tombstoned instances are dropped when committed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_by: Option<String>,
+
+    /// Values of the class's materialized derived properties, computed when the instance is
+    /// committed; kept apart from `properties` so they never count as inputs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materialized: BTreeMap<String, TypedValue>,
+    /// Fingerprint of the inputs the values in `materialized` were computed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub materialized_inputs: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -145,7 +159,9 @@ pub struct NewInstance {
 }
 
 /// Instance update model for PATCH operations
-/// All fields are optional for partial updates
+/// All fields are optional for partial updates. Properties and relationships are merged
+/// over the existing ones; a JSON null value, or a name in `removed_properties` /
+/// `removed_relationships`, removes the entry.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InstanceUpdate {
     #[serde(rename = "class")]
@@ -154,9 +170,13 @@ pub struct InstanceUpdate {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub domain: Option<Domain>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub properties: Option<HashMap<String, PropertyValue>>,
+    pub properties: Option<HashMap<String, Option<PropertyValue>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub relationships: Option<HashMap<String, RelationshipSelection>>,
+    pub relationships: Option<HashMap<String, Option<RelationshipSelection>>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_properties: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_relationships: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -168,8 +188,13 @@ pub struct ExpandedInstance {
     pub domain: Option<Domain>,
     pub properties: HashMap<String, serde_json::Value>,
     pub relationships: HashMap<String, ResolvedRelationship>,
+    /// Related instances added with `depth`, each holding its own related instances
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub included: Vec<ExpandedInstance>,
+    pub included: Vec<IncludedInstance>,
+
+    /// Instances whose relationships point at this one, populated with `expand=inverse`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub referenced_by: Vec<BackReference>,
 
     /// Local domain overrides for variables within this instance
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -184,6 +209,12 @@ pub struct ExpandedInstance {
     pub updated_by: String,
     #[serde(default = "default_timestamp")]
     pub updated_at: DateTime<Utc>,
+
+    /// Tombstone of the instance, present with `include_deleted=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_by: Option<String>,
 }
 
 impl ExpandedInstance {
@@ -225,10 +256,52 @@ impl ExpandedInstance {
             created_at: self.created_at,
             updated_by: self.updated_by.clone(),
             updated_at: self.updated_at,
+            deleted_at: self.deleted_at,
+            deleted_by: self.deleted_by.clone(),
+            materialized_inputs: None,
+            materialized: BTreeMap::new(),
         }
     }
 }
 
+/// An entry of `ExpandedInstance::included`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IncludedInstance {
+    Expanded(Box<ExpandedInstance>),
+    /// An instance already expanded higher up the same path, left unexpanded to end a cycle
+    Reference(InstanceReference),
+}
+
+impl IncludedInstance {
+    pub fn id(&self) -> &Id {
+        match self {
+            IncludedInstance::Expanded(expanded) => &expanded.id,
+            IncludedInstance::Reference(reference) => &reference.id,
+        }
+    }
+}
+
+/// Stub for an instance that closes a relationship cycle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceReference {
+    pub id: Id,
+    #[serde(rename = "class")]
+    pub class_id: Id,
+    /// Always `true`, so clients can tell the stub from an expanded instance
+    pub cycle: bool,
+}
+
+/// An instance that selects another through one of its relationships
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackReference {
+    pub instance_id: Id,
+    #[serde(rename = "class")]
+    pub class_id: Id,
+    /// Relationship key on the referring instance
+    pub relationship: String,
+}
+
 /// Enhanced relationship resolution with transparency about how IDs were resolved
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ResolvedRelationship {
@@ -241,6 +314,39 @@ pub struct ResolvedRelationship {
     /// Additional details about the resolution process
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resolution_details: Option<ResolutionDetails>,
+
+    /// Error message when this relationship could not be resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Number of resolved instances, set when the relationship was expanded in count mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+}
+
+impl ResolvedRelationship {
+    /// Build a failed resolution entry so one bad relationship doesn't fail the whole expansion
+    pub fn failed(error: String, original_definition: Option<serde_json::Value>) -> Self {
+        Self {
+            materialized_ids: Vec::new(),
+            resolution_method: ResolutionMethod::ResolutionFailed,
+            resolution_details: Some(ResolutionDetails {
+                original_definition,
+                resolved_from: None,
+                filter_description: None,
+                total_pool_size: None,
+                filtered_out_count: None,
+                resolution_time_us: None,
+                notes: Vec::new(),
+            }),
+            error: Some(error),
+            count: None,
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.resolution_method == ResolutionMethod::ResolutionFailed
+    }
 }
 
 /// Method used to resolve the relationship
@@ -261,6 +367,10 @@ pub enum ResolutionMethod {
     SchemaDefaultResolved,
     /// Resolution failed or returned empty
     EmptyResolution,
+    /// Resolution raised an error (e.g. invalid filter or missing target class)
+    ResolutionFailed,
+    /// IDs were computed as back-references from the forward relationship of an `inverse_of`
+    InverseResolved,
 }
 
 /// Additional details about how the resolution was performed
@@ -310,6 +420,10 @@ impl NewInstance {
             created_at: now,
             updated_by: user_id,
             updated_at: now,
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: BTreeMap::new(),
         }
     }
 }
@@ -330,6 +444,10 @@ impl Default for Instance {
             created_at: now,
             updated_by: system_user,
             updated_at: now,
+            deleted_at: None,
+            deleted_by: None,
+            materialized_inputs: None,
+            materialized: BTreeMap::new(),
         }
     }
 }
@@ -346,11 +464,14 @@ impl Default for ExpandedInstance {
             properties: HashMap::new(),
             relationships: HashMap::new(),
             included: Vec::new(),
+            referenced_by: Vec::new(),
             local_domains: Vec::new(),
             created_by: system_user.clone(),
             created_at: now,
             updated_by: system_user,
             updated_at: now,
+            deleted_at: None,
+            deleted_by: None,
         }
     }
 }
@@ -365,14 +486,95 @@ impl Instance {
             self.domain = Some(domain);
         }
         if let Some(properties) = update.properties {
-            self.properties = properties;
+            self.merge_properties(properties);
         }
         if let Some(relationships) = update.relationships {
-            self.relationships = relationships;
+            self.merge_relationships(relationships);
+        }
+        for name in &update.removed_properties {
+            self.properties.remove(name);
+        }
+        for name in &update.removed_relationships {
+            self.relationships.remove(name);
         }
 
         // Update audit fields (preserve created_by/created_at)
         self.updated_by = user_id;
         self.updated_at = Utc::now();
     }
+
+    /// Tombstone the instance instead of removing it
+    pub fn mark_deleted(&mut self, user_id: String) {
+        self.deleted_at = Some(Utc::now());
+        self.deleted_by = Some(user_id);
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Merge properties over the existing ones; a `None` value removes the property
+    pub fn merge_properties(&mut self, properties: HashMap<String, Option<PropertyValue>>) {
+        for (name, value) in properties {
+            match value {
+                Some(value) => {
+                    self.properties.insert(name, value);
+                }
+                None => {
+                    self.properties.remove(&name);
+                }
+            }
+        }
+    }
+
+    /// Merge relationships over the existing ones; a `None` selection removes the relationship
+    pub fn merge_relationships(
+        &mut self,
+        relationships: HashMap<String, Option<RelationshipSelection>>,
+    ) {
+        for (name, selection) in relationships {
+            match selection {
+                Some(selection) => {
+                    self.relationships.insert(name, selection);
+                }
+                None => {
+                    self.relationships.remove(&name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_integer_property_round_trips_without_precision_loss() {
+        // 2^63 - 1 and 2^53 + 1 are both unrepresentable as f64
+        let json = r#"{
+            "id": "part-1",
+            "class": "class-part",
+            "properties": {
+                "code": 9223372036854775807,
+                "quantity": {"value": 9007199254740993, "type": "number"}
+            },
+            "relationships": {}
+        }"#;
+
+        let instance: Instance = serde_json::from_str(json).unwrap();
+        let round_tripped: Instance =
+            serde_json::from_str(&serde_json::to_string(&instance).unwrap()).unwrap();
+
+        for (key, expected) in [("code", "9223372036854775807"), ("quantity", "9007199254740993")] {
+            match &round_tripped.properties[key] {
+                PropertyValue::Literal(typed) => {
+                    assert_eq!(typed.data_type, DataType::Number);
+                    assert_eq!(typed.value.to_string(), expected);
+                }
+                other => panic!("expected literal for '{}', got {:?}", key, other),
+            }
+        }
+        assert_eq!(round_tripped, instance);
+    }
 }
@@ -109,6 +109,58 @@ pub struct MergeState {
     pub resolutions: HashMap<usize, ConflictResolution>,
     /// Whether this is a rebase operation
     pub is_rebase: bool,
+    /// Options the merge was started with (reused when re-merging after resolution)
+    #[serde(default)]
+    pub options: MergeOptions,
+}
+
+/// How concurrent edits to the same id-list relationship selection are combined
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationshipMergeStrategy {
+    /// Keep ids added by either side; conflict only when one side removed an id the other kept
+    #[default]
+    Union,
+    /// Keep only ids present on both sides
+    Intersection,
+    /// Treat any concurrent change to the relationship map as a conflict
+    Conflict,
+}
+
+/// What happens to conflicts the three-way merge can't combine
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Keep conflicts for manual resolution
+    #[default]
+    Recursive,
+    /// The target (left) side wins: per property and relationship for instances, per class for
+    /// the schema
+    Ours,
+    /// The source (right) side wins, with the same granularity as `Ours`
+    Theirs,
+}
+
+/// What a merge combined, kept on the merge working commit and copied onto the commit it
+/// becomes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeMetadata {
+    pub source_branch: String,
+    pub source_commit_hash: String,
+    pub target_commit_hash: String,
+    pub strategy: MergeStrategy,
+}
+
+/// Options controlling how a three-way merge combines changes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeOptions {
+    #[serde(default)]
+    pub relationship_merge: RelationshipMergeStrategy,
+    #[serde(default)]
+    pub strategy: MergeStrategy,
+    /// Build a merge working commit even when the target could be fast-forwarded
+    #[serde(default)]
+    pub no_ff: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -122,6 +174,9 @@ pub enum ConflictResolution {
     UseCustom(serde_json::Value),
     /// Skip this change entirely
     Skip,
+    /// Keep the ids of both sides in each id-list relationship selection both changed; only
+    /// valid when the conflict is limited to such selections
+    Union,
 }
 
 /// Fields to ignore during diff/merge operations
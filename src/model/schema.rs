@@ -82,6 +82,40 @@ pub struct PropertyDef {
     pub required: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<serde_json::Value>, // Default/constant value for this property
+    /// Store literal values of this property encrypted in commit data.
+    /// Encrypted properties cannot be used in instance filters.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+    /// Inclusive lower bound for `number` values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Inclusive upper bound for `number` values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Regex that string values must match in full
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// No two instances of the class may share a non-null value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unique: Option<bool>,
+}
+
+impl PropertyDef {
+    /// Whether `value` satisfies the `min`/`max` bounds; `None` for values that aren't numbers
+    pub fn in_range(&self, value: &serde_json::Value) -> Option<bool> {
+        let value = value.as_f64()?;
+        Some(self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max))
+    }
+
+    /// Human-readable description of the bounds, e.g. "between 1 and 100"
+    pub fn range_description(&self) -> Option<String> {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => Some(format!("between {} and {}", min, max)),
+            (Some(min), None) => Some(format!("at least {}", min)),
+            (None, Some(max)) => Some(format!("at most {}", max)),
+            (None, None) => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -98,6 +132,39 @@ pub struct RelationshipDef {
     /// Default pool for this relationship - what instances are considered by default
     #[serde(default = "default_pool_all")]
     pub default_pool: DefaultPool,
+    /// Relationship on the target class(es) that this one mirrors (matched by id or name).
+    /// Inverse relationships hold no data on instances; they are computed on expand by
+    /// scanning the forward relationship of every target instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inverse_of: Option<String>,
+    /// Minimum number of resolved targets an instance must have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<usize>,
+    /// Maximum number of resolved targets an instance may have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+}
+
+impl RelationshipDef {
+    /// Whether this relationship is a computed back-reference
+    pub fn is_inverse(&self) -> bool {
+        self.inverse_of.is_some()
+    }
+
+    /// Whether `count` resolved targets satisfy the `min`/`max` cardinality
+    pub fn allows_target_count(&self, count: usize) -> bool {
+        self.min.is_none_or(|min| count >= min) && self.max.is_none_or(|max| count <= max)
+    }
+
+    /// Human-readable description of the cardinality, e.g. "between 1 and 4 target(s)"
+    pub fn cardinality_description(&self) -> Option<String> {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => Some(format!("between {} and {} target(s)", min, max)),
+            (Some(min), None) => Some(format!("at least {} target(s)", min)),
+            (None, Some(max)) => Some(format!("at most {} target(s)", max)),
+            (None, None) => None,
+        }
+    }
 }
 
 fn default_selection_type() -> SelectionType {
@@ -159,9 +226,16 @@ pub struct DerivedDef {
     pub expr: Option<Expr>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fn_short: Option<FnShort>,
+    /// Compute the value when committing and store it with the instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub materialize: Option<bool>,
 }
 
 impl DerivedDef {
+    pub fn is_materialized(&self) -> bool {
+        self.materialize.unwrap_or(false)
+    }
+
     /// Get the expression for this derived property, expanding fn_short if needed
     pub fn get_expr(&self, class_def: &ClassDef) -> Option<Expr> {
         if let Some(expr) = &self.expr {
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 use crate::model::Id;
@@ -9,10 +10,12 @@ use crate::model::Id;
 pub enum TagType {
     /// Semantic version tags (v1.0.0, v2.1.3, etc.) - version info stored in metadata
     Version,
-    /// Release tags (prod-release, staging-deploy, etc.)
+    /// Release tags named by a semantic version (v1.0.0, 2.1.0-rc.1, etc.)
     Release,
     /// Milestone markers (feature-complete, beta-ready, etc.)
     Milestone,
+    /// Known-good states to come back to (before-migration, nightly, etc.)
+    Checkpoint,
     /// Custom user-defined tags
     Custom,
 }
@@ -23,6 +26,7 @@ impl std::fmt::Display for TagType {
             TagType::Version => write!(f, "version"),
             TagType::Release => write!(f, "release"),
             TagType::Milestone => write!(f, "milestone"),
+            TagType::Checkpoint => write!(f, "checkpoint"),
             TagType::Custom => write!(f, "custom"),
         }
     }
@@ -36,6 +40,7 @@ impl std::str::FromStr for TagType {
             "version" => Ok(TagType::Version),
             "release" => Ok(TagType::Release),
             "milestone" => Ok(TagType::Milestone),
+            "checkpoint" => Ok(TagType::Checkpoint),
             "custom" => Ok(TagType::Custom),
             _ => Err(format!("Unknown tag type: {}", s)),
         }
@@ -99,6 +104,151 @@ impl VersionInfo {
     }
 }
 
+/// Semantic version parsed from a tag name, per SemVer 2.0.0 with an optional leading `v`
+///
+/// Ordering follows SemVer precedence rather than string order: `1.10.0` > `1.9.0`, a
+/// pre-release sorts below its release, and build metadata is ignored.
+#[derive(Debug, Clone)]
+pub struct SemanticVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Dot-separated pre-release identifiers; empty for a release
+    pub pre_release: Vec<String>,
+    pub build_metadata: Option<String>,
+}
+
+impl SemanticVersion {
+    /// Parse `[v]MAJOR.MINOR.PATCH[-PRE.RELEASE][+BUILD]`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("Invalid semantic version '{}': {}", value, reason);
+        let version = value.strip_prefix('v').unwrap_or(value);
+        let (version, build_metadata) = match version.split_once('+') {
+            Some((version, build)) => {
+                if !build.split('.').all(is_identifier) {
+                    return Err(invalid("malformed build metadata"));
+                }
+                (version, Some(build.to_string()))
+            }
+            None => (version, None),
+        };
+        let (core, pre_release) = match version.split_once('-') {
+            Some((core, pre)) => {
+                let identifiers: Vec<String> = pre.split('.').map(str::to_string).collect();
+                let valid = identifiers.iter().all(|identifier| {
+                    is_identifier(identifier)
+                        && !(is_numeric(identifier) && has_leading_zero(identifier))
+                });
+                if !valid {
+                    return Err(invalid("malformed pre-release"));
+                }
+                (core, identifiers)
+            }
+            None => (version, Vec::new()),
+        };
+
+        let numbers: Vec<&str> = core.split('.').collect();
+        if numbers.len() != 3 {
+            return Err(invalid("expected MAJOR.MINOR.PATCH"));
+        }
+        let mut parsed = [0u64; 3];
+        for (slot, number) in parsed.iter_mut().zip(&numbers) {
+            if !is_numeric(number) || has_leading_zero(number) {
+                return Err(invalid("version numbers must be non-negative integers"));
+            }
+            *slot = number
+                .parse()
+                .map_err(|_| invalid("version number out of range"))?;
+        }
+
+        Ok(SemanticVersion {
+            major: parsed[0],
+            minor: parsed[1],
+            patch: parsed[2],
+            pre_release,
+            build_metadata,
+        })
+    }
+
+    pub fn is_pre_release(&self) -> bool {
+        !self.pre_release.is_empty()
+    }
+}
+
+fn is_identifier(identifier: &str) -> bool {
+    !identifier.is_empty()
+        && identifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn is_numeric(identifier: &str) -> bool {
+    !identifier.is_empty() && identifier.chars().all(|c| c.is_ascii_digit())
+}
+
+fn has_leading_zero(number: &str) -> bool {
+    number.len() > 1 && number.starts_with('0')
+}
+
+/// Numeric identifiers compare numerically and sort below alphanumeric ones
+fn compare_pre_release_identifiers(a: &str, b: &str) -> Ordering {
+    match (is_numeric(a), is_numeric(b)) {
+        (true, true) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.cmp(b),
+    }
+}
+
+impl Ord for SemanticVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.is_pre_release(), other.is_pre_release()) {
+                (false, false) => Ordering::Equal,
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
+                (true, true) => {
+                    for (a, b) in self.pre_release.iter().zip(&other.pre_release) {
+                        match compare_pre_release_identifiers(a, b) {
+                            Ordering::Equal => continue,
+                            ordering => return ordering,
+                        }
+                    }
+                    self.pre_release.len().cmp(&other.pre_release.len())
+                }
+            })
+    }
+}
+
+impl PartialOrd for SemanticVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Equal when SemVer precedence is equal, so build metadata doesn't count
+impl PartialEq for SemanticVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SemanticVersion {}
+
+impl std::fmt::Display for SemanticVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if self.is_pre_release() {
+            write!(f, "-{}", self.pre_release.join("."))?;
+        }
+        if let Some(build_metadata) = &self.build_metadata {
+            write!(f, "+{}", build_metadata)?;
+        }
+        Ok(())
+    }
+}
+
 /// Request to create a new commit tag
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewCommitTag {
@@ -133,6 +283,14 @@ pub struct TaggedCommit {
     pub tags: Vec<CommitTag>,
 }
 
+/// One page of a tagged commit listing
+#[derive(Debug, Clone)]
+pub struct TaggedCommitPage {
+    pub commits: Vec<TaggedCommit>,
+    /// Number of matching commits across all pages
+    pub total: usize,
+}
+
 /// Query parameters for filtering tagged commits
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TagQuery {
@@ -142,6 +300,8 @@ pub struct TagQuery {
     pub tag_name: Option<String>,
     /// Limit number of results
     pub limit: Option<i32>,
+    /// Number of matching commits to skip
+    pub offset: Option<i32>,
 }
 
 impl NewCommitTag {
@@ -161,6 +321,16 @@ impl NewCommitTag {
 }
 
 impl CommitTag {
+    /// Semantic version of a release tag; None for other tag types and for release tags
+    /// created before release names were validated
+    pub fn release_version(&self) -> Option<SemanticVersion> {
+        if self.tag_type == TagType::Release {
+            SemanticVersion::parse(&self.tag_name).ok()
+        } else {
+            None
+        }
+    }
+
     /// Get version information from metadata if this is a version tag
     pub fn version_info(&self) -> Option<VersionInfo> {
         if self.tag_type == TagType::Version {
@@ -227,4 +397,81 @@ mod tests {
         
         assert!(milestone_tag.version_info().is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_semantic_version_parsing() {
+        let version = SemanticVersion::parse("v1.2.3-rc.1+build.5").unwrap();
+        assert_eq!((version.major, version.minor, version.patch), (1, 2, 3));
+        assert_eq!(version.pre_release, vec!["rc", "1"]);
+        assert_eq!(version.build_metadata.as_deref(), Some("build.5"));
+        assert_eq!(version.to_string(), "1.2.3-rc.1+build.5");
+        assert!(SemanticVersion::parse("0.0.0").is_ok());
+
+        for invalid in [
+            "",
+            "v",
+            "1.2",
+            "1.2.3.4",
+            "01.2.3",
+            "1.2.x",
+            "-1.2.3",
+            "1.2.3-",
+            "1.2.3-rc..1",
+            "1.2.3-01",
+            "1.2.3+",
+            "1.2.3-rc_1",
+            "prod-release",
+        ] {
+            assert!(
+                SemanticVersion::parse(invalid).is_err(),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_semantic_version_precedence() {
+        let mut versions: Vec<SemanticVersion> = [
+            "v1.10.0",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.0-alpha.beta",
+            "v1.9.0",
+            "1.0.0-beta.11",
+            "1.0.0-alpha",
+            "2.0.0-alpha",
+            "1.0.0-beta.2",
+            "1.0.0-alpha.1",
+            "1.0.0-beta",
+        ]
+        .iter()
+        .map(|v| SemanticVersion::parse(v).unwrap())
+        .collect();
+        versions.sort();
+
+        let sorted: Vec<String> = versions.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            sorted,
+            vec![
+                "1.0.0-alpha",
+                "1.0.0-alpha.1",
+                "1.0.0-alpha.beta",
+                "1.0.0-beta",
+                "1.0.0-beta.2",
+                "1.0.0-beta.11",
+                "1.0.0-rc.1",
+                "1.0.0",
+                "1.9.0",
+                "1.10.0",
+                "2.0.0-alpha",
+            ]
+        );
+
+        // Build metadata doesn't affect precedence
+        assert_eq!(
+            SemanticVersion::parse("1.0.0+a").unwrap(),
+            SemanticVersion::parse("1.0.0+b").unwrap()
+        );
+    }
+}
@@ -31,6 +31,7 @@ fn create_system_class(
         derived,
         domain_constraint,
         base: Base::default(),
+        constraints: Vec::new(),
         created_by: system_user.clone(),
         created_at: now,
         updated_by: system_user,
@@ -61,6 +62,10 @@ fn create_system_instance(
         created_at: now,
         updated_by: system_user,
         updated_at: now,
+        deleted_at: None,
+        deleted_by: None,
+        materialized_inputs: None,
+        materialized: Default::default(),
     }
 }
 
@@ -163,6 +168,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-underbed-basePrice".to_string(),
@@ -170,6 +180,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::Number,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-underbed-price".to_string(),
@@ -177,6 +192,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::Number,
                         required: Some(false),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                 ],
                 relationships: vec![
@@ -188,6 +208,9 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         universe: None,
                         selection: SelectionType::ExplicitOrFilter,
                         default_pool: DefaultPool::All,
+                        inverse_of: None,
+                        min: None,
+                        max: None,
                     },
                     RelationshipDef {
                         id: "rel-underbed-fabric".to_string(),
@@ -197,6 +220,9 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         universe: None,
                         selection: SelectionType::FilterAllowed,
                         default_pool: DefaultPool::All,
+                        inverse_of: None,
+                        min: None,
+                        max: None,
                     },
                     RelationshipDef {
                         id: "rel-underbed-leg".to_string(),
@@ -206,6 +232,9 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         universe: None,
                         selection: SelectionType::ExplicitOrFilter,
                         default_pool: DefaultPool::All,
+                        inverse_of: None,
+                        min: None,
+                        max: None,
                     },
                 ],
                 derived: vec![DerivedDef {
@@ -223,9 +252,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         }),
                     }),
                     fn_short: None,
+                    materialize: None,
                 }],
                 domain_constraint: Domain::binary(), // Each Underbed instance defaults to domain [0,1]
                 base: Base::default(),
+                constraints: Vec::new(),
                 created_by: "seed-data".to_string(),
                 created_at: chrono::Utc::now(),
                 updated_by: "seed-data".to_string(),
@@ -243,6 +274,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-size-width".to_string(),
@@ -250,6 +286,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::Number,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-size-length".to_string(),
@@ -257,12 +298,18 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::Number,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                 ],
                 relationships: vec![],
                 derived: vec![],
                 domain_constraint: Domain::constant(1), // Each Size instance defaults to domain [1,1] (always selected)
                 base: Base::default(),
+                constraints: Vec::new(),
                 created_by: "seed-data".to_string(),
                 created_at: chrono::Utc::now(),
                 updated_by: "seed-data".to_string(),
@@ -280,6 +327,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-fabric-color".to_string(),
@@ -287,6 +339,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-fabric-material".to_string(),
@@ -294,12 +351,18 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                 ],
                 relationships: vec![],
                 derived: vec![],
                 domain_constraint: Domain::new(0, 10), // Each Fabric instance defaults to domain [0,10]
                 base: Base::default(),
+                constraints: Vec::new(),
                 created_by: "seed-data".to_string(),
                 created_at: chrono::Utc::now(),
                 updated_by: "seed-data".to_string(),
@@ -317,6 +380,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-leg-material".to_string(),
@@ -324,6 +392,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-leg-price".to_string(),
@@ -331,12 +404,18 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                         data_type: DataType::Number,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                 ],
                 relationships: vec![],
                 derived: vec![],
                 domain_constraint: Domain::new(0, 4), // Each Leg instance defaults to domain [0,4]
                 base: Base::default(),
+                constraints: Vec::new(),
                 created_by: "seed-data".to_string(),
                 created_at: chrono::Utc::now(),
                 updated_by: "seed-data".to_string(),
@@ -368,6 +447,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 data_type: DataType::String,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
             PropertyDef {
                 id: "prop-component-type".to_string(),
@@ -375,6 +459,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 data_type: DataType::String,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
         ],
         relationships: vec![],
@@ -394,6 +483,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 data_type: DataType::String,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
             PropertyDef {
                 id: "prop-painting-price".to_string(),
@@ -401,6 +495,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 data_type: DataType::Number,
                 required: Some(false),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
         ],
         relationships: vec![
@@ -412,6 +511,9 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 universe: None,
                 selection: SelectionType::ExplicitOrFilter,
                 default_pool: DefaultPool::All,
+                inverse_of: None,
+                min: None,
+                max: None,
             },
             RelationshipDef {
                 id: "rel-painting-b".to_string(),
@@ -421,6 +523,9 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 universe: None,
                 selection: SelectionType::ExplicitOrFilter,
                 default_pool: DefaultPool::All,
+                inverse_of: None,
+                min: None,
+                max: None,
             },
             RelationshipDef {
                 id: "rel-painting-c".to_string(),
@@ -430,6 +535,9 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 universe: None,
                 selection: SelectionType::ExplicitOrFilter,
                 default_pool: DefaultPool::All,
+                inverse_of: None,
+                min: None,
+                max: None,
             },
         ],
         derived: vec![],
@@ -448,6 +556,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 data_type: DataType::String,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
             PropertyDef {
                 id: "prop-color-price".to_string(),
@@ -455,6 +568,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 data_type: DataType::Number,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
         ],
         relationships: vec![],
@@ -486,6 +604,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 data_type: DataType::String,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
             PropertyDef {
                 id: "prop-option-price".to_string(),
@@ -493,6 +616,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 data_type: DataType::Number,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
         ],
         relationships: vec![],
@@ -512,6 +640,11 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
             data_type: DataType::String,
             required: Some(true),
                         value: None,
+            encrypted: false,
+            min: None,
+            max: None,
+            pattern: None,
+            unique: None,
                     }],
         relationships: vec![
             RelationshipDef {
@@ -523,6 +656,9 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 selection: SelectionType::ExplicitOrFilter,
                 // Default: all Color instances are in the pool
                 default_pool: DefaultPool::All,
+                inverse_of: None,
+                min: None,
+                max: None,
             },
             RelationshipDef {
                 id: "rel-car-free-options".to_string(),
@@ -533,6 +669,9 @@ async fn load_schema<S: Store>(_store: &S, _branch_id: &Id) -> Result<()> {
                 selection: SelectionType::ExplicitOrFilter,
                 // Default: no Option instances in pool (must be explicitly selected)
                 default_pool: DefaultPool::None,
+                inverse_of: None,
+                min: None,
+                max: None,
             },
         ],
         derived: vec![],
@@ -1576,6 +1715,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-underbed-basePrice".to_string(),
@@ -1583,6 +1727,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::Number,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-underbed-price".to_string(),
@@ -1590,6 +1739,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::Number,
                         required: Some(false),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     // NEW REQUIRED PROPERTY - This will cause validation conflicts!
                     PropertyDef {
@@ -1597,6 +1751,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         name: "material".to_string(),
                         data_type: DataType::String,
                         required: Some(true), // Required! Main branch instances don't have this
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                 ],
                 relationships: vec![
@@ -1608,6 +1767,9 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         universe: None,
                         selection: SelectionType::ExplicitOrFilter,
                         default_pool: DefaultPool::All,
+                        inverse_of: None,
+                        min: None,
+                        max: None,
                     },
                     RelationshipDef {
                         id: "rel-underbed-fabric".to_string(),
@@ -1617,6 +1779,9 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         universe: None,
                         selection: SelectionType::FilterAllowed,
                         default_pool: DefaultPool::All,
+                        inverse_of: None,
+                        min: None,
+                        max: None,
                     },
                     RelationshipDef {
                         id: "rel-underbed-leg".to_string(),
@@ -1626,6 +1791,9 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         universe: None,
                         selection: SelectionType::ExplicitOrFilter,
                         default_pool: DefaultPool::All,
+                        inverse_of: None,
+                        min: None,
+                        max: None,
                     },
                 ],
                 derived: vec![DerivedDef {
@@ -1658,6 +1826,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-size-width".to_string(),
@@ -1665,6 +1838,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::Number,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-size-length".to_string(),
@@ -1672,6 +1850,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::Number,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                 ],
                 relationships: vec![],
@@ -1690,6 +1873,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-fabric-color".to_string(),
@@ -1697,6 +1885,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-fabric-material".to_string(),
@@ -1704,6 +1897,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     // NEW OPTIONAL PROPERTY - This won't cause conflicts
                     PropertyDef {
@@ -1711,6 +1909,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         name: "durability".to_string(),
                         data_type: DataType::String,
                         required: Some(false), // Optional, so safe
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                 ],
                 relationships: vec![],
@@ -1729,6 +1932,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-leg-material".to_string(),
@@ -1736,6 +1944,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::String,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                     PropertyDef {
                         id: "prop-leg-price".to_string(),
@@ -1743,6 +1956,11 @@ async fn load_feature_schema<S: Store>(store: &S, branch_id: &Id) -> Result<()>
                         data_type: DataType::Number,
                         required: Some(true),
                         value: None,
+                        encrypted: false,
+                        min: None,
+                        max: None,
+                        pattern: None,
+                        unique: None,
                     },
                 ],
                 relationships: vec![],
@@ -2197,6 +2415,11 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 data_type: DataType::Number,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
             PropertyDef {
                 id: "discount".to_string(),
@@ -2204,6 +2427,11 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 data_type: DataType::Number,
                 required: Some(false),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
         ],
         relationships: vec![
@@ -2215,6 +2443,9 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 universe: None,
                 selection: SelectionType::ExplicitOrFilter,
                 default_pool: DefaultPool::All,
+                inverse_of: None,
+                min: None,
+                max: None,
             },
             RelationshipDef {
                 id: "color".to_string(),
@@ -2224,6 +2455,9 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 universe: None,
                 selection: SelectionType::ExplicitOrFilter,
                 default_pool: DefaultPool::All,
+                inverse_of: None,
+                min: None,
+                max: None,
             },
             RelationshipDef {
                 id: "options".to_string(),
@@ -2233,6 +2467,9 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 universe: None,
                 selection: SelectionType::ExplicitOrFilter,
                 default_pool: DefaultPool::All,
+                inverse_of: None,
+                min: None,
+                max: None,
             },
         ],
         derived: vec![
@@ -2267,6 +2504,7 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                     }),
                 }),
                 fn_short: None,
+                materialize: None,
             },
         ],
         domain_constraint: Domain::binary(), // Tables can be selected (1) or not (0)
@@ -2285,6 +2523,11 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 data_type: DataType::String,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
             PropertyDef {
                 id: "price".to_string(),
@@ -2292,6 +2535,11 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 data_type: DataType::Number,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
         ],
         relationships: vec![],
@@ -2312,6 +2560,11 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 data_type: DataType::String,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
             PropertyDef {
                 id: "price".to_string(),
@@ -2319,6 +2572,11 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 data_type: DataType::Number,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
         ],
         relationships: vec![],
@@ -2339,6 +2597,11 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 data_type: DataType::String,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
             PropertyDef {
                 id: "price".to_string(),
@@ -2346,6 +2609,11 @@ async fn create_kitchen_schema(_branch_name: String) -> Result<Schema> {
                 data_type: DataType::Number,
                 required: Some(true),
                         value: None,
+                encrypted: false,
+                min: None,
+                max: None,
+                pattern: None,
+                unique: None,
                     },
         ],
         relationships: vec![],
@@ -0,0 +1,567 @@
+//! Store conformance suite
+//!
+//! The same branch-operation scenarios run against every `Store` implementation, so the
+//! in-memory store used by unit tests keeps behaving like `PostgresStore`. The Postgres
+//! variants need a live database and are ignored by default:
+//!
+//! ```text
+//! DATABASE_URL=postgres://... cargo test store::conformance -- --ignored
+//! ```
+
+use crate::config::DatabaseConfig;
+use crate::logic::{BranchOperationsV2, FastForwardBlocked};
+use crate::model::merge::{MergeOptions, MergeStrategy};
+use crate::model::{
+    Branch, ClassDef, Commit, DataType, Database, Instance, NewCommit, NewWorkingCommit,
+    PropertyValue, TypedValue, WorkingCommit, WorkingCommitStatus,
+};
+use crate::store::traits::Store;
+use crate::store::{InMemoryStore, PostgresStore};
+use serde_json::json;
+
+/// Create a database with a main branch. Names are unique so runs can share a Postgres.
+async fn setup<S: Store>(store: &S) -> Database {
+    let database = Database::new(format!("conformance-{}", crate::model::generate_id()), None);
+    store.upsert_database(database.clone()).await.unwrap();
+    store
+        .upsert_branch(Branch::new_main_branch(database.id.clone(), None))
+        .await
+        .unwrap();
+    database
+}
+
+/// Stage `change` on a fresh working commit, commit it and advance the branch head
+async fn commit_on<S: Store>(
+    store: &S,
+    database: &Database,
+    branch_name: &str,
+    message: &str,
+    change: impl FnOnce(&mut WorkingCommit),
+) -> Commit {
+    let mut wc = store
+        .create_working_commit(&database.id, branch_name, NewWorkingCommit { author: None })
+        .await
+        .unwrap();
+    change(&mut wc);
+    store.update_working_commit(wc.clone()).await.unwrap();
+    let commit = store
+        .create_commit(NewCommit {
+            database_id: database.id.clone(),
+            working_commit_id: wc.id.clone(),
+            message: message.to_string(),
+            author: None,
+            based_on_hash: None,
+        })
+        .await
+        .unwrap();
+    store.delete_working_commit(&wc.id).await.unwrap();
+
+    let mut branch = store
+        .get_branch(&database.id, branch_name)
+        .await
+        .unwrap()
+        .unwrap();
+    branch.current_commit_hash = Some(commit.hash.clone());
+    store.upsert_branch(branch).await.unwrap();
+    commit
+}
+
+async fn create_branch<S: Store>(store: &S, database: &Database, name: &str, from: &Commit) {
+    let mut branch = Branch::new_from_branch(
+        database.id.clone(),
+        "main".to_string(),
+        name.to_string(),
+        None,
+        None,
+    );
+    branch.current_commit_hash = Some(from.hash.clone());
+    store.upsert_branch(branch).await.unwrap();
+}
+
+fn part(id: &str, price: f64) -> Instance {
+    let mut instance = Instance {
+        id: id.to_string(),
+        class_id: "class-part".to_string(),
+        ..Instance::default()
+    };
+    set_price(&mut instance, price);
+    instance
+}
+
+fn set_price(instance: &mut Instance, price: f64) {
+    instance.properties.insert(
+        "price".to_string(),
+        PropertyValue::Literal(TypedValue {
+            value: json!(price),
+            data_type: DataType::Number,
+        }),
+    );
+}
+
+fn add_part_class(wc: &mut WorkingCommit) {
+    wc.schema_data.classes.push(ClassDef {
+        id: "class-part".to_string(),
+        name: "Part".to_string(),
+        ..ClassDef::default()
+    });
+}
+
+async fn commits_link_to_their_parent<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let first = commit_on(store, &database, "main", "first", |wc| {
+        add_part_class(wc);
+        wc.instances_data.push(part("part-a", 10.0));
+    })
+    .await;
+    let second = commit_on(store, &database, "main", "second", |wc| {
+        wc.instances_data.push(part("part-b", 20.0));
+    })
+    .await;
+
+    assert_eq!(first.parent_hash, None);
+    assert_eq!(second.parent_hash.as_deref(), Some(first.hash.as_str()));
+    assert_ne!(first.hash, second.hash);
+    assert_eq!(second.schema_classes_count, 1);
+    assert_eq!(second.instances_count, 2);
+
+    let data = store.get_commit_data(&second.hash).await.unwrap().unwrap();
+    assert_eq!(data.instances.len(), 2);
+    assert!(store.commit_exists(&first.hash).await.unwrap());
+
+    let history = store
+        .list_commits_for_database(&database.id, None)
+        .await
+        .unwrap();
+    let hashes: Vec<&str> = history.iter().map(|c| c.hash.as_str()).collect();
+    assert_eq!(hashes, vec![second.hash.as_str(), first.hash.as_str()]);
+
+    let page = store
+        .list_commits_paginated(&database.id, None, 1)
+        .await
+        .unwrap();
+    assert_eq!(page.commits[0].hash, second.hash);
+    let page = store
+        .list_commits_paginated(&database.id, page.next_cursor.as_ref(), 1)
+        .await
+        .unwrap();
+    assert_eq!(page.commits[0].hash, first.hash);
+    assert!(page.next_cursor.is_none());
+}
+
+async fn branches_merge_without_conflicts<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let base = commit_on(store, &database, "main", "base", |wc| {
+        add_part_class(wc);
+        wc.instances_data.push(part("part-a", 10.0));
+    })
+    .await;
+    create_branch(store, &database, "feature", &base).await;
+
+    let feature = commit_on(store, &database, "feature", "add part-b", |wc| {
+        wc.instances_data.push(part("part-b", 20.0));
+    })
+    .await;
+    assert_eq!(feature.parent_hash.as_deref(), Some(base.hash.as_str()));
+    commit_on(store, &database, "main", "add part-c", |wc| {
+        wc.instances_data.push(part("part-c", 30.0));
+    })
+    .await;
+
+    let result = BranchOperationsV2::start_merge(
+        store,
+        &database.id,
+        "feature",
+        &database.id,
+        "main",
+        None,
+        MergeOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(result.success, "unexpected conflicts: {:?}", result.conflicts);
+    // Both branches moved on, so the merge can't just move main
+    assert!(!result.fast_forward);
+
+    let merge_wc = store
+        .get_working_commit(&result.working_commit_id.unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    let mut ids: Vec<&str> = merge_wc.instances_data.iter().map(|i| i.id.as_str()).collect();
+    ids.sort();
+    assert_eq!(ids, vec!["part-a", "part-b", "part-c"]);
+}
+
+async fn merge_fast_forwards_unmoved_target<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let base = commit_on(store, &database, "main", "base", |wc| {
+        add_part_class(wc);
+        wc.instances_data.push(part("part-a", 10.0));
+    })
+    .await;
+    create_branch(store, &database, "feature", &base).await;
+    let feature = commit_on(store, &database, "feature", "add part-b", |wc| {
+        wc.instances_data.push(part("part-b", 20.0));
+    })
+    .await;
+
+    let result = BranchOperationsV2::start_merge(
+        store,
+        &database.id,
+        "feature",
+        &database.id,
+        "main",
+        None,
+        MergeOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(result.success);
+    assert!(result.fast_forward);
+    assert!(result.working_commit_id.is_none());
+
+    let main = store.get_branch(&database.id, "main").await.unwrap().unwrap();
+    assert_eq!(main.current_commit_hash, Some(feature.hash));
+    // No merge commit or working commit was created
+    assert_eq!(store.list_commits_for_database(&database.id, None).await.unwrap().len(), 2);
+    assert!(store
+        .list_working_commits_for_branch(&database.id, "main")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn fast_forward_refused_with_active_working_commit<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let base = commit_on(store, &database, "main", "base", |wc| {
+        add_part_class(wc);
+        wc.instances_data.push(part("part-a", 10.0));
+    })
+    .await;
+    create_branch(store, &database, "feature", &base).await;
+    commit_on(store, &database, "feature", "add part-b", |wc| {
+        wc.instances_data.push(part("part-b", 20.0));
+    })
+    .await;
+    store
+        .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+        .await
+        .unwrap();
+
+    let error = BranchOperationsV2::start_merge(
+        store,
+        &database.id,
+        "feature",
+        &database.id,
+        "main",
+        None,
+        MergeOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.is::<FastForwardBlocked>());
+    // main stays where the working commit expects it
+    let main = store.get_branch(&database.id, "main").await.unwrap().unwrap();
+    assert_eq!(main.current_commit_hash, Some(base.hash));
+}
+
+async fn merge_without_fast_forward<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let base = commit_on(store, &database, "main", "base", |wc| {
+        add_part_class(wc);
+        wc.instances_data.push(part("part-a", 10.0));
+    })
+    .await;
+    create_branch(store, &database, "feature", &base).await;
+    commit_on(store, &database, "feature", "add part-b", |wc| {
+        wc.instances_data.push(part("part-b", 20.0));
+    })
+    .await;
+
+    let result = BranchOperationsV2::start_merge(
+        store,
+        &database.id,
+        "feature",
+        &database.id,
+        "main",
+        None,
+        MergeOptions {
+            no_ff: true,
+            ..MergeOptions::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(result.success);
+    assert!(!result.fast_forward);
+
+    // main stays put until the merge working commit is committed
+    let main = store.get_branch(&database.id, "main").await.unwrap().unwrap();
+    assert_eq!(main.current_commit_hash, Some(base.hash));
+    let merge_wc = store
+        .get_working_commit(&result.working_commit_id.unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(merge_wc.instances_data.len(), 2);
+}
+
+async fn merge_commit_records_its_sources<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let base = commit_on(store, &database, "main", "base", |wc| {
+        add_part_class(wc);
+        wc.instances_data.push(part("part-a", 10.0));
+    })
+    .await;
+    create_branch(store, &database, "feature", &base).await;
+    let feature = commit_on(store, &database, "feature", "add part-b", |wc| {
+        wc.instances_data.push(part("part-b", 20.0));
+    })
+    .await;
+    let main = commit_on(store, &database, "main", "add part-c", |wc| {
+        wc.instances_data.push(part("part-c", 30.0));
+    })
+    .await;
+
+    let result = BranchOperationsV2::start_merge(
+        store,
+        &database.id,
+        "feature",
+        &database.id,
+        "main",
+        None,
+        MergeOptions {
+            strategy: MergeStrategy::Theirs,
+            ..MergeOptions::default()
+        },
+    )
+    .await
+    .unwrap();
+    let merge = store
+        .create_commit(NewCommit {
+            database_id: database.id.clone(),
+            working_commit_id: result.working_commit_id.unwrap(),
+            message: "merge feature".to_string(),
+            author: None,
+            based_on_hash: None,
+        })
+        .await
+        .unwrap();
+
+    let merge = store.get_commit(&merge.hash).await.unwrap().unwrap();
+    assert_eq!(merge.source_branch.as_deref(), Some("feature"));
+    assert_eq!(merge.source_commit_hash, Some(feature.hash));
+    assert_eq!(merge.target_commit_hash, Some(main.hash.clone()));
+    assert_eq!(merge.merge_strategy, Some(MergeStrategy::Theirs));
+
+    // Ordinary commits carry none of it
+    let main = store.get_commit(&main.hash).await.unwrap().unwrap();
+    assert_eq!(main.source_branch, None);
+    assert_eq!(main.source_commit_hash, None);
+    assert_eq!(main.target_commit_hash, None);
+    assert_eq!(main.merge_strategy, None);
+}
+
+async fn conflicting_edits_stop_the_merge<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let base = commit_on(store, &database, "main", "base", |wc| {
+        add_part_class(wc);
+        wc.instances_data.push(part("part-a", 10.0));
+    })
+    .await;
+    create_branch(store, &database, "feature", &base).await;
+
+    commit_on(store, &database, "feature", "feature price", |wc| {
+        set_price(&mut wc.instances_data[0], 15.0);
+    })
+    .await;
+    commit_on(store, &database, "main", "main price", |wc| {
+        set_price(&mut wc.instances_data[0], 12.0);
+    })
+    .await;
+
+    let result = BranchOperationsV2::start_merge(
+        store,
+        &database.id,
+        "feature",
+        &database.id,
+        "main",
+        None,
+        MergeOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!result.success);
+    assert!(!result.conflicts.is_empty());
+
+    let merge_wc = store
+        .get_working_commit(&result.working_commit_id.unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(merge_wc.status, WorkingCommitStatus::Merging);
+
+    // A second merge into the same branch is refused while the first is unresolved
+    let again = BranchOperationsV2::start_merge(
+        store,
+        &database.id,
+        "feature",
+        &database.id,
+        "main",
+        None,
+        MergeOptions::default(),
+    )
+    .await;
+    assert!(again.is_err());
+}
+
+async fn postgres_store() -> PostgresStore {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must point at a test database");
+    let store = PostgresStore::new(&url, &DatabaseConfig::default()).await.unwrap();
+    store.migrate().await.unwrap();
+    store
+}
+
+async fn commit_ancestry<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let first = commit_on(store, &database, "main", "first", |wc| {
+        add_part_class(wc);
+        wc.instances_data.push(part("part-a", 10.0));
+    })
+    .await;
+    let second = commit_on(store, &database, "main", "second", |wc| {
+        wc.instances_data.push(part("part-b", 20.0));
+    })
+    .await;
+    let third = commit_on(store, &database, "main", "third", |wc| {
+        wc.instances_data.push(part("part-c", 30.0));
+    })
+    .await;
+    create_branch(store, &database, "feature", &first).await;
+    let side = commit_on(store, &database, "feature", "side", |wc| {
+        wc.instances_data.push(part("part-d", 40.0));
+    })
+    .await;
+
+    let distance = |ancestor: &Commit, descendant: &Commit| {
+        let ancestor = ancestor.hash.clone();
+        let descendant = descendant.hash.clone();
+        let database_id = database.id.clone();
+        async move {
+            store
+                .is_ancestor(&database_id, &ancestor, &descendant)
+                .await
+                .unwrap()
+        }
+    };
+    assert_eq!(distance(&second, &third).await, Some(1));
+    assert_eq!(distance(&first, &third).await, Some(2));
+    assert_eq!(distance(&third, &third).await, Some(0));
+    assert_eq!(distance(&first, &side).await, Some(1));
+    // Descendants and diverged commits are not ancestors
+    assert_eq!(distance(&third, &first).await, None);
+    assert_eq!(distance(&side, &third).await, None);
+
+    let other = setup(store).await;
+    let unrelated = commit_on(store, &other, "main", "unrelated", add_part_class).await;
+    assert_eq!(distance(&first, &unrelated).await, None);
+}
+
+async fn conditional_working_commit_update<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let wc = store
+        .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+        .await
+        .unwrap();
+    store.update_working_commit(wc.clone()).await.unwrap();
+    let loaded = store.get_working_commit(&wc.id).await.unwrap().unwrap();
+
+    // Two edits made against the same version: only the first is written
+    let mut first = loaded.clone();
+    first.instances_data.push(part("part-a", 10.0));
+    let mut second = loaded.clone();
+    second.instances_data.push(part("part-b", 20.0));
+    assert!(store
+        .update_working_commit_at_version(first, loaded.version)
+        .await
+        .unwrap());
+    assert!(!store
+        .update_working_commit_at_version(second, loaded.version)
+        .await
+        .unwrap());
+
+    let saved = store.get_working_commit(&wc.id).await.unwrap().unwrap();
+    assert_eq!(saved.version, loaded.version + 1);
+    let ids: Vec<&str> = saved.instances_data.iter().map(|i| i.id.as_str()).collect();
+    assert_eq!(ids, vec!["part-a"]);
+}
+
+async fn concurrent_unconditional_working_commit_updates<S: Store>(store: &S) {
+    let database = setup(store).await;
+    let wc = store
+        .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+        .await
+        .unwrap();
+    let loaded = store.get_working_commit(&wc.id).await.unwrap().unwrap();
+
+    // Two edits without If-Match, both starting from the same version
+    let mut first = loaded.clone();
+    first.instances_data.push(part("part-a", 10.0));
+    let mut second = loaded.clone();
+    second.instances_data.push(part("part-b", 20.0));
+    let (a, b) = tokio::join!(
+        store.update_working_commit(first),
+        store.update_working_commit(second)
+    );
+    a.unwrap();
+    b.unwrap();
+
+    // Each write got its own version, so the version after both is unique to the last one
+    let saved = store.get_working_commit(&wc.id).await.unwrap().unwrap();
+    assert_eq!(saved.version, loaded.version + 2);
+    assert!(!store
+        .update_working_commit_at_version(saved.clone(), loaded.version + 1)
+        .await
+        .unwrap());
+    assert!(store
+        .update_working_commit_at_version(saved, loaded.version + 2)
+        .await
+        .unwrap());
+}
+
+/// Generates one test per store for each scenario
+macro_rules! conformance_tests {
+    ($($scenario:ident),* $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    super::$scenario(&super::InMemoryStore::new()).await;
+                }
+            )*
+        }
+
+        mod postgres {
+            $(
+                #[tokio::test]
+                #[ignore = "requires DATABASE_URL"]
+                async fn $scenario() {
+                    super::$scenario(&super::postgres_store().await).await;
+                }
+            )*
+        }
+    };
+}
+
+conformance_tests!(
+    commits_link_to_their_parent,
+    branches_merge_without_conflicts,
+    conflicting_edits_stop_the_merge,
+    commit_ancestry,
+    merge_fast_forwards_unmoved_target,
+    fast_forward_refused_with_active_working_commit,
+    merge_without_fast_forward,
+    merge_commit_records_its_sources,
+    conditional_working_commit_update,
+    concurrent_unconditional_working_commit_updates,
+);
@@ -0,0 +1,220 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{aead, hkdf, hmac};
+use serde_json::{json, Value};
+
+use crate::model::{Instance, PropertyValue, Schema};
+
+/// Key of the JSON object that replaces an encrypted property value in commit data
+pub const ENCRYPTED_VALUE_KEY: &str = "$encrypted";
+const CIPHERTEXT_VERSION: &str = "v1";
+const NONCE_LEN: usize = 12;
+
+/// Encrypts `encrypted` property values before they are written into commit blobs.
+///
+/// Each database gets its own AES-256-GCM data key, derived with HKDF from the master key
+/// in `AppConfig`. The nonce is an HMAC of the value and its location, so the same plaintext
+/// at the same place always yields the same ciphertext and commit hashes stay stable.
+pub struct PropertyCipher {
+    master: hkdf::Prk,
+}
+
+impl std::fmt::Debug for PropertyCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PropertyCipher { .. }")
+    }
+}
+
+impl PropertyCipher {
+    /// Build a cipher from a base64-encoded 32-byte master key
+    pub fn from_base64_key(key: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(key.trim())
+            .context("Encryption key is not valid base64")?;
+        if bytes.len() != 32 {
+            return Err(anyhow::anyhow!(
+                "Encryption key must be 32 bytes, got {}",
+                bytes.len()
+            ));
+        }
+
+        let salt = hkdf::Salt::new(hkdf::HKDF_SHA256, b"oat-db/property-encryption");
+        Ok(Self {
+            master: salt.extract(&bytes),
+        })
+    }
+
+    fn data_key(&self, database_id: &str) -> Result<aead::LessSafeKey> {
+        let info = [b"data-key".as_slice(), database_id.as_bytes()];
+        let okm = self
+            .master
+            .expand(&info, &aead::AES_256_GCM)
+            .map_err(|_| anyhow::anyhow!("Failed to derive data key"))?;
+        Ok(aead::LessSafeKey::new(aead::UnboundKey::from(okm)))
+    }
+
+    fn nonce_key(&self, database_id: &str) -> Result<hmac::Key> {
+        let info = [b"nonce-key".as_slice(), database_id.as_bytes()];
+        let okm = self
+            .master
+            .expand(&info, hmac::HMAC_SHA256)
+            .map_err(|_| anyhow::anyhow!("Failed to derive nonce key"))?;
+        Ok(hmac::Key::from(okm))
+    }
+
+    /// Whether a property value is an encrypted envelope produced by this module
+    pub fn is_encrypted(value: &Value) -> bool {
+        value
+            .as_object()
+            .map(|obj| obj.len() == 1 && obj.contains_key(ENCRYPTED_VALUE_KEY))
+            .unwrap_or(false)
+    }
+
+    /// Encrypt a value; `location` (instance id + property) is authenticated with it
+    pub fn encrypt_value(&self, database_id: &str, location: &str, value: &Value) -> Result<Value> {
+        let mut in_out = serde_json::to_vec(value).context("Failed to serialize property value")?;
+
+        let mut nonce_input = location.as_bytes().to_vec();
+        nonce_input.push(0);
+        nonce_input.extend_from_slice(&in_out);
+        let tag = hmac::sign(&self.nonce_key(database_id)?, &nonce_input);
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&tag.as_ref()[..NONCE_LEN]);
+
+        self.data_key(database_id)?
+            .seal_in_place_append_tag(
+                aead::Nonce::assume_unique_for_key(nonce),
+                aead::Aad::from(location.as_bytes()),
+                &mut in_out,
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt property value at {}", location))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&in_out);
+        Ok(json!({
+            ENCRYPTED_VALUE_KEY: format!("{}:{}", CIPHERTEXT_VERSION, STANDARD.encode(payload))
+        }))
+    }
+
+    /// Decrypt a value produced by `encrypt_value` for the same database and location
+    pub fn decrypt_value(&self, database_id: &str, location: &str, value: &Value) -> Result<Value> {
+        let encoded = value
+            .get(ENCRYPTED_VALUE_KEY)
+            .and_then(Value::as_str)
+            .and_then(|s| s.strip_prefix(&format!("{}:", CIPHERTEXT_VERSION)))
+            .ok_or_else(|| anyhow::anyhow!("Unsupported encrypted value at {}", location))?;
+        let mut payload = STANDARD
+            .decode(encoded)
+            .with_context(|| format!("Encrypted value at {} is not valid base64", location))?;
+        if payload.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("Encrypted value at {} is truncated", location));
+        }
+
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&payload[..NONCE_LEN]);
+        let plaintext = self
+            .data_key(database_id)?
+            .open_in_place(
+                aead::Nonce::assume_unique_for_key(nonce),
+                aead::Aad::from(location.as_bytes()),
+                &mut payload[NONCE_LEN..],
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt property value at {}", location))?;
+
+        serde_json::from_slice(plaintext).context("Decrypted property value is not valid JSON")
+    }
+
+    /// Encrypt every literal value of a property marked `encrypted` in the schema
+    pub fn encrypt_instances(
+        &self,
+        database_id: &str,
+        schema: &Schema,
+        instances: &mut [Instance],
+    ) -> Result<()> {
+        for instance in instances.iter_mut() {
+            let Some(class_def) = schema.get_class_by_id(&instance.class_id) else {
+                continue;
+            };
+            for prop_def in class_def.properties.iter().filter(|p| p.encrypted) {
+                for key in [&prop_def.name, &prop_def.id] {
+                    if let Some(PropertyValue::Literal(typed)) = instance.properties.get_mut(key) {
+                        if Self::is_encrypted(&typed.value) {
+                            continue;
+                        }
+                        let location = format!("{}/{}", instance.id, key);
+                        typed.value = self.encrypt_value(database_id, &location, &typed.value)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decrypt every encrypted literal value, returning whether anything changed
+    pub fn decrypt_instances(&self, database_id: &str, instances: &mut [Instance]) -> Result<bool> {
+        let mut changed = false;
+        for instance in instances.iter_mut() {
+            for (key, value) in instance.properties.iter_mut() {
+                if let PropertyValue::Literal(typed) = value {
+                    if Self::is_encrypted(&typed.value) {
+                        let location = format!("{}/{}", instance.id, key);
+                        typed.value = self.decrypt_value(database_id, &location, &typed.value)?;
+                        changed = true;
+                    }
+                }
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// Names and ids of properties marked `encrypted` on the given class
+pub fn encrypted_property_keys(schema: &Schema, class_id: &str) -> Vec<String> {
+    schema
+        .get_class_by_id(&class_id.to_string())
+        .map(|class_def| {
+            class_def
+                .properties
+                .iter()
+                .filter(|p| p.encrypted)
+                .flat_map(|p| [p.name.clone(), p.id.clone()])
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> PropertyCipher {
+        PropertyCipher::from_base64_key(&STANDARD.encode([7u8; 32])).unwrap()
+    }
+
+    #[test]
+    fn test_encryption_is_deterministic_and_round_trips() {
+        let cipher = cipher();
+        let value = json!(1234.5);
+
+        let first = cipher.encrypt_value("db-1", "part-1/cost", &value).unwrap();
+        let second = cipher.encrypt_value("db-1", "part-1/cost", &value).unwrap();
+        assert!(PropertyCipher::is_encrypted(&first));
+        assert_eq!(first, second);
+        assert_eq!(
+            cipher.decrypt_value("db-1", "part-1/cost", &first).unwrap(),
+            value
+        );
+
+        // Another database or location gets a different ciphertext and can't open this one
+        let other_db = cipher.encrypt_value("db-2", "part-1/cost", &value).unwrap();
+        assert_ne!(first, other_db);
+        assert!(cipher.decrypt_value("db-2", "part-1/cost", &first).is_err());
+        assert!(cipher.decrypt_value("db-1", "part-2/cost", &first).is_err());
+    }
+
+    #[test]
+    fn test_rejects_short_keys() {
+        assert!(PropertyCipher::from_base64_key(&STANDARD.encode([1u8; 16])).is_err());
+        assert!(PropertyCipher::from_base64_key("not base64!").is_err());
+    }
+}
@@ -0,0 +1,1092 @@
+use crate::model::{
+    Branch, ClassDef, Commit, CommitCursor, CommitData, CommitPage, CommitTag, Database, Id,
+    Instance, InstanceFilter, NewCommit, NewCommitTag, NewWorkingCommit, Schema, TagQuery,
+    TaggedCommit, TaggedCommitPage, WorkingCommit, WorkingCommitStatus,
+};
+use crate::store::traits::{
+    BranchStore, CommitStore, DatabaseStore, InstanceStore, SchemaStore, Store, TagStore,
+    WorkingCommitStore,
+};
+use crate::store::working_commit_cache::{WorkingCommitCache, WorkingCommitChange};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+/// Non-durable `Store` backed by in-process maps, for tests that shouldn't need Postgres.
+///
+/// Only compiled for `cfg(test)` or with the `test-util` feature, so it can never back
+/// a production server. Commits are built with `WorkingCommit::to_commit`, the same
+/// hashing and parent linking as `PostgresStore`.
+#[derive(Clone, Default)]
+pub struct InMemoryStore {
+    state: Arc<RwLock<MemoryState>>,
+    /// Fed like `PostgresStore`'s write-through cache so change subscribers see the same events
+    working_commit_cache: Arc<WorkingCommitCache>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    databases: HashMap<Id, Database>,
+    /// Keyed by (database_id, branch name), mirroring the composite key in Postgres
+    branches: HashMap<(Id, String), Branch>,
+    commits: HashMap<String, Commit>,
+    working_commits: HashMap<Id, WorkingCommit>,
+    tags: HashMap<i32, CommitTag>,
+    next_tag_id: i32,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, MemoryState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, MemoryState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Decompressed data of the commit a branch currently points at
+    async fn branch_head_data(
+        &self,
+        database_id: &Id,
+        branch_name: &str,
+    ) -> Result<Option<CommitData>> {
+        let branch = self
+            .get_branch(database_id, branch_name)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Branch not found: {}/{}", database_id, branch_name))?;
+
+        let Some(ref commit_hash) = branch.current_commit_hash else {
+            return Ok(None);
+        };
+
+        let data = self
+            .get_commit_data(commit_hash)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Commit not found: {}", commit_hash))?;
+        Ok(Some(data))
+    }
+}
+
+#[async_trait::async_trait]
+impl DatabaseStore for InMemoryStore {
+    async fn get_database(&self, id: &Id) -> Result<Option<Database>> {
+        Ok(self.read().databases.get(id).cloned())
+    }
+
+    async fn list_databases(&self) -> Result<Vec<Database>> {
+        let mut databases: Vec<Database> = self.read().databases.values().cloned().collect();
+        databases.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        Ok(databases)
+    }
+
+    async fn upsert_database(&self, database: Database) -> Result<()> {
+        let mut state = self.write();
+        match state.databases.get_mut(&database.id) {
+            // created_at is immutable once inserted, as in Postgres
+            Some(existing) => {
+                existing.name = database.name;
+                existing.description = database.description;
+                existing.default_branch_name = database.default_branch_name;
+                existing.commit_message_policy = database.commit_message_policy;
+            }
+            None => {
+                state.databases.insert(database.id.clone(), database);
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_database(&self, id: &Id) -> Result<bool> {
+        let mut state = self.write();
+        state.databases.remove(id);
+        state.branches.retain(|(database_id, _), _| database_id != id);
+        state.working_commits.retain(|_, wc| &wc.database_id != id);
+        let removed: HashSet<String> = state
+            .commits
+            .values()
+            .filter(|commit| &commit.database_id == id)
+            .map(|commit| commit.hash.clone())
+            .collect();
+        state.commits.retain(|_, commit| &commit.database_id != id);
+        // Tags go with their commits, like the foreign key cascade in Postgres
+        state.tags.retain(|_, tag| !removed.contains(&tag.commit_hash));
+        Ok(true)
+    }
+}
+
+#[async_trait::async_trait]
+impl BranchStore for InMemoryStore {
+    async fn get_branch(&self, database_id: &Id, name: &str) -> Result<Option<Branch>> {
+        let key = (database_id.clone(), name.to_string());
+        Ok(self.read().branches.get(&key).cloned())
+    }
+
+    async fn list_branches_for_database(&self, database_id: &Id) -> Result<Vec<Branch>> {
+        let mut branches: Vec<Branch> = self
+            .read()
+            .branches
+            .values()
+            .filter(|b| &b.database_id == database_id)
+            .cloned()
+            .collect();
+        branches.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        Ok(branches)
+    }
+
+    async fn upsert_branch(&self, branch: Branch) -> Result<()> {
+        let mut state = self.write();
+        let key = (branch.database_id.clone(), branch.name.clone());
+        match state.branches.get_mut(&key) {
+            Some(existing) => {
+                let created_at = existing.created_at.clone();
+                *existing = Branch {
+                    created_at,
+                    ..branch
+                };
+            }
+            None => {
+                state.branches.insert(key, branch);
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_branch(&self, database_id: &Id, name: &str) -> Result<bool> {
+        let key = (database_id.clone(), name.to_string());
+        Ok(self.write().branches.remove(&key).is_some())
+    }
+
+    async fn delete_branches(&self, database_id: &Id, names: &[String]) -> Result<usize> {
+        let mut state = self.write();
+        state.working_commits.retain(|_, wc| {
+            &wc.database_id != database_id
+                || !wc.branch_name.as_ref().is_some_and(|name| names.contains(name))
+        });
+        let before = state.branches.len();
+        state
+            .branches
+            .retain(|(db_id, name), _| db_id != database_id || !names.contains(name));
+        Ok(before - state.branches.len())
+    }
+
+    async fn get_default_branch(&self, database_id: &Id) -> Result<Option<Branch>> {
+        let database = self
+            .get_database(database_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Database not found: {}", database_id))?;
+
+        self.get_branch(database_id, &database.default_branch_name)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl SchemaStore for InMemoryStore {
+    async fn get_schema(&self, database_id: &Id, branch_name: &str) -> Result<Option<Schema>> {
+        match self.branch_head_data(database_id, branch_name).await? {
+            Some(data) => Ok(Some(data.schema)),
+            None => Ok(Some(Schema {
+                id: format!("schema-{}-{}", database_id, branch_name),
+                classes: Vec::new(),
+                description: Some("Empty schema".to_string()),
+            })),
+        }
+    }
+
+    async fn get_class(
+        &self,
+        database_id: &Id,
+        branch_name: &str,
+        class_id: &Id,
+    ) -> Result<Option<ClassDef>> {
+        let schema = self
+            .get_schema(database_id, branch_name)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Schema not found for branch: {}/{}",
+                    database_id,
+                    branch_name
+                )
+            })?;
+
+        Ok(schema.get_class_by_id(class_id).cloned())
+    }
+}
+
+#[async_trait::async_trait]
+impl InstanceStore for InMemoryStore {
+    async fn get_instance(
+        &self,
+        database_id: &Id,
+        branch_name: &str,
+        id: &Id,
+    ) -> Result<Option<Instance>> {
+        Ok(self
+            .branch_head_data(database_id, branch_name)
+            .await?
+            .and_then(|data| data.instances.into_iter().find(|inst| &inst.id == id)))
+    }
+
+    async fn list_instances_for_branch(
+        &self,
+        database_id: &Id,
+        branch_name: &str,
+        filter: Option<InstanceFilter>,
+    ) -> Result<Vec<Instance>> {
+        let Some(data) = self.branch_head_data(database_id, branch_name).await? else {
+            return Ok(Vec::new());
+        };
+
+        let mut instances: Vec<Instance> = match filter.as_ref().and_then(|f| f.types.as_ref()) {
+            Some(types) => data
+                .instances
+                .iter()
+                .filter(|inst| types.contains(&inst.class_id))
+                .cloned()
+                .collect(),
+            None => data.instances.clone(),
+        };
+        if let Some(filter_expr) = filter.as_ref().and_then(|f| f.where_clause.as_ref()) {
+            filter_expr.check_filterable(&data.schema)?;
+            // Relationship selections resolve against the whole branch, not just the listed types
+            let context = crate::logic::FilterContext {
+                instances: &data.instances,
+                schema: &data.schema,
+            };
+            instances =
+                crate::logic::filter_instances_with_context(instances, filter_expr, &context);
+        }
+
+        // Same ordering as PostgresStore
+        instances.sort_by(|a, b| a.class_id.cmp(&b.class_id).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(instances)
+    }
+
+    async fn find_by_type_in_branch(
+        &self,
+        database_id: &Id,
+        branch_name: &str,
+        class_id: &Id,
+    ) -> Result<Vec<Instance>> {
+        let filter = InstanceFilter {
+            types: Some(vec![class_id.clone()]),
+            limit: None,
+            sort: None,
+            where_clause: None,
+        };
+
+        self.list_instances_for_branch(database_id, branch_name, Some(filter))
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl CommitStore for InMemoryStore {
+    async fn get_commit(&self, hash: &str) -> Result<Option<Commit>> {
+        Ok(self.read().commits.get(hash).cloned())
+    }
+
+    async fn list_commits_for_database(
+        &self,
+        database_id: &Id,
+        parent_hash: Option<&str>,
+    ) -> Result<Vec<Commit>> {
+        let mut commits: Vec<Commit> = self
+            .read()
+            .commits
+            .values()
+            .filter(|c| &c.database_id == database_id)
+            .filter(|c| parent_hash.is_none() || c.parent_hash.as_deref() == parent_hash)
+            .cloned()
+            .collect();
+        commits.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(commits)
+    }
+
+    async fn list_commits_paginated(
+        &self,
+        database_id: &Id,
+        after: Option<&CommitCursor>,
+        limit: usize,
+    ) -> Result<CommitPage> {
+        fn key(c: &Commit) -> (&str, &str) {
+            (&c.created_at, &c.hash)
+        }
+        let mut commits: Vec<Commit> = self
+            .read()
+            .commits
+            .values()
+            .filter(|c| &c.database_id == database_id)
+            .filter(|c| after.is_none_or(|cursor| key(c) < (&cursor.created_at, &cursor.hash)))
+            .cloned()
+            .collect();
+        commits.sort_by(|a, b| key(b).cmp(&key(a)));
+
+        let next_cursor = if commits.len() > limit {
+            commits.truncate(limit);
+            commits.last().map(CommitCursor::after)
+        } else {
+            None
+        };
+        Ok(CommitPage {
+            commits,
+            next_cursor,
+        })
+    }
+
+    async fn create_commit(&self, new_commit: NewCommit) -> Result<Commit> {
+        let mut working_commit = self
+            .get_working_commit(&new_commit.working_commit_id)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!("Working commit not found: {}", new_commit.working_commit_id)
+            })?;
+        if let Some(based_on_hash) = new_commit.based_on_hash {
+            working_commit.based_on_hash = based_on_hash;
+        }
+
+        let commit = working_commit.to_commit(new_commit.message)?;
+
+        let mut state = self.write();
+        // The hash is the primary key in Postgres, so a duplicate insert fails there too
+        if state.commits.contains_key(&commit.hash) {
+            return Err(anyhow::anyhow!(
+                "Failed to create commit: hash {} already exists",
+                commit.hash
+            ));
+        }
+        state.commits.insert(commit.hash.clone(), commit.clone());
+
+        Ok(commit)
+    }
+
+    async fn get_commit_data(&self, hash: &str) -> Result<Option<CommitData>> {
+        match self.get_commit(hash).await? {
+            Some(commit) => commit
+                .get_data()
+                .map(Some)
+                .map_err(|e| anyhow::anyhow!("Failed to decompress commit data: {}", e)),
+            None => Ok(None),
+        }
+    }
+
+    async fn commit_exists(&self, hash: &str) -> Result<bool> {
+        Ok(self.read().commits.contains_key(hash))
+    }
+
+    async fn is_ancestor(
+        &self,
+        database_id: &Id,
+        ancestor_hash: &str,
+        descendant_hash: &str,
+    ) -> Result<Option<usize>> {
+        let state = self.read();
+        let mut visited = HashSet::new();
+        let mut current = state
+            .commits
+            .get(descendant_hash)
+            .filter(|commit| &commit.database_id == database_id);
+        while let Some(commit) = current {
+            if commit.hash == ancestor_hash {
+                return Ok(Some(visited.len()));
+            }
+            if !visited.insert(commit.hash.as_str()) {
+                break;
+            }
+            current = commit
+                .parent_hash
+                .as_ref()
+                .and_then(|parent| state.commits.get(parent))
+                .filter(|parent| &parent.database_id == database_id);
+        }
+        Ok(None)
+    }
+}
+
+#[async_trait::async_trait]
+impl WorkingCommitStore for InMemoryStore {
+    async fn get_working_commit(&self, id: &Id) -> Result<Option<WorkingCommit>> {
+        Ok(self.read().working_commits.get(id).cloned())
+    }
+
+    async fn list_working_commits_for_branch(
+        &self,
+        database_id: &Id,
+        branch_name: &str,
+    ) -> Result<Vec<WorkingCommit>> {
+        let mut working_commits: Vec<WorkingCommit> = self
+            .read()
+            .working_commits
+            .values()
+            .filter(|wc| {
+                &wc.database_id == database_id && wc.branch_name.as_deref() == Some(branch_name)
+            })
+            .cloned()
+            .collect();
+        working_commits.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(working_commits)
+    }
+
+    async fn create_working_commit(
+        &self,
+        database_id: &Id,
+        branch_name: &str,
+        new_working_commit: NewWorkingCommit,
+    ) -> Result<WorkingCommit> {
+        let branch = self
+            .get_branch(database_id, branch_name)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Branch not found: {}/{}", database_id, branch_name))?;
+
+        let current_schema = self
+            .get_schema(database_id, branch_name)
+            .await?
+            .unwrap_or_else(|| Schema {
+                id: format!("schema-{}", database_id),
+                description: None,
+                classes: Vec::new(),
+            });
+        let current_instances = self
+            .list_instances_for_branch(database_id, branch_name, None)
+            .await?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let working_commit = WorkingCommit {
+            id: crate::model::generate_id(),
+            database_id: database_id.clone(),
+            branch_name: Some(branch_name.to_string()),
+            based_on_hash: branch.current_commit_hash.unwrap_or_default(),
+            author: new_working_commit.author,
+            created_at: now.clone(),
+            updated_at: now,
+            version: 0,
+            schema_data: current_schema,
+            instances_data: current_instances,
+            status: WorkingCommitStatus::Active,
+            merge_state: None,
+            merge_metadata: None,
+        };
+
+        self.write()
+            .working_commits
+            .insert(working_commit.id.clone(), working_commit.clone());
+        self.working_commit_cache.put(working_commit.clone()).await;
+
+        Ok(working_commit)
+    }
+
+    async fn update_working_commit(&self, mut working_commit: WorkingCommit) -> Result<()> {
+        working_commit.touch();
+        {
+            let mut data = self.write();
+            // Counted from the stored copy, so concurrent writes each get their own version
+            let saved_version = data
+                .working_commits
+                .get(&working_commit.id)
+                .map_or(working_commit.version, |saved| saved.version);
+            working_commit.version = saved_version + 1;
+            data.working_commits
+                .insert(working_commit.id.clone(), working_commit.clone());
+        }
+        self.working_commit_cache.put(working_commit).await;
+        Ok(())
+    }
+
+    async fn update_working_commit_at_version(
+        &self,
+        mut working_commit: WorkingCommit,
+        expected_version: u64,
+    ) -> Result<bool> {
+        working_commit.touch();
+        {
+            let mut data = self.write();
+            let saved_version = data
+                .working_commits
+                .get(&working_commit.id)
+                .map(|saved| saved.version);
+            if saved_version != Some(expected_version) {
+                return Ok(false);
+            }
+            working_commit.version = expected_version + 1;
+            data.working_commits
+                .insert(working_commit.id.clone(), working_commit.clone());
+        }
+        self.working_commit_cache.put(working_commit).await;
+        Ok(true)
+    }
+
+    async fn delete_working_commit(&self, id: &Id) -> Result<bool> {
+        let removed = self.write().working_commits.remove(id).is_some();
+        self.working_commit_cache.remove(id).await;
+        Ok(removed)
+    }
+
+    async fn get_active_working_commit_for_branch(
+        &self,
+        database_id: &Id,
+        branch_name: &str,
+    ) -> Result<Option<WorkingCommit>> {
+        let mut state = self.write();
+        let mut active: Vec<&mut WorkingCommit> = state
+            .working_commits
+            .values_mut()
+            .filter(|wc| {
+                &wc.database_id == database_id
+                    && wc.branch_name.as_deref() == Some(branch_name)
+                    && wc.status == WorkingCommitStatus::Active
+            })
+            .collect();
+        active.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+
+        // Like PostgresStore, only the most recently updated active working commit survives
+        let mut active = active.into_iter();
+        let latest = active.next().map(|wc| wc.clone());
+        for duplicate in active {
+            duplicate.status = WorkingCommitStatus::Abandoned;
+        }
+
+        Ok(latest)
+    }
+
+    async fn force_persist_working_commit(&self, id: &Id) -> Result<()> {
+        // Nothing to flush; only report missing ids like PostgresStore does
+        match self.read().working_commits.contains_key(id) {
+            true => Ok(()),
+            false => Err(anyhow::anyhow!("Working commit not found: {}", id)),
+        }
+    }
+
+    fn subscribe_working_commit_changes(&self) -> broadcast::Receiver<WorkingCommitChange> {
+        self.working_commit_cache.subscribe()
+    }
+}
+
+#[async_trait::async_trait]
+impl TagStore for InMemoryStore {
+    async fn create_commit_tag(&self, tag: NewCommitTag) -> Result<CommitTag> {
+        let mut state = self.write();
+        state.next_tag_id += 1;
+        let commit_tag = tag.to_commit_tag(state.next_tag_id);
+        state.tags.insert(commit_tag.id, commit_tag.clone());
+        Ok(commit_tag)
+    }
+
+    async fn get_commit_tags(&self, commit_hash: &str) -> Result<Vec<CommitTag>> {
+        let mut tags: Vec<CommitTag> = self
+            .read()
+            .tags
+            .values()
+            .filter(|t| t.commit_hash == commit_hash)
+            .cloned()
+            .collect();
+        tags.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+        Ok(tags)
+    }
+
+    async fn get_commit_tag(&self, tag_id: i32) -> Result<Option<CommitTag>> {
+        Ok(self.read().tags.get(&tag_id).cloned())
+    }
+
+    async fn move_commit_tag(
+        &self,
+        tag_id: i32,
+        commit_hash: &str,
+        tag_description: Option<String>,
+    ) -> Result<Option<CommitTag>> {
+        let mut state = self.write();
+        let Some(tag) = state.tags.get_mut(&tag_id) else {
+            return Ok(None);
+        };
+        tag.commit_hash = commit_hash.to_string();
+        if tag_description.is_some() {
+            tag.tag_description = tag_description;
+        }
+        Ok(Some(tag.clone()))
+    }
+
+    async fn delete_commit_tag(&self, tag_id: i32) -> Result<bool> {
+        Ok(self.write().tags.remove(&tag_id).is_some())
+    }
+
+    async fn list_orphaned_commit_tags(&self) -> Result<Vec<CommitTag>> {
+        let state = self.read();
+        let mut tags: Vec<CommitTag> = state
+            .tags
+            .values()
+            .filter(|t| !state.commits.contains_key(&t.commit_hash))
+            .cloned()
+            .collect();
+        tags.sort_by_key(|t| t.id);
+        Ok(tags)
+    }
+
+    async fn search_commits_by_tags(
+        &self,
+        database_id: &Id,
+        query: TagQuery,
+    ) -> Result<Vec<TaggedCommit>> {
+        self.list_tagged_commits(database_id, query.limit).await
+    }
+
+    async fn get_tagged_commit(&self, commit_hash: &str) -> Result<Option<TaggedCommit>> {
+        let Some(commit) = self.get_commit(commit_hash).await? else {
+            return Ok(None);
+        };
+        let tags = self.get_commit_tags(commit_hash).await?;
+
+        Ok(Some(TaggedCommit {
+            commit_hash: commit.hash,
+            database_id: commit.database_id,
+            commit_message: commit.message,
+            commit_author: commit.author,
+            commit_created_at: commit.created_at,
+            tags,
+        }))
+    }
+
+    async fn list_tagged_commits(
+        &self,
+        database_id: &Id,
+        limit: Option<i32>,
+    ) -> Result<Vec<TaggedCommit>> {
+        let limit_value = limit.unwrap_or(50).min(100) as usize;
+
+        let mut commits: Vec<Commit> = {
+            let state = self.read();
+            state
+                .commits
+                .values()
+                .filter(|c| &c.database_id == database_id)
+                .filter(|c| state.tags.values().any(|t| t.commit_hash == c.hash))
+                .cloned()
+                .collect()
+        };
+        commits.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        commits.truncate(limit_value);
+
+        let mut tagged_commits = Vec::new();
+        for commit in commits {
+            let tags = self.get_commit_tags(&commit.hash).await?;
+            tagged_commits.push(TaggedCommit {
+                commit_hash: commit.hash,
+                database_id: commit.database_id,
+                commit_message: commit.message,
+                commit_author: commit.author,
+                commit_created_at: commit.created_at,
+                tags,
+            });
+        }
+
+        Ok(tagged_commits)
+    }
+
+    async fn query_tagged_commits(
+        &self,
+        database_id: &Id,
+        query: &TagQuery,
+    ) -> Result<TaggedCommitPage> {
+        let tag_name = query.tag_name.as_ref().map(|name| name.to_lowercase());
+        let matches = |tag: &CommitTag| {
+            query
+                .tag_type
+                .as_ref()
+                .is_none_or(|tag_type| &tag.tag_type == tag_type)
+                && tag_name
+                    .as_ref()
+                    .is_none_or(|name| tag.tag_name.to_lowercase().contains(name))
+        };
+
+        // Keyed by the newest matching tag, ties broken by tag ID like `get_commit_tags`
+        let mut commits: Vec<((String, i32), Commit)> = {
+            let state = self.read();
+            state
+                .commits
+                .values()
+                .filter(|c| &c.database_id == database_id)
+                .filter_map(|c| {
+                    let newest = state
+                        .tags
+                        .values()
+                        .filter(|t| t.commit_hash == c.hash && matches(t))
+                        .map(|t| (t.created_at.clone(), t.id))
+                        .max()?;
+                    Some((newest, c.clone()))
+                })
+                .collect()
+        };
+        commits.sort_by(|(a, _), (b, _)| b.cmp(a));
+        let total = commits.len();
+
+        let mut tagged_commits = Vec::new();
+        for (_, commit) in commits
+            .into_iter()
+            .skip(query.offset.unwrap_or(0).max(0) as usize)
+            .take(
+                query
+                    .limit
+                    .map_or(usize::MAX, |limit| limit.max(0) as usize),
+            )
+        {
+            let tags = self.get_commit_tags(&commit.hash).await?;
+            tagged_commits.push(TaggedCommit {
+                commit_hash: commit.hash,
+                database_id: commit.database_id,
+                commit_message: commit.message,
+                commit_author: commit.author,
+                commit_created_at: commit.created_at,
+                tags,
+            });
+        }
+
+        Ok(TaggedCommitPage {
+            commits: tagged_commits,
+            total,
+        })
+    }
+}
+
+impl Store for InMemoryStore {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ClassDef, DataType, DerivedDef, Expr, TagType};
+
+    async fn store_with_main() -> (InMemoryStore, Database) {
+        let store = InMemoryStore::new();
+        let database = Database::new("test".to_string(), None);
+        store.upsert_database(database.clone()).await.unwrap();
+        store
+            .upsert_branch(Branch::new_main_branch(database.id.clone(), None))
+            .await
+            .unwrap();
+        (store, database)
+    }
+
+    #[tokio::test]
+    async fn test_commit_links_parent_to_branch_head() {
+        let (store, database) = store_with_main().await;
+
+        let mut wc = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        assert!(wc.based_on_hash.is_empty());
+        wc.schema_data.classes.push(ClassDef {
+            id: "class-a".to_string(),
+            name: "A".to_string(),
+            ..ClassDef::default()
+        });
+        store.update_working_commit(wc.clone()).await.unwrap();
+
+        let first = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc.id.clone(),
+                message: "first".to_string(),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(first.parent_hash, None);
+        assert_eq!(first.schema_classes_count, 1);
+
+        // Advancing the branch head is the caller's job, as with PostgresStore
+        let mut main = store.get_branch(&database.id, "main").await.unwrap().unwrap();
+        assert_eq!(main.current_commit_hash, None);
+        main.current_commit_hash = Some(first.hash.clone());
+        store.upsert_branch(main).await.unwrap();
+
+        let schema = store.get_schema(&database.id, "main").await.unwrap().unwrap();
+        assert_eq!(schema.classes.len(), 1);
+
+        let wc2 = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        let second = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc2.id.clone(),
+                message: "second".to_string(),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(second.parent_hash, Some(first.hash.clone()));
+        assert_ne!(second.hash, first.hash);
+
+        let children = store
+            .list_commits_for_database(&database.id, Some(&first.hash))
+            .await
+            .unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].hash, second.hash);
+    }
+
+    #[tokio::test]
+    async fn test_commit_data_keeps_derived_number_literals() {
+        let (store, database) = store_with_main().await;
+        let expr = Expr::Gt {
+            left: Box::new(Expr::LitNumber { value: 0.5 }),
+            right: Box::new(Expr::Prop {
+                prop: "price".to_string(),
+            }),
+        };
+        let json = serde_json::to_string(&expr).unwrap();
+        assert_eq!(serde_json::from_str::<Expr>(&json).unwrap(), expr);
+
+        let mut wc = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        wc.schema_data.classes.push(ClassDef {
+            id: "class-a".to_string(),
+            name: "A".to_string(),
+            derived: vec![DerivedDef {
+                id: "der-cheap".to_string(),
+                name: "cheap".to_string(),
+                data_type: DataType::Boolean,
+                expr: Some(expr),
+                fn_short: None,
+                materialize: None,
+            }],
+            ..ClassDef::default()
+        });
+        store.update_working_commit(wc.clone()).await.unwrap();
+        let commit = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc.id.clone(),
+                message: "derived".to_string(),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(commit.get_data().unwrap().schema, wc.schema_data);
+        let stored = store.get_commit_data(&commit.hash).await.unwrap().unwrap();
+        assert_eq!(stored.schema, wc.schema_data);
+    }
+
+    #[tokio::test]
+    async fn test_only_latest_active_working_commit_survives() {
+        let (store, database) = store_with_main().await;
+
+        let older = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        let newer = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        store.update_working_commit(newer.clone()).await.unwrap();
+
+        let active = store
+            .get_active_working_commit_for_branch(&database.id, "main")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(active.id, newer.id);
+
+        let older = store.get_working_commit(&older.id).await.unwrap().unwrap();
+        assert_eq!(older.status, WorkingCommitStatus::Abandoned);
+    }
+
+    #[tokio::test]
+    async fn test_tags_and_database_delete() {
+        let (store, database) = store_with_main().await;
+        let wc = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        let commit = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc.id,
+                message: "tagged".to_string(),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+
+        store
+            .create_commit_tag(NewCommitTag {
+                commit_hash: commit.hash.clone(),
+                tag_type: TagType::Version,
+                tag_name: "v1".to_string(),
+                tag_description: None,
+                created_by: None,
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        let tagged = store.list_tagged_commits(&database.id, None).await.unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].tags[0].tag_name, "v1");
+        assert!(store.list_orphaned_commit_tags().await.unwrap().is_empty());
+
+        let orphan = store
+            .create_commit_tag(NewCommitTag {
+                commit_hash: "gone".to_string(),
+                tag_type: TagType::Version,
+                tag_name: "v0".to_string(),
+                tag_description: None,
+                created_by: None,
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        let orphans = store.list_orphaned_commit_tags().await.unwrap();
+        assert_eq!(orphans, vec![orphan.clone()]);
+
+        assert!(store.delete_database(&database.id).await.unwrap());
+        assert!(!store.commit_exists(&commit.hash).await.unwrap());
+        assert!(store.get_commit_tags(&commit.hash).await.unwrap().is_empty());
+        assert_eq!(store.list_orphaned_commit_tags().await.unwrap(), vec![orphan]);
+        assert!(store
+            .list_branches_for_database(&database.id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_filter_resolves_relationships_against_the_branch() {
+        let (store, database) = store_with_main().await;
+        let mut wc = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        wc.schema_data = serde_json::from_value(serde_json::json!({
+            "id": "schema-1",
+            "classes": [{
+                "id": "class-chair",
+                "name": "Chair",
+                "properties": [],
+                "derived": [],
+                "relationships": [{
+                    "id": "rel-legs",
+                    "name": "legs",
+                    "targets": ["class-leg"],
+                    "quantifier": "any"
+                }]
+            }]
+        }))
+        .unwrap();
+        for (id, wood) in [("leg-oak", "oak"), ("leg-pine", "pine")] {
+            let mut leg = Instance {
+                id: id.to_string(),
+                class_id: "class-leg".to_string(),
+                ..Instance::default()
+            };
+            leg.properties.insert(
+                "wood".to_string(),
+                serde_json::from_value(serde_json::json!(wood)).unwrap(),
+            );
+            wc.instances_data.push(leg);
+        }
+        // Selected by a filter, so the leg ids only exist once resolved against the branch
+        let mut chair = Instance {
+            id: "chair".to_string(),
+            class_id: "class-chair".to_string(),
+            ..Instance::default()
+        };
+        chair.relationships.insert(
+            "legs".to_string(),
+            serde_json::from_value(serde_json::json!({
+                "filter": { "type": ["class-leg"], "where": { "eq": ["$.wood", "oak"] } }
+            }))
+            .unwrap(),
+        );
+        wc.instances_data.push(chair);
+        store.update_working_commit(wc.clone()).await.unwrap();
+        let commit = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc.id,
+                message: "chairs".to_string(),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+        let mut main = store.get_branch(&database.id, "main").await.unwrap().unwrap();
+        main.current_commit_hash = Some(commit.hash);
+        store.upsert_branch(main).await.unwrap();
+
+        let chairs_with = |leg: &str| {
+            let filter = InstanceFilter {
+                types: Some(vec!["class-chair".to_string()]),
+                where_clause: Some(
+                    crate::logic::parse_filter_expr(
+                        serde_json::json!({"array_contains": ["$.legs", leg]}),
+                    )
+                    .unwrap(),
+                ),
+                sort: None,
+                limit: None,
+            };
+            store.list_instances_for_branch(&database.id, "main", Some(filter))
+        };
+        let found = chairs_with("leg-oak").await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "chair");
+        assert!(chairs_with("leg-pine").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_is_ancestor_stops_at_a_parent_cycle() {
+        let (store, database) = store_with_main().await;
+        let wc = store
+            .create_working_commit(&database.id, "main", NewWorkingCommit { author: None })
+            .await
+            .unwrap();
+        let commit = store
+            .create_commit(NewCommit {
+                database_id: database.id.clone(),
+                working_commit_id: wc.id,
+                message: "loop".to_string(),
+                author: None,
+                based_on_hash: None,
+            })
+            .await
+            .unwrap();
+        // Corrupt history: two commits that are each other's parent
+        for (hash, parent) in [("loop-a", "loop-b"), ("loop-b", "loop-a")] {
+            let mut looped = commit.clone();
+            looped.hash = hash.to_string();
+            looped.parent_hash = Some(parent.to_string());
+            store.write().commits.insert(hash.to_string(), looped);
+        }
+
+        let distance = store.is_ancestor(&database.id, "loop-b", "loop-a").await;
+        assert_eq!(distance.unwrap(), Some(1));
+        let distance = store
+            .is_ancestor(&database.id, &commit.hash, "loop-a")
+            .await;
+        assert_eq!(distance.unwrap(), None);
+
+        // The merge engine's history walk ends at the loop as well
+        let common = crate::logic::MergeEngine::find_common_ancestor(
+            &store,
+            &database.id,
+            "loop-a",
+            &commit.hash,
+        )
+        .await;
+        assert_eq!(common.unwrap(), None);
+    }
+}
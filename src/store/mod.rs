@@ -1,7 +1,15 @@
+#[cfg(test)]
+mod conformance;
+#[cfg(any(test, feature = "test-util"))]
+pub mod memory;
+pub mod encryption;
 pub mod postgres;
 pub mod traits;
 pub mod working_commit_cache;
 
+#[cfg(any(test, feature = "test-util"))]
+pub use memory::InMemoryStore;
+pub use encryption::PropertyCipher;
 pub use postgres::*;
 pub use traits::*;
 pub use working_commit_cache::*;
@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use crate::config::DatabaseConfig;
 use crate::model::{Branch, ClassDef, Database, Id, Instance, InstanceFilter, Schema};
 use crate::store::traits::{
     BranchStore, CommitStore, DatabaseStore, InstanceStore, SchemaStore, Store, WorkingCommitStore,
@@ -14,6 +15,7 @@ pub struct PostgresStore {
     pool: PgPool,
     commit_cache: Arc<Mutex<CommitCache>>,
     working_commit_cache: Arc<crate::store::working_commit_cache::WorkingCommitCache>,
+    property_cipher: Option<Arc<crate::store::encryption::PropertyCipher>>,
 }
 
 impl Clone for PostgresStore {
@@ -22,6 +24,7 @@ impl Clone for PostgresStore {
             pool: self.pool.clone(),
             commit_cache: Arc::clone(&self.commit_cache),
             working_commit_cache: Arc::clone(&self.working_commit_cache),
+            property_cipher: self.property_cipher.clone(),
         }
     }
 }
@@ -96,15 +99,46 @@ impl CommitCache {
     }
 }
 
+/// Pool settings from `config`; unset fields keep the sqlx defaults
+pub fn pool_options(config: &DatabaseConfig) -> PgPoolOptions {
+    let mut options = PgPoolOptions::new();
+    if let Some(max) = config.max_connections {
+        options = options.max_connections(max);
+    }
+    if let Some(min) = config.min_connections {
+        options = options.min_connections(min);
+    }
+    if let Some(seconds) = config.acquire_timeout {
+        options = options.acquire_timeout(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = config.idle_timeout {
+        options = options.idle_timeout(Duration::from_secs(seconds));
+    }
+    options
+}
+
+/// Whether `error` comes from waiting longer than the acquire timeout for a pool connection
+pub fn is_pool_timeout(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::PoolTimedOut)
+        )
+    })
+}
+
 impl PostgresStore {
-    /// Create a new PostgreSQL store with the given database URL
-    pub async fn new(database_url: &str) -> Result<Self> {
-        let pool = PgPoolOptions::new()
-            .max_connections(20)
+    /// Create a new PostgreSQL store with the given database URL and pool settings
+    pub async fn new(database_url: &str, config: &DatabaseConfig) -> Result<Self> {
+        let pool = pool_options(config)
             .connect(database_url)
             .await
             .context("Failed to create PostgreSQL connection pool")?;
+        Ok(Self::with_pool(pool))
+    }
 
+    /// Store on top of an existing connection pool
+    pub fn with_pool(pool: PgPool) -> Self {
         // Create commit cache with reasonable defaults:
         // - Max 1000 commits in memory
         // - TTL of 1 hour
@@ -116,11 +150,61 @@ impl PostgresStore {
         // Create working commit cache with 1 hour TTL
         let working_commit_cache = Arc::new(crate::store::working_commit_cache::WorkingCommitCache::new());
 
-        Ok(Self {
+        Self {
             pool,
             commit_cache,
             working_commit_cache,
-        })
+            property_cipher: None,
+        }
+    }
+
+    /// Encrypt `encrypted` properties in commit data with the given cipher
+    pub fn with_property_cipher(
+        mut self,
+        cipher: crate::store::encryption::PropertyCipher,
+    ) -> Self {
+        self.property_cipher = Some(Arc::new(cipher));
+        self
+    }
+
+    /// Encrypt flagged property values before they are hashed into a commit blob
+    fn encrypt_for_commit(&self, working_commit: &mut crate::model::WorkingCommit) -> Result<()> {
+        match &self.property_cipher {
+            Some(cipher) => cipher.encrypt_instances(
+                &working_commit.database_id,
+                &working_commit.schema_data,
+                &mut working_commit.instances_data,
+            ),
+            None => {
+                // Never fall back to writing sensitive values in plaintext
+                match working_commit
+                    .schema_data
+                    .classes
+                    .iter()
+                    .find(|c| c.properties.iter().any(|p| p.encrypted))
+                {
+                    Some(class_def) => Err(anyhow::anyhow!(
+                        "Class '{}' has encrypted properties but no encryption key is configured",
+                        class_def.name
+                    )),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Decrypt property values of a commit read from the database, keeping its hash
+    fn decrypt_commit(&self, commit: &mut crate::model::Commit) -> Result<()> {
+        let Some(cipher) = &self.property_cipher else {
+            return Ok(());
+        };
+        let mut data = commit
+            .get_data()
+            .map_err(|e| anyhow::anyhow!("Failed to get commit data: {}", e))?;
+        if cipher.decrypt_instances(&commit.database_id, &mut data.instances)? {
+            commit.replace_data(&data);
+        }
+        Ok(())
     }
 
     /// Run database migrations
@@ -134,6 +218,15 @@ impl PostgresStore {
         Ok(())
     }
 
+    fn parse_commit_message_policy(
+        value: Option<serde_json::Value>,
+    ) -> Result<Option<crate::model::CommitMessagePolicy>> {
+        value
+            .map(serde_json::from_value)
+            .transpose()
+            .context("Failed to deserialize commit message policy")
+    }
+
     /// Get a reference to the connection pool
     pub fn pool(&self) -> &PgPool {
         &self.pool
@@ -146,7 +239,7 @@ impl PostgresStore {
         let rows = sqlx::query(
             r#"
             SELECT id, database_id, branch_name, based_on_hash, author, created_at, updated_at,
-                   schema_data, instances_data, status, merge_state
+                   schema_data, instances_data, status, merge_state, merge_metadata, version
             FROM working_commits
             WHERE status = 'active'
             ORDER BY updated_at DESC
@@ -196,14 +289,16 @@ impl PostgresStore {
                 updated_at: row
                     .get::<chrono::DateTime<chrono::Utc>, _>("updated_at")
                     .to_rfc3339(),
+                version: row.get::<i64, _>("version") as u64,
                 schema_data,
                 instances_data,
                 status,
                 merge_state,
+                merge_metadata: Self::parse_merge_metadata(row.get("merge_metadata"))?,
             };
 
-            // Put in cache
-            self.working_commit_cache.put(working_commit).await;
+            // Cache what was read
+            self.working_commit_cache.load(working_commit).await;
             count += 1;
         }
 
@@ -233,6 +328,101 @@ impl PostgresStore {
         }
     }
 
+    /// Parse a commit's merge strategy column; unknown values read as absent
+    fn parse_merge_strategy(
+        strategy: Option<String>,
+    ) -> Option<crate::model::merge::MergeStrategy> {
+        match strategy.as_deref()? {
+            "recursive" => Some(crate::model::merge::MergeStrategy::Recursive),
+            "ours" => Some(crate::model::merge::MergeStrategy::Ours),
+            "theirs" => Some(crate::model::merge::MergeStrategy::Theirs),
+            _ => None,
+        }
+    }
+
+    /// Convert a merge strategy to its column value
+    fn merge_strategy_to_string(strategy: &crate::model::merge::MergeStrategy) -> &'static str {
+        match strategy {
+            crate::model::merge::MergeStrategy::Recursive => "recursive",
+            crate::model::merge::MergeStrategy::Ours => "ours",
+            crate::model::merge::MergeStrategy::Theirs => "theirs",
+        }
+    }
+
+    /// Parse a working commit's merge_metadata column
+    fn parse_merge_metadata(
+        value: Option<serde_json::Value>,
+    ) -> Result<Option<crate::model::merge::MergeMetadata>> {
+        value
+            .map(serde_json::from_value)
+            .transpose()
+            .context("Failed to deserialize merge_metadata")
+    }
+
+    /// Write a working commit's mutable state to its row
+    async fn persist_working_commit_row(
+        &self,
+        working_commit: &crate::model::WorkingCommit,
+    ) -> Result<Option<u64>> {
+        self.persist_working_commit_row_if(working_commit, None).await
+    }
+
+    /// Write a working commit's row, but only while the stored row is at `expected_version`
+    /// when one is given. The stored version is incremented by the UPDATE itself, so
+    /// concurrent writes each get their own version; returns it, or None when nothing was
+    /// written
+    async fn persist_working_commit_row_if(
+        &self,
+        working_commit: &crate::model::WorkingCommit,
+        expected_version: Option<u64>,
+    ) -> Result<Option<u64>> {
+        let schema_json = serde_json::to_value(&working_commit.schema_data)
+            .context("Failed to serialize schema data")?;
+        let instances_json = serde_json::to_value(&working_commit.instances_data)
+            .context("Failed to serialize instances data")?;
+        let status_str = Self::working_commit_status_to_string(&working_commit.status);
+
+        let merge_state_json = if let Some(merge_state) = &working_commit.merge_state {
+            Some(serde_json::to_value(merge_state).context("Failed to serialize merge_state")?)
+        } else {
+            None
+        };
+        let merge_metadata_json = working_commit
+            .merge_metadata
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .context("Failed to serialize merge_metadata")?;
+
+        let row = sqlx::query(
+            r#"
+            UPDATE working_commits
+            SET schema_data = $2, instances_data = $3, status = $4, updated_at = $5, merge_state = $6,
+                based_on_hash = $7, version = version + 1, merge_metadata = $8
+            WHERE id = $1 AND ($9::bigint IS NULL OR version = $9)
+            RETURNING version
+            "#
+        )
+        .bind(&working_commit.id)
+        .bind(schema_json)
+        .bind(instances_json)
+        .bind(status_str)
+        .bind(
+            chrono::DateTime::parse_from_rfc3339(&working_commit.updated_at)
+                .context("Failed to parse working commit updated_at")?
+                .with_timezone(&chrono::Utc)
+        )
+        .bind(merge_state_json)
+        .bind(if working_commit.based_on_hash.is_empty() { None } else { Some(working_commit.based_on_hash.as_str()) })
+        .bind(merge_metadata_json)
+        .bind(expected_version.map(|version| version as i64))
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to persist working commit")?;
+
+        Ok(row.map(|row| row.get::<i64, _>("version") as u64))
+    }
+
     /// Clear the commit cache (useful for testing or when memory is needed)
     pub fn clear_commit_cache(&self) {
         if let Ok(mut cache) = self.commit_cache.lock() {
@@ -252,7 +442,7 @@ impl PostgresStore {
 #[async_trait::async_trait]
 impl DatabaseStore for PostgresStore {
     async fn get_database(&self, id: &Id) -> Result<Option<Database>> {
-        let row = sqlx::query("SELECT id, name, description, created_at, default_branch_name FROM databases WHERE id = $1")
+        let row = sqlx::query("SELECT id, name, description, created_at, default_branch_name, commit_message_policy FROM databases WHERE id = $1")
             .bind(id)
             .fetch_optional(&self.pool)
             .await
@@ -268,38 +458,54 @@ impl DatabaseStore for PostgresStore {
             description: row.get("description"),
             created_at: row.get("created_at"),
             default_branch_name: row.get("default_branch_name"),
+            commit_message_policy: Self::parse_commit_message_policy(
+                row.get("commit_message_policy"),
+            )?,
         }))
     }
 
     async fn list_databases(&self) -> Result<Vec<Database>> {
-        let rows = sqlx::query("SELECT id, name, description, created_at, default_branch_name FROM databases ORDER BY created_at")
+        let rows = sqlx::query("SELECT id, name, description, created_at, default_branch_name, commit_message_policy FROM databases ORDER BY created_at")
             .fetch_all(&self.pool)
             .await
             .context("Failed to list databases")?;
 
         let databases = rows
             .into_iter()
-            .map(|row| Database {
-                id: row.get("id"),
-                name: row.get("name"),
-                description: row.get("description"),
-                created_at: row.get("created_at"),
-                default_branch_name: row.get("default_branch_name"),
+            .map(|row| {
+                Ok(Database {
+                    id: row.get("id"),
+                    name: row.get("name"),
+                    description: row.get("description"),
+                    created_at: row.get("created_at"),
+                    default_branch_name: row.get("default_branch_name"),
+                    commit_message_policy: Self::parse_commit_message_policy(
+                        row.get("commit_message_policy"),
+                    )?,
+                })
             })
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(databases)
     }
 
     async fn upsert_database(&self, database: Database) -> Result<()> {
+        let policy_json = match &database.commit_message_policy {
+            Some(policy) => Some(
+                serde_json::to_value(policy).context("Failed to serialize commit message policy")?,
+            ),
+            None => None,
+        };
+
         sqlx::query(
             r#"
-            INSERT INTO databases (id, name, description, created_at, default_branch_name)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO databases (id, name, description, created_at, default_branch_name, commit_message_policy)
+            VALUES ($1, $2, $3, $4, $5, $6)
             ON CONFLICT (id) DO UPDATE SET
                 name = EXCLUDED.name,
                 description = EXCLUDED.description,
                 default_branch_name = EXCLUDED.default_branch_name,
+                commit_message_policy = EXCLUDED.commit_message_policy,
                 updated_at = NOW()
             "#
         )
@@ -308,6 +514,7 @@ impl DatabaseStore for PostgresStore {
         .bind(&database.description)
         .bind(&database.created_at)
         .bind(&database.default_branch_name)
+        .bind(policy_json)
         .execute(&self.pool)
         .await
         .context("Failed to upsert database")?;
@@ -481,6 +688,40 @@ impl BranchStore for PostgresStore {
         Ok(result.rows_affected() > 0)
     }
 
+    async fn delete_branches(&self, database_id: &Id, names: &[String]) -> Result<usize> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start branch deletion transaction")?;
+
+        let working_commit_ids: Vec<String> = sqlx::query_scalar(
+            "DELETE FROM working_commits WHERE branch_database_id = $1 AND branch_name = ANY($2) RETURNING id"
+        )
+        .bind(database_id)
+        .bind(names)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to delete working commits of branches")?;
+
+        let result = sqlx::query("DELETE FROM branches WHERE database_id = $1 AND name = ANY($2)")
+            .bind(database_id)
+            .bind(names)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete branches")?;
+
+        tx.commit()
+            .await
+            .context("Failed to commit branch deletion transaction")?;
+
+        for id in &working_commit_ids {
+            self.working_commit_cache.remove(id).await;
+        }
+
+        Ok(result.rows_affected() as usize)
+    }
+
     async fn get_default_branch(&self, database_id: &Id) -> Result<Option<Branch>> {
         // First get the database to find the default branch name
         let database = self
@@ -611,19 +852,28 @@ impl InstanceStore for PostgresStore {
             .get_data()
             .map_err(|e| anyhow::anyhow!("Failed to get commit data: {}", e))?;
 
-        // Apply filter if provided
-        let mut instances = commit_data.instances;
-
-        if let Some(filter) = filter {
-            // Apply type filter
-            if let Some(types) = filter.types {
-                instances.retain(|inst| types.contains(&inst.class_id));
-            }
+        // Apply type filter
+        let mut instances: Vec<Instance> = match filter.as_ref().and_then(|f| f.types.as_ref()) {
+            Some(types) => commit_data
+                .instances
+                .iter()
+                .filter(|inst| types.contains(&inst.class_id))
+                .cloned()
+                .collect(),
+            None => commit_data.instances.clone(),
+        };
 
-            // Apply complex where clause filter if present
-            if let Some(filter_expr) = &filter.where_clause {
-                instances = crate::logic::filter_instances(instances, filter_expr);
-            }
+        // Apply complex where clause filter if present. Encrypted properties are never
+        // filterable, even though they are decrypted by now; relationship selections resolve
+        // against the whole branch, not just the listed types.
+        if let Some(filter_expr) = filter.as_ref().and_then(|f| f.where_clause.as_ref()) {
+            filter_expr.check_filterable(&commit_data.schema)?;
+            let context = crate::logic::FilterContext {
+                instances: &commit_data.instances,
+                schema: &commit_data.schema,
+            };
+            instances =
+                crate::logic::filter_instances_with_context(instances, filter_expr, &context);
         }
 
         // Sort by class_id and id for consistency
@@ -683,7 +933,8 @@ impl crate::store::traits::CommitStore for PostgresStore {
         let row = sqlx::query(
             r#"
             SELECT hash, database_id, parent_hash, author, message, created_at,
-                   data, data_size, schema_classes_count, instances_count
+                   data, data_size, schema_classes_count, instances_count,
+                   source_branch, source_commit_hash, target_commit_hash, merge_strategy
             FROM commits
             WHERE hash = $1
             "#
@@ -697,7 +948,7 @@ impl crate::store::traits::CommitStore for PostgresStore {
             return Ok(None);
         };
 
-        let commit = crate::model::Commit {
+        let mut commit = crate::model::Commit {
             hash: row.get("hash"),
             database_id: row.get("database_id"),
             parent_hash: row.get("parent_hash"),
@@ -710,7 +961,12 @@ impl crate::store::traits::CommitStore for PostgresStore {
             data_size: row.get("data_size"),
             schema_classes_count: row.get("schema_classes_count"),
             instances_count: row.get("instances_count"),
+            source_branch: row.get("source_branch"),
+            source_commit_hash: row.get("source_commit_hash"),
+            target_commit_hash: row.get("target_commit_hash"),
+            merge_strategy: Self::parse_merge_strategy(row.get("merge_strategy")),
         };
+        self.decrypt_commit(&mut commit)?;
 
         Ok(Some(commit))
     }
@@ -723,7 +979,8 @@ impl crate::store::traits::CommitStore for PostgresStore {
         let query_str = if parent_hash.is_some() {
             r#"
             SELECT hash, database_id, parent_hash, author, message, created_at, 
-                   data, data_size, schema_classes_count, instances_count
+                   data, data_size, schema_classes_count, instances_count,
+                   source_branch, source_commit_hash, target_commit_hash, merge_strategy
             FROM commits 
             WHERE database_id = $1 AND parent_hash = $2
             ORDER BY created_at DESC
@@ -731,7 +988,8 @@ impl crate::store::traits::CommitStore for PostgresStore {
         } else {
             r#"
             SELECT hash, database_id, parent_hash, author, message, created_at, 
-                   data, data_size, schema_classes_count, instances_count
+                   data, data_size, schema_classes_count, instances_count,
+                   source_branch, source_commit_hash, target_commit_hash, merge_strategy
             FROM commits 
             WHERE database_id = $1
             ORDER BY created_at DESC
@@ -749,7 +1007,7 @@ impl crate::store::traits::CommitStore for PostgresStore {
             .await
             .context("Failed to list commits for database")?;
 
-        let commits = rows
+        let mut commits: Vec<crate::model::Commit> = rows
             .into_iter()
             .map(|row| crate::model::Commit {
                 hash: row.get("hash"),
@@ -764,33 +1022,127 @@ impl crate::store::traits::CommitStore for PostgresStore {
                 data_size: row.get("data_size"),
                 schema_classes_count: row.get("schema_classes_count"),
                 instances_count: row.get("instances_count"),
+                source_branch: row.get("source_branch"),
+                source_commit_hash: row.get("source_commit_hash"),
+                target_commit_hash: row.get("target_commit_hash"),
+                merge_strategy: Self::parse_merge_strategy(row.get("merge_strategy")),
             })
             .collect();
+        for commit in commits.iter_mut() {
+            self.decrypt_commit(commit)?;
+        }
 
         Ok(commits)
     }
 
+    async fn list_commits_paginated(
+        &self,
+        database_id: &crate::model::Id,
+        after: Option<&crate::model::CommitCursor>,
+        limit: usize,
+    ) -> Result<crate::model::CommitPage> {
+        // Row-value comparison keeps the ordering key and the page boundary in sync;
+        // fetching one extra row tells whether another page follows
+        let query_str = if after.is_some() {
+            r#"
+            SELECT hash, database_id, parent_hash, author, message, created_at,
+                   data, data_size, schema_classes_count, instances_count,
+                   source_branch, source_commit_hash, target_commit_hash, merge_strategy
+            FROM commits
+            WHERE database_id = $1 AND (created_at, hash) < ($3, $4)
+            ORDER BY created_at DESC, hash DESC
+            LIMIT $2
+            "#
+        } else {
+            r#"
+            SELECT hash, database_id, parent_hash, author, message, created_at,
+                   data, data_size, schema_classes_count, instances_count,
+                   source_branch, source_commit_hash, target_commit_hash, merge_strategy
+            FROM commits
+            WHERE database_id = $1
+            ORDER BY created_at DESC, hash DESC
+            LIMIT $2
+            "#
+        };
+
+        let fetch_limit = i64::try_from(limit).unwrap_or(i64::MAX - 1) + 1;
+        let mut query = sqlx::query(query_str).bind(database_id).bind(fetch_limit);
+        if let Some(cursor) = after {
+            let created_at = chrono::DateTime::parse_from_rfc3339(&cursor.created_at)
+                .context("Invalid commit cursor timestamp")?
+                .with_timezone(&chrono::Utc);
+            query = query.bind(created_at).bind(&cursor.hash);
+        }
+
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to list commits for database")?;
+
+        let mut commits: Vec<crate::model::Commit> = rows
+            .into_iter()
+            .map(|row| crate::model::Commit {
+                hash: row.get("hash"),
+                database_id: row.get("database_id"),
+                parent_hash: row.get("parent_hash"),
+                author: row.get("author"),
+                message: row.get("message"),
+                created_at: row
+                    .get::<chrono::DateTime<chrono::Utc>, _>("created_at")
+                    .to_rfc3339(),
+                data: row.get("data"),
+                data_size: row.get("data_size"),
+                schema_classes_count: row.get("schema_classes_count"),
+                instances_count: row.get("instances_count"),
+                source_branch: row.get("source_branch"),
+                source_commit_hash: row.get("source_commit_hash"),
+                target_commit_hash: row.get("target_commit_hash"),
+                merge_strategy: Self::parse_merge_strategy(row.get("merge_strategy")),
+            })
+            .collect();
+
+        let next_cursor = if commits.len() > limit {
+            commits.truncate(limit);
+            commits.last().map(crate::model::CommitCursor::after)
+        } else {
+            None
+        };
+        for commit in commits.iter_mut() {
+            self.decrypt_commit(commit)?;
+        }
+
+        Ok(crate::model::CommitPage {
+            commits,
+            next_cursor,
+        })
+    }
+
     async fn create_commit(
         &self,
         new_commit: crate::model::NewCommit,
     ) -> Result<crate::model::Commit> {
         // Get the working commit to convert
-        let working_commit = self
+        let mut working_commit = self
             .get_working_commit(&new_commit.working_commit_id)
             .await?
             .ok_or_else(|| {
                 anyhow::anyhow!("Working commit not found: {}", new_commit.working_commit_id)
             })?;
+        self.encrypt_for_commit(&mut working_commit)?;
+        if let Some(based_on_hash) = new_commit.based_on_hash {
+            working_commit.based_on_hash = based_on_hash;
+        }
 
         // Convert working commit to immutable commit
-        let commit = working_commit.to_commit(new_commit.message);
+        let commit = working_commit.to_commit(new_commit.message)?;
 
         // Store the commit in database
         sqlx::query(
             r#"
             INSERT INTO commits (hash, database_id, parent_hash, author, message, created_at,
-                               data, data_size, schema_classes_count, instances_count)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                               data, data_size, schema_classes_count, instances_count,
+                               source_branch, source_commit_hash, target_commit_hash, merge_strategy)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
             "#
         )
         .bind(&commit.hash)
@@ -807,6 +1159,10 @@ impl crate::store::traits::CommitStore for PostgresStore {
         .bind(commit.data_size)
         .bind(commit.schema_classes_count)
         .bind(commit.instances_count)
+        .bind(&commit.source_branch)
+        .bind(&commit.source_commit_hash)
+        .bind(&commit.target_commit_hash)
+        .bind(commit.merge_strategy.as_ref().map(Self::merge_strategy_to_string))
         .execute(&self.pool)
         .await
         .context("Failed to create commit")?;
@@ -846,6 +1202,40 @@ impl crate::store::traits::CommitStore for PostgresStore {
 
         Ok(count > 0)
     }
+
+    async fn is_ancestor(
+        &self,
+        database_id: &Id,
+        ancestor_hash: &str,
+        descendant_hash: &str,
+    ) -> Result<Option<usize>> {
+        // Walk parent links without loading commit data; `path` stops a looping chain
+        let distance: Option<i32> = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE chain (hash, parent_hash, distance, path) AS (
+                SELECT hash, parent_hash, 0, ARRAY[hash]::text[]
+                FROM commits
+                WHERE database_id = $1 AND hash = $3
+                UNION ALL
+                SELECT c.hash, c.parent_hash, chain.distance + 1, chain.path || c.hash::text
+                FROM commits c
+                JOIN chain ON c.hash = chain.parent_hash
+                WHERE c.database_id = $1
+                  AND chain.hash <> $2
+                  AND NOT c.hash = ANY(chain.path)
+            )
+            SELECT distance FROM chain WHERE hash = $2
+            "#,
+        )
+        .bind(database_id)
+        .bind(ancestor_hash)
+        .bind(descendant_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to walk commit ancestry")?;
+
+        Ok(distance.map(|distance| distance as usize))
+    }
 }
 
 #[async_trait::async_trait]
@@ -863,7 +1253,7 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
         let row = sqlx::query(
             r#"
             SELECT id, database_id, branch_name, based_on_hash, author, created_at, updated_at,
-                   schema_data, instances_data, status, merge_state
+                   schema_data, instances_data, status, merge_state, merge_metadata, version
             FROM working_commits
             WHERE id = $1
             "#
@@ -915,14 +1305,16 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
             updated_at: row
                 .get::<chrono::DateTime<chrono::Utc>, _>("updated_at")
                 .to_rfc3339(),
+            version: row.get::<i64, _>("version") as u64,
             schema_data,
             instances_data,
             status,
             merge_state,
+            merge_metadata: Self::parse_merge_metadata(row.get("merge_metadata"))?,
         };
 
         // Put in cache for next time
-        self.working_commit_cache.put(working_commit.clone()).await;
+        self.working_commit_cache.load(working_commit.clone()).await;
 
         Ok(Some(working_commit))
     }
@@ -935,7 +1327,7 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
         let rows = sqlx::query(
             r#"
             SELECT id, database_id, branch_name, based_on_hash, author, created_at, updated_at,
-                   schema_data, instances_data, status, merge_state
+                   schema_data, instances_data, status, merge_state, merge_metadata, version
             FROM working_commits
             WHERE database_id = $1 AND branch_name = $2
             ORDER BY updated_at DESC
@@ -987,10 +1379,12 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
                 updated_at: row
                     .get::<chrono::DateTime<chrono::Utc>, _>("updated_at")
                     .to_rfc3339(),
+                version: row.get::<i64, _>("version") as u64,
                 schema_data,
                 instances_data,
                 status,
                 merge_state,
+                merge_metadata: Self::parse_merge_metadata(row.get("merge_metadata"))?,
             });
         }
 
@@ -1033,10 +1427,12 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
             author: new_working_commit.author,
             created_at: now.clone(),
             updated_at: now,
+            version: 0,
             schema_data: current_schema,
             instances_data: current_instances,
             status: crate::model::WorkingCommitStatus::Active,
             merge_state: None,
+            merge_metadata: None,
         };
 
         // Store in database
@@ -1095,103 +1491,53 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
         // Touch the working commit to update timestamp
         working_commit.touch();
 
-        // Update cache immediately - this is fast
-        self.working_commit_cache.update(working_commit.clone()).await;
-
-        // Check if there are any old dirty entries that should be persisted to Postgres
-        // This happens when a working commit hasn't been accessed for a while
-        let dirty_entries = self.working_commit_cache.get_dirty_entries().await;
+        // Write through: the staged state is durable in Postgres before the request returns,
+        // so a crash never loses an acknowledged edit. The version comes from the row, not
+        // this process's cache, so two concurrent writes never share one
+        if let Some(version) = self.persist_working_commit_row(&working_commit).await? {
+            working_commit.version = version;
+        }
+        self.working_commit_cache.put(working_commit).await;
 
-        // Persist dirty entries to Postgres in the background
-        // We do this to ensure data consistency even if cache expires
-        for dirty_working_commit in dirty_entries {
-            // Skip the current one - we'll handle it next time
-            if dirty_working_commit.id == working_commit.id {
-                continue;
+        // Flush anything still marked dirty from older code paths
+        for mut dirty_working_commit in self.working_commit_cache.get_dirty_entries().await {
+            if let Some(version) = self.persist_working_commit_row(&dirty_working_commit).await? {
+                dirty_working_commit.version = version;
             }
+            self.working_commit_cache.load(dirty_working_commit).await;
+        }
 
-            // Persist this dirty entry
-            let schema_json = serde_json::to_value(&dirty_working_commit.schema_data)
-                .context("Failed to serialize schema data")?;
-            let instances_json = serde_json::to_value(&dirty_working_commit.instances_data)
-                .context("Failed to serialize instances data")?;
-            let status_str = Self::working_commit_status_to_string(&dirty_working_commit.status);
-
-            let merge_state_json = if let Some(merge_state) = &dirty_working_commit.merge_state {
-                Some(serde_json::to_value(merge_state).context("Failed to serialize merge_state")?)
-            } else {
-                None
-            };
+        Ok(())
+    }
 
-            sqlx::query(
-                r#"
-                UPDATE working_commits
-                SET schema_data = $2, instances_data = $3, status = $4, updated_at = $5, merge_state = $6
-                WHERE id = $1
-                "#
-            )
-            .bind(&dirty_working_commit.id)
-            .bind(schema_json)
-            .bind(instances_json)
-            .bind(status_str)
-            .bind(
-                chrono::DateTime::parse_from_rfc3339(&dirty_working_commit.updated_at)
-                    .context("Failed to parse working commit updated_at")?
-                    .with_timezone(&chrono::Utc)
-            )
-            .bind(merge_state_json)
-            .execute(&self.pool)
-            .await
-            .context("Failed to update working commit")?;
+    async fn update_working_commit_at_version(
+        &self,
+        mut working_commit: crate::model::WorkingCommit,
+        expected_version: u64,
+    ) -> Result<bool> {
+        working_commit.touch();
 
-            // Mark as clean
-            self.working_commit_cache.mark_clean(&dirty_working_commit.id).await;
+        // The version check is part of the UPDATE, so two edits made against the same version
+        // can't both be written
+        match self
+            .persist_working_commit_row_if(&working_commit, Some(expected_version))
+            .await?
+        {
+            Some(version) => working_commit.version = version,
+            None => return Ok(false),
         }
-
-        Ok(())
+        self.working_commit_cache.put(working_commit).await;
+        Ok(true)
     }
 
     async fn delete_working_commit(&self, id: &crate::model::Id) -> Result<bool> {
         // Before deleting, check if this working commit is dirty and needs to be persisted
         // This ensures we don't lose any uncommitted changes
         let dirty_entries = self.working_commit_cache.get_dirty_entries().await;
-        for dirty_working_commit in dirty_entries {
-            if dirty_working_commit.id == *id {
-                // This is the one being deleted, so persist it one last time
-                let schema_json = serde_json::to_value(&dirty_working_commit.schema_data)
-                    .context("Failed to serialize schema data")?;
-                let instances_json = serde_json::to_value(&dirty_working_commit.instances_data)
-                    .context("Failed to serialize instances data")?;
-                let status_str = Self::working_commit_status_to_string(&dirty_working_commit.status);
-
-                let merge_state_json = if let Some(merge_state) = &dirty_working_commit.merge_state {
-                    Some(serde_json::to_value(merge_state).context("Failed to serialize merge_state")?)
-                } else {
-                    None
-                };
-
-                sqlx::query(
-                    r#"
-                    UPDATE working_commits
-                    SET schema_data = $2, instances_data = $3, status = $4, updated_at = $5, merge_state = $6
-                    WHERE id = $1
-                    "#
-                )
-                .bind(&dirty_working_commit.id)
-                .bind(schema_json)
-                .bind(instances_json)
-                .bind(status_str)
-                .bind(
-                    chrono::DateTime::parse_from_rfc3339(&dirty_working_commit.updated_at)
-                        .context("Failed to parse working commit updated_at")?
-                        .with_timezone(&chrono::Utc)
-                )
-                .bind(merge_state_json)
-                .execute(&self.pool)
+        if let Some(dirty_working_commit) = dirty_entries.iter().find(|wc| wc.id == *id) {
+            self.persist_working_commit_row(dirty_working_commit)
                 .await
                 .context("Failed to update working commit before deletion")?;
-                break;
-            }
         }
 
         // Now delete from database
@@ -1209,51 +1555,28 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
 
     async fn force_persist_working_commit(&self, id: &crate::model::Id) -> Result<()> {
         // Get the working commit from cache or database
-        let working_commit = match self.get_working_commit(id).await? {
+        let mut working_commit = match self.get_working_commit(id).await? {
             Some(wc) => wc,
             None => return Err(anyhow::anyhow!("Working commit not found: {}", id)),
         };
 
         // Persist to PostgreSQL immediately
-        let schema_json = serde_json::to_value(&working_commit.schema_data)
-            .context("Failed to serialize schema data")?;
-        let instances_json = serde_json::to_value(&working_commit.instances_data)
-            .context("Failed to serialize instances data")?;
-        let status_str = Self::working_commit_status_to_string(&working_commit.status);
-
-        let merge_state_json = if let Some(merge_state) = &working_commit.merge_state {
-            Some(serde_json::to_value(merge_state).context("Failed to serialize merge_state")?)
-        } else {
-            None
-        };
-
-        sqlx::query(
-            r#"
-            UPDATE working_commits
-            SET schema_data = $2, instances_data = $3, status = $4, updated_at = $5, merge_state = $6
-            WHERE id = $1
-            "#
-        )
-        .bind(&working_commit.id)
-        .bind(schema_json)
-        .bind(instances_json)
-        .bind(status_str)
-        .bind(
-            chrono::DateTime::parse_from_rfc3339(&working_commit.updated_at)
-                .context("Failed to parse working commit updated_at")?
-                .with_timezone(&chrono::Utc)
-        )
-        .bind(merge_state_json)
-        .execute(&self.pool)
-        .await
-        .context("Failed to persist working commit")?;
+        if let Some(version) = self.persist_working_commit_row(&working_commit).await? {
+            working_commit.version = version;
+        }
 
-        // Mark as clean in cache
-        self.working_commit_cache.mark_clean(id).await;
+        // Cache the persisted state as clean
+        self.working_commit_cache.load(working_commit).await;
 
         Ok(())
     }
 
+    fn subscribe_working_commit_changes(
+        &self,
+    ) -> tokio::sync::broadcast::Receiver<crate::store::working_commit_cache::WorkingCommitChange> {
+        self.working_commit_cache.subscribe()
+    }
+
     async fn get_active_working_commit_for_branch(
         &self,
         database_id: &crate::model::Id,
@@ -1293,7 +1616,7 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
         let row = sqlx::query(
             r#"
             SELECT id, database_id, branch_name, based_on_hash, author, created_at, updated_at,
-                   schema_data, instances_data, status, merge_state
+                   schema_data, instances_data, status, merge_state, merge_metadata, version
             FROM working_commits
             WHERE database_id = $1 AND branch_name = $2 AND status = 'active'
             ORDER BY updated_at DESC
@@ -1348,19 +1671,46 @@ impl crate::store::traits::WorkingCommitStore for PostgresStore {
             updated_at: row
                 .get::<chrono::DateTime<chrono::Utc>, _>("updated_at")
                 .to_rfc3339(),
+            version: row.get::<i64, _>("version") as u64,
             schema_data,
             instances_data,
             status,
             merge_state,
+            merge_metadata: Self::parse_merge_metadata(row.get("merge_metadata"))?,
         };
 
         // Put in cache for next time
-        self.working_commit_cache.put(working_commit.clone()).await;
+        self.working_commit_cache.load(working_commit.clone()).await;
 
         Ok(Some(working_commit))
     }
 }
 
+/// Convert a `commit_tags` row selected with all its columns
+fn commit_tag_from_row(row: &sqlx::postgres::PgRow) -> Result<crate::model::CommitTag> {
+    let tag_type_str: String = row.get("tag_type");
+    let tag_type = tag_type_str
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid tag type: {}", e))?;
+
+    let metadata_value: Option<serde_json::Value> = row.get("metadata");
+    let metadata = serde_json::from_value(metadata_value.unwrap_or_else(|| serde_json::json!({})))
+        .context("Failed to deserialize metadata")?;
+
+    Ok(crate::model::CommitTag {
+        id: row.get("id"),
+        commit_hash: row.get("commit_hash"),
+        tag_type,
+        tag_name: row.get("tag_name"),
+        tag_description: row.get("tag_description"),
+        created_at: row
+            .get::<chrono::DateTime<chrono::Utc>, _>("created_at")
+            .to_rfc3339(),
+        created_by: row.get("created_by"),
+        metadata,
+    })
+}
+
 // Simplified TagStore implementation using only commit_tags
 #[async_trait::async_trait]
 impl crate::store::traits::TagStore for PostgresStore {
@@ -1417,33 +1767,47 @@ impl crate::store::traits::TagStore for PostgresStore {
         .await
         .context("Failed to get commit tags")?;
 
-        let mut tags = Vec::new();
-        for row in rows {
-            let tag_type_str: String = row.get("tag_type");
-            let tag_type = tag_type_str
-                .parse()
-                .map_err(|e| anyhow::anyhow!("Invalid tag type: {}", e))?;
+        rows.iter().map(commit_tag_from_row).collect()
+    }
 
-            let metadata_value: Option<serde_json::Value> = row.get("metadata");
-            let metadata =
-                serde_json::from_value(metadata_value.unwrap_or_else(|| serde_json::json!({})))
-                    .context("Failed to deserialize metadata")?;
+    async fn get_commit_tag(&self, tag_id: i32) -> Result<Option<crate::model::CommitTag>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, commit_hash, tag_type, tag_name, tag_description, created_at, created_by, metadata
+            FROM commit_tags
+            WHERE id = $1
+            "#
+        )
+        .bind(tag_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to get commit tag")?;
 
-            tags.push(crate::model::CommitTag {
-                id: row.get("id"),
-                commit_hash: row.get("commit_hash"),
-                tag_type,
-                tag_name: row.get("tag_name"),
-                tag_description: row.get("tag_description"),
-                created_at: row
-                    .get::<chrono::DateTime<chrono::Utc>, _>("created_at")
-                    .to_rfc3339(),
-                created_by: row.get("created_by"),
-                metadata,
-            });
-        }
+        row.as_ref().map(commit_tag_from_row).transpose()
+    }
 
-        Ok(tags)
+    async fn move_commit_tag(
+        &self,
+        tag_id: i32,
+        commit_hash: &str,
+        tag_description: Option<String>,
+    ) -> Result<Option<crate::model::CommitTag>> {
+        let row = sqlx::query(
+            r#"
+            UPDATE commit_tags
+            SET commit_hash = $2, tag_description = COALESCE($3, tag_description)
+            WHERE id = $1
+            RETURNING id, commit_hash, tag_type, tag_name, tag_description, created_at, created_by, metadata
+            "#
+        )
+        .bind(tag_id)
+        .bind(commit_hash)
+        .bind(tag_description)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to move commit tag")?;
+
+        row.as_ref().map(commit_tag_from_row).transpose()
     }
 
     async fn delete_commit_tag(&self, tag_id: i32) -> Result<bool> {
@@ -1456,6 +1820,22 @@ impl crate::store::traits::TagStore for PostgresStore {
         Ok(result.rows_affected() > 0)
     }
 
+    async fn list_orphaned_commit_tags(&self) -> Result<Vec<crate::model::CommitTag>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, commit_hash, tag_type, tag_name, tag_description, created_at, created_by, metadata
+            FROM commit_tags t
+            WHERE NOT EXISTS (SELECT 1 FROM commits c WHERE c.hash = t.commit_hash)
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to list orphaned commit tags")?;
+
+        rows.iter().map(commit_tag_from_row).collect()
+    }
+
     async fn search_commits_by_tags(
         &self,
         database_id: &crate::model::Id,
@@ -1543,12 +1923,81 @@ impl crate::store::traits::TagStore for PostgresStore {
 
         Ok(tagged_commits)
     }
+
+    async fn query_tagged_commits(
+        &self,
+        database_id: &crate::model::Id,
+        query: &crate::model::TagQuery,
+    ) -> Result<crate::model::TaggedCommitPage> {
+        let tag_type = query.tag_type.as_ref().map(|tag_type| tag_type.to_string());
+        // Same conditions for the count and the page; POSITION keeps `%` and `_` in names literal
+        let filter = r#"
+            FROM commits c
+            INNER JOIN commit_tags ct ON c.hash = ct.commit_hash
+            WHERE c.database_id = $1
+              AND ($2::text IS NULL OR ct.tag_type = $2)
+              AND ($3::text IS NULL OR POSITION(LOWER($3) IN LOWER(ct.tag_name)) > 0)
+        "#;
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(DISTINCT c.hash) {}", filter))
+            .bind(database_id)
+            .bind(&tag_type)
+            .bind(&query.tag_name)
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to count tagged commits")?;
+
+        // LIMIT NULL returns every row
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT c.hash, c.database_id, c.message, c.author, c.created_at,
+                   MAX(ct.created_at) AS last_tagged_at, MAX(ct.id) AS last_tag_id
+            {}
+            GROUP BY c.hash, c.database_id, c.message, c.author, c.created_at
+            ORDER BY last_tagged_at DESC, last_tag_id DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            filter
+        ))
+        .bind(database_id)
+        .bind(&tag_type)
+        .bind(&query.tag_name)
+        .bind(query.limit.map(|limit| limit.max(0) as i64))
+        .bind(query.offset.unwrap_or(0).max(0) as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query tagged commits")?;
+
+        let mut commits = Vec::new();
+        for row in rows {
+            let commit_hash: String = row.get("hash");
+            let tags = self.get_commit_tags(&commit_hash).await?;
+
+            commits.push(crate::model::TaggedCommit {
+                commit_hash,
+                database_id: row.get("database_id"),
+                commit_message: row.get("message"),
+                commit_author: row.get("author"),
+                commit_created_at: row
+                    .get::<chrono::DateTime<chrono::Utc>, _>("created_at")
+                    .to_rfc3339(),
+                tags,
+            });
+        }
+
+        Ok(crate::model::TaggedCommitPage {
+            commits,
+            total: total as usize,
+        })
+    }
 }
 
 impl Store for PostgresStore {}
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::store::traits::DatabaseStore;
 
     #[test]
     fn test_postgres_store_schema_update_complete() {
@@ -1567,4 +2016,57 @@ mod tests {
 
         assert!(true, "PostgresStore schema update completed successfully!");
     }
+
+    #[test]
+    fn pool_options_apply_database_config() {
+        let config = DatabaseConfig {
+            connection_string: None,
+            max_connections: Some(7),
+            min_connections: Some(2),
+            acquire_timeout: Some(3),
+            idle_timeout: Some(45),
+        };
+        let options = pool_options(&config);
+        assert_eq!(options.get_max_connections(), 7);
+        assert_eq!(options.get_min_connections(), 2);
+        assert_eq!(options.get_acquire_timeout(), Duration::from_secs(3));
+        assert_eq!(options.get_idle_timeout(), Some(Duration::from_secs(45)));
+    }
+
+    #[tokio::test]
+    async fn exhausted_pool_times_out_instead_of_blocking() {
+        // A server that accepts connections but never answers the startup handshake, so the
+        // single pooled connection never becomes ready and every caller waits on the pool
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let config = DatabaseConfig {
+            max_connections: Some(1),
+            acquire_timeout: Some(1),
+            ..DatabaseConfig::default()
+        };
+        let pool = pool_options(&config)
+            .connect_lazy(&format!("postgres://oat:oat@{}/oat?sslmode=disable", address))
+            .unwrap();
+        let store = PostgresStore::with_pool(pool);
+
+        let calls = (0..5).map(|_| store.list_databases());
+        let results = tokio::time::timeout(
+            Duration::from_secs(10),
+            futures_util::future::join_all(calls),
+        )
+        .await
+        .expect("pool acquisition should time out rather than block");
+
+        for result in results {
+            let error = result.unwrap_err();
+            assert!(is_pool_timeout(&error), "unexpected error: {:#}", error);
+        }
+    }
 }
@@ -1,5 +1,7 @@
-use crate::model::{Branch, ClassDef, Commit, CommitData, Database, Id, Instance, InstanceFilter, NewCommit, NewWorkingCommit, Schema, WorkingCommit, CommitTag, NewCommitTag, TaggedCommit, TagQuery};
+use crate::model::{Branch, ClassDef, Commit, CommitCursor, CommitData, CommitPage, Database, Id, Instance, InstanceFilter, NewCommit, NewWorkingCommit, Schema, WorkingCommit, CommitTag, NewCommitTag, TaggedCommit, TaggedCommitPage, TagQuery};
+use crate::store::working_commit_cache::WorkingCommitChange;
 use anyhow::Result;
+use tokio::sync::broadcast;
 
 #[async_trait::async_trait]
 pub trait DatabaseStore: Send + Sync {
@@ -15,6 +17,9 @@ pub trait BranchStore: Send + Sync {
     async fn list_branches_for_database(&self, database_id: &Id) -> Result<Vec<Branch>>;
     async fn upsert_branch(&self, branch: Branch) -> Result<()>;
     async fn delete_branch(&self, database_id: &Id, name: &str) -> Result<bool>;
+    /// Delete several branches and their working commits atomically, returning how many
+    /// branches were removed
+    async fn delete_branches(&self, database_id: &Id, names: &[String]) -> Result<usize>;
     async fn get_default_branch(&self, database_id: &Id) -> Result<Option<Branch>>;
 }
 
@@ -98,12 +103,24 @@ pub trait CommitStore: Send + Sync {
     async fn get_commit(&self, hash: &str) -> Result<Option<Commit>>;
     /// List commits for a database (with optional parent filtering)
     async fn list_commits_for_database(&self, database_id: &Id, parent_hash: Option<&str>) -> Result<Vec<Commit>>;
+    /// List up to `limit` commits for a database, newest first, starting after `after`
+    async fn list_commits_paginated(&self, database_id: &Id, after: Option<&CommitCursor>, limit: usize) -> Result<CommitPage>;
     /// Create a new commit from a working commit
     async fn create_commit(&self, commit: NewCommit) -> Result<Commit>;
     /// Get commit data (decompressed schema + instances)
     async fn get_commit_data(&self, hash: &str) -> Result<Option<CommitData>>;
     /// Check if a commit exists
     async fn commit_exists(&self, hash: &str) -> Result<bool>;
+    /// Number of parent links from `descendant_hash` back to `ancestor_hash` within the
+    /// database, 0 when they are the same commit. `None` when `ancestor_hash` is not an
+    /// ancestor, including unrelated histories and unknown commits. A parent chain that
+    /// loops back on itself ends the walk instead of running forever.
+    async fn is_ancestor(
+        &self,
+        database_id: &Id,
+        ancestor_hash: &str,
+        descendant_hash: &str,
+    ) -> Result<Option<usize>>;
 }
 
 #[async_trait::async_trait]
@@ -116,12 +133,17 @@ pub trait WorkingCommitStore: Send + Sync {
     async fn create_working_commit(&self, database_id: &Id, branch_name: &str, working_commit: NewWorkingCommit) -> Result<WorkingCommit>;
     /// Update a working commit (schema and/or instances)
     async fn update_working_commit(&self, working_commit: WorkingCommit) -> Result<()>;
+    /// Update a working commit only while the stored copy is still at `expected_version`, as
+    /// one atomic step; returns `false` and saves nothing when it was saved in between
+    async fn update_working_commit_at_version(&self, working_commit: WorkingCommit, expected_version: u64) -> Result<bool>;
     /// Delete/abandon a working commit
     async fn delete_working_commit(&self, id: &Id) -> Result<bool>;
     /// Get the active working commit for a branch (if any)
     async fn get_active_working_commit_for_branch(&self, database_id: &Id, branch_name: &str) -> Result<Option<WorkingCommit>>;
     /// Force persist a working commit to database immediately (bypassing cache delay)
     async fn force_persist_working_commit(&self, id: &Id) -> Result<()>;
+    /// Subscribe to changes staged in any working commit, as detected by the working commit cache
+    fn subscribe_working_commit_changes(&self) -> broadcast::Receiver<WorkingCommitChange>;
 }
 
 /// Store for managing commit tags
@@ -131,14 +153,24 @@ pub trait TagStore: Send + Sync {
     async fn create_commit_tag(&self, tag: NewCommitTag) -> Result<CommitTag>;
     /// Get all tags for a specific commit
     async fn get_commit_tags(&self, commit_hash: &str) -> Result<Vec<CommitTag>>;
+    /// Get a tag by ID
+    async fn get_commit_tag(&self, tag_id: i32) -> Result<Option<CommitTag>>;
+    /// Point a tag at another commit, replacing its description when one is given.
+    /// Returns None if the tag doesn't exist.
+    async fn move_commit_tag(&self, tag_id: i32, commit_hash: &str, tag_description: Option<String>) -> Result<Option<CommitTag>>;
     /// Delete a commit tag
     async fn delete_commit_tag(&self, tag_id: i32) -> Result<bool>;
+    /// List tags whose commit no longer exists, ordered by tag ID
+    async fn list_orphaned_commit_tags(&self) -> Result<Vec<CommitTag>>;
     /// Search for commits by tag criteria
     async fn search_commits_by_tags(&self, database_id: &Id, query: TagQuery) -> Result<Vec<TaggedCommit>>;
     /// Get complete tagged commit information (commit + tags)
     async fn get_tagged_commit(&self, commit_hash: &str) -> Result<Option<TaggedCommit>>;
     /// List all tagged commits for a database
     async fn list_tagged_commits(&self, database_id: &Id, limit: Option<i32>) -> Result<Vec<TaggedCommit>>;
+    /// Page through the commits with a tag matching `query`, most recently tagged first;
+    /// without a limit every match is returned
+    async fn query_tagged_commits(&self, database_id: &Id, query: &TagQuery) -> Result<TaggedCommitPage>;
 }
 
 pub trait Store: DatabaseStore + BranchStore + SchemaStore + InstanceStore + CommitStore + WorkingCommitStore + TagStore + Send + Sync {}
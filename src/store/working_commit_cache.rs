@@ -1,8 +1,94 @@
 use crate::model::{Id, WorkingCommit};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+
+/// How many unread change events a slow subscriber may fall behind before it starts missing them
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// What changed in a working commit between two cached versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkingCommitChangeKind {
+    InstanceAdded,
+    InstanceModified,
+    InstanceDeleted,
+    SchemaChanged,
+}
+
+/// A single change to a working commit, published to subscribers of the cache
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkingCommitChange {
+    pub working_commit_id: Id,
+    pub database_id: Id,
+    pub branch_name: Option<String>,
+    pub kind: WorkingCommitChangeKind,
+    /// The affected instance; absent for schema changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<Id>,
+    /// Working commit version after the change
+    pub version: u64,
+}
+
+/// Changes from `previous` to `current`: the schema first, then instances in staging order
+/// with deletions last
+pub fn diff_working_commits(
+    previous: &WorkingCommit,
+    current: &WorkingCommit,
+) -> Vec<WorkingCommitChange> {
+    changes_since(Some(previous), current)
+}
+
+/// Changes from `previous` to `current`; with no previous version the schema and every
+/// instance count as new
+fn changes_since(
+    previous: Option<&WorkingCommit>,
+    current: &WorkingCommit,
+) -> Vec<WorkingCommitChange> {
+    let change = |kind, instance_id: Option<&Id>| WorkingCommitChange {
+        working_commit_id: current.id.clone(),
+        database_id: current.database_id.clone(),
+        branch_name: current.branch_name.clone(),
+        kind,
+        instance_id: instance_id.cloned(),
+        version: current.version,
+    };
+
+    let mut changes = Vec::new();
+    if previous.is_none_or(|previous| previous.schema_data != current.schema_data) {
+        changes.push(change(WorkingCommitChangeKind::SchemaChanged, None));
+    }
+
+    let previous_instances = previous.map_or(&[][..], |previous| &previous.instances_data);
+    let previous_by_id: HashMap<&Id, _> = previous_instances
+        .iter()
+        .map(|instance| (&instance.id, instance))
+        .collect();
+    for instance in &current.instances_data {
+        match previous_by_id.get(&instance.id) {
+            None => changes.push(change(
+                WorkingCommitChangeKind::InstanceAdded,
+                Some(&instance.id),
+            )),
+            Some(previous) if *previous != instance => changes.push(change(
+                WorkingCommitChangeKind::InstanceModified,
+                Some(&instance.id),
+            )),
+            Some(_) => {}
+        }
+    }
+    for instance in previous_instances {
+        if !current.instances_data.iter().any(|i| i.id == instance.id) {
+            changes.push(change(
+                WorkingCommitChangeKind::InstanceDeleted,
+                Some(&instance.id),
+            ));
+        }
+    }
+    changes
+}
 
 /// Cache entry for a working commit
 #[derive(Clone, Debug)]
@@ -21,6 +107,8 @@ pub struct WorkingCommitCache {
     active_by_branch: Arc<RwLock<HashMap<(Id, String), Id>>>,
     /// Time-to-live for cache entries (1 hour)
     ttl: Duration,
+    /// Changes detected when a cached working commit is replaced
+    changes: broadcast::Sender<WorkingCommitChange>,
 }
 
 impl WorkingCommitCache {
@@ -30,9 +118,15 @@ impl WorkingCommitCache {
             entries: Arc::new(RwLock::new(HashMap::new())),
             active_by_branch: Arc::new(RwLock::new(HashMap::new())),
             ttl: Duration::from_secs(3600), // 1 hour
+            changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
         }
     }
 
+    /// Receive every change detected from now on; dropping the receiver unsubscribes
+    pub fn subscribe(&self) -> broadcast::Receiver<WorkingCommitChange> {
+        self.changes.subscribe()
+    }
+
     /// Get a working commit from cache if present and not expired
     pub async fn get(&self, id: &Id) -> Option<WorkingCommit> {
         let mut entries = self.entries.write().await;
@@ -59,16 +153,29 @@ impl WorkingCommitCache {
         active_by_branch.get(&key).cloned()
     }
 
-    /// Put a working commit into cache
+    /// Put a working commit into cache and publish what changed since the cached version; with
+    /// none cached, its schema and every instance are published as new
     pub async fn put(&self, working_commit: WorkingCommit) {
+        self.insert(working_commit, true).await;
+    }
+
+    /// Cache a working commit just read from storage; nothing changed, so nothing is published
+    pub async fn load(&self, working_commit: WorkingCommit) {
+        self.insert(working_commit, false).await;
+    }
+
+    async fn insert(&self, working_commit: WorkingCommit, publish: bool) {
         let mut entries = self.entries.write().await;
         let id = working_commit.id.clone();
 
-        entries.insert(id.clone(), CacheEntry {
+        let previous = entries.insert(id.clone(), CacheEntry {
             working_commit: working_commit.clone(),
             last_accessed: Instant::now(),
             dirty: false,
         });
+        if publish {
+            self.publish(previous.as_ref().map(|e| &e.working_commit), &working_commit);
+        }
 
         // Update active branch mapping if this is an active working commit
         if working_commit.status == crate::model::WorkingCommitStatus::Active {
@@ -95,11 +202,19 @@ impl WorkingCommitCache {
         let mut entries = self.entries.write().await;
         let id = working_commit.id.clone();
 
-        entries.insert(id, CacheEntry {
-            working_commit,
+        let previous = entries.insert(id, CacheEntry {
+            working_commit: working_commit.clone(),
             last_accessed: Instant::now(),
             dirty: true,
         });
+        self.publish(previous.as_ref().map(|e| &e.working_commit), &working_commit);
+    }
+
+    fn publish(&self, previous: Option<&WorkingCommit>, current: &WorkingCommit) {
+        for change in changes_since(previous, current) {
+            // Sending only fails when nobody is subscribed
+            let _ = self.changes.send(change);
+        }
     }
 
     /// Get all dirty entries that need to be persisted to Postgres
@@ -202,6 +317,7 @@ mod tests {
             author: Some("test".to_string()),
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
+            version: 0,
             schema_data: Schema {
                 id: "schema-1".to_string(),
                 description: None,
@@ -210,6 +326,7 @@ mod tests {
             instances_data: Vec::new(),
             status: WorkingCommitStatus::Active,
             merge_state: None,
+            merge_metadata: None,
         };
 
         // Put into cache
@@ -243,6 +360,7 @@ mod tests {
             author: Some("test".to_string()),
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
+            version: 0,
             schema_data: Schema {
                 id: "schema-1".to_string(),
                 description: None,
@@ -251,6 +369,7 @@ mod tests {
             instances_data: Vec::new(),
             status: WorkingCommitStatus::Active,
             merge_state: None,
+            merge_metadata: None,
         };
 
         // Put into cache (not dirty initially)
@@ -268,4 +387,61 @@ mod tests {
         let dirty = cache.get_dirty_entries().await;
         assert_eq!(dirty.len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_cache_publishes_changes_on_replace() {
+        let cache = WorkingCommitCache::new();
+        let mut receiver = cache.subscribe();
+
+        let mut working_commit = WorkingCommit {
+            id: "wc-test-3".to_string(),
+            database_id: "db-1".to_string(),
+            branch_name: Some("main".to_string()),
+            based_on_hash: "abc123".to_string(),
+            author: Some("test".to_string()),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            version: 0,
+            schema_data: Schema {
+                id: "schema-1".to_string(),
+                description: None,
+                classes: Vec::new(),
+            },
+            instances_data: vec![crate::model::Instance {
+                id: "leg-1".to_string(),
+                ..Default::default()
+            }],
+            status: WorkingCommitStatus::Active,
+            merge_state: None,
+            merge_metadata: None,
+        };
+
+        // Loading from storage changes nothing
+        cache.load(working_commit.clone()).await;
+        assert!(receiver.try_recv().is_err());
+        cache.remove(&working_commit.id).await;
+
+        // The first put has nothing to compare against, so everything is new
+        cache.put(working_commit.clone()).await;
+        let schema_change = receiver.try_recv().unwrap();
+        assert_eq!(schema_change.kind, WorkingCommitChangeKind::SchemaChanged);
+        let addition = receiver.try_recv().unwrap();
+        assert_eq!(addition.kind, WorkingCommitChangeKind::InstanceAdded);
+        assert_eq!(addition.instance_id.as_deref(), Some("leg-1"));
+        assert!(receiver.try_recv().is_err());
+
+        working_commit.version = 1;
+        working_commit.schema_data.description = Some("Furniture".to_string());
+        working_commit.instances_data = Vec::new();
+        cache.put(working_commit).await;
+
+        let schema_change = receiver.try_recv().unwrap();
+        assert_eq!(schema_change.kind, WorkingCommitChangeKind::SchemaChanged);
+        assert_eq!(schema_change.instance_id, None);
+        let deletion = receiver.try_recv().unwrap();
+        assert_eq!(deletion.kind, WorkingCommitChangeKind::InstanceDeleted);
+        assert_eq!(deletion.instance_id.as_deref(), Some("leg-1"));
+        assert_eq!(deletion.version, 1);
+        assert!(receiver.try_recv().is_err());
+    }
 }